@@ -1,3 +1,4 @@
+use crate::metrics::Histogram;
 use crate::HashMap;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -8,6 +9,20 @@ pub struct ClientData {
     // - mapping from operation end time to all latencies registered at that
     //   end time
     data: HashMap<u64, Vec<Duration>>,
+    // - mapping from operation end time to the latency breakdown (i.e. time
+    //   to commit and time to execute) registered at that end time; this is
+    //   only recorded if the executor reports when commands are committed and
+    //   executed
+    #[serde(default)]
+    phases: HashMap<u64, Vec<(Duration, Duration)>>,
+    // - number of operations that timed out (and thus have no latency
+    //   registered)
+    #[serde(default)]
+    timed_out: usize,
+    // - number of operations that were rejected by an overloaded process (and
+    //   thus have no latency registered)
+    #[serde(default)]
+    rejected: usize,
 }
 
 impl ClientData {
@@ -18,7 +33,10 @@ impl ClientData {
 
     /// Merges two histograms.
     pub fn merge(&mut self, other: &Self) {
-        data_merge(&mut self.data, &other.data)
+        data_merge(&mut self.data, &other.data);
+        data_merge(&mut self.phases, &other.phases);
+        self.timed_out += other.timed_out;
+        self.rejected += other.rejected;
     }
 
     /// Records a more mata.
@@ -27,10 +45,71 @@ impl ClientData {
         latencies.push(latency);
     }
 
+    /// Records an operation that timed out.
+    pub fn record_timed_out(&mut self) {
+        self.timed_out += 1;
+    }
+
+    /// Returns the number of operations that timed out.
+    pub fn timed_out(&self) -> usize {
+        self.timed_out
+    }
+
+    /// Records an operation that was rejected.
+    pub fn record_rejected(&mut self) {
+        self.rejected += 1;
+    }
+
+    /// Returns the number of operations that were rejected.
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// Records the latency breakdown of an operation: the time it took to be
+    /// committed and the time it took to be executed after that.
+    pub fn record_phases(
+        &mut self,
+        time_to_commit: Duration,
+        time_to_execute: Duration,
+        end_time: u64,
+    ) {
+        let phases = self.phases.entry(end_time).or_insert_with(Vec::new);
+        phases.push((time_to_commit, time_to_execute));
+    }
+
+    /// Returns the time to commit of each operation with a latency breakdown.
+    pub fn commit_latency_data(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.phases
+            .values()
+            .flat_map(|v| v.iter())
+            .map(|(time_to_commit, _)| *time_to_commit)
+    }
+
+    /// Returns the time to execute of each operation with a latency
+    /// breakdown.
+    pub fn execute_latency_data(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.phases
+            .values()
+            .flat_map(|v| v.iter())
+            .map(|(_, time_to_execute)| *time_to_execute)
+    }
+
     pub fn latency_data(&self) -> impl Iterator<Item = Duration> + '_ {
         self.data.values().flat_map(|v| v.iter()).cloned()
     }
 
+    /// Returns the latencies of operations that ended at or after `start`.
+    pub fn latency_data_since(
+        &self,
+        start: u64,
+    ) -> impl Iterator<Item = Duration> + '_ {
+        self.data
+            .iter()
+            .filter(move |(&time, _)| time >= start)
+            .flat_map(|(_, v)| v.iter())
+            .cloned()
+    }
+
     pub fn throughput_data(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
         self.data
             .iter()
@@ -68,12 +147,45 @@ impl ClientData {
         })
     }
 
+    /// Buckets operations into windows of `window` (according to their end
+    /// time), starting at this client's start time (see `start_and_end`), and
+    /// returns the start time of each window (in milliseconds) along with a
+    /// histogram of the latencies (in microseconds) of the operations that
+    /// ended within it. Windows without operations have an empty histogram.
+    /// If the data has been pruned, the window bounds are the pruned bounds.
+    pub fn latency_timeline(&self, window: Duration) -> Vec<(u64, Histogram)> {
+        let window = window.as_millis() as u64;
+        assert!(window > 0, "the window should be positive");
+
+        let (start, end) = match self.start_and_end() {
+            Some(bounds) => bounds,
+            None => return Vec::new(),
+        };
+
+        // create all windows between start and end
+        let window_count = ((end - start) / window + 1) as usize;
+        let mut timeline: Vec<_> = (0..window_count)
+            .map(|index| (start + index as u64 * window, Histogram::new()))
+            .collect();
+
+        // add each latency to its window
+        for (time, latencies) in self.data.iter() {
+            let index = ((time - start) / window) as usize;
+            let (_, histogram) = &mut timeline[index];
+            for latency in latencies {
+                histogram.increment(latency.as_micros() as u64);
+            }
+        }
+        timeline
+    }
+
     /// Prune events that are before `start` or after `end`.
     pub fn prune(&mut self, start: u64, end: u64) {
         self.data.retain(|&time, _| {
             // retain if within the given bounds
             time >= start && time <= end
-        })
+        });
+        self.phases.retain(|&time, _| time >= start && time <= end);
     }
 }
 
@@ -174,4 +286,75 @@ mod tests {
         throughput.sort();
         assert_eq!(throughput, vec![]);
     }
+
+    #[test]
+    fn latency_timeline() {
+        let mut data = ClientData::new();
+        // without data, there are no windows
+        assert!(data.latency_timeline(Duration::from_secs(1)).is_empty());
+
+        // a client spanning 3 seconds: 2 operations ended at time 100, 1 at
+        // time 1500, and 3 at time 2999
+        data.record(Duration::from_millis(1), 100);
+        data.record(Duration::from_millis(2), 100);
+        data.record(Duration::from_millis(3), 1500);
+        for _ in 0..3 {
+            data.record(Duration::from_millis(4), 2999);
+        }
+
+        let timeline = data.latency_timeline(Duration::from_secs(1));
+        let counts: Vec<_> = timeline
+            .iter()
+            .map(|(window_start, histogram)| (*window_start, histogram.count()))
+            .collect();
+        assert_eq!(counts, vec![(100, 2), (1100, 1), (2100, 3)]);
+
+        // latencies are in microseconds
+        let (_, histogram) = &timeline[1];
+        assert_eq!(histogram.values().collect::<Vec<_>>(), vec![3000]);
+    }
+
+    #[test]
+    fn client_data_phases() {
+        let mut data = ClientData::new();
+        // by default, there's no latency breakdown
+        assert_eq!(data.commit_latency_data().count(), 0);
+        assert_eq!(data.execute_latency_data().count(), 0);
+
+        // at time 10, an operation committed after 3 and executed after 2
+        data.record(Duration::from_millis(5), 10);
+        data.record_phases(
+            Duration::from_millis(3),
+            Duration::from_millis(2),
+            10,
+        );
+
+        // at time 20, an operation committed after 4 and executed after 6
+        let mut other = ClientData::new();
+        other.record(Duration::from_millis(10), 20);
+        other.record_phases(
+            Duration::from_millis(4),
+            Duration::from_millis(6),
+            20,
+        );
+        data.merge(&other);
+
+        let mut commit: Vec<_> = data.commit_latency_data().collect();
+        commit.sort();
+        assert_eq!(
+            commit,
+            vec![Duration::from_millis(3), Duration::from_millis(4)]
+        );
+        let mut execute: Vec<_> = data.execute_latency_data().collect();
+        execute.sort();
+        assert_eq!(
+            execute,
+            vec![Duration::from_millis(2), Duration::from_millis(6)]
+        );
+
+        // prune event 10 out
+        data.prune(15, 20);
+        let commit: Vec<_> = data.commit_latency_data().collect();
+        assert_eq!(commit, vec![Duration::from_millis(4)]);
+    }
 }
@@ -1,7 +1,9 @@
 use crate::id::ClientId;
 use crate::kvs::Key;
+use crate::HashMap;
 use rand::distributions::Distribution;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use zipf::ZipfDistribution;
 
@@ -25,7 +27,18 @@ impl KeyGen {
         shard_count: usize,
         client_id: ClientId,
     ) -> KeyGenState {
-        KeyGenState::new(self, shard_count, client_id)
+        KeyGenState::new(self, shard_count, client_id, None)
+    }
+
+    /// Creates a key generator state whose randomness is fully determined by
+    /// `seed` and `client_id`.
+    pub fn seeded_state(
+        self,
+        shard_count: usize,
+        client_id: ClientId,
+        seed: u64,
+    ) -> KeyGenState {
+        KeyGenState::new(self, shard_count, client_id, Some(seed))
     }
 }
 
@@ -55,10 +68,19 @@ pub struct KeyGenState {
     key_gen: KeyGen,
     client_id: ClientId,
     zipf: Option<ZipfDistribution>,
+    rng: StdRng,
+    // number of times each key was accessed (only tracked if the workload
+    // records accesses)
+    access_counts: HashMap<Key, usize>,
 }
 
 impl KeyGenState {
-    fn new(key_gen: KeyGen, shard_count: usize, client_id: ClientId) -> Self {
+    fn new(
+        key_gen: KeyGen,
+        shard_count: usize,
+        client_id: ClientId,
+        seed: Option<u64>,
+    ) -> Self {
         let zipf = match key_gen {
             KeyGen::ConflictPool { .. } => None,
             KeyGen::Zipf {
@@ -75,13 +97,51 @@ impl KeyGenState {
                 Some(zipf)
             }
         };
+        // if there's a seed, mix it with the client identifier so that
+        // different clients generate different (but reproducible) commands
+        let rng = match seed {
+            Some(seed) => {
+                StdRng::seed_from_u64(seed ^ client_id.rotate_left(32))
+            }
+            None => StdRng::from_entropy(),
+        };
         Self {
             key_gen,
             client_id,
             zipf,
+            rng,
+            access_counts: HashMap::new(),
         }
     }
 
+    /// Changes the conflict rate used by the `ConflictPool` key generator.
+    pub fn set_conflict_rate(&mut self, rate: usize) {
+        match &mut self.key_gen {
+            KeyGen::ConflictPool { conflict_rate, .. } => {
+                *conflict_rate = rate;
+            }
+            KeyGen::Zipf { .. } => {
+                panic!("can't set the conflict rate of a zipf key generator")
+            }
+        }
+    }
+
+    /// Returns the random number generator used by this state.
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Records an access to `key`.
+    pub fn record_access(&mut self, key: &Key) {
+        *self.access_counts.entry(key.clone()).or_default() += 1;
+    }
+
+    /// Returns the number of times each key was accessed. This is only
+    /// populated if the workload records accesses.
+    pub fn access_histogram(&self) -> HashMap<Key, usize> {
+        self.access_counts.clone()
+    }
+
     pub fn gen_cmd_key(&mut self) -> Key {
         match self.key_gen {
             KeyGen::ConflictPool {
@@ -93,15 +153,20 @@ impl KeyGenState {
     }
 
     /// Generate a command key based on the conflict rate provided.
-    fn gen_conflict_rate(&self, conflict_rate: usize, pool_size: usize) -> Key {
+    fn gen_conflict_rate(
+        &mut self,
+        conflict_rate: usize,
+        pool_size: usize,
+    ) -> Key {
         debug_assert!(conflict_rate <= 100);
         debug_assert!(pool_size >= 1);
 
         // check if we should generate a conflict
-        let should_conflict = true_if_random_is_less_than(conflict_rate);
+        let should_conflict =
+            true_if_random_is_less_than(&mut self.rng, conflict_rate);
         if should_conflict {
             // if it should conflict, select a random key from the pool
-            let random_key = rand::thread_rng().gen_range(0..pool_size);
+            let random_key = self.rng.gen_range(0..pool_size);
             format!("{}{}", CONFLICT_COLOR, random_key)
         } else {
             // avoid conflict with unique client key
@@ -114,15 +179,17 @@ impl KeyGenState {
         let zipf = self
             .zipf
             .expect("ZipfDistribution should already be initialized");
-        let mut rng = rand::thread_rng();
-        zipf.sample(&mut rng).to_string()
+        zipf.sample(&mut self.rng).to_string()
     }
 }
 
-pub fn true_if_random_is_less_than(percentage: usize) -> bool {
+pub fn true_if_random_is_less_than<R: Rng>(
+    rng: &mut R,
+    percentage: usize,
+) -> bool {
     match percentage {
         0 => false,
         100 => true,
-        _ => rand::thread_rng().gen_range(0..100) < percentage,
+        _ => rng.gen_range(0..100) < percentage,
     }
 }
@@ -17,12 +17,14 @@ pub use key_gen::KeyGen;
 pub use pending::Pending;
 pub use workload::Workload;
 
-use crate::command::Command;
+use crate::command::{Command, PhaseTimes};
 use crate::id::{ClientId, ProcessId, Rifl, RiflGen, ShardId};
 use crate::time::SysTime;
 use crate::HashMap;
 use crate::{info, trace};
 use key_gen::KeyGenState;
+use std::collections::VecDeque;
+use std::time::Duration;
 
 pub struct Client {
     /// id of this client
@@ -38,11 +40,19 @@ pub struct Client {
     key_gen_state: KeyGenState,
     /// map from pending command RIFL to its start time
     pending: Pending,
+    /// pending commands (and the shard they were submitted to), kept so that
+    /// they can be retried if aborted
+    in_flight: HashMap<Rifl, (ShardId, Command)>,
+    /// aborted commands waiting to be retried
+    retries: VecDeque<(ShardId, Command)>,
     /// mapping from
     data: ClientData,
     /// frequency of status messages; if set with Some(1), a status message
     /// will be shown after each command completes
     status_frequency: Option<usize>,
+    /// time after which a pending command is considered to have timed out,
+    /// if any
+    cmd_timeout: Option<Duration>,
 }
 
 impl Client {
@@ -53,9 +63,7 @@ impl Client {
         status_frequency: Option<usize>,
     ) -> Self {
         // create key gen state
-        let key_gen_state = workload
-            .key_gen()
-            .initial_state(workload.shard_count(), client_id);
+        let key_gen_state = workload.key_gen_state(client_id);
         // create client
         Self {
             client_id,
@@ -64,11 +72,43 @@ impl Client {
             workload,
             key_gen_state,
             pending: Pending::new(),
+            in_flight: HashMap::new(),
+            retries: VecDeque::new(),
             data: ClientData::new(),
             status_frequency,
+            cmd_timeout: None,
         }
     }
 
+    /// Sets the time after which a pending command is considered to have
+    /// timed out.
+    pub fn set_cmd_timeout<T: Into<Option<Duration>>>(&mut self, timeout: T) {
+        self.cmd_timeout = timeout.into();
+    }
+
+    /// Returns the time after which a pending command is considered to have
+    /// timed out, if any.
+    pub fn cmd_timeout(&self) -> Option<Duration> {
+        self.cmd_timeout
+    }
+
+    /// Checks whether the command with this `rifl` is still pending, i.e. it
+    /// has neither completed nor timed out.
+    pub fn is_pending(&self, rifl: &Rifl) -> bool {
+        self.pending.contains(rifl)
+    }
+
+    /// Returns the number of commands pending.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Checks whether the client can have one more command in flight, given
+    /// the workload's concurrency.
+    pub fn can_submit(&self) -> bool {
+        self.pending.len() < self.workload.concurrency()
+    }
+
     /// Returns the client identifier.
     pub fn id(&self) -> ClientId {
         self.client_id
@@ -87,34 +127,69 @@ impl Client {
             .expect("client should be connected to all shards")
     }
 
-    /// Generates the next command in this client's workload.
+    /// Generates the next command in this client's workload. Aborted commands
+    /// are retried (with the same rifl) before new commands are generated.
     pub fn cmd_send(
         &mut self,
         time: &dyn SysTime,
     ) -> Option<(ShardId, Command)> {
-        // generate next command in the workload if some process_id
-        self.workload
-            .next_cmd(&mut self.rifl_gen, &mut self.key_gen_state)
-            .map(|(target_shard, cmd)| {
-                // if a new command was generated, start it in pending
-                let rifl = cmd.rifl();
-                trace!(
-                    "c{}: new rifl pending {:?} | time = {}",
-                    self.client_id,
-                    rifl,
-                    time.micros()
-                );
-                self.pending.start(rifl, time);
-                (target_shard, cmd)
-            })
+        // retry the next aborted command, if any; otherwise, generate next
+        // command in the workload if some process_id
+        let next = match self.retries.pop_front() {
+            Some(retry) => Some(retry),
+            None => self.workload.next_cmd_at(
+                &mut self.rifl_gen,
+                &mut self.key_gen_state,
+                time,
+            ),
+        };
+        next.map(|(target_shard, cmd)| {
+            // if a command was generated, start it in pending
+            let rifl = cmd.rifl();
+            trace!(
+                "c{}: new rifl pending {:?} | time = {}",
+                self.client_id,
+                rifl,
+                time.micros()
+            );
+            self.pending.start(rifl, time);
+            self.in_flight.insert(rifl, (target_shard, cmd.clone()));
+            (target_shard, cmd)
+        })
     }
 
     /// Handle executed command and return a boolean indicating whether we have
     /// generated all commands and receive all the corresponding command
     /// results.
     pub fn cmd_recv(&mut self, rifl: Rifl, time: &dyn SysTime) {
+        self.cmd_recv_with_phases(rifl, None, time)
+    }
+
+    /// Same as `cmd_recv`, but if the times at which the command was committed
+    /// and executed are known, the command latency is also broken down into
+    /// time to commit and time to execute.
+    pub fn cmd_recv_with_phases(
+        &mut self,
+        rifl: Rifl,
+        phase_times: Option<PhaseTimes>,
+        time: &dyn SysTime,
+    ) {
         // end command in pending and save command latency
         let (latency, end_time) = self.pending.end(rifl, time);
+        self.in_flight.remove(&rifl);
+        if let Some(phase_times) = phase_times {
+            // compute the command start time (in milliseconds)
+            let start_time = end_time - latency.as_millis() as u64;
+            // clocks may not be synchronized, so saturate the durations
+            let time_to_commit = phase_times.commit.saturating_sub(start_time);
+            let time_to_execute =
+                phase_times.execute.saturating_sub(phase_times.commit);
+            self.data.record_phases(
+                Duration::from_millis(time_to_commit),
+                Duration::from_millis(time_to_execute),
+                end_time,
+            );
+        }
         trace!(
             "c{}: rifl {:?} ended after {} micros at {}",
             self.client_id,
@@ -136,15 +211,70 @@ impl Client {
         }
     }
 
+    /// Handle a command that timed out: the command is no longer pending, and
+    /// it's accounted for as timed out (with no latency registered). If the
+    /// command had already completed, this is a noop.
+    pub fn cmd_timed_out(&mut self, rifl: Rifl, time: &dyn SysTime) {
+        if self.pending.cancel(&rifl) {
+            self.in_flight.remove(&rifl);
+            trace!(
+                "c{}: rifl {:?} timed out at {}",
+                self.client_id,
+                rifl,
+                time.millis()
+            );
+            self.data.record_timed_out();
+        }
+    }
+
+    /// Handle a command that was rejected by an overloaded process: the command
+    /// is no longer pending, and it's accounted for as rejected (with no
+    /// latency registered). If the command had already completed, this is a
+    /// noop.
+    pub fn cmd_rejected(&mut self, rifl: Rifl, time: &dyn SysTime) {
+        if self.pending.cancel(&rifl) {
+            self.in_flight.remove(&rifl);
+            trace!(
+                "c{}: rifl {:?} rejected at {}",
+                self.client_id,
+                rifl,
+                time.millis()
+            );
+            self.data.record_rejected();
+        }
+    }
+
+    /// Handle a command that was aborted: the command is no longer pending,
+    /// and, since it did not complete, no latency is registered and the same
+    /// command is retried by the next `cmd_send`. If the command had already
+    /// completed, this is a noop.
+    pub fn cmd_aborted(&mut self, rifl: Rifl, time: &dyn SysTime) {
+        if self.pending.cancel(&rifl) {
+            trace!(
+                "c{}: rifl {:?} aborted at {}",
+                self.client_id,
+                rifl,
+                time.millis()
+            );
+            let retry = self
+                .in_flight
+                .remove(&rifl)
+                .expect("pending commands should be in flight");
+            self.retries.push_back(retry);
+        }
+    }
+
+    /// Checks whether there are no more commands to submit, i.e. the workload
+    /// is finished and there are no aborted commands to retry.
     pub fn workload_finished(&self) -> bool {
-        self.workload.finished()
+        self.workload.finished() && self.retries.is_empty()
     }
 
     pub fn finished(&self) -> bool {
         // we're done once:
-        // - the workload is finished and
+        // - the workload is finished (and there's nothing to retry) and
         // - pending is empty
-        self.workload.finished() && self.pending.is_empty()
+        self.workload_finished() && self.pending.is_empty()
     }
 
     pub fn data(&self) -> &ClientData {
@@ -300,4 +430,86 @@ mod tests {
         throughput.sort();
         assert_eq!(throughput, vec![(10, 1), (15, 1)],);
     }
+
+    #[test]
+    fn client_timeout() {
+        // there's a single shard
+        let shard_id = 0;
+
+        // client
+        let commands_per_client = 2;
+        let mut client = gen_client(commands_per_client);
+        client.connect(HashMap::from_iter(vec![(shard_id, 1)]));
+        client.set_cmd_timeout(Duration::from_millis(5));
+        assert_eq!(client.cmd_timeout(), Some(Duration::from_millis(5)));
+
+        // create system time
+        let mut time = SimTime::new();
+
+        // start client at time 0
+        let (_, cmd) = client
+            .cmd_send(&time)
+            .expect("there should a first operation");
+        assert!(client.is_pending(&cmd.rifl()));
+
+        // the first command times out at time 5
+        time.add_millis(5);
+        client.cmd_timed_out(cmd.rifl(), &time);
+        assert!(!client.is_pending(&cmd.rifl()));
+        let (_, cmd) = client
+            .cmd_send(&time)
+            .expect("there should a second operation");
+
+        // the second command completes at time 15, so its timeout is a noop
+        time.add_millis(10);
+        client.cmd_recv(cmd.rifl(), &time);
+        client.cmd_timed_out(cmd.rifl(), &time);
+        assert!(client.cmd_send(&time).is_none());
+        assert!(client.finished());
+
+        // only the second command has its latency registered
+        let latency: Vec<_> = client.data().latency_data().collect();
+        assert_eq!(latency, vec![Duration::from_millis(10)]);
+        assert_eq!(client.data().timed_out(), 1);
+    }
+
+    #[test]
+    fn client_abort() {
+        // there's a single shard
+        let shard_id = 0;
+
+        // client
+        let commands_per_client = 1;
+        let mut client = gen_client(commands_per_client);
+        client.connect(HashMap::from_iter(vec![(shard_id, 1)]));
+
+        // create system time
+        let mut time = SimTime::new();
+
+        // the command is aborted at time 5
+        let (_, cmd) = client
+            .cmd_send(&time)
+            .expect("there should a first operation");
+        time.add_millis(5);
+        client.cmd_aborted(cmd.rifl(), &time);
+        assert!(!client.is_pending(&cmd.rifl()));
+        assert!(!client.workload_finished());
+        assert!(!client.finished());
+
+        // so the same command is issued again, completing at time 15
+        let (_, retry) = client
+            .cmd_send(&time)
+            .expect("the aborted operation should be issued again");
+        assert_eq!(retry, cmd);
+        assert!(client.is_pending(&cmd.rifl()));
+        assert_eq!(client.issued_commands(), 1);
+        time.add_millis(10);
+        client.cmd_recv(cmd.rifl(), &time);
+        assert!(client.cmd_send(&time).is_none());
+        assert!(client.finished());
+
+        // only the retry has its latency registered
+        let latency: Vec<_> = client.data().latency_data().collect();
+        assert_eq!(latency, vec![Duration::from_millis(10)]);
+    }
 }
@@ -44,10 +44,25 @@ impl Pending {
         (latency, end_time)
     }
 
+    /// Checks whether a command is pending given its rifl.
+    pub fn contains(&self, rifl: &Rifl) -> bool {
+        self.pending.contains_key(rifl)
+    }
+
+    /// Cancels a command given its rifl, returning whether it was pending.
+    pub fn cancel(&mut self, rifl: &Rifl) -> bool {
+        self.pending.remove(rifl).is_some()
+    }
+
     /// Checks whether pending is empty.
     pub fn is_empty(&self) -> bool {
         self.pending.is_empty()
     }
+
+    /// Returns the number of pending commands.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
 }
 
 #[cfg(test)]
@@ -1,13 +1,14 @@
 use crate::client::key_gen::{KeyGen, KeyGenState};
 use crate::command::Command;
-use crate::id::{RiflGen, ShardId};
-use crate::kvs::{KVOp, Key, Value};
+use crate::id::{ClientId, RiflGen, ShardId};
+use crate::kvs::{KVOp, Key, Value, RECORDS_PER_KEY};
+use crate::time::SysTime;
 use crate::trace;
 use crate::HashMap;
 use rand::distributions::{Distribution, WeightedIndex};
-use rand::thread_rng;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 const MAX_NUMBER: u16 = u16::MAX;
 const MIN_NUMBER: u16 = u16::MIN; 
@@ -15,6 +16,58 @@ const MIN_NUMBER: u16 = u16::MIN;
 // Put, Add, Subtract, Delete
 const WEIGHTED_KVO_WRITES: [u8;4] = [0,4,4,0];
 
+// prefix of the keys accessed by commands in a dependency chain
+const CHAIN_KEY_PREFIX: &str = "CHAIN";
+
+/// Scripted sequence of operations that stresses concurrent `Subtract`s on the
+/// same key: the key is first funded with an `AddReturningOld` of
+/// `initial_balance` (which, unlike `Add`, also funds a missing key), and then
+/// drained by `Subtract`s of `subtract_amount`, which sum to exactly
+/// `initial_balance`. Once drained, the key is funded again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubtractContention {
+    pub initial_balance: Value,
+    pub subtract_amount: Value,
+}
+
+impl SubtractContention {
+    /// Returns the operation at position `index` (starting at 0) of the
+    /// scripted sequence.
+    fn op(&self, index: usize) -> KVOp {
+        // each cycle has one `AddReturningOld` followed by the `Subtract`s
+        // that drain it
+        let subtracts = (self.initial_balance / self.subtract_amount) as usize;
+        if index % (subtracts + 1) == 0 {
+            KVOp::AddReturningOld(self.initial_balance)
+        } else {
+            KVOp::Subtract(self.subtract_amount)
+        }
+    }
+}
+
+/// Conflict rate that changes over time: it starts at `start_rate` and, after
+/// each `interval` (since the first command), moves `step` towards `end_rate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictRateRamp {
+    pub start_rate: usize,
+    pub end_rate: usize,
+    pub step: usize,
+    pub interval: Duration,
+}
+
+impl ConflictRateRamp {
+    /// Returns the conflict rate `elapsed` after the first command.
+    fn rate(&self, elapsed: Duration) -> usize {
+        let intervals = elapsed.as_micros() / self.interval.as_micros();
+        let delta = (intervals as usize).saturating_mul(self.step);
+        if self.start_rate <= self.end_rate {
+            std::cmp::min(self.start_rate.saturating_add(delta), self.end_rate)
+        } else {
+            std::cmp::max(self.start_rate.saturating_sub(delta), self.end_rate)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Workload {
     /// number of shards
@@ -31,6 +84,42 @@ pub struct Workload {
     payload_size: usize,
     /// number of commands already issued in this workload
     command_count: usize,
+    /// number of commands issued that accessed a conflict key
+    #[serde(default)]
+    conflict_count: usize,
+    /// seed used to make the commands generated reproducible
+    #[serde(default)]
+    seed: Option<u64>,
+    /// if set, commands follow a scripted sequence of `AddReturningOld`s and
+    /// `Subtract`s
+    #[serde(default)]
+    subtract_contention: Option<SubtractContention>,
+    /// if enabled, keys are assigned to shards using consistent hashing
+    #[serde(default)]
+    consistent_hashing: bool,
+    /// if set, the conflict rate changes over time
+    #[serde(default)]
+    conflict_rate_ramp: Option<ConflictRateRamp>,
+    /// time (in micros) at which the first command was generated (only
+    /// tracked if there's a conflict rate ramp)
+    #[serde(default)]
+    start_time_micros: Option<u64>,
+    /// if enabled, per-key access counts are recorded in the client's
+    /// `KeyGenState`
+    #[serde(default)]
+    record_accesses: bool,
+    /// if set, commands are grouped in chains of this length, where all the
+    /// commands in a chain access the same key
+    #[serde(default)]
+    dependency_chain: Option<usize>,
+    /// maximum number of commands each client keeps in flight; if not set,
+    /// each client has a single command in flight
+    #[serde(default)]
+    concurrency: Option<usize>,
+    /// if set, each `Put` carries this many values (one per record of the
+    /// key)
+    #[serde(default)]
+    values_per_put: Option<usize>,
 }
 
 impl Workload {
@@ -69,6 +158,16 @@ impl Workload {
             read_only_percentage,
             payload_size,
             command_count: 0,
+            conflict_count: 0,
+            seed: None,
+            subtract_contention: None,
+            consistent_hashing: false,
+            conflict_rate_ramp: None,
+            start_time_micros: None,
+            record_accesses: false,
+            dependency_chain: None,
+            concurrency: None,
+            values_per_put: None,
         }
     }
 
@@ -77,6 +176,19 @@ impl Workload {
         self.shard_count as usize
     }
 
+    /// Checks whether keys are assigned to shards using consistent hashing.
+    pub fn consistent_hashing(&self) -> bool {
+        self.consistent_hashing
+    }
+
+    /// Changes the value of `consistent_hashing`. If enabled, keys are
+    /// assigned to shards using consistent hashing, and thus changing the
+    /// number of shards only moves a fraction of the keys; otherwise, shards
+    /// are assigned by hashing keys modulo the number of shards.
+    pub fn set_consistent_hashing(&mut self, consistent_hashing: bool) {
+        self.consistent_hashing = consistent_hashing;
+    }
+
     /// Returns the key generator.
     pub fn key_gen(&self) -> KeyGen {
         self.key_gen
@@ -87,6 +199,17 @@ impl Workload {
         self.commands_per_client
     }
 
+    /// Returns a copy of this workload with `commands_per_client` multiplied
+    /// by `factor`. The copy starts fresh, i.e. with no commands issued.
+    pub fn scale(&self, factor: usize) -> Workload {
+        let mut workload = *self;
+        workload.commands_per_client *= factor;
+        workload.command_count = 0;
+        workload.conflict_count = 0;
+        workload.start_time_micros = None;
+        workload
+    }
+
     /// Returns the number of keys accessed by commands generated by this
     /// workload.
     pub fn keys_per_command(&self) -> usize {
@@ -109,6 +232,160 @@ impl Workload {
         self.read_only_percentage = read_only_percentage;
     }
 
+    /// Makes this workload deterministic: clients with the same identifier
+    /// will generate the same sequence of commands given the same `seed`.
+    pub fn deterministic(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Returns the seed of this workload, if deterministic.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Makes commands follow the scripted sequence of operations described in
+    /// `SubtractContention`, instead of random ones. This is meant to be used
+    /// with a key generator that always generates the same key (e.g. a
+    /// `ConflictPool` with a conflict rate of 100), so that all clients
+    /// contend on the same key.
+    pub fn set_subtract_contention(
+        &mut self,
+        initial_balance: Value,
+        subtract_amount: Value,
+    ) {
+        assert_eq!(
+            self.keys_per_command, 1,
+            "subtract contention requires a single key per command"
+        );
+        assert!(subtract_amount > 0, "the subtract amount must be positive");
+        assert_eq!(
+            initial_balance % subtract_amount,
+            0,
+            "the initial balance must be a multiple of the subtract amount"
+        );
+        self.subtract_contention = Some(SubtractContention {
+            initial_balance,
+            subtract_amount,
+        });
+    }
+
+    /// Sets a conflict rate ramp, so that the conflict rate of the
+    /// `ConflictPool` key generator changes over time. This requires commands
+    /// to be generated with `next_cmd_at`.
+    pub fn set_conflict_rate_ramp(
+        &mut self,
+        start_rate: usize,
+        end_rate: usize,
+        step: usize,
+        interval: Duration,
+    ) {
+        assert!(
+            matches!(self.key_gen, KeyGen::ConflictPool { .. }),
+            "a conflict rate ramp requires the conflict pool key generator"
+        );
+        assert!(
+            start_rate <= 100 && end_rate <= 100,
+            "the conflict rate must be less or equal to 100"
+        );
+        assert!(
+            self.keys_per_command == 1 || (start_rate < 100 && end_rate < 100),
+            "can't generate more than one key when the conflict_rate is 100"
+        );
+        assert!(step > 0, "the ramp step must be positive");
+        assert!(
+            interval > Duration::from_secs(0),
+            "the ramp interval must be positive"
+        );
+        self.conflict_rate_ramp = Some(ConflictRateRamp {
+            start_rate,
+            end_rate,
+            step,
+            interval,
+        });
+    }
+
+    /// Enables (or disables) the recording of per-key access counts. These
+    /// can then be retrieved with `KeyGenState::access_histogram`.
+    pub fn set_record_accesses(&mut self, record_accesses: bool) {
+        self.record_accesses = record_accesses;
+    }
+
+    /// Returns whether per-key access counts are being recorded.
+    pub fn record_accesses(&self) -> bool {
+        self.record_accesses
+    }
+
+    /// Makes commands form chains of `length` mutually-conflicting commands:
+    /// the commands in each chain write on the same key, which is not
+    /// accessed by any other chain (of the same or of other clients). This
+    /// allows building dependency structures of a known depth.
+    pub fn set_dependency_chain(&mut self, length: usize) {
+        assert_eq!(
+            self.keys_per_command, 1,
+            "a dependency chain requires a single key per command"
+        );
+        assert!(length > 0, "the chain length must be positive");
+        self.dependency_chain = Some(length);
+    }
+
+    /// Returns the length of the dependency chains, if any.
+    pub fn dependency_chain(&self) -> Option<usize> {
+        self.dependency_chain
+    }
+
+    /// Sets the maximum number of commands each client keeps in flight: a
+    /// client submits a new command whenever it has fewer than `concurrency`
+    /// commands pending. Open-loop clients (which submit a new command at a
+    /// fixed interval) ignore it.
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        assert!(concurrency > 0, "the concurrency must be positive");
+        self.concurrency = Some(concurrency);
+    }
+
+    /// Returns the maximum number of commands each client keeps in flight.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(1)
+    }
+
+    /// Makes each `Put` carry `count` values, one per record of the key (see
+    /// `KVOp::PutRecords`). Since keys have at most `RECORDS_PER_KEY` records,
+    /// `count` can't be higher than that.
+    pub fn set_values_per_put(&mut self, count: usize) {
+        assert!(count > 0, "there should be at least one value per put");
+        assert!(
+            count <= RECORDS_PER_KEY,
+            "there should be at most {} values per put",
+            RECORDS_PER_KEY
+        );
+        self.values_per_put = Some(count);
+    }
+
+    /// Returns the number of values carried by each `Put`, if set.
+    pub fn values_per_put(&self) -> Option<usize> {
+        self.values_per_put
+    }
+
+    /// Returns the conflict rate ramp, if any.
+    pub fn conflict_rate_ramp(&self) -> Option<ConflictRateRamp> {
+        self.conflict_rate_ramp
+    }
+
+    /// Returns the scripted sequence of operations, if any.
+    pub fn subtract_contention(&self) -> Option<SubtractContention> {
+        self.subtract_contention
+    }
+
+    /// Creates the key generator state to be used by client `client_id`.
+    pub fn key_gen_state(&self, client_id: ClientId) -> KeyGenState {
+        let shard_count = self.shard_count();
+        match self.seed {
+            Some(seed) => {
+                self.key_gen.seeded_state(shard_count, client_id, seed)
+            }
+            None => self.key_gen.initial_state(shard_count, client_id),
+        }
+    }
+
     /// Returns the payload size of the commands to be generated by this
     /// workload.
     pub fn payload_size(&self) -> usize {
@@ -133,11 +410,38 @@ impl Workload {
         }
     }
 
+    /// Generate the next command at time `time`. If there's a conflict rate
+    /// ramp, the command is generated with the conflict rate at that time.
+    pub fn next_cmd_at(
+        &mut self,
+        rifl_gen: &mut RiflGen,
+        key_gen_state: &mut KeyGenState,
+        time: &dyn SysTime,
+    ) -> Option<(ShardId, Command)> {
+        if let Some(ramp) = self.conflict_rate_ramp {
+            let now = time.micros();
+            let start = *self.start_time_micros.get_or_insert(now);
+            let elapsed = Duration::from_micros(now.saturating_sub(start));
+            key_gen_state.set_conflict_rate(ramp.rate(elapsed));
+        }
+        self.next_cmd(rifl_gen, key_gen_state)
+    }
+
     /// Returns the number of commands already issued.
     pub fn issued_commands(&self) -> usize {
         self.command_count
     }
 
+    /// Returns the fraction (between 0 and 1) of the commands issued so far
+    /// that accessed a conflict key of the `ConflictPool` key generator.
+    pub fn realized_conflict_rate(&self) -> f64 {
+        if self.command_count == 0 {
+            0.0
+        } else {
+            self.conflict_count as f64 / self.command_count as f64
+        }
+    }
+
     /// Returns a boolean indicating whether the workload has finished, i.e. all
     /// commands have been issued.
     pub fn finished(&self) -> bool {
@@ -159,28 +463,61 @@ impl Workload {
         // generate unique keys:
         // - since we store them in Vec, this ensures that the target shard will
         // be the shard of the first key generated
-        let keys = self.gen_unique_keys(key_gen_state);
-        // check if the command should be read-only
-        let read_only = super::key_gen::true_if_random_is_less_than(
-            self.read_only_percentage,
-        );
+        // - if there's a dependency chain, the key is given by the client and
+        // the chain this command belongs to (so that chains of different
+        // clients don't interleave)
+        let keys = if let Some(length) = self.dependency_chain {
+            let chain = (self.command_count - 1) / length;
+            vec![format!("{}_{}{}", rifl.source(), CHAIN_KEY_PREFIX, chain)]
+        } else {
+            self.gen_unique_keys(key_gen_state)
+        };
+        // track whether the command accessed a conflict key
+        if keys
+            .iter()
+            .any(|key| key.starts_with(super::key_gen::CONFLICT_COLOR))
+        {
+            self.conflict_count += 1;
+        }
+        // record key accesses, if enabled
+        if self.record_accesses {
+            keys.iter().for_each(|key| key_gen_state.record_access(key));
+        }
+        // check if the command should be read-only (commands in a dependency
+        // chain never are, as otherwise they wouldn't conflict)
+        let read_only = self.dependency_chain.is_none()
+            && super::key_gen::true_if_random_is_less_than(
+                key_gen_state.rng(),
+                self.read_only_percentage,
+            );
         let mut target_shard = None;
 
         for key in keys {
             // compute op
-            let op = if read_only {
+            let op = if let Some(contention) = self.subtract_contention {
+                // if scripted, the op is given by the position of this command
+                // in the script
+                contention.op(self.command_count - 1)
+            } else if read_only {
                 // if read-only, the op is a `Get`
                 KVOp::Get
             } else {
                 // if not read-only, the op is a `Put`, `Add`, `Subtract` or ``Delete`:
                 // - generate payload for op
-                let value = self.gen_cmd_value();
+                let rng = key_gen_state.rng();
+                let value = self.gen_cmd_value(rng);
 
-                let mut rng = thread_rng();
                 let dist = WeightedIndex::new(&WEIGHTED_KVO_WRITES).unwrap();
 
-                match dist.sample(&mut rng) {
-                    0 => KVOp::Put(value),
+                match dist.sample(rng) {
+                    0 => match self.values_per_put {
+                        Some(count) => KVOp::PutRecords(
+                            (0..count)
+                                .map(|_| self.gen_cmd_value(rng))
+                                .collect(),
+                        ),
+                        None => KVOp::Put(value),
+                    },
                     1 => KVOp::Add(value),
                     2 => KVOp::Subtract(value),
                     3 => KVOp::Delete,
@@ -198,8 +535,12 @@ impl Workload {
         let target_shard =
             target_shard.expect("there should be a target shard");
 
-        // create command
-        (target_shard, Command::new(rifl, ops))
+        // create command; only commands that write carry a payload
+        let mut cmd = Command::new(rifl, ops);
+        if !read_only {
+            cmd.set_payload_size(self.payload_size);
+        }
+        (target_shard, cmd)
     }
 
     fn gen_unique_keys(&self, key_gen_state: &mut KeyGenState) -> Vec<Key> {
@@ -213,27 +554,23 @@ impl Workload {
         keys
     }
 
-    /// Generate a command payload with the payload size provided.
-    fn gen_cmd_value(&self) -> Value {
-        let mut rng = rand::thread_rng();
-
+    /// Generate a command payload. The payload is drawn from the RNG in the
+    /// client's `KeyGenState`, and thus it's reproducible if the workload is
+    /// deterministic.
+    fn gen_cmd_value<R: Rng>(&self, rng: &mut R) -> Value {
         rng.gen_range(MIN_NUMBER..=MAX_NUMBER)
-        // let mut rng = rand::thread_rng();
-        // iter::repeat(())
-        //     .map(|_| rng.sample(Alphanumeric) as char)
-        //     .take(self.payload_size)
-        //     .collect()
     }
 
     /// Computes which shard the key belongs to.
     fn shard_id(&self, key: &Key) -> ShardId {
-        crate::util::key_hash(key) % self.shard_count
+        crate::util::key_shard(key, self.shard_count, self.consistent_hashing)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kvs::KVStore;
 
     const POOL_SIZE: usize = 1;
     // since the pool size is 1, the conflict color must be the following
@@ -293,6 +630,18 @@ mod tests {
         assert_eq!(command.keys(target_shard).collect::<Vec<_>>(), vec!["1"]);
     }
 
+    #[test]
+    #[should_panic]
+    fn too_many_values_per_put() {
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 100,
+            pool_size: 1,
+        };
+        let mut workload = Workload::new(1, key_gen, 1, 1, 0);
+        // keys don't have enough records for these values
+        workload.set_values_per_put(RECORDS_PER_KEY + 1);
+    }
+
     #[test]
     fn next_cmd() {
         // create rilf gen
@@ -418,6 +767,490 @@ mod tests {
         }
     }
 
+    #[test]
+    fn realized_conflict_rate() {
+        // create rilf gen
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+
+        // general config
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let commands_per_client = 100000;
+        let payload_size = 0;
+        let conflict_rate = 20;
+
+        // create workload
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate,
+            pool_size: POOL_SIZE,
+        };
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        let mut key_gen_state =
+            key_gen.initial_state(workload.shard_count(), client_id);
+
+        // nothing has been issued yet
+        assert_eq!(workload.realized_conflict_rate(), 0.0);
+
+        while workload
+            .next_cmd(&mut rifl_gen, &mut key_gen_state)
+            .is_some()
+        {}
+
+        // the realized conflict rate should converge to the configured one
+        let percentage = workload.realized_conflict_rate() * 100.0;
+        assert_eq!(percentage.round() as usize, conflict_rate);
+    }
+
+    #[test]
+    fn conflict_rate_ramp() {
+        use crate::client::key_gen::CONFLICT_COLOR;
+        use crate::time::SimTime;
+
+        // the rate moves by `step` after each interval, stopping at the end
+        let ramp = ConflictRateRamp {
+            start_rate: 0,
+            end_rate: 100,
+            step: 30,
+            interval: Duration::from_secs(1),
+        };
+        let rates: Vec<_> = (0..6)
+            .map(|secs| ramp.rate(Duration::from_millis(secs * 1000 + 500)))
+            .collect();
+        assert_eq!(rates, vec![0, 30, 60, 90, 100, 100]);
+        let down = ConflictRateRamp {
+            start_rate: 50,
+            end_rate: 10,
+            ..ramp
+        };
+        assert_eq!(down.rate(Duration::from_secs(1)), 20);
+        assert_eq!(down.rate(Duration::from_secs(2)), 10);
+
+        // create workload whose conflict rate goes from 0 to 100 after 1s
+        let client_id = 1;
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let commands_per_client = 200;
+        let payload_size = 0;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 0,
+            pool_size: 1,
+        };
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        workload.set_conflict_rate_ramp(0, 100, 100, Duration::from_secs(1));
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen_state(client_id);
+
+        // generates `count` commands, `step_ms` apart, and returns how many
+        // accessed a conflict key
+        let mut time = SimTime::new();
+        let mut gen_conflicts = |count: usize, step_ms: u64| {
+            (0..count)
+                .filter(|_| {
+                    let (shard_id, cmd) = workload
+                        .next_cmd_at(&mut rifl_gen, &mut key_gen_state, &time)
+                        .expect("there should be a next command");
+                    time.add_millis(step_ms);
+                    let conflict = cmd
+                        .iter(shard_id)
+                        .any(|(key, _)| key.starts_with(CONFLICT_COLOR));
+                    conflict
+                })
+                .count()
+        };
+
+        // in the first second, there are no conflicts
+        assert_eq!(gen_conflicts(100, 10), 0);
+        // after that, all commands conflict
+        assert_eq!(gen_conflicts(100, 10), 100);
+    }
+
+    #[test]
+    fn scale() {
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let commands_per_client = 10;
+        let payload_size = 0;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 50,
+            pool_size: POOL_SIZE,
+        };
+        let client_id = 1;
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+
+        // issue some commands in the base workload
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen_state(client_id);
+        for _ in 0..5 {
+            workload.next_cmd(&mut rifl_gen, &mut key_gen_state);
+        }
+        assert_eq!(workload.issued_commands(), 5);
+
+        // the scaled workload has 3 times more commands and starts fresh
+        let mut scaled = workload.scale(3);
+        assert_eq!(scaled.commands_per_client(), 30);
+        assert_eq!(scaled.issued_commands(), 0);
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = scaled.key_gen_state(client_id);
+        while scaled.next_cmd(&mut rifl_gen, &mut key_gen_state).is_some() {}
+        assert_eq!(scaled.issued_commands(), 30);
+
+        // the base workload is not affected
+        assert_eq!(workload.commands_per_client(), 10);
+        assert_eq!(workload.issued_commands(), 5);
+    }
+
+    #[test]
+    fn access_histogram() {
+        // general config
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let commands_per_client = 10_000;
+        let payload_size = 0;
+        let conflict_rate = 80;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate,
+            pool_size: POOL_SIZE,
+        };
+        let client_id = 1;
+
+        // by default, accesses are not recorded
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        assert!(!workload.record_accesses());
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen_state(client_id);
+        while workload
+            .next_cmd(&mut rifl_gen, &mut key_gen_state)
+            .is_some()
+        {}
+        assert!(key_gen_state.access_histogram().is_empty());
+
+        // enable access recording
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        workload.set_record_accesses(true);
+        workload.deterministic(42);
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen_state(client_id);
+        while workload
+            .next_cmd(&mut rifl_gen, &mut key_gen_state)
+            .is_some()
+        {}
+        let histogram = key_gen_state.access_histogram();
+
+        // only the conflict key and the client key were accessed
+        assert_eq!(histogram.len(), 2);
+        let total: usize = histogram.values().sum();
+        assert_eq!(total, commands_per_client * keys_per_command);
+
+        // the conflict key dominates the histogram
+        let conflicts = histogram
+            .get(CONFLICT_COLOR)
+            .expect("the conflict key should have been accessed");
+        let most_accessed = histogram
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(key, _)| key.as_str());
+        assert_eq!(most_accessed, Some(CONFLICT_COLOR));
+
+        // and its share is given by the conflict rate
+        let percentage = (*conflicts * 100) as f64 / total as f64;
+        assert_eq!(percentage.round() as usize, conflict_rate);
+    }
+
+    #[test]
+    fn deterministic() {
+        // general config
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let commands_per_client = 1000;
+        let payload_size = 0;
+        let key_gen = KeyGen::Zipf {
+            coefficient: 1.0,
+            total_keys_per_shard: 1_000,
+        };
+
+        // generates all commands of a workload with some seed
+        let gen_cmds = |client_id: ClientId, seed: u64| {
+            let mut workload = Workload::new(
+                shard_count,
+                key_gen,
+                keys_per_command,
+                commands_per_client,
+                payload_size,
+            );
+            workload.set_read_only_percentage(50);
+            workload.deterministic(seed);
+            let mut rifl_gen = RiflGen::new(client_id);
+            let mut key_gen_state = workload.key_gen_state(client_id);
+            let mut cmds = Vec::new();
+            while let Some((_, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                cmds.push(
+                    bincode::serialize(&cmd)
+                        .expect("command should be serializable"),
+                );
+            }
+            cmds
+        };
+
+        // same seed and same client: same commands
+        assert_eq!(gen_cmds(1, 42), gen_cmds(1, 42));
+        // different seeds: different commands
+        assert_ne!(gen_cmds(1, 42), gen_cmds(1, 43));
+    }
+
+    #[test]
+    fn deterministic_payloads() {
+        // general config
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let commands_per_client = 100;
+        let payload_size = 0;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 100,
+            pool_size: 1,
+        };
+
+        // generates the payloads of all commands of a workload with some seed
+        let gen_payloads = |client_id: ClientId, seed: u64| {
+            let mut workload = Workload::new(
+                shard_count,
+                key_gen,
+                keys_per_command,
+                commands_per_client,
+                payload_size,
+            );
+            workload.deterministic(seed);
+            let mut rifl_gen = RiflGen::new(client_id);
+            let mut key_gen_state = workload.key_gen_state(client_id);
+            let mut payloads = Vec::new();
+            while let Some((shard_id, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                let payload: Vec<_> = cmd
+                    .iter(shard_id)
+                    .flat_map(|(_, ops)| ops.iter())
+                    .map(|op| match op {
+                        KVOp::Put(value)
+                        | KVOp::Add(value)
+                        | KVOp::Subtract(value) => Some(*value),
+                        _ => None,
+                    })
+                    .collect();
+                payloads.push(payload);
+            }
+            payloads
+        };
+
+        // same seed and same client: same payload for each command index
+        assert_eq!(gen_payloads(1, 42), gen_payloads(1, 42));
+        // different clients: different payloads
+        assert_ne!(gen_payloads(1, 42), gen_payloads(2, 42));
+    }
+
+    #[test]
+    fn dependency_chain() {
+        // general config
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let payload_size = 0;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 0,
+            pool_size: POOL_SIZE,
+        };
+        let chain_length = 4;
+        let commands_per_client = 3 * chain_length;
+
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        workload.set_read_only_percentage(100);
+        workload.set_dependency_chain(chain_length);
+        assert_eq!(workload.dependency_chain(), Some(chain_length));
+
+        for client_id in 1..=2 {
+            let mut workload = workload;
+            let mut rifl_gen = RiflGen::new(client_id);
+            let mut key_gen_state = workload.key_gen_state(client_id);
+            let mut keys = Vec::new();
+            while let Some((shard_id, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                // commands in a chain are never read-only
+                assert!(!cmd.read_only());
+                let cmd_keys: Vec<_> = cmd.keys(shard_id).cloned().collect();
+                assert_eq!(cmd_keys.len(), 1);
+                keys.extend(cmd_keys);
+            }
+
+            // each chain (of each client) accesses its own key
+            let expected: Vec<_> = (0..3)
+                .flat_map(|chain| {
+                    let key = format!("{}_CHAIN{}", client_id, chain);
+                    std::iter::repeat(key).take(chain_length)
+                })
+                .collect();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn subtract_contention() {
+        // general config
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let payload_size = 0;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 100,
+            pool_size: POOL_SIZE,
+        };
+        let initial_balance = 100;
+        let subtract_amount = 10;
+        // fund once and then drain the balance
+        let commands_per_client = 1 + (initial_balance / subtract_amount);
+
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client as usize,
+            payload_size,
+        );
+        workload.set_subtract_contention(initial_balance, subtract_amount);
+
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen_state(client_id);
+        let mut ops = Vec::new();
+        while let Some((shard_id, cmd)) =
+            workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+        {
+            let mut cmd_ops: Vec<_> = cmd
+                .into_iter(shard_id)
+                .map(|(key, ops)| (key, ops.as_ref().clone()))
+                .collect();
+            assert_eq!(cmd_ops.len(), 1);
+            let (key, mut key_ops) = cmd_ops.pop().unwrap();
+            assert_eq!(key, CONFLICT_COLOR);
+            assert_eq!(key_ops.len(), 1);
+            ops.push(key_ops.pop().unwrap());
+        }
+
+        // the key is first funded, and then subtracts sum to the balance
+        assert_eq!(ops[0], KVOp::AddReturningOld(initial_balance));
+        let subtracted: Value = ops[1..]
+            .iter()
+            .map(|op| match op {
+                KVOp::Subtract(value) => *value,
+                op => panic!("unexpected op {:?}", op),
+            })
+            .sum();
+        assert_eq!(subtracted, initial_balance);
+
+        // executed in order (starting with a missing key), all subtracts
+        // succeed
+        let mut store = KVStore::new(false);
+        let key = String::from(CONFLICT_COLOR);
+        let mut balance = None;
+        for op in ops {
+            let expected = match op {
+                KVOp::AddReturningOld(value) => {
+                    // the funding returns the balance before it
+                    let old = balance;
+                    balance = Some(old.unwrap_or(0) + value);
+                    old
+                }
+                KVOp::Subtract(value) => {
+                    balance = balance.map(|balance| balance - value);
+                    balance
+                }
+                _ => unreachable!(),
+            };
+            assert_eq!(store.test_execute(&key, op), expected);
+        }
+        assert_eq!(balance, Some(0));
+    }
+
+    #[test]
+    fn consistent_hashing() {
+        // general config
+        let keys_per_command = 1;
+        let commands_per_client = 100;
+        let payload_size = 0;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 0,
+            pool_size: POOL_SIZE,
+        };
+        let workload = |shard_count: usize, consistent_hashing: bool| {
+            let mut workload = Workload::new(
+                shard_count,
+                key_gen,
+                keys_per_command,
+                commands_per_client,
+                payload_size,
+            );
+            workload.set_consistent_hashing(consistent_hashing);
+            workload
+        };
+
+        // computes how many keys change shard when going from 4 to 5 shards
+        let key_count = 10_000;
+        let moved = |consistent_hashing: bool| {
+            let before = workload(4, consistent_hashing);
+            let after = workload(5, consistent_hashing);
+            (0..key_count)
+                .map(|i| format!("key{}", i))
+                .filter(|key| {
+                    let shard = after.shard_id(key);
+                    assert!(shard < 5);
+                    before.shard_id(key) != shard
+                })
+                .count()
+        };
+
+        // with consistent hashing, only around 1/5 of the keys should move,
+        // while with the modulo scheme, around 4/5 of them move
+        let consistent_moved = moved(true);
+        let modulo_moved = moved(false);
+        assert!(consistent_moved < key_count * 25 / 100);
+        assert!(modulo_moved > key_count * 70 / 100);
+    }
+
     #[test]
     fn two_shards() {
         // in order for this test to pass, `check_two_shards` should generate a
@@ -1,6 +1,7 @@
 use crate::executor::ExecutorResult;
-use crate::id::{Rifl, ShardId};
+use crate::id::{ProcessId, Rifl, ShardId};
 use crate::kvs::{KVOp, KVOpResult, KVStore, Key};
+use crate::util::DebugMap;
 use crate::HashMap;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug};
@@ -11,6 +12,76 @@ use crate::kvs::Value;
 
 pub const DEFAULT_SHARD_ID: ShardId = 0;
 
+/// Defines when two commands accessing the same key conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictMode {
+    /// Any two accesses to the same key conflict.
+    Strict,
+    /// Two accesses to the same key conflict unless both are `Get`s.
+    ReadsDontConflict,
+}
+
+/// Decides whether two commands conflict. This allows protocols to exploit
+/// application-level commutativity, instead of assuming that any two accesses
+/// to the same key conflict.
+pub trait ConflictOracle: Debug + Send + Sync {
+    /// Checks if the operations of two commands on the same key conflict.
+    fn ops_conflict(&self, ops: &[KVOp], other_ops: &[KVOp]) -> bool;
+
+    /// Checks if a command conflicts with another given command, i.e. if
+    /// they have conflicting operations on some key.
+    fn conflicts(&self, cmd: &Command, other: &Command) -> bool {
+        cmd.shard_to_ops.iter().any(|(shard_id, shard_ops)| {
+            shard_ops.iter().any(|(key, ops)| {
+                other
+                    .shard_to_ops
+                    .get(shard_id)
+                    .and_then(|other_ops| other_ops.get(key))
+                    .map(|other_ops| self.ops_conflict(ops, other_ops))
+                    .unwrap_or(false)
+            })
+        })
+    }
+}
+
+impl ConflictOracle for ConflictMode {
+    fn ops_conflict(&self, ops: &[KVOp], other_ops: &[KVOp]) -> bool {
+        match self {
+            Self::Strict => true,
+            Self::ReadsDontConflict => {
+                let only_gets = |ops: &[KVOp]| ops.iter().all(KVOp::is_read);
+                !(only_gets(ops) && only_gets(other_ops))
+            }
+        }
+    }
+}
+
+/// `ConflictOracle` in which any two accesses to the same key conflict (as in
+/// `ConflictMode::Strict`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyOverlapOracle;
+
+impl ConflictOracle for KeyOverlapOracle {
+    fn ops_conflict(&self, ops: &[KVOp], other_ops: &[KVOp]) -> bool {
+        ConflictMode::Strict.ops_conflict(ops, other_ops)
+    }
+}
+
+/// `ConflictOracle` in which accesses to the same key don't conflict if they
+/// commute, i.e. if both only have `Get`s (as in
+/// `ConflictMode::ReadsDontConflict`) or both only have `Add`s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommutativityAwareOracle;
+
+impl ConflictOracle for CommutativityAwareOracle {
+    fn ops_conflict(&self, ops: &[KVOp], other_ops: &[KVOp]) -> bool {
+        let only_adds =
+            |ops: &[KVOp]| ops.iter().all(|op| matches!(op, KVOp::Add(_)));
+        ConflictMode::ReadsDontConflict.ops_conflict(ops, other_ops)
+            && !(only_adds(ops) && only_adds(other_ops))
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Command {
     rifl: Rifl,
@@ -18,6 +89,15 @@ pub struct Command {
     // mapping from shard to the keys on that shard; this will be used by
     // `Tempo` to exchange `MStable` messages between shards
     shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
+    // whether all keys should be read at a single consistent point in the
+    // execution order
+    #[serde(default)]
+    snapshot: bool,
+    // size (in bytes) of the payload carried by the command; the payload is
+    // neither materialized nor serialized, but it's accounted for by
+    // `serialized_size` (see `payload`)
+    #[serde(default, with = "payload")]
+    payload_size: usize,
     // field used to output and empty iterator of keys when rustc can't figure
     // out what we mean
     _empty_keys: HashMap<Key, Arc<Vec<KVOp>>>,
@@ -55,10 +135,58 @@ impl Command {
             rifl,
             shard_to_ops,
             shard_to_keys: Arc::new(shard_to_keys),
+            snapshot: false,
+            payload_size: 0,
             _empty_keys: HashMap::new(),
         }
     }
 
+    /// Create a new read-only `Command` that reads all `keys` at a single
+    /// consistent point in the execution order, i.e. it either observes all
+    /// the writes of a concurrent command or none of them.
+    pub fn snapshot_read<I: IntoIterator<Item = Key>>(
+        rifl: Rifl,
+        keys: I,
+    ) -> Self {
+        let mut cmd =
+            Self::from(rifl, keys.into_iter().map(|key| (key, KVOp::Get)));
+        cmd.snapshot = true;
+        cmd
+    }
+
+    /// Create a new read-only `Command` that reads all `keys`.
+    pub fn multi_get<I: IntoIterator<Item = Key>>(rifl: Rifl, keys: I) -> Self {
+        Self::from(rifl, keys.into_iter().map(|key| (key, KVOp::Get)))
+    }
+
+    /// Create a new read-only `Command` that reads the keys
+    /// `format!("{}{}", prefix, i)` for each `i` in `start..end`. Keys are
+    /// assigned to shards as done by the workload (see `util::key_shard`).
+    pub fn range_get(
+        rifl: Rifl,
+        prefix: &str,
+        start: u64,
+        end: u64,
+        shard_count: usize,
+        consistent_hashing: bool,
+    ) -> Self {
+        assert!(shard_count >= 1, "the shard count should be at least 1");
+        let mut shard_to_ops: HashMap<_, HashMap<_, _>> = HashMap::new();
+        for i in start..end {
+            let key = format!("{}{}", prefix, i);
+            let shard_id = crate::util::key_shard(
+                &key,
+                shard_count as u64,
+                consistent_hashing,
+            );
+            shard_to_ops
+                .entry(shard_id)
+                .or_default()
+                .insert(key, vec![KVOp::Get]);
+        }
+        Self::new(rifl, shard_to_ops)
+    }
+
     // Create a new `Command` from an iterator.
     pub fn from<I: IntoIterator<Item = (Key, KVOp)>>(
         rifl: Rifl,
@@ -78,16 +206,30 @@ impl Command {
         self.read_only() && self.total_key_count() == 1
     }
 
+    /// Checks if the command is a read-only command accessing more than one
+    /// key (e.g. a multi-key snapshot read).
+    pub fn is_read_only_multikey(&self) -> bool {
+        self.read_only() && self.total_key_count() > 1
+    }
+
     /// Checks if the command is read-only.
     pub fn read_only(&self) -> bool {
-        // a command is read-only if all ops are `Get`s
+        // a command is read-only if all ops are `Get`s (or `GetRecords`)
         self.shard_to_ops.values().all(|shard_ops| {
-            shard_ops
-                .values()
-                .all(|ops| ops.iter().all(|op| op == &KVOp::Get))
+            shard_ops.values().all(|ops| ops.iter().all(KVOp::is_read))
         })
     }
 
+    /// Checks if the command is a snapshot read.
+    /// Executors that execute each command as a single unit (e.g. the graph
+    /// executor) guarantee that such reads observe a consistent snapshot, as
+    /// the command depends on the latest write on each of its keys (and it's
+    /// only executed after these, even if commands are executed at commit
+    /// time).
+    pub fn is_snapshot_read(&self) -> bool {
+        self.snapshot
+    }
+
     /// Checks if the command is replicated by `shard_id`.
     pub fn replicated_by(&self, shard_id: &ShardId) -> bool {
         self.shard_to_ops.contains_key(&shard_id)
@@ -98,6 +240,51 @@ impl Command {
         self.rifl
     }
 
+    /// Returns a new command (with the same identifier) containing only the
+    /// operations on keys of `shard_id`, or `None` if the command doesn't
+    /// access that shard.
+    pub fn project(&self, shard_id: ShardId) -> Option<Command> {
+        self.project_shards(std::iter::once(&shard_id))
+    }
+
+    /// Returns a new command (with the same identifier) containing only the
+    /// operations on keys of the `shards` provided, or `None` if the command
+    /// doesn't access any of them.
+    pub fn project_shards<'a, I>(&self, shards: I) -> Option<Command>
+    where
+        I: IntoIterator<Item = &'a ShardId>,
+    {
+        let shard_to_ops: HashMap<_, _> = shards
+            .into_iter()
+            .filter_map(|shard_id| {
+                let shard_ops = self.shard_to_ops.get(shard_id)?.clone();
+                Some((*shard_id, shard_ops))
+            })
+            .collect();
+        if shard_to_ops.is_empty() {
+            return None;
+        }
+        let shard_to_keys = shard_to_ops
+            .keys()
+            .map(|shard_id| {
+                let shard_keys = self
+                    .shard_to_keys
+                    .get(shard_id)
+                    .cloned()
+                    .unwrap_or_default();
+                (*shard_id, shard_keys)
+            })
+            .collect();
+        Some(Self {
+            rifl: self.rifl,
+            shard_to_ops,
+            shard_to_keys: Arc::new(shard_to_keys),
+            snapshot: self.snapshot,
+            payload_size: self.payload_size,
+            _empty_keys: HashMap::new(),
+        })
+    }
+
     /// Returns the number of keys accessed by this command on the shard
     /// provided.
     pub fn key_count(&self, shard_id: ShardId) -> usize {
@@ -112,6 +299,32 @@ impl Command {
         self.shard_to_ops.values().map(|ops| ops.len()).sum()
     }
 
+    /// Sets the size (in bytes) of the payload carried by this command. The
+    /// payload is not materialized (nor serialized), but it's accounted for
+    /// when estimating or computing the serialized size of the command.
+    pub fn set_payload_size(&mut self, payload_size: usize) {
+        self.payload_size = payload_size;
+    }
+
+    /// Returns the size (in bytes) of the payload carried by this command.
+    pub fn payload_size(&self) -> usize {
+        self.payload_size
+    }
+
+    /// Estimates the size (in bytes) of this command once serialized, without
+    /// serializing it: its identifier, keys and operations, and its payload.
+    pub fn estimated_serialized_size(&self) -> usize {
+        let ops_size: usize = self
+            .shard_to_ops
+            .values()
+            .flat_map(|shard_ops| shard_ops.iter())
+            .map(|(key, ops)| {
+                key.len() + ops.len() * std::mem::size_of::<KVOp>()
+            })
+            .sum();
+        std::mem::size_of::<Rifl>() + ops_size + self.payload_size
+    }
+
     /// Returns references to the keys accessed by this command on the shard
     /// provided.
     pub fn keys(&self, shard_id: ShardId) -> impl Iterator<Item = &Key> {
@@ -160,16 +373,49 @@ impl Command {
         self,
         shard_id: ShardId,
         store: &'a mut KVStore,
+    ) -> impl Iterator<Item = ExecutorResult> + 'a {
+        self.do_execute(shard_id, None, store)
+    }
+
+    /// Executes self, coordinated by process `coordinator`, in a `KVStore`
+    /// (see `KVStore::execute_from`), returning the resulting an iterator of
+    /// `ExecutorResult`.
+    pub fn execute_from<'a>(
+        self,
+        shard_id: ShardId,
+        coordinator: ProcessId,
+        store: &'a mut KVStore,
+    ) -> impl Iterator<Item = ExecutorResult> + 'a {
+        self.do_execute(shard_id, Some(coordinator), store)
+    }
+
+    fn do_execute<'a>(
+        self,
+        shard_id: ShardId,
+        coordinator: Option<ProcessId>,
+        store: &'a mut KVStore,
     ) -> impl Iterator<Item = ExecutorResult> + 'a {
         let rifl = self.rifl;
+        // only record which shard produced each result in multi-shard commands
+        let multi_shard = self.shard_count() > 1;
         self.into_iter(shard_id).map(move |(key, ops)| {
             // take the ops inside the arc if we're the last with a
             // reference to it (otherwise, clone them)
             let ops =
                 Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone());
             // execute this op
-            let partial_results = store.execute(&key, ops, rifl);
-            ExecutorResult::new(rifl, key, partial_results)
+            let partial_results = match coordinator {
+                Some(coordinator) => {
+                    store.execute_from(&key, ops, rifl, coordinator)
+                }
+                None => store.execute(&key, ops, rifl),
+            };
+            let result = ExecutorResult::new(rifl, key, partial_results);
+            if multi_shard {
+                result.with_shard(shard_id)
+            } else {
+                result
+            }
         })
     }
 
@@ -204,6 +450,16 @@ impl Command {
         })
     }
 
+    /// Checks if a command conflicts with another given command, under the
+    /// conflict definition given by `mode`.
+    pub fn conflicts_with_mode(
+        &self,
+        other: &Command,
+        mode: ConflictMode,
+    ) -> bool {
+        mode.conflicts(self, other)
+    }
+
     /// Checks if `key` is accessed by this command.
     fn contains_key(&self, shard_id: ShardId, key: &Key) -> bool {
         self.shard_to_ops
@@ -214,6 +470,9 @@ impl Command {
 
     /// Adds the operations in the `other` command to this command.
     pub fn merge(&mut self, other: Command) {
+        // merged commands are no longer snapshot reads if any of them is not
+        self.snapshot = self.snapshot && other.snapshot;
+        self.payload_size += other.payload_size;
         for (shard_id, shard_ops) in other.shard_to_ops {
             let current_shard_ops =
                 self.shard_to_ops.entry(shard_id).or_default();
@@ -239,12 +498,68 @@ impl fmt::Debug for Command {
     }
 }
 
+/// Computes the size (in bytes) of `value` once serialized, including the
+/// payload of the commands it carries (which is only accounted for, and not
+/// actually serialized; see `Command::set_payload_size`).
+pub fn serialized_size<T: Serialize>(value: &T) -> u64 {
+    payload::start_counting();
+    let size = bincode::serialized_size(value);
+    let payload_size = payload::stop_counting();
+    size.expect("serialized size should be computed") + payload_size as u64
+}
+
+// (De)serializes the size of a command's payload. While counting (see
+// `serialized_size`), the payload of each command serialized is added to the
+// payload count of the current thread.
+mod payload {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::cell::Cell;
+
+    thread_local! {
+        static COUNT: Cell<Option<usize>> = Cell::new(None);
+    }
+
+    pub fn start_counting() {
+        COUNT.with(|count| count.set(Some(0)));
+    }
+
+    pub fn stop_counting() -> usize {
+        COUNT
+            .with(|count| count.take())
+            .expect("payload count should have been started")
+    }
+
+    pub fn serialize<S>(
+        payload_size: &usize,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        COUNT.with(|count| {
+            if let Some(current) = count.get() {
+                count.set(Some(current + payload_size));
+            }
+        });
+        serializer.serialize_u64(*payload_size as u64)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<usize, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u64::deserialize(deserializer).map(|payload_size| payload_size as usize)
+    }
+}
+
 /// Structure that aggregates partial results of multi-key commands.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CommandResultBuilder {
     rifl: Rifl,
     key_count: usize,
     results: HashMap<Key, Vec<KVOpResult>>,
+    shards: Option<HashMap<Key, ShardId>>,
+    phase_times: Option<PhaseTimes>,
 }
 
 impl CommandResultBuilder {
@@ -255,12 +570,35 @@ impl CommandResultBuilder {
             rifl,
             key_count,
             results: HashMap::new(),
+            shards: None,
+            phase_times: None,
         }
     }
 
-    /// Adds a partial command result to the overall result.
-    /// Returns a boolean indicating whether the full result is ready.
-    pub fn add_partial(&mut self, key: Key, partial_results: Vec<KVOpResult>) {
+    /// Adds the times at which a partial command result was committed and
+    /// executed. Since the command is only committed (resp. executed) once all
+    /// its partials are, the latest of each is kept.
+    pub fn add_phase_times(&mut self, phase_times: PhaseTimes) {
+        let current = self.phase_times.get_or_insert(phase_times);
+        current.commit = std::cmp::max(current.commit, phase_times.commit);
+        current.execute = std::cmp::max(current.execute, phase_times.execute);
+    }
+
+    /// Adds a partial command result to the overall result, along with the
+    /// shard that produced it (if known).
+    pub fn add_partial(
+        &mut self,
+        key: Key,
+        partial_results: Vec<KVOpResult>,
+        shard_id: Option<ShardId>,
+    ) {
+        // record the shard that produced the result for `key`
+        if let Some(shard_id) = shard_id {
+            self.shards
+                .get_or_insert_with(HashMap::new)
+                .insert(key.clone(), shard_id);
+        }
+
         // add op result for `key`
         let res = self.results.insert(key, partial_results);
 
@@ -274,17 +612,110 @@ impl CommandResultBuilder {
     }
 }
 
+/// Times (in milliseconds) at which a command was committed (i.e. handed to
+/// the executor) and executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTimes {
+    pub commit: u64,
+    pub execute: u64,
+}
+
+impl PhaseTimes {
+    /// Creates a new `PhaseTimes`.
+    pub fn new(commit: u64, execute: u64) -> Self {
+        assert!(commit <= execute);
+        Self { commit, execute }
+    }
+}
+
 /// Structure that aggregates partial results of multi-key commands.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CommandResult {
     rifl: Rifl,
     results: HashMap<Key, Vec<KVOpResult>>,
+    // mapping from key to the shard that produced its result; only set for
+    // multi-shard commands
+    #[serde(default)]
+    shards: Option<HashMap<Key, ShardId>>,
+    // times at which the command was committed and executed; only set if the
+    // executor records them
+    #[serde(default)]
+    phase_times: Option<PhaseTimes>,
+    // whether this result was synthesized because the command timed out
+    #[serde(default)]
+    timed_out: bool,
+    // whether the command was aborted
+    #[serde(default)]
+    aborted: bool,
+    // whether the command was rejected because the process was overloaded
+    #[serde(default)]
+    rejected: bool,
+}
+
+impl fmt::Debug for CommandResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandResult")
+            .field("rifl", &self.rifl)
+            .field("results", &DebugMap::new(&self.results))
+            .field("shards", &self.shards.as_ref().map(DebugMap::new))
+            .field("phase_times", &self.phase_times)
+            .field("timed_out", &self.timed_out)
+            .field("aborted", &self.aborted)
+            .field("rejected", &self.rejected)
+            .finish()
+    }
 }
 
 impl CommandResult {
     /// Creates a new `CommandResult`.
     pub fn new(rifl: Rifl, results: HashMap<Key, Vec<KVOpResult>>) -> Self {
-        CommandResult { rifl, results }
+        CommandResult {
+            rifl,
+            results,
+            shards: None,
+            phase_times: None,
+            timed_out: false,
+            aborted: false,
+            rejected: false,
+        }
+    }
+
+    /// Creates a (result-less) `CommandResult` for a command that timed out.
+    pub fn new_timed_out(rifl: Rifl) -> Self {
+        let mut cmd_result = Self::new(rifl, HashMap::new());
+        cmd_result.timed_out = true;
+        cmd_result
+    }
+
+    /// Checks whether this result was synthesized because the command timed
+    /// out.
+    pub fn is_timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Creates a (result-less) `CommandResult` for a command that was aborted.
+    pub fn new_aborted(rifl: Rifl) -> Self {
+        let mut cmd_result = Self::new(rifl, HashMap::new());
+        cmd_result.aborted = true;
+        cmd_result
+    }
+
+    /// Checks whether the command was aborted (and should be retried).
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Creates a (result-less) `CommandResult` for a command that was rejected
+    /// because the process it was submitted to was overloaded.
+    pub fn new_rejected(rifl: Rifl) -> Self {
+        let mut cmd_result = Self::new(rifl, HashMap::new());
+        cmd_result.rejected = true;
+        cmd_result
+    }
+
+    /// Checks whether the command was rejected.
+    pub fn is_rejected(&self) -> bool {
+        self.rejected
     }
 
     /// Returns the command identifier.
@@ -296,6 +727,55 @@ impl CommandResult {
     pub fn results(&self) -> &HashMap<Key, Vec<KVOpResult>> {
         &self.results
     }
+
+    /// Returns the shard that produced the result for `key`. This is only
+    /// known for commands that span multiple shards.
+    pub fn result_shard(&self, key: &Key) -> Option<ShardId> {
+        self.shards
+            .as_ref()
+            .and_then(|shards| shards.get(key))
+            .cloned()
+    }
+
+    /// Returns the times at which the command was committed and executed, if
+    /// recorded by the executor.
+    pub fn phase_times(&self) -> Option<PhaseTimes> {
+        self.phase_times
+    }
+
+    /// Checks whether both results are for the same command and have the same
+    /// results on each key, ignoring the order of the results on each key and
+    /// any metadata (i.e. the shards that produced the results and the times
+    /// at which the command was committed and executed). This is useful for
+    /// test assertions.
+    pub fn results_eq_unordered(&self, other: &CommandResult) -> bool {
+        let sorted = |results: &Vec<KVOpResult>| {
+            let mut results = results.clone();
+            results.sort();
+            results
+        };
+        self.rifl == other.rifl
+            && self.results.len() == other.results.len()
+            && self.results.iter().all(|(key, results)| {
+                other.results.get(key).map_or(false, |other_results| {
+                    sorted(results) == sorted(other_results)
+                })
+            })
+    }
+
+    /// Returns the value returned by the first operation on each of the `keys`
+    /// provided, in the same order. This is useful for commands with a single
+    /// operation per key, such as the ones created with `Command::multi_get`.
+    pub fn values_in_order(&self, keys: &[Key]) -> Vec<Option<Value>> {
+        keys.iter()
+            .map(|key| {
+                self.results
+                    .get(key)
+                    .and_then(|results| results.first().cloned())
+                    .and_then(KVOpResult::into_value)
+            })
+            .collect()
+    }
 }
 
 impl From<CommandResultBuilder> for CommandResult {
@@ -304,6 +784,11 @@ impl From<CommandResultBuilder> for CommandResult {
         Self {
             rifl: cmd_result_builder.rifl,
             results: cmd_result_builder.results,
+            shards: cmd_result_builder.shards,
+            phase_times: cmd_result_builder.phase_times,
+            timed_out: false,
+            aborted: false,
+            rejected: false,
         }
     }
 }
@@ -353,4 +838,415 @@ mod tests {
         assert!(!cmd_ab.conflicts(&cmd_c));
         assert!(cmd_ab.conflicts(&cmd_ab));
     }
+
+    #[test]
+    fn range_get() {
+        let rifl = Rifl::new(1, 1);
+        let shard_count = 3;
+        for consistent_hashing in vec![false, true] {
+            let cmd = Command::range_get(
+                rifl,
+                "K",
+                10,
+                20,
+                shard_count,
+                consistent_hashing,
+            );
+
+            // there are 10 `Get`s
+            assert_eq!(cmd.total_key_count(), 10);
+            assert!(cmd.read_only());
+
+            // each key is on the shard the workload would assign it to
+            let mut keys = Vec::new();
+            for shard_id in 0..shard_count as u64 {
+                for (key, ops) in cmd.iter(shard_id) {
+                    assert_eq!(
+                        crate::util::key_shard(
+                            key,
+                            shard_count as u64,
+                            consistent_hashing
+                        ),
+                        shard_id
+                    );
+                    assert_eq!(ops.as_ref(), &vec![KVOp::Get]);
+                    keys.push(key.clone());
+                }
+            }
+            keys.sort();
+            let expected: Vec<_> =
+                (10..20).map(|i| format!("K{}", i)).collect();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn conflicts_with_mode() {
+        let key = String::from("A");
+        let get_1 =
+            Command::from(Rifl::new(1, 1), vec![(key.clone(), KVOp::Get)]);
+        let get_2 =
+            Command::from(Rifl::new(2, 1), vec![(key.clone(), KVOp::Get)]);
+        let put =
+            Command::from(Rifl::new(3, 1), vec![(key.clone(), KVOp::Put(1))]);
+        let other = Command::from(
+            Rifl::new(4, 1),
+            vec![(String::from("B"), KVOp::Put(1))],
+        );
+
+        // under the strict mode, two reads conflict
+        let strict = ConflictMode::Strict;
+        assert!(get_1.conflicts_with_mode(&get_2, strict));
+        assert!(get_1.conflicts_with_mode(&put, strict));
+        assert!(!get_1.conflicts_with_mode(&other, strict));
+
+        // but not when reads don't conflict
+        let reads = ConflictMode::ReadsDontConflict;
+        assert!(!get_1.conflicts_with_mode(&get_2, reads));
+        assert!(!get_2.conflicts_with_mode(&get_1, reads));
+        // while a read and a write still conflict
+        assert!(get_1.conflicts_with_mode(&put, reads));
+        assert!(put.conflicts_with_mode(&get_1, reads));
+        assert!(put.conflicts_with_mode(&put, reads));
+        assert!(!get_1.conflicts_with_mode(&other, reads));
+    }
+
+    #[test]
+    fn conflict_oracles() {
+        let key = String::from("A");
+        let get =
+            Command::from(Rifl::new(1, 1), vec![(key.clone(), KVOp::Get)]);
+        let add_1 =
+            Command::from(Rifl::new(2, 1), vec![(key.clone(), KVOp::Add(1))]);
+        let add_2 =
+            Command::from(Rifl::new(3, 1), vec![(key.clone(), KVOp::Add(2))]);
+        let cmds = vec![&get, &add_1, &add_2];
+
+        // the key overlap oracle agrees with the strict mode
+        for cmd in &cmds {
+            for other in &cmds {
+                assert_eq!(
+                    KeyOverlapOracle.conflicts(cmd, other),
+                    cmd.conflicts_with_mode(other, ConflictMode::Strict)
+                );
+            }
+        }
+
+        // while the commutativity-aware one also lets adds commute
+        let oracle = CommutativityAwareOracle;
+        assert!(!oracle.conflicts(&get, &get));
+        assert!(!oracle.conflicts(&add_1, &add_2));
+        assert!(oracle.conflicts(&get, &add_1));
+        assert!(oracle.conflicts(&add_2, &get));
+    }
+
+    #[test]
+    fn snapshot_read() {
+        let rifl = Rifl::new(1, 1);
+        let keys = vec![String::from("A"), String::from("B")];
+        let cmd = Command::snapshot_read(rifl, keys.clone());
+        assert!(cmd.is_snapshot_read());
+        assert!(cmd.read_only());
+        assert_eq!(cmd.total_key_count(), 2);
+        // multi-key reads can't use the NFR optimization
+        assert!(!cmd.nfr_allowed());
+
+        // regular commands are not snapshot reads
+        let cmd = multi_put(rifl, keys);
+        assert!(!cmd.is_snapshot_read());
+    }
+
+    #[test]
+    fn multi_get() {
+        let keys =
+            vec![String::from("C"), String::from("A"), String::from("B")];
+
+        // populate store with keys `A` and `C`
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+        let put = Command::from(
+            Rifl::new(1, 1),
+            vec![
+                (String::from("A"), KVOp::Put(10)),
+                (String::from("C"), KVOp::Put(30)),
+            ],
+        );
+        put.execute(DEFAULT_SHARD_ID, &mut store).for_each(drop);
+
+        // execute multi-get and aggregate its results
+        let rifl = Rifl::new(1, 2);
+        let cmd = Command::multi_get(rifl, keys.clone());
+        assert!(cmd.read_only());
+        assert_eq!(cmd.total_key_count(), 3);
+        let results = cmd
+            .execute(DEFAULT_SHARD_ID, &mut store)
+            .map(|result| result.unwrap_ready())
+            .map(|result| (result.key, result.partial_results))
+            .collect();
+        let cmd_result = CommandResult::new(rifl, results);
+
+        // results are aligned with the keys provided
+        assert_eq!(
+            cmd_result.values_in_order(&keys),
+            vec![Some(30), Some(10), None]
+        );
+    }
+
+    #[test]
+    fn result_shard() {
+        let rifl = Rifl::new(1, 1);
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+
+        // create a command with key `A` on shard 0 and key `B` on shard 1
+        let mut shard_to_ops: HashMap<_, HashMap<_, _>> = HashMap::new();
+        shard_to_ops
+            .entry(0)
+            .or_default()
+            .insert(key_a.clone(), vec![KVOp::Put(10)]);
+        shard_to_ops
+            .entry(1)
+            .or_default()
+            .insert(key_b.clone(), vec![KVOp::Put(20)]);
+        let cmd = Command::new(rifl, shard_to_ops);
+
+        // execute the command in each shard and aggregate its results
+        let mut builder = CommandResultBuilder::new(rifl, 2);
+        for shard_id in 0..2 {
+            let monitor = false;
+            let mut store = KVStore::new(monitor);
+            for result in cmd.clone().execute(shard_id, &mut store) {
+                let result = result.unwrap_ready();
+                builder.add_partial(
+                    result.key,
+                    result.partial_results,
+                    result.shard_id,
+                );
+            }
+        }
+        let cmd_result = CommandResult::from(builder);
+
+        // each key maps to the shard that produced it
+        assert_eq!(cmd_result.result_shard(&key_a), Some(0));
+        assert_eq!(cmd_result.result_shard(&key_b), Some(1));
+
+        // single-shard commands don't record shards
+        let cmd = Command::from(rifl, vec![(key_a.clone(), KVOp::Get)]);
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+        assert!(cmd
+            .execute(DEFAULT_SHARD_ID, &mut store)
+            .all(|result| result.unwrap_ready().shard_id.is_none()));
+    }
+
+    #[test]
+    fn project() {
+        let rifl = Rifl::new(1, 1);
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let key_c = String::from("C");
+
+        // command with keys A and B on shard 0 and key C on shard 1
+        let mut shard_to_ops: HashMap<_, HashMap<_, _>> = HashMap::new();
+        shard_to_ops
+            .entry(0)
+            .or_default()
+            .insert(key_a.clone(), vec![KVOp::Put(10)]);
+        shard_to_ops
+            .entry(0)
+            .or_default()
+            .insert(key_b.clone(), vec![KVOp::Get]);
+        shard_to_ops
+            .entry(1)
+            .or_default()
+            .insert(key_c.clone(), vec![KVOp::Put(20)]);
+        let cmd = Command::new(rifl, shard_to_ops);
+        assert_eq!(cmd.shard_count(), 2);
+
+        // projection on shard 0
+        let cmd_0 = cmd.project(0).expect("shard 0 is accessed");
+        assert_eq!(cmd_0.rifl(), rifl);
+        assert_eq!(cmd_0.shard_count(), 1);
+        let mut keys: Vec<_> = cmd_0.keys(0).cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec![key_a.clone(), key_b]);
+        assert_eq!(cmd_0.key_count(1), 0);
+        assert_eq!(cmd_0.shard_to_keys().len(), 1);
+        assert!(cmd_0.operations(0, &key_a).eq(vec![KVOp::Put(10)].iter()));
+
+        // projection on shard 1
+        let cmd_1 = cmd.project(1).expect("shard 1 is accessed");
+        assert_eq!(cmd_1.rifl(), rifl);
+        assert_eq!(cmd_1.keys(1).collect::<Vec<_>>(), vec![&key_c]);
+        assert_eq!(cmd_1.key_count(0), 0);
+
+        // projection on a shard not accessed
+        assert!(cmd.project(2).is_none());
+
+        // projection on several shards
+        let cmd_01 = cmd.project_shards(&[0, 1, 2]).expect("shards accessed");
+        assert_eq!(cmd_01, cmd);
+        let cmd_12 = cmd.project_shards(&[1, 2]).expect("shard 1 is accessed");
+        assert_eq!(cmd_12, cmd_1);
+        assert!(cmd.project_shards(&[2, 3]).is_none());
+    }
+
+    #[test]
+    fn results_eq_unordered() {
+        let rifl = Rifl::new(1, 1);
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let results = |values: Vec<Option<Value>>| {
+            values.into_iter().map(KVOpResult::Value).collect()
+        };
+
+        // add partials for A and B, in this order
+        let mut builder = CommandResultBuilder::new(rifl, 2);
+        builder.add_partial(key_a.clone(), results(vec![Some(1), None]), None);
+        builder.add_partial(key_b.clone(), results(vec![Some(2)]), Some(0));
+        let result_1 = CommandResult::from(builder);
+
+        // add partials for B and A, in this order, with results on A in a
+        // different order and with phase times
+        let mut builder = CommandResultBuilder::new(rifl, 2);
+        builder.add_phase_times(PhaseTimes::new(1, 2));
+        builder.add_partial(key_b.clone(), results(vec![Some(2)]), None);
+        builder.add_partial(key_a.clone(), results(vec![None, Some(1)]), None);
+        let result_2 = CommandResult::from(builder);
+
+        // although not equal, they're equal ignoring order
+        assert_ne!(result_1, result_2);
+        assert!(result_1.results_eq_unordered(&result_2));
+        assert!(result_2.results_eq_unordered(&result_1));
+
+        // results with different values are not equal
+        let mut builder = CommandResultBuilder::new(rifl, 2);
+        builder.add_partial(key_a, results(vec![Some(1), None]), None);
+        builder.add_partial(key_b.clone(), results(vec![Some(3)]), None);
+        let result_3 = CommandResult::from(builder);
+        assert!(!result_1.results_eq_unordered(&result_3));
+
+        // results for different commands are not equal
+        let mut builder = CommandResultBuilder::new(Rifl::new(1, 2), 1);
+        builder.add_partial(key_b, results(vec![Some(2)]), None);
+        let result_4 = CommandResult::from(builder);
+        assert!(!result_1.results_eq_unordered(&result_4));
+    }
+
+    #[test]
+    fn phase_times() {
+        let rifl = Rifl::new(1, 1);
+        let results = vec![KVOpResult::Value(None)];
+        let mut builder = CommandResultBuilder::new(rifl, 2);
+        builder.add_partial(String::from("A"), results.clone(), None);
+        builder.add_phase_times(PhaseTimes::new(10, 15));
+        builder.add_partial(String::from("B"), results.clone(), None);
+        builder.add_phase_times(PhaseTimes::new(12, 13));
+        let cmd_result = CommandResult::from(builder);
+
+        // the latest commit and execution times are kept
+        assert_eq!(cmd_result.phase_times(), Some(PhaseTimes::new(12, 15)));
+
+        // results created directly have no phase times
+        let cmd_result = CommandResult::new(rifl, HashMap::new());
+        assert_eq!(cmd_result.phase_times(), None);
+    }
+
+    #[test]
+    fn estimated_serialized_size() {
+        let rifl = Rifl::new(1, 1);
+        let mut cmd = multi_put(rifl, vec![String::from("A")]);
+        let size = cmd.estimated_serialized_size();
+        assert!(size > 0);
+
+        // the payload is accounted for
+        cmd.set_payload_size(1000);
+        assert_eq!(cmd.payload_size(), 1000);
+        assert_eq!(cmd.estimated_serialized_size(), size + 1000);
+    }
+
+    #[test]
+    fn serialized_payload() {
+        let rifl = Rifl::new(1, 1);
+        let mut cmd = multi_put(rifl, vec![String::from("A")]);
+        let size = bincode::serialized_size(&cmd).unwrap();
+        assert_eq!(serialized_size(&cmd), size);
+
+        // the payload is not serialized, but it survives a round trip
+        cmd.set_payload_size(1000);
+        let bytes = bincode::serialize(&cmd).unwrap();
+        assert_eq!(bytes.len() as u64, size);
+        let cmd: Command = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(cmd.payload_size(), 1000);
+
+        // it's accounted for in the size of anything carrying the command
+        assert_eq!(serialized_size(&cmd), size + 1000);
+        let cmds = vec![cmd.clone(), cmd];
+        let cmds_size = bincode::serialized_size(&cmds).unwrap();
+        assert_eq!(serialized_size(&cmds), cmds_size + 2000);
+    }
+
+    #[test]
+    fn is_read_only_multikey() {
+        let rifl = Rifl::new(1, 1);
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+
+        // single-key read
+        let read = Command::from(rifl, vec![(key_a.clone(), KVOp::Get)]);
+        assert!(read.nfr_allowed());
+        assert!(!read.is_read_only_multikey());
+
+        // multi-key read
+        let multi_read = Command::from(
+            rifl,
+            vec![(key_a.clone(), KVOp::Get), (key_b.clone(), KVOp::Get)],
+        );
+        assert!(!multi_read.nfr_allowed());
+        assert!(multi_read.is_read_only_multikey());
+
+        // multi-key read and write
+        let mixed = Command::from(
+            rifl,
+            vec![(key_a.clone(), KVOp::Get), (key_b.clone(), KVOp::Put(1))],
+        );
+        assert!(!mixed.is_read_only_multikey());
+
+        // multi-key write
+        let multi_write = multi_put(rifl, vec![key_a, key_b]);
+        assert!(!multi_write.is_read_only_multikey());
+    }
+
+    #[cfg(feature = "debug_sorted")]
+    #[test]
+    fn debug_sorted() {
+        let rifl = Rifl::new(1, 1);
+        let keys: Vec<_> =
+            (b'A'..=b'Z').map(|c| (c as char).to_string()).collect();
+        let cmd_result = |keys: Vec<Key>| {
+            let results = keys
+                .into_iter()
+                .map(|key| {
+                    let value = key.as_bytes()[0] as Value;
+                    (key, vec![KVOpResult::Value(Some(value))])
+                })
+                .collect();
+            CommandResult::new(rifl, results)
+        };
+
+        // the same results, inserted in opposite orders, are shown the same
+        let keys_rev: Vec<_> = keys.iter().rev().cloned().collect();
+        let expected = format!("{:?}", cmd_result(keys.clone()));
+        for _ in 0..10 {
+            assert_eq!(format!("{:?}", cmd_result(keys.clone())), expected);
+        }
+        assert_eq!(format!("{:?}", cmd_result(keys_rev)), expected);
+
+        // and keys are shown in order
+        let positions: Vec<_> = keys
+            .iter()
+            .map(|key| expected.find(&format!("{:?}:", key)).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
 }
@@ -1,4 +1,7 @@
-use crate::id::ProcessId;
+use crate::id::{ProcessId, ShardId};
+use crate::kvs::StorageMode;
+use crate::util;
+use crate::HashMap;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -23,10 +26,65 @@ pub struct Config {
     /// defines whether the executor should monitor the execution order of
     /// commands
     executor_monitor_execution_order: bool,
+    /// defines the maximum number of dots in each request sent to another
+    /// shard's executor, if any
+    #[serde(default)]
+    executor_max_request_batch: Option<usize>,
+    /// defines the artificial delay added to the execution of each command,
+    /// if any (only supported by the basic executor)
+    #[serde(default)]
+    executor_execution_delay: Option<Duration>,
+    /// defines whether read-only commands should be served from a snapshot of
+    /// the store that is refreshed every `staleness`, if any (only supported
+    /// by the basic executor)
+    #[serde(default)]
+    executor_eventual_read_staleness: Option<Duration>,
+    /// defines whether the graph executor should find SCCs with an iterative
+    /// (instead of recursive) implementation of Tarjan's algorithm, which
+    /// supports arbitrarily long dependency chains
+    #[serde(default)]
+    executor_iterative_scc_finder: bool,
+    /// defines the maximum number of writes per second on any single key,
+    /// if any; excess writes are delayed (only supported by the graph
+    /// executor)
+    #[serde(default)]
+    executor_max_writes_per_sec: Option<u64>,
+    /// defines for how long executed commands are remembered in order to
+    /// detect duplicate deliveries (only supported by the basic executor)
+    #[serde(default = "default_executor_executed_retention")]
+    executor_executed_retention: Duration,
+    /// defines the percentage of commands that are aborted the first time
+    /// they're executed, forcing their clients to retry them (only supported
+    /// by the basic executor)
+    #[serde(default)]
+    executor_abort_percentage: usize,
+    /// defines whether numeric keys should be routed to executors in ranges
+    /// of this many keys, if any (by default, keys are routed by their hash)
+    #[serde(default)]
+    executor_keys_per_range: Option<u64>,
+    /// defines whether commands that become ready at the same time in the
+    /// dependency graph are executed in the order they were added to it
+    /// (instead of in the order their SCCs are found)
+    #[serde(default)]
+    executor_fifo_ordering: bool,
+    /// defines the maximum number of ready commands executed by the graph
+    /// executor each time it handles a message or is cleaned up, if any (the
+    /// remaining ones are executed in the next cleanups)
+    #[serde(default)]
+    executor_execute_chunk_size: Option<usize>,
+    /// defines the number of pending commands the graph executor's dependency
+    /// graph is pre-sized for, if any
+    #[serde(default)]
+    executor_graph_capacity: Option<usize>,
     /// defines the interval between garbage collections
     gc_interval: Option<Duration>,
     /// starting leader process
     leader: Option<ProcessId>,
+    /// starting leader process of each shard, if any; this is stored as a
+    /// bitset indexed by process identifier (each shard has at most one
+    /// leader) so that `Config` remains `Copy`
+    #[serde(default)]
+    shard_leaders: [u64; 4],
     /// defines whether protocols (atlas, epaxos and tempo) should employ the
     /// NFR optimization
     nfr: bool,
@@ -42,6 +100,41 @@ pub struct Config {
     /// defines whether protocols should try to bypass the fast quorum process
     /// ack (which is only possible if the fast quorum size is 2)
     skip_fast_ack: bool,
+    /// defines whether protocols (atlas and epaxos) should transitively
+    /// reduce the dependencies of committed commands before execution (only
+    /// effective with garbage collection, as otherwise the dependencies of
+    /// committed commands are not kept)
+    #[serde(default)]
+    transitive_conflicts: bool,
+    /// defines whether protocols whose key dependencies implementation is
+    /// picked at runtime (atlas and epaxos with `AutoKeyDeps`) should pick
+    /// the parallel one
+    #[serde(default)]
+    parallel_key_deps: bool,
+    /// defines the maximum number of commands submitted to a process that
+    /// can be outstanding (i.e. not yet committed); further submits are
+    /// rejected until some of the outstanding commands are committed
+    #[serde(default)]
+    max_outstanding: Option<usize>,
+    /// if set, the randomness in a simulation (workload, message loss and
+    /// reordering, and the record selection of `EPaxosMRV`) is derived from
+    /// this seed; since the simulator schedules the events of each step in a
+    /// fixed order, the simulation is then reproducible
+    #[serde(default)]
+    seed: Option<u64>,
+    /// defines how the key-value store lays out the value of each key (and,
+    /// for protocols that track conflicts per record, which record each
+    /// arithmetic operation accesses)
+    #[serde(default)]
+    storage_mode: StorageMode,
+    /// number of quorum acks after which a command is committed in basic, if
+    /// different from its fast quorum size
+    #[serde(default)]
+    basic_commit_quorum_size: Option<usize>,
+    /// number of quorum acks after which a committed command is executable in
+    /// basic, if different from the commit quorum size
+    #[serde(default)]
+    basic_execute_quorum_size: Option<usize>,
 }
 
 impl Config {
@@ -65,10 +158,34 @@ impl Config {
         let executor_monitor_pending_interval = None;
         // by default, executors do not monitor execution order
         let executor_monitor_execution_order = false;
+        // by default, requests to other shards are not split
+        let executor_max_request_batch = None;
+        // by default, there's no artificial execution delay
+        let executor_execution_delay = None;
+        // by default, reads are served from the live store
+        let executor_eventual_read_staleness = None;
+        // by default, SCCs are found with the recursive implementation
+        let executor_iterative_scc_finder = false;
+        // by default, writes are not rate limited
+        let executor_max_writes_per_sec = None;
+        // by default, executed commands are remembered for 10 seconds
+        let executor_executed_retention = default_executor_executed_retention();
+        // by default, commands are never aborted
+        let executor_abort_percentage = 0;
+        // by default, keys are routed to executors by their hash
+        let executor_keys_per_range = None;
+        // by default, ready commands are executed in SCC completion order
+        let executor_fifo_ordering = false;
+        // by default, all ready commands are executed at once
+        let executor_execute_chunk_size = None;
+        // by default, the dependency graph is not pre-sized
+        let executor_graph_capacity = None;
         // by default, commands are deleted at commit time
         let gc_interval = None;
         // by default, there's no leader
         let leader = None;
+        // by default, there are no per-shard leaders
+        let shard_leaders = [0; 4];
         // by default, `nfr = false`
         let nfr = false;
         // by default, `tempo_tiny_quorums = false`
@@ -81,6 +198,20 @@ impl Config {
         let caesar_wait_condition = true;
         // by default `skip_fast_ack = false;
         let skip_fast_ack = false;
+        // by default, `transitive_conflicts = false`
+        let transitive_conflicts = false;
+        // by default, `parallel_key_deps = false`
+        let parallel_key_deps = false;
+        // by default, there's no limit on the number of outstanding commands
+        let max_outstanding = None;
+        // by default, there's no seed
+        let seed = None;
+        // by default, each key has a single record
+        let storage_mode = StorageMode::SingleRecord;
+        // by default, commands commit in basic on the fast quorum, and are
+        // executable once committed
+        let basic_commit_quorum_size = None;
+        let basic_execute_quorum_size = None;
         Self {
             n,
             f,
@@ -90,14 +221,33 @@ impl Config {
             executor_executed_notification_interval,
             executor_monitor_pending_interval,
             executor_monitor_execution_order,
+            executor_max_request_batch,
+            executor_execution_delay,
+            executor_eventual_read_staleness,
+            executor_iterative_scc_finder,
+            executor_max_writes_per_sec,
+            executor_executed_retention,
+            executor_abort_percentage,
+            executor_keys_per_range,
+            executor_fifo_ordering,
+            executor_execute_chunk_size,
+            executor_graph_capacity,
             gc_interval,
             leader,
+            shard_leaders,
             nfr,
             tempo_tiny_quorums,
             tempo_clock_bump_interval,
             tempo_detached_send_interval,
             caesar_wait_condition,
             skip_fast_ack,
+            transitive_conflicts,
+            parallel_key_deps,
+            max_outstanding,
+            seed,
+            storage_mode,
+            basic_commit_quorum_size,
+            basic_execute_quorum_size,
         }
     }
 
@@ -169,6 +319,155 @@ impl Config {
             executor_monitor_execution_order;
     }
 
+    /// Checks the executor maximum request batch size.
+    pub fn executor_max_request_batch(&self) -> Option<usize> {
+        self.executor_max_request_batch
+    }
+
+    /// Sets the executor maximum request batch size.
+    pub fn set_executor_max_request_batch<B>(&mut self, batch: B)
+    where
+        B: Into<Option<usize>>,
+    {
+        let batch = batch.into();
+        assert!(batch != Some(0), "request batch size must be positive");
+        self.executor_max_request_batch = batch;
+    }
+
+    /// Checks the executor artificial execution delay.
+    pub fn executor_execution_delay(&self) -> Option<Duration> {
+        self.executor_execution_delay
+    }
+
+    /// Sets the executor artificial execution delay.
+    pub fn set_executor_execution_delay<I>(&mut self, delay: I)
+    where
+        I: Into<Option<Duration>>,
+    {
+        self.executor_execution_delay = delay.into();
+    }
+
+    /// Checks the executor eventual read staleness.
+    pub fn executor_eventual_read_staleness(&self) -> Option<Duration> {
+        self.executor_eventual_read_staleness
+    }
+
+    /// Sets the executor eventual read staleness.
+    pub fn set_executor_eventual_read_staleness<I>(&mut self, staleness: I)
+    where
+        I: Into<Option<Duration>>,
+    {
+        self.executor_eventual_read_staleness = staleness.into();
+    }
+
+    /// Checks whether the executor finds SCCs iteratively.
+    pub fn executor_iterative_scc_finder(&self) -> bool {
+        self.executor_iterative_scc_finder
+    }
+
+    /// Changes the value of `executor_iterative_scc_finder`.
+    pub fn set_executor_iterative_scc_finder(&mut self, iterative: bool) {
+        self.executor_iterative_scc_finder = iterative;
+    }
+
+    /// Checks the executor maximum number of writes per second on each key.
+    pub fn executor_max_writes_per_sec(&self) -> Option<u64> {
+        self.executor_max_writes_per_sec
+    }
+
+    /// Sets the executor maximum number of writes per second on each key.
+    pub fn set_executor_max_writes_per_sec<I>(&mut self, max_writes: I)
+    where
+        I: Into<Option<u64>>,
+    {
+        let max_writes = max_writes.into();
+        assert_ne!(
+            max_writes,
+            Some(0),
+            "the maximum number of writes per second must be non-zero"
+        );
+        self.executor_max_writes_per_sec = max_writes;
+    }
+
+    /// Checks for how long the executor remembers executed commands.
+    pub fn executor_executed_retention(&self) -> Duration {
+        self.executor_executed_retention
+    }
+
+    /// Sets for how long the executor remembers executed commands.
+    pub fn set_executor_executed_retention(&mut self, retention: Duration) {
+        self.executor_executed_retention = retention;
+    }
+
+    /// Checks the percentage of commands aborted by the executor.
+    pub fn executor_abort_percentage(&self) -> usize {
+        self.executor_abort_percentage
+    }
+
+    /// Sets the percentage of commands aborted by the executor.
+    pub fn set_executor_abort_percentage(&mut self, percentage: usize) {
+        assert!(
+            percentage <= 100,
+            "the abort percentage must be at most 100"
+        );
+        self.executor_abort_percentage = percentage;
+    }
+
+    /// Checks the size of the key ranges routed to each executor, if any.
+    pub fn executor_keys_per_range(&self) -> Option<u64> {
+        self.executor_keys_per_range
+    }
+
+    /// Sets the size of the key ranges routed to each executor.
+    pub fn set_executor_keys_per_range<I>(&mut self, keys_per_range: I)
+    where
+        I: Into<Option<u64>>,
+    {
+        let keys_per_range = keys_per_range.into();
+        assert_ne!(keys_per_range, Some(0), "the range size must be positive");
+        self.executor_keys_per_range = keys_per_range;
+    }
+
+    /// Checks whether ready commands are executed in FIFO order.
+    pub fn executor_fifo_ordering(&self) -> bool {
+        self.executor_fifo_ordering
+    }
+
+    /// Sets whether ready commands are executed in FIFO order.
+    pub fn set_executor_fifo_ordering(&mut self, executor_fifo_ordering: bool) {
+        self.executor_fifo_ordering = executor_fifo_ordering;
+    }
+
+    /// Checks the maximum number of ready commands executed at once, if any.
+    pub fn executor_execute_chunk_size(&self) -> Option<usize> {
+        self.executor_execute_chunk_size
+    }
+
+    /// Sets the maximum number of ready commands executed at once.
+    pub fn set_executor_execute_chunk_size<C>(&mut self, chunk_size: C)
+    where
+        C: Into<Option<usize>>,
+    {
+        let chunk_size = chunk_size.into();
+        assert!(chunk_size != Some(0), "execute chunk size must be positive");
+        self.executor_execute_chunk_size = chunk_size;
+    }
+
+    /// Checks the number of pending commands the dependency graph is
+    /// pre-sized for, if any.
+    pub fn executor_graph_capacity(&self) -> Option<usize> {
+        self.executor_graph_capacity
+    }
+
+    /// Sets the number of pending commands the dependency graph is pre-sized
+    /// for.
+    pub fn set_executor_graph_capacity<C>(&mut self, capacity: C)
+    where
+        C: Into<Option<usize>>,
+    {
+        self.executor_graph_capacity = capacity.into();
+    }
+
     /// Checks the executed notification interval.
     pub fn executor_executed_notification_interval(&self) -> Duration {
         self.executor_executed_notification_interval
@@ -208,6 +507,46 @@ impl Config {
         self.leader = leader.into();
     }
 
+    /// Sets the starting leader of each shard. Shards without a leader in
+    /// `leaders` fallback to the leader set with `set_leader`, if any.
+    pub fn set_leaders(&mut self, leaders: HashMap<ShardId, ProcessId>) {
+        self.shard_leaders = [0; 4];
+        for (shard_id, leader) in leaders {
+            assert!(
+                util::process_ids(shard_id, self.n).any(|id| id == leader),
+                "leader {} does not belong to shard {}",
+                leader,
+                shard_id
+            );
+            let (index, bit) = Self::shard_leader_bit(leader);
+            self.shard_leaders[index] |= bit;
+        }
+    }
+
+    /// Returns the starting leader of shard `shard_id`, if any.
+    pub fn leader_of(&self, shard_id: ShardId) -> Option<ProcessId> {
+        util::process_ids(shard_id, self.n)
+            .find(|id| {
+                let (index, bit) = Self::shard_leader_bit(*id);
+                self.shard_leaders[index] & bit != 0
+            })
+            .or(self.leader)
+    }
+
+    /// Checks whether some starting leader (either global or per-shard) has
+    /// been defined.
+    pub fn has_leader(&self) -> bool {
+        self.leader.is_some()
+            || self.shard_leaders.iter().any(|bits| *bits != 0)
+    }
+
+    // Returns the position of `process_id` in the `shard_leaders` bitset (it
+    // has one bit for each possible process identifier).
+    fn shard_leader_bit(process_id: ProcessId) -> (usize, u64) {
+        let process_id = process_id as usize;
+        (process_id / 64, 1 << (process_id % 64))
+    }
+
     /// Checks whether deps NFR is enabled or not.
     pub fn nfr(&self) -> bool {
         self.nfr
@@ -273,6 +612,105 @@ impl Config {
     pub fn set_skip_fast_ack(&mut self, skip_fast_ack: bool) {
         self.skip_fast_ack = skip_fast_ack;
     }
+
+    /// Checks whether dependencies should be transitively reduced.
+    pub fn transitive_conflicts(&self) -> bool {
+        self.transitive_conflicts
+    }
+
+    /// Changes the value of `transitive_conflicts`.
+    pub fn set_transitive_conflicts(&mut self, transitive_conflicts: bool) {
+        self.transitive_conflicts = transitive_conflicts;
+    }
+
+    /// Checks whether key dependencies picked at runtime should be parallel.
+    pub fn parallel_key_deps(&self) -> bool {
+        self.parallel_key_deps
+    }
+
+    /// Changes the value of `parallel_key_deps`.
+    pub fn set_parallel_key_deps(&mut self, parallel_key_deps: bool) {
+        self.parallel_key_deps = parallel_key_deps;
+    }
+
+    /// Retrieves the maximum number of outstanding commands (if any).
+    pub fn max_outstanding(&self) -> Option<usize> {
+        self.max_outstanding
+    }
+
+    /// Changes the maximum number of outstanding commands.
+    pub fn set_max_outstanding(&mut self, max_outstanding: Option<usize>) {
+        self.max_outstanding = max_outstanding;
+    }
+
+    /// Retrieves the seed (if any).
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Changes the seed.
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    /// Retrieves the storage mode.
+    pub fn storage_mode(&self) -> StorageMode {
+        self.storage_mode
+    }
+
+    /// Changes the storage mode.
+    pub fn set_storage_mode(&mut self, storage_mode: StorageMode) {
+        self.storage_mode = storage_mode;
+    }
+
+    /// Retrieves the commit quorum size in basic (if any).
+    pub fn basic_commit_quorum_size(&self) -> Option<usize> {
+        self.basic_commit_quorum_size
+    }
+
+    /// Changes the commit quorum size in basic.
+    pub fn set_basic_commit_quorum_size(&mut self, size: Option<usize>) {
+        self.basic_commit_quorum_size = size;
+        self.check_basic_quorum_sizes();
+    }
+
+    /// Retrieves the execute quorum size in basic (if any).
+    pub fn basic_execute_quorum_size(&self) -> Option<usize> {
+        self.basic_execute_quorum_size
+    }
+
+    /// Changes the execute quorum size in basic.
+    pub fn set_basic_execute_quorum_size(&mut self, size: Option<usize>) {
+        self.basic_execute_quorum_size = size;
+        self.check_basic_quorum_sizes();
+    }
+
+    // Quorums must have at least one and at most `n` processes. Moreover, a
+    // command can only be executable once it's committed, and thus the
+    // execute quorum can't be smaller than the commit quorum.
+    fn check_basic_quorum_sizes(&self) {
+        let sizes = [
+            self.basic_commit_quorum_size,
+            self.basic_execute_quorum_size,
+        ];
+        for &size in sizes.iter().flatten() {
+            assert!(
+                size >= 1 && size <= self.n,
+                "quorum size {} should be between 1 and n = {}",
+                size,
+                self.n
+            );
+        }
+        if let (Some(commit), Some(execute)) = (
+            self.basic_commit_quorum_size,
+            self.basic_execute_quorum_size,
+        ) {
+            assert!(
+                execute >= commit,
+                "execute quorum size can't be smaller than the commit quorum size"
+            );
+        }
+    }
 }
 
 impl Config {
@@ -349,9 +787,16 @@ impl Config {
     }
 }
 
+// Retention of executed commands, also used when deserializing configs that
+// don't set it.
+fn default_executor_executed_retention() -> Duration {
+    Duration::from_secs(10)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kvs::RecordSelector;
 
     #[test]
     fn config() {
@@ -415,6 +860,89 @@ mod tests {
         config.set_executor_monitor_execution_order(true);
         assert_eq!(config.executor_monitor_execution_order(), true);
 
+        // by default, there's no executor maximum request batch size
+        assert_eq!(config.executor_max_request_batch(), None);
+
+        // change its value and check it has changed
+        config.set_executor_max_request_batch(10);
+        assert_eq!(config.executor_max_request_batch(), Some(10));
+
+        // by default, there's no executor execution delay
+        assert_eq!(config.executor_execution_delay(), None);
+
+        // change its value and check it has changed
+        let delay = Duration::from_millis(10);
+        config.set_executor_execution_delay(delay);
+        assert_eq!(config.executor_execution_delay(), Some(delay));
+
+        // by default, there's no executor eventual read staleness
+        assert_eq!(config.executor_eventual_read_staleness(), None);
+
+        // change its value and check it has changed
+        let staleness = Duration::from_millis(10);
+        config.set_executor_eventual_read_staleness(staleness);
+        assert_eq!(config.executor_eventual_read_staleness(), Some(staleness));
+
+        // by default, executors find SCCs recursively
+        assert!(!config.executor_iterative_scc_finder());
+
+        // but that can change
+        config.set_executor_iterative_scc_finder(true);
+        assert!(config.executor_iterative_scc_finder());
+
+        // by default, there's no executor write rate limit
+        assert_eq!(config.executor_max_writes_per_sec(), None);
+
+        // change its value and check it has changed
+        config.set_executor_max_writes_per_sec(100);
+        assert_eq!(config.executor_max_writes_per_sec(), Some(100));
+
+        // by default, executed commands are remembered for 10 seconds
+        assert_eq!(
+            config.executor_executed_retention(),
+            Duration::from_secs(10)
+        );
+
+        // change its value and check it has changed
+        let retention = Duration::from_secs(60);
+        config.set_executor_executed_retention(retention);
+        assert_eq!(config.executor_executed_retention(), retention);
+
+        // by default, commands are never aborted
+        assert_eq!(config.executor_abort_percentage(), 0);
+
+        // change its value and check it has changed
+        config.set_executor_abort_percentage(50);
+        assert_eq!(config.executor_abort_percentage(), 50);
+
+        // by default, keys are routed to executors by their hash
+        assert_eq!(config.executor_keys_per_range(), None);
+
+        // change its value and check it has changed
+        config.set_executor_keys_per_range(100);
+        assert_eq!(config.executor_keys_per_range(), Some(100));
+
+        // by default, ready commands are executed in SCC completion order
+        assert!(!config.executor_fifo_ordering());
+
+        // change its value and check it has changed
+        config.set_executor_fifo_ordering(true);
+        assert!(config.executor_fifo_ordering());
+
+        // by default, all ready commands are executed at once
+        assert_eq!(config.executor_execute_chunk_size(), None);
+
+        // change its value and check it has changed
+        config.set_executor_execute_chunk_size(10);
+        assert_eq!(config.executor_execute_chunk_size(), Some(10));
+
+        // by default, the dependency graph is not pre-sized
+        assert_eq!(config.executor_graph_capacity(), None);
+
+        // change its value and check it has changed
+        config.set_executor_graph_capacity(1000);
+        assert_eq!(config.executor_graph_capacity(), Some(1000));
+
         // by default, there's no garbage collection interval
         assert_eq!(config.gc_interval(), None);
 
@@ -429,6 +957,7 @@ mod tests {
         let leader = 1;
         config.set_leader(leader);
         assert_eq!(config.leader(), Some(leader));
+        assert!(config.has_leader());
 
         // by default, deps NFR is false
         assert!(!config.nfr());
@@ -487,6 +1016,62 @@ mod tests {
         // if we change it to true, it becomes true
         config.set_skip_fast_ack(true);
         assert!(config.skip_fast_ack());
+
+        // by default, transitive conflicts is false
+        assert!(!config.transitive_conflicts());
+
+        // if we change it to false, remains false
+        config.set_transitive_conflicts(false);
+        assert!(!config.transitive_conflicts());
+
+        // if we change it to true, it becomes true
+        config.set_transitive_conflicts(true);
+        assert!(config.transitive_conflicts());
+
+        // by default, key dependencies picked at runtime are sequential
+        assert!(!config.parallel_key_deps());
+
+        // if we change it to true, it becomes true
+        config.set_parallel_key_deps(true);
+        assert!(config.parallel_key_deps());
+
+        // by default, there's no max outstanding
+        assert_eq!(config.max_outstanding(), None);
+
+        // but that can change
+        config.set_max_outstanding(Some(100));
+        assert_eq!(config.max_outstanding(), Some(100));
+
+        // by default, there's no seed
+        assert_eq!(config.seed(), None);
+
+        // but that can change
+        config.set_seed(Some(42));
+        assert_eq!(config.seed(), Some(42));
+
+        // by default, each key has a single record
+        assert_eq!(config.storage_mode(), StorageMode::SingleRecord);
+
+        // but that can change
+        let mode = StorageMode::MultiRecord(RecordSelector::PnCounter {
+            process_id: 1,
+        });
+        config.set_storage_mode(mode);
+        assert_eq!(config.storage_mode(), mode);
+
+        // by default, there's no commit quorum size in basic
+        assert_eq!(config.basic_commit_quorum_size(), None);
+
+        // but that can change
+        config.set_basic_commit_quorum_size(Some(2));
+        assert_eq!(config.basic_commit_quorum_size(), Some(2));
+
+        // by default, there's no execute quorum size in basic
+        assert_eq!(config.basic_execute_quorum_size(), None);
+
+        // but that can change
+        config.set_basic_execute_quorum_size(Some(3));
+        assert_eq!(config.basic_execute_quorum_size(), Some(3));
     }
 
     #[test]
@@ -599,4 +1184,59 @@ mod tests {
         config.set_tempo_tiny_quorums(true);
         assert_eq!(config.tempo_quorum_sizes(), (4, 3, 5));
     }
+
+    #[test]
+    fn leaders() {
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_shard_count(3);
+
+        // by default, there's no leader
+        assert!(!config.has_leader());
+        assert_eq!(config.leader_of(0), None);
+
+        // set leaders for shards 0 and 1
+        let leaders = HashMap::from_iter(vec![(0, 2), (1, 4)]);
+        config.set_leaders(leaders);
+        assert!(config.has_leader());
+        assert_eq!(config.leader_of(0), Some(2));
+        assert_eq!(config.leader_of(1), Some(4));
+        assert_eq!(config.leader_of(2), None);
+
+        // shards without leader fallback to the global leader
+        config.set_leader(1);
+        assert_eq!(config.leader_of(0), Some(2));
+        assert_eq!(config.leader_of(2), Some(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn leaders_wrong_shard() {
+        let mut config = Config::new(3, 1);
+        // process 4 belongs to shard 1
+        config.set_leaders(HashMap::from_iter(vec![(0, 4)]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn execute_quorum_smaller_than_commit_quorum() {
+        let mut config = Config::new(5, 2);
+        config.set_basic_commit_quorum_size(Some(3));
+        config.set_basic_execute_quorum_size(Some(2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn quorum_size_larger_than_n() {
+        let mut config = Config::new(5, 2);
+        config.set_basic_execute_quorum_size(Some(6));
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_quorum() {
+        let mut config = Config::new(5, 2);
+        config.set_basic_commit_quorum_size(Some(0));
+    }
 }
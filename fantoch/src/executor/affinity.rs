@@ -0,0 +1,106 @@
+use crate::config::Config;
+use crate::kvs::Key;
+use crate::util;
+use crate::HashMap;
+
+/// Policy that decides which executor handles each key. Co-locating related
+/// keys on the same executor reduces cross-executor coordination. The policy
+/// is set per process, in the pool that forwards execution infos to its
+/// executors.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum KeyToExecutor {
+    /// Keys are routed by their hash.
+    #[default]
+    Hash,
+    /// Numeric keys are routed in ranges of `keys_per_executor`: keys `1` to
+    /// `keys_per_executor` go to executor 0, the following
+    /// `keys_per_executor` keys to executor 1, and so on. Non-numeric keys
+    /// are routed by their hash.
+    Range { keys_per_executor: u64 },
+    /// Keys are routed to the executor explicitly given. Keys not in the map
+    /// are routed by their hash. As maps can't be part of the `Config`, this
+    /// policy is set with the `executor_keys` option of protocol binaries.
+    Explicit(HashMap<Key, usize>),
+}
+
+impl KeyToExecutor {
+    /// Creates the policy set in `config`: keys are routed in ranges if
+    /// `executor_keys_per_range` is set, and by their hash otherwise.
+    pub fn from_config(config: &Config) -> Self {
+        match config.executor_keys_per_range() {
+            Some(keys_per_executor) => Self::Range { keys_per_executor },
+            None => Self::Hash,
+        }
+    }
+
+    /// Returns the index of the executor that should handle `key`. The
+    /// actual executor is this index modulo the number of executors.
+    pub fn index(&self, key: &Key) -> usize {
+        match self {
+            Self::Hash => Self::hash_index(key),
+            Self::Range { keys_per_executor } => match key.parse::<u64>() {
+                Ok(key) if key > 0 => ((key - 1) / keys_per_executor) as usize,
+                _ => Self::hash_index(key),
+            },
+            Self::Explicit(executors) => executors
+                .get(key)
+                .cloned()
+                .unwrap_or_else(|| Self::hash_index(key)),
+        }
+    }
+
+    /// Panics if the policy is not valid.
+    pub fn validate(&self) {
+        if let Self::Range { keys_per_executor } = self {
+            assert!(*keys_per_executor > 0, "the range size must be positive");
+        }
+    }
+
+    #[allow(clippy::ptr_arg)]
+    fn hash_index(key: &Key) -> usize {
+        util::key_hash(key) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range() {
+        let policy = KeyToExecutor::Range {
+            keys_per_executor: 100,
+        };
+        assert!((1..=100).all(|key| policy.index(&key.to_string()) == 0));
+        assert!((101..=200).all(|key| policy.index(&key.to_string()) == 1));
+
+        // non-numeric keys are routed by their hash
+        let key = String::from("A");
+        assert_eq!(policy.index(&key), KeyToExecutor::Hash.index(&key));
+    }
+
+    #[test]
+    fn from_config() {
+        let mut config = Config::new(3, 1);
+        assert_eq!(KeyToExecutor::from_config(&config), KeyToExecutor::Hash);
+
+        config.set_executor_keys_per_range(100);
+        assert_eq!(
+            KeyToExecutor::from_config(&config),
+            KeyToExecutor::Range {
+                keys_per_executor: 100
+            }
+        );
+    }
+
+    #[test]
+    fn explicit() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let mut executors = HashMap::new();
+        executors.insert(key_a.clone(), 3);
+        let policy = KeyToExecutor::Explicit(executors);
+        assert_eq!(policy.index(&key_a), 3);
+        assert_eq!(policy.index(&key_b), KeyToExecutor::Hash.index(&key_b));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::command::{Command, CommandResult, CommandResultBuilder};
-use crate::executor::ExecutorResult;
+use crate::executor::{ExecutorResult, ReadyResult};
 use crate::id::{ProcessId, Rifl, ShardId};
 use crate::trace;
 use crate::HashMap;
@@ -43,25 +43,54 @@ impl AggregatePending {
         self.pending.insert(rifl, cmd_result).is_none()
     }
 
+    /// Stops tracking a command that was rejected by an overloaded process,
+    /// returning the (result-less) `CommandResult` to be sent to the client.
+    pub fn rejected(&mut self, rifl: Rifl) -> Option<CommandResult> {
+        trace!(
+            "p{}: AggregatePending::rejected {:?}",
+            self.process_id,
+            rifl
+        );
+        self.pending.remove(&rifl)?;
+        Some(CommandResult::new_rejected(rifl))
+    }
+
     /// Adds a new partial command result.
     pub fn add_executor_result(
         &mut self,
         executor_result: ExecutorResult,
     ) -> Option<CommandResult> {
-        let ExecutorResult {
-            rifl,
-            key,
-            partial_results,
-        } = executor_result;
         // get current value:
         // - if it's not part of pending, then ignore it
         // (if it's not part of pending, it means that it is from a client from
         // another tempo process, and `pending.wait_for*` has not been
         // called)
+        let rifl = executor_result.rifl();
         let cmd_result_builder = self.pending.get_mut(&rifl)?;
 
+        // if the command was aborted, stop tracking it and notify the client
+        if executor_result.is_aborted() {
+            trace!(
+                "p{}: AggregatePending::add_partial {:?} was aborted",
+                self.process_id,
+                rifl
+            );
+            self.pending.remove(&rifl);
+            return Some(CommandResult::new_aborted(rifl));
+        }
+        let ReadyResult {
+            key,
+            partial_results,
+            shard_id,
+            phase_times,
+            ..
+        } = executor_result.unwrap_ready();
+
         // add partial result and check if it's ready
-        cmd_result_builder.add_partial(key, partial_results);
+        cmd_result_builder.add_partial(key, partial_results, shard_id);
+        if let Some(phase_times) = phase_times {
+            cmd_result_builder.add_phase_times(phase_times);
+        }
         if cmd_result_builder.ready() {
             trace!(
                 "p{}: AggregatePending::add_partial {:?} is ready",
@@ -90,7 +119,7 @@ impl AggregatePending {
 mod tests {
     use super::*;
     use crate::command::Command;
-    use crate::kvs::{KVOp, KVStore};
+    use crate::kvs::{KVOp, KVOpResult, KVStore};
 
     #[test]
     fn pending_flow() {
@@ -136,7 +165,8 @@ mod tests {
         assert!(!pending.wait_for(&put_b));
 
         // add the result of get b and assert that the command is not ready yet
-        let get_b_res = store.test_execute(&key_b, KVOp::Get);
+        let get_b_res =
+            KVOpResult::Value(store.test_execute(&key_b, KVOp::Get));
         let res = pending.add_executor_result(ExecutorResult::new(
             get_ab_rifl,
             key_b.clone(),
@@ -145,7 +175,9 @@ mod tests {
         assert!(res.is_none());
 
         // add the result of put a before being waited for
-        let put_a_res = store.test_execute(&key_a, KVOp::Put(foo.clone()));
+        let put_a_res = KVOpResult::Value(
+            store.test_execute(&key_a, KVOp::Put(foo.clone())),
+        );
         let res = pending.add_executor_result(ExecutorResult::new(
             put_a_rifl,
             key_a.clone(),
@@ -170,10 +202,15 @@ mod tests {
         assert_eq!(res.results().len(), 1);
 
         // check that there was nothing in the kvs before
-        assert_eq!(res.results().get(&key_a).unwrap(), &vec![None]);
+        assert_eq!(
+            res.results().get(&key_a).unwrap(),
+            &vec![KVOpResult::Value(None)]
+        );
 
         // add the result of put b and assert that the command is ready
-        let put_b_res = store.test_execute(&key_b, KVOp::Put(bar.clone()));
+        let put_b_res = KVOpResult::Value(
+            store.test_execute(&key_b, KVOp::Put(bar.clone())),
+        );
         let res = pending.add_executor_result(ExecutorResult::new(
             put_b_rifl,
             key_b.clone(),
@@ -186,10 +223,14 @@ mod tests {
         assert_eq!(res.results().len(), 1);
 
         // check that there was nothing in the kvs before
-        assert_eq!(res.results().get(&key_b).unwrap(), &vec![None]);
+        assert_eq!(
+            res.results().get(&key_b).unwrap(),
+            &vec![KVOpResult::Value(None)]
+        );
 
         // add the result of get a and assert that the command is ready
-        let get_a_res = store.test_execute(&key_a, KVOp::Get);
+        let get_a_res =
+            KVOpResult::Value(store.test_execute(&key_a, KVOp::Get));
         let res = pending.add_executor_result(ExecutorResult::new(
             get_ab_rifl,
             key_a.clone(),
@@ -203,7 +244,78 @@ mod tests {
         assert_eq!(res.results().len(), 2);
 
         // check that `get_ab` saw `put_a` but not `put_b`
-        assert_eq!(res.results().get(&key_a).unwrap(), &vec![Some(foo)]);
-        assert_eq!(res.results().get(&key_b).unwrap(), &vec![None]);
+        assert_eq!(
+            res.results().get(&key_a).unwrap(),
+            &vec![KVOpResult::Value(Some(foo))]
+        );
+        assert_eq!(
+            res.results().get(&key_b).unwrap(),
+            &vec![KVOpResult::Value(None)]
+        );
+    }
+
+    #[test]
+    fn aborted() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut pending = AggregatePending::new(process_id, shard_id);
+
+        // command get a and b submitted by client 3
+        let client_id = 3;
+        let rifl = Rifl::new(client_id, 1);
+        let cmd = Command::from(
+            rifl,
+            vec![
+                (String::from("A"), KVOp::Get),
+                (String::from("B"), KVOp::Get),
+            ],
+        );
+        assert!(pending.wait_for(&cmd));
+
+        // an aborted result is mapped to the client that submitted it
+        let executor_result = ExecutorResult::aborted(rifl);
+        assert!(executor_result.is_aborted());
+        assert_eq!(executor_result.client(), client_id);
+
+        // even though there were two keys, the command is immediately
+        // reported as aborted
+        let res = pending
+            .add_executor_result(executor_result)
+            .expect("there should be a command result");
+        assert!(res.is_aborted());
+        assert_eq!(res.rifl(), rifl);
+        assert!(res.results().is_empty());
+
+        // the command is no longer pending
+        assert!(pending
+            .add_executor_result(ExecutorResult::aborted(rifl))
+            .is_none());
+    }
+
+    #[test]
+    fn rejected() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut pending = AggregatePending::new(process_id, shard_id);
+
+        // command put a submitted by client 2
+        let rifl = Rifl::new(2, 1);
+        let cmd = Command::from(rifl, vec![(String::from("A"), KVOp::Put(1))]);
+
+        // rejecting a command that's not pending is a noop
+        assert!(pending.rejected(rifl).is_none());
+
+        // once waited for, the command is reported as rejected
+        assert!(pending.wait_for(&cmd));
+        let res = pending
+            .rejected(rifl)
+            .expect("there should be a command result");
+        assert!(res.is_rejected());
+        assert_eq!(res.rifl(), rifl);
+        assert!(res.results().is_empty());
+
+        // the command is no longer pending
+        assert!(pending.rejected(rifl).is_none());
+        assert!(pending.wait_for(&cmd));
     }
 }
@@ -1,51 +1,158 @@
+use crate::command::PhaseTimes;
+use crate::command_span;
 use crate::config::Config;
 use crate::executor::{
-    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
-    MessageKey,
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult, MessageKey,
 };
-use crate::id::{ProcessId, Rifl, ShardId};
+use crate::id::{Dot, ProcessId, Rifl, ShardId};
 use crate::kvs::{KVOp, KVStore, Key};
 use crate::time::SysTime;
+use crate::HashSet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct BasicExecutor {
     store: KVStore,
     metrics: ExecutorMetrics,
     to_clients: Vec<ExecutorResult>,
+    // if set, results are only released `execution_delay` after execution;
+    // until then, they're kept in `delayed` along with their release time
+    execution_delay: Option<Duration>,
+    delayed: VecDeque<(u64, ExecutorResult)>,
+    // if set, read-only operations are executed in `snapshot`, a copy of
+    // `store` taken at `snapshot_time` and refreshed every
+    // `eventual_read_staleness`
+    eventual_read_staleness: Option<Duration>,
+    snapshot: KVStore,
+    snapshot_time: u64,
+    // commands (and the key) already executed, so that duplicate deliveries
+    // are not executed twice; `executed_order` keeps them sorted by execution
+    // time so that they can be forgotten once `executed_retention` expires
+    executed: HashSet<(Rifl, Key)>,
+    executed_retention: Duration,
+    executed_order: VecDeque<(u64, Rifl, Key)>,
+    // percentage of commands aborted the first time they're executed;
+    // `aborted` keeps the commands (and the key) already aborted, so that
+    // their retries are executed
+    abort_percentage: usize,
+    aborted: HashSet<(Rifl, Key)>,
 }
 
 impl Executor for BasicExecutor {
     type ExecutionInfo = BasicExecutionInfo;
 
-    fn new(
-        _process_id: ProcessId,
-        _shard_id: ShardId,
-        _config: Config,
-    ) -> Self {
+    fn new(_process_id: ProcessId, _shard_id: ShardId, config: Config) -> Self {
         let monitor = false;
-        let store = KVStore::new(monitor);
+        let store = KVStore::with_mode(monitor, config.storage_mode());
         let metrics = ExecutorMetrics::new();
         let to_clients = Vec::new();
+        let execution_delay = config.executor_execution_delay();
+        let delayed = VecDeque::new();
+        let eventual_read_staleness = config.executor_eventual_read_staleness();
+        let snapshot = KVStore::with_mode(monitor, config.storage_mode());
+        let snapshot_time = 0;
+        let executed = HashSet::new();
+        let executed_retention = config.executor_executed_retention();
+        let executed_order = VecDeque::new();
+        let abort_percentage = config.executor_abort_percentage();
+        let aborted = HashSet::new();
 
         Self {
             store,
             metrics,
             to_clients,
+            execution_delay,
+            delayed,
+            eventual_read_staleness,
+            snapshot,
+            snapshot_time,
+            executed,
+            executed_retention,
+            executed_order,
+            abort_percentage,
+            aborted,
         }
     }
 
-    fn handle(&mut self, info: Self::ExecutionInfo, _time: &dyn SysTime) {
-        let BasicExecutionInfo { rifl, key, ops } = info;
+    fn cleanup(&mut self, time: &dyn SysTime) {
+        self.release_delayed(time);
+        self.maybe_refresh_snapshot(time);
+        self.gc_executed(time);
+    }
+
+    fn handle(&mut self, info: Self::ExecutionInfo, time: &dyn SysTime) {
+        let BasicExecutionInfo {
+            dot,
+            rifl,
+            key,
+            ops,
+        } = info;
+        // ignore duplicate deliveries (this check must come before the abort
+        // one, as the abort marker is cleared once the retry is executed)
+        self.gc_executed(time);
+        if self.executed.contains(&(rifl, key.clone())) {
+            self.metrics.aggregate(ExecutorMetricsKind::Duplicate, 1);
+            return;
+        }
+        // abort the first execution of some commands, so that their clients
+        // retry them
+        if self.should_abort(rifl) && self.aborted.insert((rifl, key.clone())) {
+            self.to_clients.push(ExecutorResult::aborted(rifl));
+            return;
+        }
+        self.aborted.remove(&(rifl, key.clone()));
+        self.executed.insert((rifl, key.clone()));
+        self.executed_order
+            .push_back((time.millis(), rifl, key.clone()));
         // take the ops inside the arc if we're the last with a
         // reference to it (otherwise, clone them)
         let ops =
             Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone());
-        // execute op in the `KVStore`
-        let partial_results = self.store.execute(&key, ops, rifl);
-        self.to_clients
-            .push(ExecutorResult::new(rifl, key, partial_results));
+        // execute op in the `KVStore`; if eventual reads are enabled,
+        // read-only ops are executed in the (possibly stale) snapshot
+        let read_only = ops.iter().all(KVOp::is_read);
+        let partial_results = if read_only && self.eventual_reads() {
+            self.maybe_refresh_snapshot(time);
+            command_span!(
+                "execute",
+                rifl,
+                time,
+                self.snapshot.execute_from(&key, ops, rifl, dot.source())
+            )
+        } else {
+            command_span!(
+                "execute",
+                rifl,
+                time,
+                self.store.execute_from(&key, ops, rifl, dot.source())
+            )
+        };
+        let executor_result = ExecutorResult::new(rifl, key, partial_results);
+        // commands are committed when handed to the executor, and executed
+        // once the execution delay (if any) expires
+        let commit_time = time.millis();
+        match self.execution_delay {
+            Some(delay) => {
+                let release_time = commit_time + delay.as_millis() as u64;
+                let phase_times = PhaseTimes::new(commit_time, release_time);
+                let executor_result =
+                    executor_result.with_phase_times(phase_times);
+                self.delayed.push_back((release_time, executor_result));
+                self.release_delayed(time);
+            }
+            None => {
+                let phase_times = PhaseTimes::new(commit_time, commit_time);
+                let executor_result =
+                    executor_result.with_phase_times(phase_times);
+                self.to_clients.push(executor_result);
+            }
+        }
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
@@ -65,16 +172,79 @@ impl Executor for BasicExecutor {
     }
 }
 
+impl BasicExecutor {
+    fn eventual_reads(&self) -> bool {
+        self.eventual_read_staleness.is_some()
+    }
+
+    // the decision only depends on the command identifier, so that all
+    // replicas (and all keys) abort the same commands
+    fn should_abort(&self, rifl: Rifl) -> bool {
+        if self.abort_percentage == 0 {
+            return false;
+        }
+        let seed = rifl.source().rotate_left(32) ^ rifl.sequence();
+        StdRng::seed_from_u64(seed).gen_range(0..100) < self.abort_percentage
+    }
+
+    fn maybe_refresh_snapshot(&mut self, time: &dyn SysTime) {
+        if let Some(staleness) = self.eventual_read_staleness {
+            let now = time.millis();
+            if now >= self.snapshot_time + staleness.as_millis() as u64 {
+                self.snapshot = self.store.clone();
+                self.snapshot_time = now;
+            }
+        }
+    }
+
+    fn gc_executed(&mut self, time: &dyn SysTime) {
+        let retention = self.executed_retention.as_millis() as u64;
+        // since time never goes backwards, execution times are sorted
+        let now = time.millis();
+        while let Some((execution_time, _, _)) = self.executed_order.front() {
+            if execution_time + retention > now {
+                break;
+            }
+            let (_, rifl, key) = self
+                .executed_order
+                .pop_front()
+                .expect("there should be an executed command");
+            self.executed.remove(&(rifl, key));
+        }
+    }
+
+    fn release_delayed(&mut self, time: &dyn SysTime) {
+        // since time never goes backwards, release times are sorted
+        let now = time.millis();
+        while let Some((release_time, _)) = self.delayed.front() {
+            if *release_time > now {
+                break;
+            }
+            let (_, executor_result) = self
+                .delayed
+                .pop_front()
+                .expect("there should be a delayed result");
+            self.to_clients.push(executor_result);
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BasicExecutionInfo {
+    dot: Dot,
     rifl: Rifl,
     key: Key,
     ops: Arc<Vec<KVOp>>,
 }
 
 impl BasicExecutionInfo {
-    pub fn new(rifl: Rifl, key: Key, ops: Arc<Vec<KVOp>>) -> Self {
-        Self { rifl, key, ops }
+    pub fn new(dot: Dot, rifl: Rifl, key: Key, ops: Arc<Vec<KVOp>>) -> Self {
+        Self {
+            dot,
+            rifl,
+            key,
+            ops,
+        }
     }
 }
 
@@ -83,3 +253,159 @@ impl MessageKey for BasicExecutionInfo {
         &self.key
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kvs::KVOpResult;
+    use crate::time::SimTime;
+
+    fn execute(
+        executor: &mut BasicExecutor,
+        rifl: Rifl,
+        op: KVOp,
+        time: &dyn SysTime,
+    ) -> ExecutorResult {
+        let key = String::from("A");
+        let dot = Dot::new(1, rifl.sequence());
+        let ops = Arc::new(vec![op]);
+        let info = BasicExecutionInfo::new(dot, rifl, key, ops);
+        executor.handle(info, time);
+        executor.to_clients().expect("there should be a result")
+    }
+
+    #[test]
+    fn duplicate() {
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut executor = BasicExecutor::new(1, 0, config);
+        let time = SimTime::new();
+
+        // deliver the same command twice
+        let dot = Dot::new(1, 1);
+        let rifl = Rifl::new(1, 1);
+        let key = String::from("A");
+        let ops = Arc::new(vec![KVOp::Add(10)]);
+        for _ in 0..2 {
+            let info =
+                BasicExecutionInfo::new(dot, rifl, key.clone(), ops.clone());
+            executor.handle(info, &time);
+        }
+
+        // it's only executed once
+        let result = executor.to_clients().expect("there should be a result");
+        assert_eq!(result.rifl(), rifl);
+        assert!(executor.to_clients().is_none());
+        assert_eq!(
+            executor
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::Duplicate),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn abort_then_duplicate() {
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_executor_abort_percentage(100);
+        let mut executor = BasicExecutor::new(1, 0, config);
+        let time = SimTime::new();
+
+        // deliver a command, its retry, and then the same command again
+        let dot = Dot::new(1, 1);
+        let rifl = Rifl::new(1, 1);
+        let key = String::from("A");
+        let ops = Arc::new(vec![KVOp::Add(10)]);
+        let mut results = Vec::new();
+        for _ in 0..3 {
+            let info =
+                BasicExecutionInfo::new(dot, rifl, key.clone(), ops.clone());
+            executor.handle(info, &time);
+            while let Some(result) = executor.to_clients() {
+                results.push(result);
+            }
+        }
+
+        // it's aborted exactly once, executed once, and then a duplicate
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.iter().filter(|r| r.is_aborted()).count(), 1);
+        assert_eq!(
+            executor
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::Duplicate),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn executed_gc() {
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        let mut time = SimTime::new();
+
+        // by default, executed commands are forgotten after a while
+        let mut executor = BasicExecutor::new(1, 0, config);
+        execute(&mut executor, Rifl::new(1, 1), KVOp::Add(10), &time);
+        time.add_millis(1_000_000);
+        executor.cleanup(&time);
+        assert!(executor.executed.is_empty());
+        assert!(executor.executed_order.is_empty());
+
+        // and the retention window can be changed
+        let retention = Duration::from_secs(60);
+        config.set_executor_executed_retention(retention);
+        let mut executor = BasicExecutor::new(1, 0, config);
+
+        // execute a command
+        execute(&mut executor, Rifl::new(1, 1), KVOp::Add(10), &time);
+        assert_eq!(executor.executed.len(), 1);
+
+        // within the retention window, it's remembered
+        time.add_millis(retention.as_millis() as u64 - 1);
+        executor.cleanup(&time);
+        assert_eq!(executor.executed.len(), 1);
+
+        // after the retention window, it's forgotten
+        time.add_millis(1);
+        executor.cleanup(&time);
+        assert!(executor.executed.is_empty());
+        assert!(executor.executed_order.is_empty());
+    }
+
+    #[test]
+    fn eventual_reads() {
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_executor_eventual_read_staleness(Duration::from_millis(10));
+        let mut executor = BasicExecutor::new(1, 0, config);
+        let mut time = SimTime::new();
+
+        // the write goes to the live store
+        let put = execute(&mut executor, Rifl::new(1, 1), KVOp::Put(10), &time);
+        assert_eq!(
+            put.unwrap_ready().partial_results,
+            vec![KVOpResult::Value(None)]
+        );
+
+        // within the staleness window, the read sees the pre-write value
+        time.add_millis(5);
+        let get = execute(&mut executor, Rifl::new(1, 2), KVOp::Get, &time);
+        assert_eq!(
+            get.unwrap_ready().partial_results,
+            vec![KVOpResult::Value(None)]
+        );
+
+        // after the staleness window, the read sees the write
+        time.add_millis(5);
+        let get = execute(&mut executor, Rifl::new(1, 3), KVOp::Get, &time);
+        assert_eq!(
+            get.unwrap_ready().partial_results,
+            vec![KVOpResult::Value(Some(10))]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 // This module contains the definition of `Pending`.
 mod aggregate;
 
+// This module contains the definition of `KeyToExecutor`.
+mod affinity;
+
 // This module contains the implementation of a basic executor that executes
 // operations as soon as it receives them.
 mod basic;
@@ -9,17 +12,19 @@ mod basic;
 mod monitor;
 
 // Re-exports.
+pub use affinity::KeyToExecutor;
 pub use aggregate::AggregatePending;
 pub use basic::{BasicExecutionInfo, BasicExecutor};
 pub use monitor::ExecutionOrderMonitor;
 
+use crate::command::PhaseTimes;
 use crate::config::Config;
-use crate::id::{ProcessId, Rifl, ShardId};
+use crate::id::{ClientId, ProcessId, Rifl, ShardId};
 use crate::kvs::{KVOpResult, Key};
 use crate::metrics::Metrics;
 use crate::protocol::{CommittedAndExecuted, MessageIndex};
 use crate::time::SysTime;
-use crate::util;
+use crate::HashSet;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug};
@@ -28,6 +33,8 @@ pub trait Executor: Clone {
     // TODO why is Send needed?
     type ExecutionInfo: Debug
         + Clone
+        + PartialEq
+        + Eq
         + Serialize
         + DeserializeOwned
         + Send
@@ -49,6 +56,11 @@ pub trait Executor: Clone {
         // overwrite this
     }
 
+    fn set_replicated_shards(&mut self, _shards: HashSet<ShardId>) {
+        // executors that support replicating only some shards should overwrite
+        // this
+    }
+
     fn handle(&mut self, infos: Self::ExecutionInfo, time: &dyn SysTime);
 
     #[must_use]
@@ -85,6 +97,11 @@ pub trait Executor: Clone {
 
     fn metrics(&self) -> &ExecutorMetrics;
 
+    fn reset_metrics(&mut self) {
+        // executors that collect metrics should overwrite this, so that
+        // metrics can be measured separately in each phase of an experiment
+    }
+
     fn monitor(&self) -> Option<ExecutionOrderMonitor>;
 }
 
@@ -127,6 +144,9 @@ pub enum ExecutorMetricsKind {
     OutRequests,
     InRequests,
     InRequestReplies,
+    DepCount,
+    Duplicate,
+    CrossShardWait,
 }
 
 impl Debug for ExecutorMetricsKind {
@@ -134,6 +154,7 @@ impl Debug for ExecutorMetricsKind {
         match self {
             // general metric
             ExecutorMetricsKind::ExecutionDelay => write!(f, "execution_delay"),
+            ExecutorMetricsKind::Duplicate => write!(f, "duplicate"),
             // graph executor specific
             ExecutorMetricsKind::ChainSize => write!(f, "chain_size"),
             ExecutorMetricsKind::OutRequests => write!(f, "out_requests"),
@@ -141,6 +162,10 @@ impl Debug for ExecutorMetricsKind {
             ExecutorMetricsKind::InRequestReplies => {
                 write!(f, "in_request_replies")
             }
+            ExecutorMetricsKind::DepCount => write!(f, "dep_count"),
+            ExecutorMetricsKind::CrossShardWait => {
+                write!(f, "cross_shard_wait")
+            }
         }
     }
 }
@@ -148,6 +173,13 @@ impl Debug for ExecutorMetricsKind {
 pub trait MessageKey {
     /// Returns which `key` the execution info is about.
     fn key(&self) -> &Key;
+
+    /// Returns all the keys the execution info is about. This should be
+    /// overridden by execution infos spanning keys that may be handled by
+    /// different executors, so that they are forwarded to all of them.
+    fn keys(&self) -> Vec<&Key> {
+        vec![self.key()]
+    }
 }
 
 impl<A> MessageIndex for A
@@ -155,30 +187,167 @@ where
     A: MessageKey,
 {
     fn index(&self) -> Option<(usize, usize)> {
-        Some(key_index(self.key()))
+        self.index_with(&KeyToExecutor::Hash)
+    }
+
+    fn indexes(&self) -> Vec<(usize, usize)> {
+        self.indexes_with(&KeyToExecutor::Hash)
+    }
+
+    fn index_with(
+        &self,
+        key_to_executor: &KeyToExecutor,
+    ) -> Option<(usize, usize)> {
+        Some(key_index(key_to_executor, self.key()))
+    }
+
+    fn indexes_with(
+        &self,
+        key_to_executor: &KeyToExecutor,
+    ) -> Vec<(usize, usize)> {
+        let mut indexes: Vec<_> = self
+            .keys()
+            .into_iter()
+            .map(|key| key_index(key_to_executor, key))
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes
     }
 }
 
-// The index of a key is its hash
+// The index of a key is given by the `KeyToExecutor` policy (by default, its
+// hash)
 #[allow(clippy::ptr_arg)]
-fn key_index(key: &Key) -> (usize, usize) {
-    let index = util::key_hash(key) as usize;
-    (0, index)
+fn key_index(key_to_executor: &KeyToExecutor, key: &Key) -> (usize, usize) {
+    (0, key_to_executor.index(key))
 }
 
 #[derive(Debug, Clone)]
-pub struct ExecutorResult {
-    pub rifl: Rifl,
-    pub key: Key,
-    pub partial_results: Vec<KVOpResult>,
+pub enum ExecutorResult {
+    // partial result of a command, produced by the executor of one its keys
+    Ready(ReadyResult),
+    // the command with this identifier was aborted (and there are no partial
+    // results), so that its client can retry it
+    Aborted(Rifl),
 }
 
 impl ExecutorResult {
     pub fn new(rifl: Rifl, key: Key, partial_results: Vec<KVOpResult>) -> Self {
-        ExecutorResult {
+        ExecutorResult::Ready(ReadyResult {
             rifl,
             key,
             partial_results,
+            shard_id: None,
+            phase_times: None,
+        })
+    }
+
+    /// Creates a result notifying that the command with identifier `rifl` was
+    /// aborted, so that its client can retry it.
+    pub fn aborted(rifl: Rifl) -> Self {
+        ExecutorResult::Aborted(rifl)
+    }
+
+    /// Checks whether the command was aborted.
+    pub fn is_aborted(&self) -> bool {
+        matches!(self, ExecutorResult::Aborted(_))
+    }
+
+    /// Returns the identifier of the command.
+    pub fn rifl(&self) -> Rifl {
+        match self {
+            ExecutorResult::Ready(result) => result.rifl,
+            ExecutorResult::Aborted(rifl) => *rifl,
         }
     }
+
+    /// Returns the client that submitted the command.
+    pub fn client(&self) -> ClientId {
+        self.rifl().source()
+    }
+
+    /// Returns the partial result, panicking if the command was aborted.
+    pub fn unwrap_ready(self) -> ReadyResult {
+        match self {
+            ExecutorResult::Ready(result) => result,
+            ExecutorResult::Aborted(rifl) => {
+                panic!("called unwrap_ready on aborted command {:?}", rifl)
+            }
+        }
+    }
+
+    /// Records the shard that produced this result.
+    pub fn with_shard(self, shard_id: ShardId) -> Self {
+        self.map_ready(|result| result.shard_id = Some(shard_id))
+    }
+
+    /// Records the times at which the command was committed and executed.
+    pub fn with_phase_times(self, phase_times: PhaseTimes) -> Self {
+        self.map_ready(|result| result.phase_times = Some(phase_times))
+    }
+
+    fn map_ready<F>(mut self, update: F) -> Self
+    where
+        F: FnOnce(&mut ReadyResult),
+    {
+        if let ExecutorResult::Ready(result) = &mut self {
+            update(result);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadyResult {
+    pub rifl: Rifl,
+    pub key: Key,
+    pub partial_results: Vec<KVOpResult>,
+    // shard that produced this result; only set for multi-shard commands
+    pub shard_id: Option<ShardId>,
+    // times at which the command was committed and executed; only set by
+    // executors that record them
+    pub phase_times: Option<PhaseTimes>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executor_result_ready() {
+        let client_id = 3;
+        let rifl = Rifl::new(client_id, 1);
+        let key = String::from("A");
+        let partial_results = vec![KVOpResult::Value(Some(10))];
+        let executor_result =
+            ExecutorResult::new(rifl, key.clone(), partial_results.clone())
+                .with_phase_times(PhaseTimes::new(1, 2));
+        assert!(!executor_result.is_aborted());
+        assert_eq!(executor_result.rifl(), rifl);
+        assert_eq!(executor_result.client(), client_id);
+
+        let result = executor_result.unwrap_ready();
+        assert_eq!(result.rifl, rifl);
+        assert_eq!(result.key, key);
+        assert_eq!(result.partial_results, partial_results);
+        assert_eq!(result.phase_times, Some(PhaseTimes::new(1, 2)));
+    }
+
+    #[test]
+    fn executor_result_aborted() {
+        let client_id = 3;
+        let rifl = Rifl::new(client_id, 1);
+        let executor_result = ExecutorResult::aborted(rifl).with_shard(1);
+        assert!(executor_result.is_aborted());
+        assert_eq!(executor_result.rifl(), rifl);
+        assert_eq!(executor_result.client(), client_id);
+    }
+
+    #[test]
+    #[should_panic]
+    fn executor_result_aborted_unwrap_ready() {
+        let rifl = Rifl::new(3, 1);
+        ExecutorResult::aborted(rifl).unwrap_ready();
+    }
 }
@@ -1,25 +1,30 @@
 use crate::id::Rifl;
 use crate::kvs::Key;
-use crate::HashMap;
+use crate::{HashMap, HashSet};
 
 /// This structure can be used to monitor the order in which commands are
 /// executed, per key, and then check that all processes have the same order
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExecutionOrderMonitor {
     order_per_key: HashMap<Key, Vec<Rifl>>,
+    // read-only commands executed (whose order is not monitored)
+    reads: HashSet<Rifl>,
 }
 
 impl ExecutionOrderMonitor {
     pub fn new() -> Self {
         Self {
             order_per_key: Default::default(),
+            reads: Default::default(),
         }
     }
 
     /// Adds a new command to the monitor.
-    /// Read-only commandds are ignored.
+    /// The order of read-only commands is ignored (they're only recorded as
+    /// executed).
     pub fn add(&mut self, key: &Key, read_only: bool, rifl: Rifl) {
         if read_only {
+            self.reads.insert(rifl);
             return;
         }
 
@@ -39,6 +44,7 @@ impl ExecutionOrderMonitor {
             // that's not the case
             assert!(result.is_none());
         }
+        self.reads.extend(other.reads);
     }
 
     pub fn get_order(&self, key: &Key) -> Option<&Vec<Rifl>> {
@@ -52,4 +58,80 @@ impl ExecutionOrderMonitor {
     pub fn len(&self) -> usize {
         self.order_per_key.len()
     }
+
+    /// Returns the number of (distinct) commands executed. Commands that
+    /// access multiple keys are only counted once.
+    pub fn executed_count(&self) -> usize {
+        self.executed().len()
+    }
+
+    /// Checks whether the command with this `rifl` has been executed.
+    pub fn contains(&self, rifl: Rifl) -> bool {
+        self.reads.contains(&rifl)
+            || self
+                .order_per_key
+                .values()
+                .any(|rifls| rifls.contains(&rifl))
+    }
+
+    /// Returns the commands in `expected` that have not been executed.
+    pub fn missing(&self, expected: &HashSet<Rifl>) -> HashSet<Rifl> {
+        let executed = self.executed();
+        expected
+            .iter()
+            .filter(|rifl| !executed.contains(rifl))
+            .cloned()
+            .collect()
+    }
+
+    fn executed(&self) -> HashSet<Rifl> {
+        self.order_per_key
+            .values()
+            .flatten()
+            .chain(self.reads.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let rifl_1 = Rifl::new(1, 1);
+        let rifl_2 = Rifl::new(1, 2);
+        let rifl_3 = Rifl::new(2, 1);
+        let rifl_4 = Rifl::new(2, 2);
+        let rifl_5 = Rifl::new(3, 1);
+
+        let mut monitor = ExecutionOrderMonitor::new();
+        // rifl 1 accesses both keys
+        monitor.add(&key_a, false, rifl_1);
+        monitor.add(&key_b, false, rifl_1);
+        monitor.add(&key_a, false, rifl_2);
+        monitor.add(&key_b, false, rifl_3);
+        // read-only commands are not part of the order, but are executed
+        monitor.add(&key_a, true, rifl_4);
+        assert_eq!(monitor.get_order(&key_a), Some(&vec![rifl_1, rifl_2]));
+
+        assert_eq!(monitor.executed_count(), 4);
+        assert!(monitor.contains(rifl_1));
+        assert!(monitor.contains(rifl_3));
+        assert!(monitor.contains(rifl_4));
+        assert!(!monitor.contains(rifl_5));
+
+        // all expected commands have been executed
+        let mut expected: HashSet<_> =
+            [rifl_1, rifl_2, rifl_3, rifl_4].into_iter().collect();
+        assert!(monitor.missing(&expected).is_empty());
+
+        // rifl 5 was never executed
+        expected.insert(rifl_5);
+        let missing: HashSet<_> = [rifl_5].into_iter().collect();
+        assert_eq!(monitor.missing(&expected), missing);
+    }
 }
@@ -1,34 +1,356 @@
 use crate::executor::ExecutionOrderMonitor;
-use crate::id::Rifl;
+use crate::id::{ProcessId, Rifl};
 use crate::HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 // Definition of `Key` and `Value` types.
 pub type Key = String;
 pub type Value = u16;
 
+/// Number of records of each key in protocols that split keys into multiple
+/// records (and in the `KVStore`, with `StorageMode::MultiRecord`).
+pub const RECORDS_PER_KEY: usize = 30;
+
 #[derive(
     Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
 )]
-pub enum KVOp {
+pub enum KVOp<V = Value> {
     Get,
-    Put(Value),
-    Add(Value),
-    Subtract(Value),
+    Put(V),
+    /// Like `Put`, but with one value per record of the key (for protocols
+    /// that split keys into multiple records), starting with the first
+    /// record; values beyond the last record are ignored. If the key has a
+    /// single record, its value is the sum of all values.
+    PutRecords(Vec<V>),
+    Add(V),
+    Subtract(V),
+    /// Like `Add`, but returns the value before the add (i.e. fetch-and-add).
+    /// A missing key is treated as zero (see `ValueCodec::zero`).
+    AddReturningOld(V),
     Delete,
+    /// Deletes the key only if its current value is the one provided. In both
+    /// cases, the current value is returned.
+    DeleteIf(V),
+    /// Redistributes the value of a key evenly across its records, returning
+    /// the total. If the key has a single record, this is a no-op;
+    /// multi-record protocols treat it as accessing all of the key's records.
+    Rebalance,
+    /// Like `Get`, but returns the value of each record of the key instead of
+    /// their sum. If the key has a single record, a single value is returned.
+    GetRecords,
+}
+
+impl<V> KVOp<V> {
+    /// Checks whether the op only reads the key (i.e. it's a `Get` or a
+    /// `GetRecords`).
+    pub fn is_read(&self) -> bool {
+        matches!(self, Self::Get | Self::GetRecords)
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum KVOpResult<V = Value> {
+    /// The value of the key (if any), as returned by all ops but
+    /// `GetRecords`.
+    Value(Option<V>),
+    /// The value of each record of the key (if the key exists), as returned
+    /// by `GetRecords`.
+    Records(Option<Vec<V>>),
+}
+
+impl<V> KVOpResult<V> {
+    /// Returns the value of the key (if any). Panics if this is the result of
+    /// a `GetRecords`.
+    pub fn into_value(self) -> Option<V> {
+        match self {
+            Self::Value(value) => value,
+            Self::Records(_) => {
+                panic!("GetRecords should return the value of each record")
+            }
+        }
+    }
+
+    /// Returns the value of each record of the key (if the key exists).
+    /// Panics if this is not the result of a `GetRecords`.
+    pub fn into_records(self) -> Option<Vec<V>> {
+        match self {
+            Self::Records(records) => records,
+            Self::Value(_) => {
+                panic!("only GetRecords returns the value of each record")
+            }
+        }
+    }
+}
+
+/// Strategy used to pick the record accessed by `Add`s and `Subtract`s when
+/// keys are split into `RECORDS_PER_KEY` records. Except for `PnCounter`, the
+/// record picked only depends on the command's rifl, so that the dependencies
+/// computed by the protocols and the `KVStore` of every replica agree on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordSelector {
+    /// Picks a pseudo-random record, given `seed`.
+    Random { seed: u64 },
+    /// Cycles through all records, one command of each client at a time.
+    RoundRobin,
+    /// Always picks the record with the given index.
+    Fixed(usize),
+    /// Picks the record owned by process `process_id` (i.e. record
+    /// `process_id - 1`). This turns keys into PN-counters: the `Add`s and
+    /// `Subtract`s coordinated by a process are ordered on the record owned
+    /// by that process, and so they never conflict with the ones coordinated
+    /// by other processes. Every replica applies them to the record of the
+    /// coordinator, and so replicas end up with the same records.
+    ///
+    /// The process identifier set in the `Config` is only a placeholder: the
+    /// actual one is the coordinator of each command, set with `for_process`
+    /// (see `KVStore::execute_from`). There are only `RECORDS_PER_KEY`
+    /// records, and so record ownership wraps around: processes `p` and
+    /// `p + RECORDS_PER_KEY` share a record.
+    PnCounter { process_id: ProcessId },
+}
+
+impl Default for RecordSelector {
+    fn default() -> Self {
+        Self::Random { seed: 0 }
+    }
+}
+
+impl RecordSelector {
+    /// Returns the selector of the commands coordinated by process
+    /// `process_id`: with `PnCounter`, their `Add`s and `Subtract`s target the
+    /// record owned by that process; other selectors are the same for every
+    /// process.
+    pub fn for_process(self, process_id: ProcessId) -> Self {
+        match self {
+            Self::PnCounter { .. } => Self::PnCounter { process_id },
+            selector => selector,
+        }
+    }
+
+    /// Returns the record accessed by the `Add`s and `Subtract`s of the
+    /// command with this `rifl`.
+    pub fn select(&self, rifl: Rifl) -> usize {
+        match *self {
+            Self::Random { seed } => {
+                // mix the rifl into the seed, so that each command picks its
+                // own record
+                let seed =
+                    seed ^ rifl.source().rotate_left(32) ^ rifl.sequence();
+                StdRng::seed_from_u64(seed).gen_range(0..RECORDS_PER_KEY)
+            }
+            Self::RoundRobin => rifl.sequence() as usize % RECORDS_PER_KEY,
+            Self::Fixed(index) => {
+                assert!(
+                    index < RECORDS_PER_KEY,
+                    "record index should be lower than {}",
+                    RECORDS_PER_KEY
+                );
+                index
+            }
+            Self::PnCounter { process_id } => {
+                // process identifiers start at 1; process 0 wraps around to
+                // the last record
+                let process_id = process_id as usize % RECORDS_PER_KEY;
+                (process_id + RECORDS_PER_KEY - 1) % RECORDS_PER_KEY
+            }
+        }
+    }
+}
+
+/// How the `KVStore` lays out the value of each key.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum StorageMode {
+    /// Each key has a single record.
+    #[default]
+    SingleRecord,
+    /// Each key is split into `RECORDS_PER_KEY` records, and its value is the
+    /// sum of all records. `Add`s and `Subtract`s target the record picked by
+    /// the selector (which should be the one used to compute the dependencies
+    /// of commands).
+    MultiRecord(RecordSelector),
+}
+
+impl StorageMode {
+    /// Returns the storage mode of the commands coordinated by process
+    /// `process_id` (see `RecordSelector::for_process`).
+    pub fn for_process(self, process_id: ProcessId) -> Self {
+        match self {
+            Self::SingleRecord => Self::SingleRecord,
+            Self::MultiRecord(selector) => {
+                Self::MultiRecord(selector.for_process(process_id))
+            }
+        }
+    }
+}
+
+/// Error returned when an operation isn't supported by the values of a
+/// `ValueCodec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedOp;
+
+/// Defines the values held by a `KVStore`, and the semantics of the
+/// arithmetic operations on them. Values either support all arithmetic
+/// operations, or none of them.
+pub trait ValueCodec {
+    type Value: Clone + PartialEq;
+
+    /// Returns whether values support arithmetic operations.
+    fn supports_arithmetic() -> bool;
+
+    /// Returns the value of keys (and records) never written.
+    fn zero() -> Self::Value;
+
+    /// Computes the result of adding `value` to `current`.
+    fn add(
+        current: &Self::Value,
+        value: &Self::Value,
+    ) -> Result<Self::Value, UnsupportedOp>;
+
+    /// Computes the result of subtracting `value` from `current`.
+    fn subtract(
+        current: &Self::Value,
+        value: &Self::Value,
+    ) -> Result<Self::Value, UnsupportedOp>;
+
+    /// Splits `total` into `parts` values, as even as possible, that add up
+    /// to `total`.
+    fn split(
+        total: &Self::Value,
+        parts: usize,
+    ) -> Result<Vec<Self::Value>, UnsupportedOp>;
+}
+
+/// Codec for numeric values, in which arithmetic operations saturate instead
+/// of overflowing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumericValue;
+
+impl ValueCodec for NumericValue {
+    type Value = Value;
+
+    fn supports_arithmetic() -> bool {
+        true
+    }
+
+    fn zero() -> Value {
+        0
+    }
+
+    fn add(current: &Value, value: &Value) -> Result<Value, UnsupportedOp> {
+        Ok(current.saturating_add(*value))
+    }
+
+    fn subtract(
+        current: &Value,
+        value: &Value,
+    ) -> Result<Value, UnsupportedOp> {
+        Ok(current.saturating_sub(*value))
+    }
+
+    fn split(total: &Value, parts: usize) -> Result<Vec<Value>, UnsupportedOp> {
+        // each part gets the same share of the total, with the remainder
+        // spread across the first parts
+        let share = total / parts as Value;
+        let remainder = (total % parts as Value) as usize;
+        let parts = (0..parts)
+            .map(|index| share + (index < remainder) as Value)
+            .collect();
+        Ok(parts)
+    }
 }
 
-pub type KVOpResult = Option<Value>;
+/// Codec for byte blobs, on which arithmetic operations are not supported.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlobValue;
+
+impl ValueCodec for BlobValue {
+    type Value = Vec<u8>;
+
+    fn supports_arithmetic() -> bool {
+        false
+    }
+
+    fn zero() -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn add(_: &Vec<u8>, _: &Vec<u8>) -> Result<Vec<u8>, UnsupportedOp> {
+        Err(UnsupportedOp)
+    }
+
+    fn subtract(_: &Vec<u8>, _: &Vec<u8>) -> Result<Vec<u8>, UnsupportedOp> {
+        Err(UnsupportedOp)
+    }
+
+    fn split(_: &Vec<u8>, _: usize) -> Result<Vec<Vec<u8>>, UnsupportedOp> {
+        Err(UnsupportedOp)
+    }
+}
 
 #[derive(Default, Clone)]
-pub struct KVStore {
-    store: HashMap<Key, Value>,
+pub struct KVStore<C: ValueCodec = NumericValue> {
+    store: HashMap<Key, C::Value>,
     monitor: Option<ExecutionOrderMonitor>,
+    mode: StorageMode,
+    // the value of each record of each key, if in a multi-record mode
+    records: HashMap<Key, Vec<C::Value>>,
 }
 
 impl KVStore {
     /// Creates a new `KVStore` instance.
     pub fn new(monitor_execution_order: bool) -> Self {
+        Self::with_mode(monitor_execution_order, StorageMode::SingleRecord)
+    }
+
+    /// Creates a new `KVStore` instance that lays out keys according to
+    /// `mode`.
+    pub fn with_mode(monitor_execution_order: bool, mode: StorageMode) -> Self {
+        Self::with_codec(monitor_execution_order, mode)
+    }
+
+    /// Executes `KVOp`s in the `KVStore`, returning the value of the key.
+    #[cfg(test)]
+    pub fn test_execute(&mut self, key: &Key, op: KVOp) -> Option<Value> {
+        let mut results = self.execute(key, vec![op], Rifl::new(1, 1));
+        assert_eq!(results.len(), 1);
+        results.pop().unwrap().into_value()
+    }
+
+    pub fn execute(
+        &mut self,
+        key: &Key,
+        ops: Vec<KVOp>,
+        rifl: Rifl,
+    ) -> Vec<KVOpResult> {
+        self.try_execute(key, ops, rifl)
+            .expect("numeric values should support all operations")
+    }
+
+    pub fn execute_from(
+        &mut self,
+        key: &Key,
+        ops: Vec<KVOp>,
+        rifl: Rifl,
+        coordinator: ProcessId,
+    ) -> Vec<KVOpResult> {
+        self.try_execute_from(key, ops, rifl, coordinator)
+            .expect("numeric values should support all operations")
+    }
+}
+
+impl<C: ValueCodec> KVStore<C> {
+    /// Creates a new `KVStore` instance, holding values of the given codec,
+    /// that lays out keys according to `mode`.
+    pub fn with_codec(
+        monitor_execution_order: bool,
+        mode: StorageMode,
+    ) -> Self {
         let monitor = if monitor_execution_order {
             Some(ExecutionOrderMonitor::new())
         } else {
@@ -37,6 +359,8 @@ impl KVStore {
         Self {
             store: Default::default(),
             monitor,
+            mode,
+            records: Default::default(),
         }
     }
 
@@ -44,81 +368,242 @@ impl KVStore {
         self.monitor.as_ref()
     }
 
-    /// Executes `KVOp`s in the `KVStore`.
-    #[cfg(test)]
-    pub fn test_execute(&mut self, key: &Key, op: KVOp) -> KVOpResult {
-        let mut results = self.do_execute(key, vec![op]);
-        assert_eq!(results.len(), 1);
-        results.pop().unwrap()
+    /// Returns the value of each record of `key`, if the key exists and keys
+    /// are split into multiple records.
+    pub fn records(&self, key: &Key) -> Option<&Vec<C::Value>> {
+        self.records.get(key)
     }
 
-    pub fn execute(
+    /// Executes `KVOp`s in the `KVStore`. If some of them isn't supported by
+    /// its values, none of them is executed.
+    pub fn try_execute(
         &mut self,
         key: &Key,
-        ops: Vec<KVOp>,
+        ops: Vec<KVOp<C::Value>>,
         rifl: Rifl,
-    ) -> Vec<KVOpResult> {
+    ) -> Result<Vec<KVOpResult<C::Value>>, UnsupportedOp> {
+        self.do_try_execute(key, ops, rifl, self.mode)
+    }
+
+    /// Executes `KVOp`s of a command coordinated by process `coordinator` in
+    /// the `KVStore`: in a PN-counter, its `Add`s and `Subtract`s target the
+    /// record owned by the coordinator. If some of them isn't supported by its
+    /// values, none of them is executed.
+    pub fn try_execute_from(
+        &mut self,
+        key: &Key,
+        ops: Vec<KVOp<C::Value>>,
+        rifl: Rifl,
+        coordinator: ProcessId,
+    ) -> Result<Vec<KVOpResult<C::Value>>, UnsupportedOp> {
+        let mode = self.mode.for_process(coordinator);
+        self.do_try_execute(key, ops, rifl, mode)
+    }
+
+    fn do_try_execute(
+        &mut self,
+        key: &Key,
+        ops: Vec<KVOp<C::Value>>,
+        rifl: Rifl,
+        mode: StorageMode,
+    ) -> Result<Vec<KVOpResult<C::Value>>, UnsupportedOp> {
+        // validate every op before applying any, so that commands are never
+        // left half-executed
+        if !ops.iter().all(|op| self.supports(op)) {
+            return Err(UnsupportedOp);
+        }
         // update monitor, if we're monitoring
         if let Some(monitor) = self.monitor.as_mut() {
-            let read_only = ops.iter().all(|op| op == &KVOp::Get);
+            let read_only = ops.iter().all(KVOp::is_read);
             monitor.add(&key, read_only, rifl);
         }
-        self.do_execute(key, ops)
-    }
-
-    #[allow(clippy::ptr_arg)]
-    fn do_execute(&mut self, key: &Key, ops: Vec<KVOp>) -> Vec<KVOpResult> {
         ops.into_iter()
-            .map(|op| self.do_execute_op(key, op))
+            .map(|op| match mode {
+                StorageMode::SingleRecord => self.do_execute_op(key, op),
+                StorageMode::MultiRecord(selector) => {
+                    self.do_execute_records_op(key, op, selector.select(rifl))
+                }
+            })
             .collect()
     }
 
-    fn do_execute_op(&mut self, key: &Key, op: KVOp) -> KVOpResult {
-        match op {
+    // Checks whether `op` is supported by the values of this store, i.e. if
+    // it doesn't require arithmetic on values that don't support it.
+    fn supports(&self, op: &KVOp<C::Value>) -> bool {
+        if C::supports_arithmetic() {
+            return true;
+        }
+        match self.mode {
+            StorageMode::SingleRecord => !matches!(
+                op,
+                KVOp::Add(_)
+                    | KVOp::Subtract(_)
+                    | KVOp::AddReturningOld(_)
+                    | KVOp::PutRecords(_)
+            ),
+            // the value of a key split into records is their sum, and thus
+            // only ops that don't compute it are supported
+            StorageMode::MultiRecord(_) => matches!(
+                op,
+                KVOp::GetRecords | KVOp::Put(_) | KVOp::PutRecords(_)
+            ),
+        }
+    }
+
+    fn do_execute_op(
+        &mut self,
+        key: &Key,
+        op: KVOp<C::Value>,
+    ) -> Result<KVOpResult<C::Value>, UnsupportedOp> {
+        let result = match op {
             KVOp::Get => self.store.get(key).cloned(),
+            KVOp::GetRecords => {
+                let records =
+                    self.store.get(key).cloned().map(|value| vec![value]);
+                return Ok(KVOpResult::Records(records));
+            }
             KVOp::Put(value) => {
                 // don't return the previous value
                 self.store.insert(key.clone(), value);
                 None
             }
-            KVOp::Add(value) => {
-                // don't return the previous value
-                if let Some(old_value) = self.store.get_mut(key) {
-                    // In case the sum overflows, we will put the maximum possible value
-                    return match old_value.checked_add(value) {
-                        Some(new_value) => {
-                            *old_value = new_value;
-                            Some(new_value)
-                        },
-                        None => {
-                            let new_value = Value::MAX;
-                            *old_value = new_value;
-                            Some(new_value)
-                        }
-                    }
-                } 
+            KVOp::PutRecords(values) => {
+                let value = Self::sum(&values)?;
+                self.store.insert(key.clone(), value);
                 None
             }
-            KVOp::Subtract(value) => {
-                // don't return the previous value
-                if let Some(old_value) = self.store.get_mut(key) {
-                    // In case the subtraction overflows, we will put the minimum possible value
-                    return match old_value.checked_sub(value) {
-                        Some(new_value) => {
-                            *old_value = new_value;
-                            Some(new_value)
-                        },
-                        None => {
-                            let new_value = Value::MIN;
-                            *old_value = new_value;
-                            Some(new_value)
-                        }
-                    }
-                } 
-                None
+            KVOp::Add(value) => match self.store.get_mut(key) {
+                Some(current) => {
+                    *current = C::add(current, &value)?;
+                    Some(current.clone())
+                }
+                None => None,
+            },
+            KVOp::Subtract(value) => match self.store.get_mut(key) {
+                Some(current) => {
+                    *current = C::subtract(current, &value)?;
+                    Some(current.clone())
+                }
+                None => None,
+            },
+            KVOp::AddReturningOld(value) => {
+                let old_value = self.store.get(key).cloned();
+                let current = old_value.clone().unwrap_or_else(C::zero);
+                let new_value = C::add(&current, &value)?;
+                self.store.insert(key.clone(), new_value);
+                old_value
             }
             KVOp::Delete => self.store.remove(key),
-        }
+            KVOp::DeleteIf(value) => match self.store.get(key) {
+                // only delete if the value matches
+                Some(current) if *current == value => self.store.remove(key),
+                // otherwise, return the current value (if any)
+                current => current.cloned(),
+            },
+            // a single record is always balanced
+            KVOp::Rebalance => self.store.get(key).cloned(),
+        };
+        Ok(KVOpResult::Value(result))
+    }
+
+    // Same as `do_execute_op`, but with keys split into `RECORDS_PER_KEY`
+    // records. The value of a key is the sum of its records, and arithmetic
+    // operations target record `index`.
+    fn do_execute_records_op(
+        &mut self,
+        key: &Key,
+        op: KVOp<C::Value>,
+        index: usize,
+    ) -> Result<KVOpResult<C::Value>, UnsupportedOp> {
+        let zeroed = || vec![C::zero(); RECORDS_PER_KEY];
+        let result = match op {
+            KVOp::Get => self.records_value(key)?,
+            KVOp::GetRecords => {
+                let records = self.records.get(key).cloned();
+                return Ok(KVOpResult::Records(records));
+            }
+            KVOp::Put(value) => {
+                // the first record holds the value; the others are zeroed
+                let mut records = zeroed();
+                records[0] = value;
+                self.records.insert(key.clone(), records);
+                None
+            }
+            KVOp::PutRecords(values) => {
+                // only the records with a value are written
+                let records =
+                    self.records.entry(key.clone()).or_insert_with(zeroed);
+                records
+                    .iter_mut()
+                    .zip(values)
+                    .for_each(|(record, value)| *record = value);
+                None
+            }
+            KVOp::Add(value) => match self.records.get_mut(key) {
+                Some(records) => {
+                    records[index] = C::add(&records[index], &value)?;
+                    Some(Self::sum(records)?)
+                }
+                None => None,
+            },
+            KVOp::Subtract(value) => match self.records.get_mut(key) {
+                // the subtraction saturates on the record, even if the other
+                // records would be enough to cover it
+                Some(records) => {
+                    records[index] = C::subtract(&records[index], &value)?;
+                    Some(Self::sum(records)?)
+                }
+                None => None,
+            },
+            KVOp::AddReturningOld(value) => {
+                let old_value = self.records_value(key)?;
+                let records =
+                    self.records.entry(key.clone()).or_insert_with(zeroed);
+                records[index] = C::add(&records[index], &value)?;
+                old_value
+            }
+            KVOp::Delete => match self.records.remove(key) {
+                Some(records) => Some(Self::sum(&records)?),
+                None => None,
+            },
+            KVOp::DeleteIf(value) => match self.records_value(key)? {
+                // only delete if the value matches
+                Some(current) if current == value => {
+                    self.records.remove(key);
+                    Some(current)
+                }
+                // otherwise, return the current value (if any)
+                current => current,
+            },
+            KVOp::Rebalance => match self.records.get_mut(key) {
+                Some(records) => {
+                    let total = Self::sum(records)?;
+                    *records = C::split(&total, RECORDS_PER_KEY)?;
+                    Some(total)
+                }
+                None => None,
+            },
+        };
+        Ok(KVOpResult::Value(result))
+    }
+
+    // Computes the value of `key` (if it exists), i.e. the sum of its
+    // records.
+    fn records_value(
+        &self,
+        key: &Key,
+    ) -> Result<Option<C::Value>, UnsupportedOp> {
+        self.records
+            .get(key)
+            .map(|records| Self::sum(records))
+            .transpose()
+    }
+
+    // Computes the sum of `values`.
+    fn sum(values: &[C::Value]) -> Result<C::Value, UnsupportedOp> {
+        values
+            .iter()
+            .try_fold(C::zero(), |total, value| C::add(&total, value))
     }
 }
 
@@ -263,4 +748,450 @@ mod tests {
         // subtract key_a Maximum_Value -> some(MIM)
         assert_eq!(store.test_execute(&key_c, KVOp::Subtract(Value::MAX)), Some(Value::MIN));
     }
+
+    #[test]
+    fn put_records_flow() {
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        let key = String::from("A");
+
+        // put key with values 1, 2 and 3 -> none, and the value is their sum
+        assert_eq!(
+            store.test_execute(&key, KVOp::PutRecords(vec![1, 2, 3])),
+            None
+        );
+        assert_eq!(store.test_execute(&key, KVOp::Get), Some(6));
+
+        // put key with values that overflow -> none, and the value is MAX
+        assert_eq!(
+            store.test_execute(&key, KVOp::PutRecords(vec![Value::MAX, 1])),
+            None
+        );
+        assert_eq!(store.test_execute(&key, KVOp::Get), Some(Value::MAX));
+    }
+
+    // Executes `op` on `key`, as part of the command with rifl `(1,
+    // sequence)`.
+    fn execute(
+        store: &mut KVStore,
+        key: &Key,
+        op: KVOp,
+        sequence: u64,
+    ) -> Option<Value> {
+        let mut results = store.execute(key, vec![op], Rifl::new(1, sequence));
+        assert_eq!(results.len(), 1);
+        results.pop().unwrap().into_value()
+    }
+
+    #[test]
+    fn multi_record_flow() {
+        // store
+        let monitor = false;
+        let mode = StorageMode::MultiRecord(RecordSelector::RoundRobin);
+        let mut store = KVStore::with_mode(monitor, mode);
+
+        let key = String::from("A");
+
+        // a put with a value per record populates all of them
+        let values: Vec<_> = (1..=RECORDS_PER_KEY as Value).collect();
+        let total = values.iter().sum();
+        let put = KVOp::PutRecords(values.clone());
+        assert_eq!(execute(&mut store, &key, put, 1), None);
+        assert_eq!(store.records(&key), Some(&values));
+        assert_eq!(execute(&mut store, &key, KVOp::Get, 2), Some(total));
+
+        // a put with fewer values only writes the first records
+        let put = KVOp::PutRecords(vec![0, 0]);
+        assert_eq!(execute(&mut store, &key, put, 3), None);
+        let mut expected = values.clone();
+        expected[0] = 0;
+        expected[1] = 0;
+        assert_eq!(store.records(&key), Some(&expected));
+
+        // adds and subtracts only change the record picked by the rifl
+        let total = total - 1 - 2;
+        assert_eq!(
+            execute(&mut store, &key, KVOp::Add(10), 4),
+            Some(total + 10)
+        );
+        assert_eq!(
+            execute(&mut store, &key, KVOp::Subtract(Value::MAX), 5),
+            Some(total + 10 - 6)
+        );
+        expected[4] += 10;
+        expected[5] = 0;
+        assert_eq!(store.records(&key), Some(&expected));
+
+        // a put resets all records but the first
+        assert_eq!(execute(&mut store, &key, KVOp::Put(7), 6), None);
+        let mut expected = vec![0; RECORDS_PER_KEY];
+        expected[0] = 7;
+        assert_eq!(store.records(&key), Some(&expected));
+
+        // delete key -> some(7)
+        assert_eq!(execute(&mut store, &key, KVOp::Delete, 7), Some(7));
+        assert_eq!(store.records(&key), None);
+    }
+
+    #[test]
+    fn multi_record_rebalance_flow() {
+        // store
+        let monitor = false;
+        let mode = StorageMode::MultiRecord(RecordSelector::RoundRobin);
+        let mut store = KVStore::with_mode(monitor, mode);
+
+        let key = String::from("A");
+
+        // rebalance on a missing key -> none
+        assert_eq!(execute(&mut store, &key, KVOp::Rebalance, 1), None);
+
+        // add everything to a single record
+        let total = 3 * RECORDS_PER_KEY as Value + 2;
+        assert_eq!(execute(&mut store, &key, KVOp::Put(total), 2), None);
+
+        // rebalance returns the total and spreads it evenly
+        assert_eq!(execute(&mut store, &key, KVOp::Rebalance, 3), Some(total));
+        let records = store.records(&key).expect("key should exist");
+        assert!(records
+            .iter()
+            .enumerate()
+            .all(|(index, value)| *value == if index < 2 { 4 } else { 3 }));
+        assert_eq!(execute(&mut store, &key, KVOp::Get, 4), Some(total));
+    }
+
+    #[test]
+    fn fixed_record_flow() {
+        // store
+        let monitor = false;
+        let mode = StorageMode::MultiRecord(RecordSelector::Fixed(2));
+        let mut store = KVStore::with_mode(monitor, mode);
+
+        let key = String::from("A");
+        assert_eq!(execute(&mut store, &key, KVOp::Put(0), 1), None);
+
+        // every add and subtract lands on record 2
+        for sequence in 2..=10 {
+            execute(&mut store, &key, KVOp::Add(2), sequence);
+            execute(&mut store, &key, KVOp::Subtract(1), sequence);
+        }
+        let mut expected = vec![0; RECORDS_PER_KEY];
+        expected[2] = 9;
+        assert_eq!(store.records(&key), Some(&expected));
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_record_out_of_range() {
+        // there's no such record
+        let selector = RecordSelector::Fixed(RECORDS_PER_KEY);
+        selector.select(Rifl::new(1, 1));
+    }
+
+    #[test]
+    fn get_records_flow() {
+        // store
+        let monitor = false;
+        let mode = StorageMode::MultiRecord(RecordSelector::RoundRobin);
+        let mut store = KVStore::with_mode(monitor, mode);
+
+        let key = String::from("A");
+        let get_records = |store: &mut KVStore, sequence| {
+            let op = KVOp::GetRecords;
+            let mut results =
+                store.execute(&key, vec![op], Rifl::new(1, sequence));
+            assert_eq!(results.len(), 1);
+            results.pop().unwrap().into_records()
+        };
+
+        // get records on a missing key -> none
+        assert_eq!(get_records(&mut store, 1), None);
+
+        // write to specific records: the rifl with sequence `n` targets
+        // record `n`
+        assert_eq!(execute(&mut store, &key, KVOp::Put(0), 2), None);
+        assert_eq!(execute(&mut store, &key, KVOp::Add(10), 3), Some(10));
+        assert_eq!(execute(&mut store, &key, KVOp::Add(20), 5), Some(30));
+
+        // each record is read back individually, while a get returns their
+        // sum
+        let mut expected = vec![0; RECORDS_PER_KEY];
+        expected[3] = 10;
+        expected[5] = 20;
+        assert_eq!(get_records(&mut store, 6), Some(expected));
+        assert_eq!(execute(&mut store, &key, KVOp::Get, 7), Some(30));
+
+        // with a single record, get records returns its value
+        let mut store = KVStore::new(monitor);
+        assert_eq!(execute(&mut store, &key, KVOp::Put(7), 1), None);
+        assert_eq!(get_records(&mut store, 2), Some(vec![7]));
+    }
+
+    #[test]
+    fn pn_counter_flow() {
+        let key = String::from("A");
+
+        // each process coordinates arithmetic ops on the key; the subtract
+        // coordinated by process 3 saturates its record
+        let cmds: Vec<(ProcessId, Rifl, KVOp)> = vec![
+            (1, Rifl::new(1, 1), KVOp::Add(10)),
+            (2, Rifl::new(2, 1), KVOp::Add(20)),
+            (3, Rifl::new(3, 1), KVOp::Subtract(15)),
+            (3, Rifl::new(3, 2), KVOp::Add(5)),
+            (1, Rifl::new(1, 2), KVOp::Subtract(5)),
+        ];
+
+        // the same commands are executed by the store of each replica: the
+        // ones coordinated by the same process conflict, and so they're
+        // executed in the same order, while the others are executed in a
+        // different order by each replica
+        let replicas: Vec<ProcessId> = vec![1, 2, 3];
+        let records: Vec<_> = replicas
+            .into_iter()
+            .map(|replica| {
+                let selector = RecordSelector::PnCounter { process_id: 0 };
+                let mode = StorageMode::MultiRecord(selector);
+                let mut store = KVStore::with_mode(false, mode);
+                let put = KVOp::PutRecords(vec![10; 3]);
+                store.execute(&key, vec![put], Rifl::new(4, 1));
+
+                let mut coordinators: Vec<ProcessId> = vec![1, 2, 3];
+                coordinators.rotate_left(replica as usize - 1);
+                for coordinator in coordinators {
+                    cmds.iter()
+                        .filter(|(process_id, _, _)| *process_id == coordinator)
+                        .for_each(|(_, rifl, op)| {
+                            let ops = vec![op.clone()];
+                            store.execute_from(&key, ops, *rifl, coordinator);
+                        });
+                }
+
+                // the value of the counter is the sum of all records
+                let get = KVOp::Get;
+                let value = store.execute(&key, vec![get], Rifl::new(4, 2));
+                assert_eq!(value, vec![KVOpResult::Value(Some(15 + 30 + 5))]);
+                store.records(&key).cloned()
+            })
+            .collect();
+
+        // all ops landed on the record owned by their coordinator, and so
+        // every replica ends up with the same records
+        let mut expected = vec![0; RECORDS_PER_KEY];
+        expected[..3].copy_from_slice(&[15, 30, 5]);
+        let expected = Some(expected);
+        assert!(records.into_iter().all(|records| records == expected));
+    }
+
+    #[test]
+    fn pn_counter_record_ownership() {
+        // processes own records in order, wrapping around
+        let record = |process_id| {
+            RecordSelector::PnCounter { process_id }.select(Rifl::new(1, 1))
+        };
+        assert_eq!(record(1), 0);
+        assert_eq!(record(RECORDS_PER_KEY as ProcessId), RECORDS_PER_KEY - 1);
+        assert_eq!(record(RECORDS_PER_KEY as ProcessId + 1), 0);
+        assert_eq!(record(0), RECORDS_PER_KEY - 1);
+
+        // and the record doesn't depend on the client
+        let selector = RecordSelector::PnCounter { process_id: 0 };
+        let selector = selector.for_process(2);
+        assert_eq!(selector.select(Rifl::new(1, 1)), 1);
+        assert_eq!(selector.select(Rifl::new(7, 3)), 1);
+
+        // other selectors are the same in every process
+        let selector = RecordSelector::Fixed(4);
+        assert_eq!(selector.for_process(2), selector);
+    }
+
+    #[test]
+    fn add_returning_old_flow() {
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        let key = String::from("Counter");
+
+        // the first add on a fresh key returns none
+        assert_eq!(store.test_execute(&key, KVOp::AddReturningOld(1)), None);
+        // the following ones return the value before the add
+        for old_value in 1..10 {
+            assert_eq!(
+                store.test_execute(&key, KVOp::AddReturningOld(1)),
+                Some(old_value)
+            );
+        }
+        // get key -> some(10)
+        assert_eq!(store.test_execute(&key, KVOp::Get), Some(10));
+
+        // add Maximum_value -> some(10), and the value is now MAX
+        assert_eq!(
+            store.test_execute(&key, KVOp::AddReturningOld(Value::MAX)),
+            Some(10)
+        );
+        assert_eq!(store.test_execute(&key, KVOp::Get), Some(Value::MAX));
+    }
+
+    #[test]
+    fn delete_if_flow() {
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        let key = String::from("A");
+        let value_x = 12;
+        let value_y = 10;
+
+        // delete_if on a missing key -> none
+        assert_eq!(store.test_execute(&key, KVOp::DeleteIf(value_x)), None);
+        assert_eq!(store.test_execute(&key, KVOp::Get), None);
+
+        // put key value_x -> none
+        assert_eq!(store.test_execute(&key, KVOp::Put(value_x)), None);
+
+        // delete_if key value_y -> some(value_x), and nothing is deleted
+        assert_eq!(
+            store.test_execute(&key, KVOp::DeleteIf(value_y)),
+            Some(value_x)
+        );
+        assert_eq!(store.test_execute(&key, KVOp::Get), Some(value_x));
+
+        // delete_if key value_x -> some(value_x), and the key is deleted
+        assert_eq!(
+            store.test_execute(&key, KVOp::DeleteIf(value_x)),
+            Some(value_x)
+        );
+        assert_eq!(store.test_execute(&key, KVOp::Get), None);
+    }
+
+    #[test]
+    fn rebalance_flow() {
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        let key = String::from("A");
+        let value_x = 12;
+        let value_y = 10;
+
+        // rebalance on a missing key -> none
+        assert_eq!(store.test_execute(&key, KVOp::Rebalance), None);
+        assert_eq!(store.test_execute(&key, KVOp::Get), None);
+
+        // put key value_x and add value_y
+        assert_eq!(store.test_execute(&key, KVOp::Put(value_x)), None);
+        assert_eq!(
+            store.test_execute(&key, KVOp::Add(value_y)),
+            Some(value_x + value_y)
+        );
+
+        // rebalance returns the total, which is preserved
+        assert_eq!(
+            store.test_execute(&key, KVOp::Rebalance),
+            Some(value_x + value_y)
+        );
+        assert_eq!(
+            store.test_execute(&key, KVOp::Get),
+            Some(value_x + value_y)
+        );
+    }
+
+    // Executes `op` on `key`, returning an error if it isn't supported.
+    fn try_execute<C: ValueCodec>(
+        store: &mut KVStore<C>,
+        key: &Key,
+        op: KVOp<C::Value>,
+    ) -> Result<Option<C::Value>, UnsupportedOp> {
+        let mut results = store.try_execute(key, vec![op], Rifl::new(1, 1))?;
+        assert_eq!(results.len(), 1);
+        Ok(results.pop().unwrap().into_value())
+    }
+
+    #[test]
+    fn blob_values_flow() {
+        let mode = StorageMode::SingleRecord;
+        let mut store = KVStore::<BlobValue>::with_codec(false, mode);
+
+        let key = String::from("A");
+        let blob = vec![0xca, 0xfe, 0xba, 0xbe];
+
+        // put key blob -> none
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Put(blob.clone())),
+            Ok(None)
+        );
+        // get key      -> some(blob)
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Get),
+            Ok(Some(blob.clone()))
+        );
+
+        // arithmetic operations are not supported, and the blob is kept
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Add(vec![1])),
+            Err(UnsupportedOp)
+        );
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Subtract(vec![1])),
+            Err(UnsupportedOp)
+        );
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Get),
+            Ok(Some(blob.clone()))
+        );
+
+        // if some op of a command is not supported, none of them is applied
+        let ops = vec![KVOp::Put(vec![0]), KVOp::Add(vec![1])];
+        assert_eq!(
+            store.try_execute(&key, ops, Rifl::new(1, 1)),
+            Err(UnsupportedOp)
+        );
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Get),
+            Ok(Some(blob.clone()))
+        );
+
+        // delete key   -> some(blob)
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Delete),
+            Ok(Some(blob.clone()))
+        );
+        // get key      -> none
+        assert_eq!(try_execute(&mut store, &key, KVOp::Get), Ok(None));
+
+        // since the value of a key split into records is their sum, blobs
+        // can't be read from multiple records
+        let mode = StorageMode::MultiRecord(RecordSelector::RoundRobin);
+        let mut store = KVStore::<BlobValue>::with_codec(false, mode);
+        assert_eq!(try_execute(&mut store, &key, KVOp::Put(blob)), Ok(None));
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Get),
+            Err(UnsupportedOp)
+        );
+    }
+
+    #[test]
+    fn numeric_values_flow() {
+        let mode = StorageMode::SingleRecord;
+        let mut store = KVStore::<NumericValue>::with_codec(false, mode);
+
+        let key = String::from("A");
+        let value_x = 12;
+        let value_y = 10;
+
+        // add on a missing key -> none
+        assert_eq!(try_execute(&mut store, &key, KVOp::Add(value_x)), Ok(None));
+
+        // put key value_x, then add and subtract value_y
+        assert_eq!(try_execute(&mut store, &key, KVOp::Put(value_x)), Ok(None));
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Add(value_y)),
+            Ok(Some(value_x + value_y))
+        );
+        assert_eq!(
+            try_execute(&mut store, &key, KVOp::Subtract(Value::MAX)),
+            Ok(Some(Value::MIN))
+        );
+    }
 }
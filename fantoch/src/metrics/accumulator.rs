@@ -0,0 +1,254 @@
+use crate::metrics::{F64Error, Stats, F64};
+
+/// Statistics of a stream of values, as computed by `StatsAccumulator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamStats {
+    count: u64,
+    mean: F64,
+    stddev: F64,
+    cov: F64,
+    mdtm: F64,
+}
+
+impl StreamStats {
+    /// Returns the number of values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean of the values.
+    pub fn mean(&self) -> F64 {
+        self.mean
+    }
+
+    /// Returns the (corrected) standard deviation of the values.
+    pub fn stddev(&self) -> F64 {
+        self.stddev
+    }
+
+    /// Returns the coefficient of variation of the values.
+    pub fn cov(&self) -> F64 {
+        self.cov
+    }
+
+    /// Returns the mean distance to mean of the values. Unless computed in a
+    /// second pass over the values (see `StreamStats::mdtm_pass`), this is an
+    /// approximation, as the distance of each value is measured to the mean
+    /// at the time it was pushed.
+    pub fn mdtm(&self) -> F64 {
+        self.mdtm
+    }
+
+    /// Returns the statistic selected by `stat`.
+    pub fn stat(&self, stat: Stats) -> F64 {
+        match stat {
+            Stats::Mean => self.mean,
+            Stats::COV => self.cov,
+            Stats::MDTM => self.mdtm,
+        }
+    }
+
+    /// Starts a second pass over the values, which computes their exact mean
+    /// distance to mean.
+    pub fn mdtm_pass(&self) -> MdtmAccumulator {
+        MdtmAccumulator {
+            stats: *self,
+            count: 0,
+            distances: 0.0,
+        }
+    }
+}
+
+/// Computes the mean, standard deviation and coefficient of variation of a
+/// stream of values without storing them, using Welford's online algorithm.
+/// Unlike `Histogram`, it can't compute percentiles, as those require all
+/// values, and it only approximates the mean distance to mean (which can be
+/// computed exactly in a second pass with `StreamStats::mdtm_pass`).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StatsAccumulator {
+    count: u64,
+    mean: f64,
+    // sum of squared differences from the current mean
+    m2: f64,
+    // sum of the distances of each value to the mean at the time it was pushed
+    distances: f64,
+}
+
+impl StatsAccumulator {
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new value to the accumulator.
+    pub fn push(&mut self, value: u64) {
+        let value = value as f64;
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        let delta2 = value - self.mean;
+        self.m2 += delta * delta2;
+        self.distances += delta2.abs();
+    }
+
+    /// Returns the number of values pushed.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the statistics of the values pushed, matching the ones
+    /// computed by `Histogram` (i.e. using the corrected variance).
+    pub fn finish(&self) -> StreamStats {
+        if self.count == 0 {
+            return StreamStats {
+                count: 0,
+                mean: F64::nan(),
+                stddev: F64::nan(),
+                cov: F64::nan(),
+                mdtm: F64::nan(),
+            };
+        }
+        // we divide by (count - 1) to have the corrected version of variance,
+        // as in `Histogram`
+        let variance = if self.count == 1 {
+            0.0
+        } else {
+            self.m2 / (self.count as f64 - 1.0)
+        };
+        let stddev = variance.sqrt();
+        let cov = stddev / self.mean;
+        let mdtm = self.distances / self.count as f64;
+        StreamStats {
+            count: self.count,
+            mean: F64::new(self.mean),
+            stddev: F64::new(stddev),
+            cov: F64::new(cov),
+            mdtm: F64::new(mdtm),
+        }
+    }
+
+    /// Returns the statistics of the values pushed, failing if any of them is
+    /// not a finite number (e.g. no value was pushed or the mean is zero).
+    pub fn try_finish(&self) -> Result<StreamStats, F64Error> {
+        let stats = self.finish();
+        Ok(StreamStats {
+            count: stats.count,
+            mean: F64::try_new(stats.mean.value())?,
+            stddev: F64::try_new(stats.stddev.value())?,
+            cov: F64::try_new(stats.cov.value())?,
+            mdtm: F64::try_new(stats.mdtm.value())?,
+        })
+    }
+}
+
+/// Computes the exact mean distance to mean of a stream of values in a second
+/// pass over them, given the statistics computed in the first pass (see
+/// `StreamStats::mdtm_pass`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MdtmAccumulator {
+    stats: StreamStats,
+    count: u64,
+    // sum of the distances of each value to the mean
+    distances: f64,
+}
+
+impl MdtmAccumulator {
+    /// Adds a new value to the accumulator. Values should be pushed in the
+    /// same way as in the first pass.
+    pub fn push(&mut self, value: u64) {
+        self.count += 1;
+        self.distances += (value as f64 - self.stats.mean.value()).abs();
+    }
+
+    /// Returns the statistics of the first pass with the exact mean distance
+    /// to mean.
+    pub fn finish(&self) -> StreamStats {
+        assert_eq!(
+            self.count, self.stats.count,
+            "the second pass should push the values of the first pass"
+        );
+        if self.count == 0 {
+            return self.stats;
+        }
+        let mdtm = self.distances / self.count as f64;
+        StreamStats {
+            mdtm: F64::new(mdtm),
+            ..self.stats
+        }
+    }
+
+    /// Returns the statistics of the first pass with the exact mean distance
+    /// to mean, failing if any of them is not a finite number.
+    pub fn try_finish(&self) -> Result<StreamStats, F64Error> {
+        let stats = self.finish();
+        F64::try_new(stats.mean.value())?;
+        F64::try_new(stats.stddev.value())?;
+        F64::try_new(stats.cov.value())?;
+        F64::try_new(stats.mdtm.value())?;
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Histogram;
+
+    #[test]
+    fn accumulator_matches_histogram() {
+        let values = vec![10, 20, 20, 35, 40, 40, 40, 51, 103, 7];
+
+        let mut acc = StatsAccumulator::new();
+        values.iter().for_each(|value| acc.push(*value));
+        let histogram = Histogram::from(values.clone());
+        assert_eq!(acc.count(), histogram.count() as u64);
+
+        let stats = acc.finish();
+        assert_eq!(stats.count(), histogram.count() as u64);
+        let close = |a: F64, b: F64| (a.value() - b.value()).abs() < 1e-9;
+        assert!(close(stats.mean(), histogram.mean()));
+        assert!(close(stats.stddev(), histogram.stddev()));
+        assert!(close(stats.cov(), histogram.cov()));
+        assert_eq!(acc.try_finish(), Ok(stats));
+
+        // the mean distance to mean is only approximated
+        assert_eq!(stats.mdtm(), stats.stat(Stats::MDTM));
+        assert!((stats.mdtm().value() - histogram.mdtm().value()).abs() < 1.5);
+
+        // but it's exact if computed in a second pass
+        let mut mdtm_acc = stats.mdtm_pass();
+        values.iter().for_each(|value| mdtm_acc.push(*value));
+        let exact = mdtm_acc.finish();
+        assert!(close(exact.mdtm(), histogram.mdtm()));
+        assert_eq!(exact.mean(), stats.mean());
+        assert_eq!(exact.stat(Stats::Mean), stats.mean());
+        assert_eq!(exact.stat(Stats::COV), stats.cov());
+        assert_eq!(mdtm_acc.try_finish(), Ok(exact));
+    }
+
+    #[test]
+    fn approximate_mdtm() {
+        // with many values, the approximation gets close to the exact value
+        let values: Vec<_> = (0..10_000).map(|i| (i * 37) % 101).collect();
+        let mut acc = StatsAccumulator::new();
+        values.iter().for_each(|value| acc.push(*value));
+        let approximate = acc.finish().mdtm().value();
+        let exact = Histogram::from(values).mdtm().value();
+        assert!((approximate - exact).abs() / exact < 0.01);
+    }
+
+    #[test]
+    fn try_finish_degenerate() {
+        // no values
+        let acc = StatsAccumulator::new();
+        assert_eq!(acc.try_finish(), Err(F64Error::NaN));
+        let mdtm_acc = acc.finish().mdtm_pass();
+        assert_eq!(mdtm_acc.try_finish(), Err(F64Error::NaN));
+
+        // zero mean
+        let mut acc = StatsAccumulator::new();
+        acc.push(0);
+        acc.push(0);
+        assert_eq!(acc.try_finish(), Err(F64Error::NaN));
+    }
+}
@@ -2,6 +2,23 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::fmt;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum F64Error {
+    NaN,
+    Infinite,
+}
+
+impl fmt::Display for F64Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            F64Error::NaN => write!(f, "value is NaN"),
+            F64Error::Infinite => write!(f, "value is infinite"),
+        }
+    }
+}
+
+impl std::error::Error for F64Error {}
+
 #[derive(PartialOrd, Deserialize, Serialize, Clone, Copy)]
 pub struct F64(f64);
 
@@ -10,6 +27,17 @@ impl F64 {
         Self(x)
     }
 
+    /// Creates a new `F64`, failing if `x` is NaN or infinite.
+    pub fn try_new(x: f64) -> Result<Self, F64Error> {
+        if x.is_nan() {
+            Err(F64Error::NaN)
+        } else if x.is_infinite() {
+            Err(F64Error::Infinite)
+        } else {
+            Ok(Self(x))
+        }
+    }
+
     pub fn zero() -> Self {
         Self::new(0.0)
     }
@@ -111,6 +139,14 @@ mod tests {
         assert_eq!(F64::new(0.0), F64::zero());
     }
 
+    #[test]
+    fn try_new() {
+        assert_eq!(F64::try_new(11.2), Ok(F64::new(11.2)));
+        assert_eq!(F64::try_new(f64::NAN), Err(F64Error::NaN));
+        assert_eq!(F64::try_new(f64::INFINITY), Err(F64Error::Infinite));
+        assert_eq!(F64::try_new(f64::NEG_INFINITY), Err(F64Error::Infinite));
+    }
+
     #[test]
     fn value() {
         assert_eq!(F64::zero().value(), 0.0);
@@ -1,4 +1,4 @@
-use crate::metrics::F64;
+use crate::metrics::{F64Error, F64};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
@@ -10,6 +10,15 @@ pub enum Stats {
     MDTM, // mean distance to mean
 }
 
+/// Difference between the mean, p50 and p99 of two histograms (see
+/// `Histogram::delta_summary`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaSummary {
+    pub mean: F64,
+    pub p50: F64,
+    pub p99: F64,
+}
+
 // TODO maybe use https://docs.rs/hdrhistogram/7.0.0/hdrhistogram/
 #[derive(Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Histogram {
@@ -63,22 +72,48 @@ impl Histogram {
         F64::new(mean)
     }
 
+    /// Computes the mean, failing if it's not a finite number (e.g. the
+    /// histogram is empty).
+    pub fn try_mean(&self) -> Result<F64, F64Error> {
+        let (mean, _) = self.compute_mean_and_count();
+        F64::try_new(mean)
+    }
+
     pub fn stddev(&self) -> F64 {
         let (mean, count) = self.compute_mean_and_count();
         let stddev = self.compute_stddev(mean, count);
         F64::new(stddev)
     }
 
+    /// Computes the standard deviation, failing if it's not a finite number
+    /// (e.g. the histogram is empty).
+    pub fn try_stddev(&self) -> Result<F64, F64Error> {
+        let (mean, count) = self.compute_mean_and_count();
+        F64::try_new(self.compute_stddev(mean, count))
+    }
+
     pub fn cov(&self) -> F64 {
         let cov = self.compute_cov();
         F64::new(cov)
     }
 
+    /// Computes the coefficient of variation, failing if it's not a finite
+    /// number (e.g. the histogram is empty or its mean is zero).
+    pub fn try_cov(&self) -> Result<F64, F64Error> {
+        F64::try_new(self.compute_cov())
+    }
+
     pub fn mdtm(&self) -> F64 {
         let mdtm = self.compute_mdtm();
         F64::new(mdtm)
     }
 
+    /// Computes the mean distance to mean, failing if it's not a finite number
+    /// (e.g. the histogram is empty).
+    pub fn try_mdtm(&self) -> Result<F64, F64Error> {
+        F64::try_new(self.compute_mdtm())
+    }
+
     pub fn mean_improv(&self, other: &Self) -> F64 {
         self.mean() - other.mean()
     }
@@ -91,6 +126,45 @@ impl Histogram {
         self.mdtm() - other.mdtm()
     }
 
+    /// Computes the improvement (in percentage) of `self.mean()` over
+    /// `other.mean()`. If `other.mean()` is zero, there's no improvement.
+    pub fn mean_improv_pct(&self, other: &Self) -> F64 {
+        let other_mean = other.mean().value();
+        if other_mean == 0.0 {
+            return F64::zero();
+        }
+        let improv = (other_mean - self.mean().value()) / other_mean * 100.0;
+        F64::new(improv)
+    }
+
+    /// Computes the difference between the mean, p50 and p99 of this histogram
+    /// and the ones of `baseline`. A negative delta means that this
+    /// histogram's values are lower than the baseline's (e.g. an improvement,
+    /// if the values are latencies).
+    pub fn delta_summary(&self, baseline: &Self) -> DeltaSummary {
+        DeltaSummary {
+            mean: self.mean() - baseline.mean(),
+            p50: self.percentile(0.5) - baseline.percentile(0.5),
+            p99: self.percentile(0.99) - baseline.percentile(0.99),
+        }
+    }
+
+    /// Returns the header of the CSV row produced by `to_csv_row`.
+    pub fn csv_header() -> &'static str {
+        "mean,cov,mdtm"
+    }
+
+    /// Creates a CSV row with the mean, cov and mdtm of this histogram (with
+    /// full precision).
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{}",
+            self.mean().value(),
+            self.cov().value(),
+            self.mdtm().value()
+        )
+    }
+
     pub fn min(&self) -> F64 {
         self.values
             .iter()
@@ -202,6 +276,15 @@ impl Histogram {
     }
 
     fn compute_variance(&self, mean: f64, count: f64) -> f64 {
+        // no values have no variance, just like they have no mean
+        if count == 0.0 {
+            return f64::NAN;
+        }
+        // a single value has no variance (and dividing by (count - 1) below
+        // would produce a NaN)
+        if count == 1.0 {
+            return 0.0;
+        }
         let sum = self
             .values
             .iter()
@@ -387,6 +470,25 @@ mod proptests {
 mod tests {
     use super::*;
 
+    #[test]
+    fn single_value_stats() {
+        let stats = Histogram::from(vec![5]);
+        assert_eq!(stats.stddev(), F64::new(0.0));
+        assert_eq!(stats.cov(), F64::new(0.0));
+        assert_eq!(stats.try_cov(), Ok(F64::new(0.0)));
+
+        assert_eq!(stats.try_mean(), Ok(F64::new(5.0)));
+        assert_eq!(stats.try_stddev(), Ok(F64::new(0.0)));
+        assert_eq!(stats.try_mdtm(), Ok(F64::new(0.0)));
+
+        // degenerate histograms produce an error
+        assert_eq!(Histogram::new().try_mean(), Err(F64Error::NaN));
+        assert_eq!(Histogram::new().try_stddev(), Err(F64Error::NaN));
+        assert_eq!(Histogram::new().try_cov(), Err(F64Error::NaN));
+        assert_eq!(Histogram::new().try_mdtm(), Err(F64Error::NaN));
+        assert_eq!(Histogram::from(vec![0, 0]).try_cov(), Err(F64Error::NaN));
+    }
+
     #[test]
     fn stats() {
         let stats = Histogram::from(vec![1, 1, 1]);
@@ -432,6 +534,13 @@ mod tests {
         assert_eq!(stats.mdtm().round(), "10.0");
     }
 
+    #[test]
+    fn csv_row() {
+        assert_eq!(Histogram::csv_header(), "mean,cov,mdtm");
+        let stats = Histogram::from(vec![10, 20, 30]);
+        assert_eq!(stats.to_csv_row(), "20,0.5,6.666666666666667");
+    }
+
     #[test]
     fn stats_improv() {
         let stats_a = Histogram::from(vec![1, 1, 1]);
@@ -447,6 +556,38 @@ mod tests {
         assert_eq!(stats_a.mdtm_improv(&stats_b), F64::new(-5.0));
     }
 
+    #[test]
+    fn stats_improv_pct() {
+        let stats_a = Histogram::from(vec![10, 10]);
+        let stats_b = Histogram::from(vec![10, 30]);
+        assert_eq!(stats_a.mean_improv_pct(&stats_b), F64::new(50.0));
+        assert_eq!(stats_b.mean_improv_pct(&stats_a), F64::new(-100.0));
+
+        // no improvement over a zero mean
+        let stats_zero = Histogram::from(vec![0, 0]);
+        assert_eq!(stats_a.mean_improv_pct(&stats_zero), F64::zero());
+    }
+
+    #[test]
+    fn delta_summary() {
+        let baseline = Histogram::from((1..=100).map(|value| value * 2));
+        let stats = Histogram::from(1..=100);
+
+        let delta = stats.delta_summary(&baseline);
+        assert_eq!(delta.mean, stats.mean() - baseline.mean());
+        assert_eq!(delta.mean, F64::new(-50.5));
+        assert_eq!(delta.p50, stats.percentile(0.5) - baseline.percentile(0.5));
+        assert_eq!(
+            delta.p99,
+            stats.percentile(0.99) - baseline.percentile(0.99)
+        );
+        assert_eq!(delta.p99, F64::new(-99.5));
+
+        // against itself, there's no delta
+        let delta = stats.delta_summary(&stats);
+        assert_eq!(delta.p99, F64::zero());
+    }
+
     #[test]
     fn percentile() {
         let data = vec![
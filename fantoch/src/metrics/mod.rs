@@ -4,10 +4,15 @@ pub mod float;
 // This module contains the definition of `Histogram`.
 mod histogram;
 
+// This module contains the definition of `StatsAccumulator`.
+mod accumulator;
+
 // Re-exports.
-pub use float::F64;
-pub use histogram::{Histogram, Stats};
+pub use accumulator::{MdtmAccumulator, StatsAccumulator, StreamStats};
+pub use float::{F64Error, F64};
+pub use histogram::{DeltaSummary, Histogram, Stats};
 
+use crate::util;
 use crate::HashMap;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -55,6 +60,11 @@ where
         self.aggregated.get(&kind)
     }
 
+    pub fn clear(&mut self) {
+        self.collected.clear();
+        self.aggregated.clear();
+    }
+
     pub fn merge(&mut self, other: &Self) {
         for (k, hist) in other.collected.iter() {
             let current = self.collected.entry(*k).or_default();
@@ -72,10 +82,10 @@ where
     K: Eq + Hash + fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (kind, histogram) in self.collected.iter() {
+        for (kind, histogram) in util::debug_entries(&self.collected) {
             writeln!(f, "{:?}: {:?}", kind, histogram)?;
         }
-        for (kind, value) in self.aggregated.iter() {
+        for (kind, value) in util::debug_entries(&self.aggregated) {
             writeln!(f, "{:?}: {:?}", kind, value)?;
         }
         Ok(())
@@ -5,7 +5,7 @@ mod dat;
 pub mod region;
 
 // Re-exports.
-pub use region::Region;
+pub use region::{Continent, Region};
 
 use crate::planet::dat::Dat;
 use crate::HashMap;
@@ -18,6 +18,18 @@ const GCP_LAT_DIR: &str = "../latency_gcp/";
 // assume that intra region latency is 0
 const INTRA_REGION_LATENCY: u64 = 0;
 
+/// Error returned when a region is not part of the `Planet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownRegion(pub Region);
+
+impl fmt::Display for UnknownRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown region {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownRegion {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Planet {
     /// mapping from region A to a mapping from region B to the latency between
@@ -53,6 +65,28 @@ impl Planet {
         Planet { latencies, sorted }
     }
 
+    /// Creates a new `Planet` instance from the latency between each pair of
+    /// regions provided. This allows tests to control the exact distances
+    /// between regions without depending on latency files. Latencies are not
+    /// made symmetric, and unless provided, the latency within each region is
+    /// assumed to be 0.
+    pub fn from_pairs(pairs: HashMap<(Region, Region), u64>) -> Self {
+        let mut latencies: HashMap<_, HashMap<_, _>> = HashMap::new();
+        for ((from, to), latency) in pairs {
+            // make sure both regions exist
+            latencies
+                .entry(to.clone())
+                .or_default()
+                .entry(to.clone())
+                .or_insert(INTRA_REGION_LATENCY);
+            let from_latencies = latencies.entry(from.clone()).or_default();
+            from_latencies.entry(from).or_insert(INTRA_REGION_LATENCY);
+            // and save the latency between them
+            from_latencies.insert(to, latency);
+        }
+        Self::from_latencies(latencies)
+    }
+
     /// Creates a equidistant `Planet`.
     pub fn equidistant(
         planet_distance: u64,
@@ -103,6 +137,19 @@ impl Planet {
         self.latencies.keys().cloned().collect()
     }
 
+    /// Retrieves a (sorted) list with the regions in `continent`. This can be
+    /// used, e.g., to place a quorum within a single continent.
+    pub fn continent_regions(&self, continent: Continent) -> Vec<Region> {
+        let mut regions: Vec<_> = self
+            .latencies
+            .keys()
+            .filter(|region| region.continent() == continent)
+            .cloned()
+            .collect();
+        regions.sort();
+        regions
+    }
+
     /// Retrieves the distance between the two regions passed as argument.
     pub fn ping_latency(&self, from: &Region, to: &Region) -> Option<u64> {
         // get from's entries
@@ -112,12 +159,67 @@ impl Planet {
         entries.get(to).cloned()
     }
 
+    /// Returns the ping latency between every pair of the regions passed as
+    /// argument: entry `[i][j]` is the latency from `regions[i]` to
+    /// `regions[j]`.
+    ///
+    /// Panics if some region is unknown; see `try_latency_matrix` for a
+    /// non-panicking version.
+    pub fn latency_matrix(&self, regions: &[Region]) -> Vec<Vec<u64>> {
+        self.try_latency_matrix(regions)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as `latency_matrix`, but returns an error if some region is
+    /// unknown.
+    pub fn try_latency_matrix(
+        &self,
+        regions: &[Region],
+    ) -> Result<Vec<Vec<u64>>, UnknownRegion> {
+        regions
+            .iter()
+            .map(|from| {
+                regions
+                    .iter()
+                    .map(|to| {
+                        self.ping_latency(from, to).ok_or_else(|| {
+                            // find out which of the regions is unknown
+                            let unknown = if self.latencies.contains_key(from) {
+                                to
+                            } else {
+                                from
+                            };
+                            UnknownRegion(unknown.clone())
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
     /// Returns a list of `Region`s sorted by the distance to the `Region`
     /// passed as argument. The distance to each region is also returned.
     pub fn sorted(&self, from: &Region) -> Option<&Vec<(u64, Region)>> {
         self.sorted.get(from)
     }
 
+    /// Returns the `n` regions closest to the `Region` passed as argument
+    /// (including itself), along with their distance, sorted by distance.
+    /// Returns `None` if the region is not part of the planet.
+    pub fn nearest(
+        &self,
+        from: &Region,
+        n: usize,
+    ) -> Option<Vec<(Region, u64)>> {
+        self.sorted(from).map(|sorted| {
+            sorted
+                .iter()
+                .take(n)
+                .map(|(distance, region)| (region.clone(), *distance))
+                .collect()
+        })
+    }
+
     /// Returns a mapping from region to regions sorted by distance (ASC).
     fn sort_by_distance(
         latencies: HashMap<Region, HashMap<Region, u64>>,
@@ -209,6 +311,44 @@ mod tests {
         assert!(!symmetric(&us_w1, &eu_w3, &planet));
     }
 
+    #[test]
+    fn continent_regions() {
+        // planet
+        let planet = Planet::new();
+
+        let expected: Vec<_> = [
+            "europe-north1",
+            "europe-west1",
+            "europe-west2",
+            "europe-west3",
+            "europe-west4",
+            "europe-west6",
+        ]
+        .iter()
+        .map(|name| Region::new(*name))
+        .collect();
+        assert_eq!(planet.continent_regions(Continent::Europe), expected);
+        assert!(planet.continent_regions(Continent::Africa).is_empty());
+
+        // all regions with latency data (in GCP and AWS) are in a known
+        // continent
+        let aws_lat_dirs =
+            ["../latency_aws/2020_06_05", "../latency_aws/2021_02_13"];
+        let lat_dirs =
+            std::iter::once(GCP_LAT_DIR).chain(aws_lat_dirs.iter().cloned());
+        for lat_dir in lat_dirs {
+            let planet = Planet::from(lat_dir);
+            assert!(planet.continent_regions(Continent::Unknown).is_empty());
+        }
+
+        // in AWS, the only region in Oceania is Sydney
+        let planet = Planet::from(aws_lat_dirs[0]);
+        assert_eq!(
+            planet.continent_regions(Continent::Oceania),
+            vec![Region::new("ap-southeast-2")]
+        );
+    }
+
     #[test]
     fn sorted() {
         // planet
@@ -253,6 +393,66 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn nearest() {
+        // planet
+        let planet = Planet::new();
+
+        // regions
+        let eu_w3 = Region::new("europe-west3");
+
+        let n = 5;
+        let nearest = planet.nearest(&eu_w3, n).unwrap();
+        assert_eq!(nearest.len(), n);
+
+        // the closest region is the region itself
+        assert_eq!(nearest[0], (eu_w3.clone(), INTRA_REGION_LATENCY));
+
+        // regions are sorted by distance, and distances are the ping latency
+        for window in nearest.windows(2) {
+            assert!(window[0].1 <= window[1].1);
+        }
+        for (region, distance) in nearest {
+            assert_eq!(planet.ping_latency(&eu_w3, &region), Some(distance));
+        }
+
+        // if `n` is larger than the number of regions, all regions are
+        // returned
+        let all = planet.nearest(&eu_w3, 100).unwrap();
+        assert_eq!(all.len(), planet.regions().len());
+
+        // unknown regions have no nearest regions
+        let unknown = Region::new("unknown");
+        assert_eq!(planet.nearest(&unknown, n), None);
+    }
+
+    #[test]
+    fn from_pairs() {
+        let a = Region::new("a");
+        let b = Region::new("b");
+        let mut pairs = HashMap::new();
+        pairs.insert((a.clone(), b.clone()), 20);
+        pairs.insert((b.clone(), a.clone()), 30);
+        let planet = Planet::from_pairs(pairs);
+
+        let mut regions = planet.regions();
+        regions.sort();
+        assert_eq!(regions, vec![a.clone(), b.clone()]);
+
+        // latencies are the ones provided
+        assert_eq!(planet.ping_latency(&a, &b), Some(20));
+        assert_eq!(planet.ping_latency(&b, &a), Some(30));
+        // and intra-region latency is 0
+        assert_eq!(planet.ping_latency(&a, &a), Some(INTRA_REGION_LATENCY));
+        assert_eq!(planet.ping_latency(&b, &b), Some(INTRA_REGION_LATENCY));
+
+        // regions are sorted by distance
+        assert_eq!(
+            planet.nearest(&a, 2),
+            Some(vec![(a.clone(), INTRA_REGION_LATENCY), (b, 20)])
+        );
+    }
+
     #[test]
     fn equidistant() {
         let planet_distance = 10;
@@ -298,4 +498,35 @@ mod tests {
         ];
         assert!(planet.distance_matrix(regions).is_ok());
     }
+
+    #[test]
+    fn latency_matrix() {
+        let planet = Planet::new();
+        let regions = vec![
+            Region::new("asia-east1"),
+            Region::new("us-central1"),
+            Region::new("us-west1"),
+        ];
+        let matrix = planet.latency_matrix(&regions);
+        assert_eq!(matrix.len(), regions.len());
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row.len(), regions.len());
+            // the diagonal is zero
+            assert_eq!(row[i], INTRA_REGION_LATENCY);
+            // the matrix is symmetric
+            for (j, latency) in row.iter().enumerate() {
+                assert_eq!(*latency, matrix[j][i]);
+            }
+        }
+        assert_eq!(matrix[1][2], 34);
+
+        // unknown regions are reported
+        let unknown = Region::new("mars-north1");
+        let mut regions = regions;
+        regions.push(unknown.clone());
+        assert_eq!(
+            planet.try_latency_matrix(&regions),
+            Err(UnknownRegion(unknown))
+        );
+    }
 }
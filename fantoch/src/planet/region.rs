@@ -1,6 +1,19 @@
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 
+/// Continent in which a `Region` is located.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Continent {
+    NorthAmerica,
+    SouthAmerica,
+    Europe,
+    Asia,
+    Oceania,
+    Africa,
+    MiddleEast,
+    Unknown,
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Region {
     name: String,
@@ -15,6 +28,28 @@ impl Region {
     pub fn name(&self) -> &String {
         &self.name
     }
+
+    /// Returns the continent of this region, derived from the prefix of its
+    /// name (both AWS, e.g. `eu-west-1`, and GCP, e.g. `europe-west1`, names
+    /// are supported).
+    pub fn continent(&self) -> Continent {
+        // the only AWS region starting with `ap-` that is not in Asia is
+        // Sydney
+        if self.name == "ap-southeast-2" {
+            return Continent::Oceania;
+        }
+        let prefix = self.name.split('-').next().unwrap_or_default();
+        match prefix {
+            "us" | "ca" | "northamerica" => Continent::NorthAmerica,
+            "sa" | "southamerica" => Continent::SouthAmerica,
+            "eu" | "europe" => Continent::Europe,
+            "ap" | "asia" => Continent::Asia,
+            "australia" => Continent::Oceania,
+            "af" => Continent::Africa,
+            "me" => Continent::MiddleEast,
+            _ => Continent::Unknown,
+        }
+    }
 }
 
 impl fmt::Debug for Region {
@@ -43,3 +78,30 @@ impl<'de> Deserialize<'de> for Region {
         Ok(Region::new(name))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continent() {
+        let continent = |name| Region::new(name).continent();
+
+        // aws
+        assert_eq!(continent("us-west-1"), Continent::NorthAmerica);
+        assert_eq!(continent("ca-central-1"), Continent::NorthAmerica);
+        assert_eq!(continent("sa-east-1"), Continent::SouthAmerica);
+        assert_eq!(continent("eu-west-1"), Continent::Europe);
+        assert_eq!(continent("ap-southeast-1"), Continent::Asia);
+        assert_eq!(continent("ap-southeast-2"), Continent::Oceania);
+
+        // gcp
+        assert_eq!(continent("us-west1"), Continent::NorthAmerica);
+        assert_eq!(continent("europe-west3"), Continent::Europe);
+        assert_eq!(continent("asia-east1"), Continent::Asia);
+        assert_eq!(continent("australia-southeast1"), Continent::Oceania);
+
+        // unknown
+        assert_eq!(continent("mars-north1"), Continent::Unknown);
+    }
+}
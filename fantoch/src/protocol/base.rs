@@ -2,9 +2,72 @@ use crate::command::Command;
 use crate::config::Config;
 use crate::id::{Dot, DotGen, ProcessId, ShardId};
 use crate::protocol::{ProtocolMetrics, ProtocolMetricsKind};
+use crate::time::SysTime;
 use crate::trace;
 use crate::{HashMap, HashSet};
+use std::fmt;
 use std::iter::FromIterator;
+use std::sync::Arc;
+use threshold::{AboveExSet, EventSet};
+
+/// Callback invoked each time a command is committed.
+pub type CommitCallback = Arc<dyn Fn(Dot, &Command) + Send + Sync>;
+
+// wrapper around `CommitCallback` so that `BaseProcess` can still derive
+// `Debug`, `PartialEq` and `Eq`
+#[derive(Clone)]
+struct OnCommit(CommitCallback);
+
+impl fmt::Debug for OnCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OnCommit")
+    }
+}
+
+impl PartialEq for OnCommit {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for OnCommit {}
+
+/// Callback invoked each time the stable frontier of a process advances, with
+/// the id of the process, the current time (in millis) and the new frontier,
+/// i.e. the highest stable sequence of each process.
+pub type StableCallback =
+    Arc<dyn Fn(ProcessId, u64, &HashMap<ProcessId, u64>) + Send + Sync>;
+
+// wrapper around `StableCallback` (see `OnCommit`)
+#[derive(Clone)]
+struct OnStable(StableCallback);
+
+impl fmt::Debug for OnStable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OnStable")
+    }
+}
+
+impl PartialEq for OnStable {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for OnStable {}
+
+/// Error returned when the fast quorum is requested before `discover` has
+/// been called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastQuorumUnknown(pub ProcessId);
+
+impl fmt::Display for FastQuorumUnknown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "p{}: the fast quorum is not yet known", self.0)
+    }
+}
+
+impl std::error::Error for FastQuorumUnknown {}
 
 // a `BaseProcess` has all functionalities shared by Atlas, Tempo, ...
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,8 +85,22 @@ pub struct BaseProcess {
     closest_shard_process: HashMap<ShardId, ProcessId>,
     fast_quorum_size: usize,
     write_quorum_size: usize,
+    // number of quorum acks after which a command is committed (resp.
+    // executable), if different from the default
+    commit_quorum_size: Option<usize>,
+    execute_quorum_size: Option<usize>,
     dot_gen: DotGen,
     metrics: ProtocolMetrics,
+    on_commit: Option<OnCommit>,
+    // stable sequences of each process, and the highest sequence of each
+    // process up to which all sequences are stable
+    stable_events: HashMap<ProcessId, AboveExSet>,
+    stable_frontier: HashMap<ProcessId, u64>,
+    on_stable: Option<OnStable>,
+    // number of quorum acks received by each in-flight command
+    pending_acks: HashMap<Dot, usize>,
+    // commands submitted to this process that are not yet committed
+    outstanding: HashSet<Dot>,
 }
 
 impl BaseProcess {
@@ -52,8 +129,16 @@ impl BaseProcess {
             closest_shard_process: HashMap::new(),
             fast_quorum_size,
             write_quorum_size,
+            commit_quorum_size: None,
+            execute_quorum_size: None,
             dot_gen: DotGen::new(process_id),
             metrics: ProtocolMetrics::new(),
+            on_commit: None,
+            stable_events: HashMap::new(),
+            stable_frontier: HashMap::new(),
+            on_stable: None,
+            pending_acks: HashMap::new(),
+            outstanding: HashSet::new(),
         }
     }
 
@@ -181,9 +266,24 @@ impl BaseProcess {
 
     // Returns the fast quorum.
     pub fn fast_quorum(&self) -> HashSet<ProcessId> {
+        self.fast_quorum_or_err()
+            .expect("the fast quorum should be known")
+    }
+
+    // Returns the fast quorum, or an error if it's not yet known (i.e.
+    // `discover` hasn't been called).
+    pub fn fast_quorum_or_err(
+        &self,
+    ) -> Result<HashSet<ProcessId>, FastQuorumUnknown> {
         self.fast_quorum
             .clone()
-            .expect("the fast quorum should be known")
+            .ok_or(FastQuorumUnknown(self.process_id))
+    }
+
+    // Checks whether the fast quorum is known (i.e. `discover` has been
+    // called).
+    pub fn discovered(&self) -> bool {
+        self.fast_quorum.is_some()
     }
 
     // Returns the write quorum.
@@ -248,10 +348,175 @@ impl BaseProcess {
             .aggregate(ProtocolMetricsKind::Stable, len as u64);
     }
 
+    // Advances the stable frontier given the newly stable dots (as ranges of
+    // sequences per process), notifying the on-stable callback (if any) if the
+    // frontier advanced. Since dots may become stable out of order, the
+    // frontier of each process only advances up to the highest sequence below
+    // which all sequences are stable.
+    pub fn advance_stable_frontier(
+        &mut self,
+        stable: &[(ProcessId, u64, u64)],
+        time: &dyn SysTime,
+    ) {
+        let mut advanced = false;
+        for (process_id, start, end) in stable {
+            let events = self
+                .stable_events
+                .entry(*process_id)
+                .or_insert_with(AboveExSet::new);
+            events.add_event_range(*start, *end);
+            let frontier = events.frontier();
+            let current = self.stable_frontier.entry(*process_id).or_default();
+            if frontier > *current {
+                *current = frontier;
+                advanced = true;
+            }
+        }
+        if advanced {
+            if let Some(OnStable(callback)) = &self.on_stable {
+                callback(self.process_id, time.millis(), &self.stable_frontier);
+            }
+        }
+    }
+
+    // Returns the highest sequence of each process up to which all sequences
+    // are stable.
+    pub fn stable_frontier(&self) -> &HashMap<ProcessId, u64> {
+        &self.stable_frontier
+    }
+
     // Collect a new metric.
     pub fn collect_metric(&mut self, kind: ProtocolMetricsKind, value: u64) {
         self.metrics.collect(kind, value);
     }
+
+    // Starts tracking the quorum acks received by command `dot`.
+    pub fn track_acks(&mut self, dot: Dot) {
+        self.pending_acks.insert(dot, 0);
+    }
+
+    // Records a new quorum ack for command `dot` (if it's being tracked).
+    pub fn ack(&mut self, dot: Dot) {
+        if let Some(acks) = self.pending_acks.get_mut(&dot) {
+            *acks += 1;
+        }
+    }
+
+    // Changes the number of quorum acks after which a command is committed
+    // and executable (`None` keeps the default of each).
+    pub fn set_quorum_sizes(
+        &mut self,
+        commit_quorum_size: Option<usize>,
+        execute_quorum_size: Option<usize>,
+    ) {
+        self.commit_quorum_size = commit_quorum_size;
+        self.execute_quorum_size = execute_quorum_size;
+    }
+
+    // Returns the number of quorum acks after which a command is committed:
+    // the one set with `set_quorum_sizes`, or the write quorum size otherwise
+    // (the fast quorum size for protocols without a write quorum, like
+    // `Basic`).
+    pub fn commit_quorum_size(&self) -> usize {
+        self.commit_quorum_size.unwrap_or_else(|| {
+            if self.write_quorum_size > 0 {
+                self.write_quorum_size
+            } else {
+                self.fast_quorum_size
+            }
+        })
+    }
+
+    // Returns the number of quorum acks after which a committed command is
+    // executable: the one set with `set_quorum_sizes`, or the commit quorum
+    // size otherwise.
+    pub fn execute_quorum_size(&self) -> usize {
+        self.execute_quorum_size
+            .unwrap_or_else(|| self.commit_quorum_size())
+    }
+
+    // Checks whether command `dot` has received enough quorum acks to be
+    // committed.
+    pub fn commit_quorum_reached(&self, dot: Dot) -> bool {
+        self.acks(dot) >= self.commit_quorum_size()
+    }
+
+    // Checks whether command `dot` has received enough quorum acks to be
+    // committed and executed.
+    pub fn execute_quorum_reached(&self, dot: Dot) -> bool {
+        self.commit_quorum_reached(dot)
+            && self.acks(dot) >= self.execute_quorum_size()
+    }
+
+    // Returns the number of quorum acks received by command `dot` (zero if
+    // it's not being tracked).
+    fn acks(&self, dot: Dot) -> usize {
+        self.pending_acks.get(&dot).cloned().unwrap_or_default()
+    }
+
+    // Stops tracking the quorum acks received by command `dot`.
+    pub fn untrack_acks(&mut self, dot: Dot) {
+        self.pending_acks.remove(&dot);
+    }
+
+    // Returns, for each number of quorum acks, how many in-flight commands
+    // have received that many acks.
+    pub fn pending_by_ack_count(&self) -> HashMap<usize, usize> {
+        let mut by_ack_count = HashMap::new();
+        for acks in self.pending_acks.values() {
+            *by_ack_count.entry(*acks).or_default() += 1;
+        }
+        by_ack_count
+    }
+
+    // Collects the number of quorum acks received by each in-flight command.
+    pub fn collect_pending_acks(&mut self) {
+        for acks in self.pending_acks.values() {
+            self.metrics
+                .collect(ProtocolMetricsKind::PendingAcks, *acks as u64);
+        }
+    }
+
+    // Checks whether the number of outstanding commands reached
+    // `Config::max_outstanding`, in which case new submits should be rejected.
+    pub fn overloaded(&self) -> bool {
+        matches!(
+            self.config.max_outstanding(),
+            Some(max) if self.outstanding.len() >= max
+        )
+    }
+
+    // Starts tracking command `dot` as outstanding.
+    pub fn track_outstanding(&mut self, dot: Dot) {
+        self.outstanding.insert(dot);
+        self.metrics.collect(
+            ProtocolMetricsKind::Outstanding,
+            self.outstanding.len() as u64,
+        );
+    }
+
+    // Stops tracking command `dot` as outstanding (if it's being tracked).
+    pub fn untrack_outstanding(&mut self, dot: Dot) {
+        self.outstanding.remove(&dot);
+    }
+
+    // Registers a callback to be invoked each time a command is committed.
+    pub fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.on_commit = Some(OnCommit(callback));
+    }
+
+    // Registers a callback to be invoked each time the stable frontier
+    // advances.
+    pub fn set_on_stable(&mut self, callback: StableCallback) {
+        self.on_stable = Some(OnStable(callback));
+    }
+
+    // Notifies the on-commit callback (if any) that `cmd` was committed.
+    pub fn committed(&self, dot: Dot, cmd: &Command) {
+        if let Some(OnCommit(callback)) = &self.on_commit {
+            callback(dot, cmd);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -495,4 +760,208 @@ mod tests {
         assert!(cmd_both_shards.replicated_by(&shard_id_0));
         assert!(cmd_both_shards.replicated_by(&shard_id_1));
     }
+
+    #[test]
+    fn fast_quorum_or_err() {
+        // config
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        let shard_id = 0;
+        let fast_quorum_size = 2;
+        let write_quorum_size = 2;
+        let mut bp = BaseProcess::new(
+            1,
+            shard_id,
+            config,
+            fast_quorum_size,
+            write_quorum_size,
+        );
+
+        // before discover, the fast quorum is not known
+        assert!(!bp.discovered());
+        assert_eq!(bp.fast_quorum_or_err(), Err(FastQuorumUnknown(1)));
+
+        // after discover, it is
+        let sorted = vec![(1, shard_id), (2, shard_id), (3, shard_id)];
+        assert!(bp.discover(sorted));
+        assert!(bp.discovered());
+        assert_eq!(
+            bp.fast_quorum_or_err().map(BTreeSet::from_iter),
+            Ok(BTreeSet::from_iter(vec![1, 2]))
+        );
+    }
+
+    #[test]
+    fn pending_by_ack_count() {
+        // config
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        let shard_id = 0;
+        let fast_quorum_size = 2;
+        let write_quorum_size = 2;
+        let mut bp = BaseProcess::new(
+            1,
+            shard_id,
+            config,
+            fast_quorum_size,
+            write_quorum_size,
+        );
+
+        // track three commands
+        let dot_a = bp.next_dot();
+        let dot_b = bp.next_dot();
+        let dot_c = bp.next_dot();
+        bp.track_acks(dot_a);
+        bp.track_acks(dot_b);
+        bp.track_acks(dot_c);
+
+        // `a` gets two acks, `b` gets one, and `c` none
+        bp.ack(dot_a);
+        bp.ack(dot_a);
+        bp.ack(dot_b);
+        let expected = HashMap::from_iter(vec![(0, 1), (1, 1), (2, 1)]);
+        assert_eq!(bp.pending_by_ack_count(), expected);
+
+        // acks of untracked commands are ignored
+        bp.untrack_acks(dot_a);
+        bp.ack(dot_a);
+        let expected = HashMap::from_iter(vec![(0, 1), (1, 1)]);
+        assert_eq!(bp.pending_by_ack_count(), expected);
+
+        // collect the pending acks
+        bp.collect_pending_acks();
+        let pending_acks = bp
+            .metrics()
+            .get_collected(ProtocolMetricsKind::PendingAcks)
+            .expect("pending acks should have been collected");
+        assert_eq!(pending_acks.values().collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn stable_frontier() {
+        use crate::time::SimTime;
+        use std::sync::Mutex;
+
+        // config
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        let shard_id = 0;
+        let fast_quorum_size = 2;
+        let write_quorum_size = 2;
+        let mut bp = BaseProcess::new(
+            1,
+            shard_id,
+            config,
+            fast_quorum_size,
+            write_quorum_size,
+        );
+        let time = SimTime::new();
+
+        // count the advancements of the frontier
+        let advances = Arc::new(Mutex::new(0));
+        let advances_clone = advances.clone();
+        bp.set_on_stable(Arc::new(
+            move |_: ProcessId, _: u64, _: &HashMap<ProcessId, u64>| {
+                *advances_clone.lock().unwrap() += 1;
+            },
+        ));
+
+        // dots 3 and 4 of process 2 become stable before dots 1 and 2, so the
+        // frontier doesn't advance
+        bp.advance_stable_frontier(&[(2, 3, 4)], &time);
+        assert_eq!(bp.stable_frontier().get(&2).cloned().unwrap_or(0), 0);
+        assert_eq!(*advances.lock().unwrap(), 0);
+
+        // once they're stable, the frontier covers all four
+        bp.advance_stable_frontier(&[(2, 1, 2)], &time);
+        assert_eq!(bp.stable_frontier().get(&2), Some(&4));
+        assert_eq!(*advances.lock().unwrap(), 1);
+
+        // already stable dots don't advance it
+        bp.advance_stable_frontier(&[(2, 2, 3)], &time);
+        assert_eq!(bp.stable_frontier().get(&2), Some(&4));
+        assert_eq!(*advances.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn overloaded() {
+        // config
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_max_outstanding(Some(2));
+
+        let shard_id = 0;
+        let fast_quorum_size = 2;
+        let write_quorum_size = 2;
+        let mut bp = BaseProcess::new(
+            1,
+            shard_id,
+            config,
+            fast_quorum_size,
+            write_quorum_size,
+        );
+
+        // the process is overloaded once there are two outstanding commands
+        let dot_a = bp.next_dot();
+        let dot_b = bp.next_dot();
+        bp.track_outstanding(dot_a);
+        assert!(!bp.overloaded());
+        bp.track_outstanding(dot_b);
+        assert!(bp.overloaded());
+
+        // and no longer once one of them is committed
+        bp.untrack_outstanding(dot_a);
+        assert!(!bp.overloaded());
+    }
+
+    #[test]
+    fn commit_and_execute_quorums() {
+        // config
+        let n = 5;
+        let f = 2;
+        let config = Config::new(n, f);
+
+        let shard_id = 0;
+        let fast_quorum_size = 3;
+        let write_quorum_size = 3;
+        let mut bp = BaseProcess::new(
+            1,
+            shard_id,
+            config,
+            fast_quorum_size,
+            write_quorum_size,
+        );
+
+        // by default, both quorums are the write quorum
+        assert_eq!(bp.commit_quorum_size(), write_quorum_size);
+        assert_eq!(bp.execute_quorum_size(), write_quorum_size);
+
+        // commit after 2 acks, but only execute after 4
+        bp.set_quorum_sizes(Some(2), Some(4));
+        let dot = bp.next_dot();
+        bp.track_acks(dot);
+
+        bp.ack(dot);
+        assert!(!bp.commit_quorum_reached(dot));
+        assert!(!bp.execute_quorum_reached(dot));
+
+        // committed, but not yet executable
+        bp.ack(dot);
+        assert!(bp.commit_quorum_reached(dot));
+        assert!(!bp.execute_quorum_reached(dot));
+        bp.ack(dot);
+        assert!(!bp.execute_quorum_reached(dot));
+
+        // executable
+        bp.ack(dot);
+        assert!(bp.commit_quorum_reached(dot));
+        assert!(bp.execute_quorum_reached(dot));
+    }
 }
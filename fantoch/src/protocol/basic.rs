@@ -1,18 +1,20 @@
 use crate::command::Command;
+use crate::command_span;
 use crate::config::Config;
 use crate::executor::{BasicExecutionInfo, BasicExecutor, Executor};
 use crate::id::{Dot, ProcessId, ShardId};
 use crate::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    Action, BaseProcess, CommitCallback, Info, MessageIndex, Protocol,
+    ProtocolMetrics, SequentialCommandsInfo, StableCallback, VClockGCTrack,
 };
 use crate::singleton;
 use crate::time::SysTime;
 use crate::trace;
+use crate::warn;
 use crate::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
-use threshold::VClock;
+use threshold::{AboveExSet, EventSet, VClock};
 
 type ExecutionInfo = <BasicExecutor as Executor>::ExecutionInfo;
 
@@ -24,6 +26,9 @@ pub struct Basic {
     to_processes: Vec<Action<Self>>,
     to_executors: Vec<ExecutionInfo>,
     buffered_mcommits: HashSet<Dot>,
+    // sequences of each process already garbage-collected, so that late
+    // messages about them are ignored (instead of recreating their info)
+    collected: HashMap<ProcessId, AboveExSet>,
 }
 
 impl Protocol for Basic {
@@ -37,18 +42,27 @@ impl Protocol for Basic {
         shard_id: ShardId,
         config: Config,
     ) -> (Self, Vec<(PeriodicEvent, Duration)>) {
-        // compute fast and write quorum sizes
-        let fast_quorum_size = config.basic_quorum_size();
+        // compute fast and write quorum sizes; since only the processes in
+        // the fast quorum reply to `MStore`, it must be large enough for the
+        // commit and execute quorums (if set) to be reached
+        let fast_quorum_size = config
+            .basic_quorum_size()
+            .max(config.basic_commit_quorum_size().unwrap_or_default())
+            .max(config.basic_execute_quorum_size().unwrap_or_default());
         let write_quorum_size = 0; // there's no write quorum as we have 100% fast paths
 
         // create protocol data-structures
-        let bp = BaseProcess::new(
+        let mut bp = BaseProcess::new(
             process_id,
             shard_id,
             config,
             fast_quorum_size,
             write_quorum_size,
         );
+        bp.set_quorum_sizes(
+            config.basic_commit_quorum_size(),
+            config.basic_execute_quorum_size(),
+        );
         let cmds = SequentialCommandsInfo::new(
             process_id,
             shard_id,
@@ -61,6 +75,7 @@ impl Protocol for Basic {
         let to_processes = Vec::new();
         let to_executors = Vec::new();
         let buffered_mcommits = HashSet::new();
+        let collected = HashMap::new();
 
         // create `Basic`
         let protocol = Self {
@@ -70,6 +85,7 @@ impl Protocol for Basic {
             to_processes,
             to_executors,
             buffered_mcommits,
+            collected,
         };
 
         // create periodic events
@@ -105,6 +121,7 @@ impl Protocol for Basic {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd);
     }
 
@@ -118,15 +135,20 @@ impl Protocol for Basic {
     ) {
         match msg {
             Message::MStore { dot, cmd, quorum } => {
-                self.handle_mstore(from, dot, cmd, quorum)
+                self.handle_mstore(from, dot, cmd, quorum, _time)
             }
             Message::MStoreAck { dot } => self.handle_mstoreack(from, dot),
-            Message::MCommit { dot } => self.handle_mcommit(dot),
+            Message::MCommit { dot, executable } => {
+                self.handle_mcommit(dot, executable, _time)
+            }
+            Message::MExecute { dot } => self.handle_mexecute(dot),
             Message::MCommitDot { dot } => self.handle_mcommit_dot(from, dot),
             Message::MGarbageCollection { committed } => {
                 self.handle_mgc(from, committed)
             }
-            Message::MStable { stable } => self.handle_mstable(from, stable),
+            Message::MStable { stable } => {
+                self.handle_mstable(from, stable, _time)
+            }
         }
     }
 
@@ -161,6 +183,22 @@ impl Protocol for Basic {
         true
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
@@ -172,11 +210,25 @@ impl Basic {
         // compute the command identifier
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
-        // create `MStore` and target
-        let quorum = self.bp.fast_quorum();
+        // create `MStore` and target; if the fast quorum is not yet known
+        // (i.e. a submit arrived before `discover`), the command is dropped
+        // (which is why submits to processes that haven't `discovered` should
+        // be rejected before reaching the protocol)
+        let quorum = match self.bp.fast_quorum_or_err() {
+            Ok(quorum) => quorum,
+            Err(e) => {
+                warn!("{}: rejecting submit of {:?}", e, cmd.rifl());
+                return;
+            }
+        };
         let mstore = Message::MStore { dot, cmd, quorum };
         let target = self.bp.all();
 
+        // track the quorum acks of this command, and the command itself until
+        // it's committed
+        self.bp.track_acks(dot);
+        self.bp.track_outstanding(dot);
+
         // save new action
         self.to_processes.push(Action::ToSend {
             target,
@@ -190,6 +242,7 @@ impl Basic {
         dot: Dot,
         cmd: Command,
         quorum: HashSet<ProcessId>,
+        _time: &dyn SysTime,
     ) {
         trace!(
             "p{}: MStore({:?}, {:?}, {:?}) from {}",
@@ -200,6 +253,11 @@ impl Basic {
             from
         );
 
+        // ignore the message if the command has already been collected
+        if self.collected(dot) {
+            return;
+        }
+
         // get cmd info
         let info = self.cmds.get(dot);
 
@@ -222,22 +280,41 @@ impl Basic {
         // check if there's a buffered commit notification; if yes, handle
         // the commit again (since now we have the payload)
         if self.buffered_mcommits.remove(&dot) {
-            self.handle_mcommit(dot);
+            // whether the command is executable has already been recorded
+            let executable = false;
+            self.handle_mcommit(dot, executable, _time);
         }
     }
 
     fn handle_mstoreack(&mut self, from: ProcessId, dot: Dot) {
         trace!("p{}: MStoreAck({:?}) from {}", self.id(), dot, from);
 
+        // ignore the message if the command has already been collected
+        if self.collected(dot) {
+            return;
+        }
+
         // get cmd info
         let info = self.cmds.get(dot);
 
-        // update quorum clocks
-        info.acks.insert(from);
-
-        // check if we have all necessary replies
-        if info.acks.len() == self.bp.config.basic_quorum_size() {
-            let mcommit = Message::MCommit { dot };
+        // update quorum clocks; duplicate acks are ignored, so that the
+        // quorums are only reached with enough distinct processes
+        if !info.acks.insert(from) {
+            return;
+        }
+        let was_committed = self.bp.commit_quorum_reached(dot);
+        let was_executable = self.bp.execute_quorum_reached(dot);
+        self.bp.ack(dot);
+
+        let committed = self.bp.commit_quorum_reached(dot);
+        let executable = self.bp.execute_quorum_reached(dot);
+
+        if !was_committed && committed {
+            // once the commit quorum is reached, the command is committed
+            // (and thus no longer outstanding); all processes are notified,
+            // and told whether the execute quorum has also been reached
+            self.bp.untrack_outstanding(dot);
+            let mcommit = Message::MCommit { dot, executable };
             let target = self.bp.all();
 
             // save new action
@@ -245,35 +322,47 @@ impl Basic {
                 target,
                 msg: mcommit,
             });
+        } else if !was_executable && executable {
+            // if the command was committed before, notify all processes
+            // that it's now executable
+            let mexecute = Message::MExecute { dot };
+            let target = self.bp.all();
+
+            // save new action
+            self.to_processes.push(Action::ToSend {
+                target,
+                msg: mexecute,
+            });
         }
     }
 
-    fn handle_mcommit(&mut self, dot: Dot) {
-        trace!("p{}: MCommit({:?})", self.id(), dot);
+    fn handle_mcommit(
+        &mut self,
+        dot: Dot,
+        executable: bool,
+        _time: &dyn SysTime,
+    ) {
+        trace!("p{}: MCommit({:?}, {:?})", self.id(), dot, executable);
+
+        // ignore the message if the command has already been collected
+        if self.collected(dot) {
+            return;
+        }
 
         // get cmd info and its rifl
         let info = self.cmds.get(dot);
+        info.executable |= executable;
 
         // check if we have received the initial `MStore`
         if let Some(cmd) = info.cmd.as_ref() {
-            // if so, create execution info:
-            // - one entry per key being accessed will be created, which allows
-            //   the basic executor to run in parallel
-            let rifl = cmd.rifl();
-            let execution_info =
-                cmd.iter(self.bp.shard_id).map(|(key, ops)| {
-                    BasicExecutionInfo::new(rifl, key.clone(), ops.clone())
-                });
-            self.to_executors.extend(execution_info);
-
-            if self.gc_running() {
-                // notify self with the committed dot
-                self.to_processes.push(Action::ToForward {
-                    msg: Message::MCommitDot { dot },
-                });
-            } else {
-                // if we're not running gc, remove the dot info now
-                self.cmds.gc_single(dot);
+            // if so, notify the on-commit callback (if any)
+            command_span!("commit", cmd.rifl(), _time);
+            self.bp.committed(dot, cmd);
+            info.committed = true;
+
+            // execute the command if it's already executable
+            if info.executable {
+                self.execute(dot);
             }
         } else {
             // if not, buffer this `MCommit` notification
@@ -281,6 +370,58 @@ impl Basic {
         }
     }
 
+    fn handle_mexecute(&mut self, dot: Dot) {
+        trace!("p{}: MExecute({:?})", self.id(), dot);
+
+        // ignore the message if the command has already been collected
+        if self.collected(dot) {
+            return;
+        }
+
+        // get cmd info
+        let info = self.cmds.get(dot);
+        info.executable = true;
+
+        // execute the command if it's already committed
+        if info.committed {
+            self.execute(dot);
+        }
+    }
+
+    // Sends a committed and executable command to the executors.
+    fn execute(&mut self, dot: Dot) {
+        // get cmd info
+        let info = self.cmds.get(dot);
+        let cmd = info
+            .cmd
+            .as_ref()
+            .expect("a committed command should have a payload");
+
+        // create execution info:
+        // - one entry per key being accessed will be created, which allows the
+        //   basic executor to run in parallel
+        let rifl = cmd.rifl();
+        let execution_info = cmd.iter(self.bp.shard_id).map(|(key, ops)| {
+            BasicExecutionInfo::new(dot, rifl, key.clone(), ops.clone())
+        });
+        self.to_executors.extend(execution_info);
+
+        if self.gc_running() {
+            // notify self with the committed dot
+            self.to_processes.push(Action::ToForward {
+                msg: Message::MCommitDot { dot },
+            });
+        } else {
+            // if we're not running gc, remove the dot info now
+            self.cmds.gc_single(dot);
+            self.bp.untrack_acks(dot);
+            self.collected
+                .entry(dot.source())
+                .or_insert_with(AboveExSet::new)
+                .add_event(dot.sequence());
+        }
+    }
+
     fn handle_mcommit_dot(&mut self, from: ProcessId, dot: Dot) {
         trace!("p{}: MCommitDot({:?})", self.id(), dot);
         assert_eq!(from, self.bp.process_id);
@@ -309,9 +450,21 @@ impl Basic {
         &mut self,
         from: ProcessId,
         stable: Vec<(ProcessId, u64, u64)>,
+        time: &dyn SysTime,
     ) {
         trace!("p{}: MStable({:?}) from {}", self.id(), stable, from);
         assert_eq!(from, self.bp.process_id);
+        // stable commands are no longer in-flight (and are collected below)
+        for dot in crate::util::dots(stable.clone()) {
+            self.bp.untrack_acks(dot);
+        }
+        for (process_id, start, end) in &stable {
+            self.collected
+                .entry(*process_id)
+                .or_insert_with(AboveExSet::new)
+                .add_event_range(*start, *end);
+        }
+        self.bp.advance_stable_frontier(&stable, time);
         let stable_count = self.cmds.gc(stable);
         self.bp.stable(stable_count);
     }
@@ -319,6 +472,9 @@ impl Basic {
     fn handle_event_garbage_collection(&mut self) {
         trace!("p{}: PeriodicEvent::GarbageCollection", self.id());
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         // retrieve the committed clock
         let committed = self.gc_track.clock().frontier();
 
@@ -329,6 +485,14 @@ impl Basic {
         });
     }
 
+    // Checks whether command `dot` has already been garbage-collected.
+    fn collected(&self, dot: Dot) -> bool {
+        self.collected
+            .get(&dot.source())
+            .map(|events| events.is_event(dot.sequence()))
+            .unwrap_or_default()
+    }
+
     fn gc_running(&self) -> bool {
         self.bp.config.gc_interval().is_some()
     }
@@ -340,6 +504,8 @@ impl Basic {
 struct BasicInfo {
     cmd: Option<Command>,
     acks: HashSet<ProcessId>,
+    committed: bool,
+    executable: bool,
 }
 
 impl Info for BasicInfo {
@@ -355,6 +521,8 @@ impl Info for BasicInfo {
         Self {
             cmd: None,
             acks: HashSet::with_capacity(fast_quorum_size),
+            committed: false,
+            executable: false,
         }
     }
 }
@@ -372,6 +540,10 @@ pub enum Message {
     },
     MCommit {
         dot: Dot,
+        executable: bool,
+    },
+    MExecute {
+        dot: Dot,
     },
     MCommitDot {
         dot: Dot,
@@ -394,6 +566,7 @@ impl MessageIndex for Message {
             Self::MStore { dot, .. } => worker_dot_index_shift(&dot),
             Self::MStoreAck { dot, .. } => worker_dot_index_shift(&dot),
             Self::MCommit { dot, .. } => worker_dot_index_shift(&dot),
+            Self::MExecute { dot } => worker_dot_index_shift(&dot),
             // GC messages
             Self::MCommitDot { .. } => worker_index_no_shift(GC_WORKER_INDEX),
             Self::MGarbageCollection { .. } => {
@@ -631,4 +804,174 @@ mod tests {
         let check_msg = |msg: &Message| matches!(msg, Message::MStore {dot, ..} if dot == &Dot::new(process_id_1, 2));
         assert!(matches!(mstore, Action::ToSend {msg, ..} if check_msg(&msg)));
     }
+
+    #[test]
+    fn submit_before_discover() {
+        use crate::id::Rifl;
+        use crate::kvs::KVOp;
+
+        // config
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let (mut basic, _) = Basic::new(1, 0, config);
+
+        // submitting before discover should not panic nor produce actions
+        assert!(!basic.discovered());
+        let time = SimTime::new();
+        let cmd = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Get)],
+        );
+        basic.submit(None, cmd, &time);
+        assert_eq!(basic.to_processes_iter().count(), 0);
+    }
+
+    #[test]
+    fn commit_and_execute_quorums() {
+        use crate::id::Rifl;
+        use crate::kvs::KVOp;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // config: commit after 1 ack, but only execute after 2
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_basic_commit_quorum_size(Some(1));
+        config.set_basic_execute_quorum_size(Some(2));
+        config.set_max_outstanding(Some(1));
+        let (mut basic, _) = Basic::new(1, 0, config);
+        basic.discover(vec![(1, 0), (2, 0), (3, 0)]);
+
+        // count the commits notified
+        let commits = Arc::new(AtomicUsize::new(0));
+        let commits_clone = commits.clone();
+        basic.set_on_commit(Arc::new(move |_dot: Dot, _cmd: &Command| {
+            commits_clone.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        // submit a command and handle its `MStore`
+        let time = SimTime::new();
+        let cmd = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Get)],
+        );
+        basic.submit(None, cmd, &time);
+        let dot = Dot::new(1, 1);
+        let mut actions: Vec<_> = basic.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        assert!(basic.overloaded());
+        let mstore = match actions.pop().unwrap() {
+            Action::ToSend { msg, .. } => msg,
+            _ => panic!("expecting Action::ToSend"),
+        };
+        basic.handle(1, 0, mstore, &time);
+        basic.to_processes_iter().for_each(drop);
+
+        // after the first ack, the command is committed (and thus no longer
+        // outstanding), but not yet executable
+        basic.handle(1, 0, Message::MStoreAck { dot }, &time);
+        assert!(!basic.overloaded());
+        let mut actions: Vec<_> = basic.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let mcommit = match actions.pop().unwrap() {
+            Action::ToSend { msg, .. } => msg,
+            _ => panic!("expecting Action::ToSend"),
+        };
+        assert_eq!(
+            mcommit,
+            Message::MCommit {
+                dot,
+                executable: false
+            }
+        );
+
+        // once the commit is handled, the on-commit callback is notified, but
+        // nothing is sent to the executors
+        basic.handle(1, 0, mcommit, &time);
+        assert_eq!(commits.load(Ordering::SeqCst), 1);
+        assert_eq!(basic.to_executors_iter().count(), 0);
+
+        // after the second ack, the command is executable
+        basic.handle(2, 0, Message::MStoreAck { dot }, &time);
+        let mut actions: Vec<_> = basic.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let mexecute = match actions.pop().unwrap() {
+            Action::ToSend { msg, .. } => msg,
+            _ => panic!("expecting Action::ToSend"),
+        };
+        assert_eq!(mexecute, Message::MExecute { dot });
+
+        // and once that's handled, it's sent to the executors
+        basic.handle(1, 0, mexecute, &time);
+        assert_eq!(commits.load(Ordering::SeqCst), 1);
+        assert_eq!(basic.to_executors_iter().count(), 1);
+    }
+
+    #[test]
+    fn duplicate_ack_and_late_execute() {
+        use crate::id::Rifl;
+        use crate::kvs::KVOp;
+
+        // config: commit and execute after 2 acks
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_basic_commit_quorum_size(Some(2));
+        config.set_basic_execute_quorum_size(Some(2));
+        let (mut basic, _) = Basic::new(1, 0, config);
+        basic.discover(vec![(1, 0), (2, 0), (3, 0)]);
+
+        // submit a command and handle its `MStore`
+        let time = SimTime::new();
+        let cmd = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Get)],
+        );
+        basic.submit(None, cmd, &time);
+        let dot = Dot::new(1, 1);
+        let mut actions: Vec<_> = basic.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let mstore = match actions.pop().unwrap() {
+            Action::ToSend { msg, .. } => msg,
+            _ => panic!("expecting Action::ToSend"),
+        };
+        basic.handle(1, 0, mstore, &time);
+        basic.to_processes_iter().for_each(drop);
+
+        // a duplicate ack doesn't count towards the quorums
+        basic.handle(1, 0, Message::MStoreAck { dot }, &time);
+        basic.handle(1, 0, Message::MStoreAck { dot }, &time);
+        assert_eq!(basic.to_processes_iter().count(), 0);
+
+        // but an ack from another process does
+        basic.handle(2, 0, Message::MStoreAck { dot }, &time);
+        let mut actions: Vec<_> = basic.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let mcommit = match actions.pop().unwrap() {
+            Action::ToSend { msg, .. } => msg,
+            _ => panic!("expecting Action::ToSend"),
+        };
+        assert_eq!(
+            mcommit,
+            Message::MCommit {
+                dot,
+                executable: true
+            }
+        );
+
+        // once the commit is handled, the command is executed and, since gc
+        // is not running, collected
+        basic.handle(1, 0, mcommit, &time);
+        assert_eq!(basic.to_executors_iter().count(), 1);
+        assert!(!basic.cmds.contains(&dot));
+
+        // late messages about the command are ignored
+        basic.handle(1, 0, Message::MExecute { dot }, &time);
+        basic.handle(3, 0, Message::MStoreAck { dot }, &time);
+        assert_eq!(basic.to_processes_iter().count(), 0);
+        assert_eq!(basic.to_executors_iter().count(), 0);
+        assert!(!basic.cmds.contains(&dot));
+    }
 }
@@ -73,6 +73,12 @@ where
             .count()
     }
 
+    /// Checks whether there's an `Info` associated with `Dot`.
+    #[cfg(test)]
+    pub fn contains(&self, dot: &Dot) -> bool {
+        self.dot_to_info.contains_key(dot)
+    }
+
     /// Removes a command has been committed.
     pub fn gc_single(&mut self, dot: Dot) {
         assert!(self.dot_to_info.remove(&dot).is_some());
@@ -18,14 +18,16 @@ mod basic;
 mod gc;
 
 // Re-exports.
-pub use base::BaseProcess;
+pub use base::{
+    BaseProcess, CommitCallback, FastQuorumUnknown, StableCallback,
+};
 pub use basic::Basic;
 pub use gc::{BasicGCTrack, ClockGCTrack, VClockGCTrack};
 pub use info::{Info, LockedCommandsInfo, SequentialCommandsInfo};
 
 use crate::command::Command;
 use crate::config::Config;
-use crate::executor::Executor;
+use crate::executor::{Executor, KeyToExecutor};
 use crate::id::{Dot, ProcessId, ShardId};
 use crate::metrics::Metrics;
 use crate::time::SysTime;
@@ -79,6 +81,26 @@ pub trait Protocol: Debug + Clone {
 
     fn handle_event(&mut self, event: Self::PeriodicEvent, time: &dyn SysTime);
 
+    fn set_on_commit(&mut self, _callback: CommitCallback) {
+        // protocols that support on-commit callbacks should overwrite this
+    }
+
+    fn set_on_stable(&mut self, _callback: StableCallback) {
+        // protocols that support on-stable callbacks should overwrite this
+    }
+
+    fn overloaded(&self) -> bool {
+        // protocols that support admission control (see
+        // `Config::max_outstanding`) should overwrite this
+        false
+    }
+
+    fn discovered(&self) -> bool {
+        // protocols that can't handle submits before `discover` should
+        // overwrite this
+        true
+    }
+
     fn handle_executed(
         &mut self,
         _committed_and_executed: CommittedAndExecuted,
@@ -109,6 +131,13 @@ pub trait Protocol: Debug + Clone {
 
     fn parallel() -> bool;
 
+    /// Same as `parallel`, but checks the process instance; this allows
+    /// protocols whose implementation is picked at runtime (e.g. with
+    /// `AutoKeyDeps`) to report the one picked.
+    fn is_parallel(&self) -> bool {
+        Self::parallel()
+    }
+
     fn leaderless() -> bool;
 
     fn metrics(&self) -> &ProtocolMetrics;
@@ -196,6 +225,10 @@ pub enum ProtocolMetricsKind {
     WaitConditionDelay,
     CommittedDepsLen,
     CommandKeyCount,
+    /// number of quorum acks received by each in-flight command
+    PendingAcks,
+    /// number of outstanding commands each time a new command is admitted
+    Outstanding,
 }
 
 impl Debug for ProtocolMetricsKind {
@@ -218,6 +251,8 @@ impl Debug for ProtocolMetricsKind {
             ProtocolMetricsKind::CommandKeyCount => {
                 write!(f, "command_key_count")
             }
+            ProtocolMetricsKind::PendingAcks => write!(f, "pending_acks"),
+            ProtocolMetricsKind::Outstanding => write!(f, "outstanding"),
         }
     }
 }
@@ -234,6 +269,31 @@ pub trait MessageIndex {
     ///   making sure that index is higher than `reserved`
     /// - None: no indexing; message will be sent to all workers
     fn index(&self) -> Option<(usize, usize)>;
+
+    /// Returns all the indexes the message should be forwarded to. By default,
+    /// this is the single index returned by `index`. If empty, the message is
+    /// sent to all workers.
+    fn indexes(&self) -> Vec<(usize, usize)> {
+        self.index().into_iter().collect()
+    }
+
+    /// Same as `index`, but routing keys to executors with `key_to_executor`.
+    /// By default, the policy is ignored.
+    fn index_with(
+        &self,
+        _key_to_executor: &KeyToExecutor,
+    ) -> Option<(usize, usize)> {
+        self.index()
+    }
+
+    /// Same as `indexes`, but routing keys to executors with
+    /// `key_to_executor`. By default, the policy is ignored.
+    fn indexes_with(
+        &self,
+        _key_to_executor: &KeyToExecutor,
+    ) -> Vec<(usize, usize)> {
+        self.indexes()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -79,7 +79,7 @@ const CONNECT_RETRIES: usize = 100;
 
 use crate::client::Workload;
 use crate::config::Config;
-use crate::executor::Executor;
+use crate::executor::{Executor, KeyToExecutor};
 use crate::hash_map::HashMap;
 use crate::id::{AtomicDotGen, ClientId, ProcessId, ShardId};
 use crate::info;
@@ -110,6 +110,7 @@ pub async fn process<P, A>(
     client_channel_buffer_size: usize,
     workers: usize,
     executors: usize,
+    key_to_executor: KeyToExecutor,
     multiplexing: usize,
     execution_log: Option<String>,
     ping_interval: Option<Duration>,
@@ -138,6 +139,7 @@ where
         client_channel_buffer_size,
         workers,
         executors,
+        key_to_executor,
         multiplexing,
         execution_log,
         ping_interval,
@@ -165,6 +167,7 @@ async fn process_with_notify_and_inspect<P, A, R>(
     client_channel_buffer_size: usize,
     workers: usize,
     executors: usize,
+    key_to_executor: KeyToExecutor,
     multiplexing: usize,
     execution_log: Option<String>,
     ping_interval: Option<Duration>,
@@ -177,8 +180,13 @@ where
     A: ToSocketAddrs + Debug + Clone,
     R: Clone + Debug + Send + 'static,
 {
+    // create process
+    let (mut process, process_events) = P::new(process_id, shard_id, config);
+
     // panic if protocol is not parallel and we have more than one worker
-    if workers > 1 && !P::parallel() {
+    // (the process instance is checked, as some protocols only pick their
+    // implementation at runtime)
+    if workers > 1 && !process.is_parallel() {
         panic!("running non-parallel protocol with {} workers", workers);
     }
 
@@ -188,12 +196,12 @@ where
     }
 
     // panic if protocol is leaderless and there's a leader
-    if P::leaderless() && config.leader().is_some() {
+    if P::leaderless() && config.has_leader() {
         panic!("running leaderless protocol with a leader");
     }
 
     // panic if leader-based and there's no leader
-    if !P::leaderless() && config.leader().is_none() {
+    if !P::leaderless() && !config.has_leader() {
         panic!("running leader-based protocol without a leader");
     }
 
@@ -212,11 +220,12 @@ where
     );
 
     // create forward channels: worker /readers -> executors
-    let (to_executors, to_executors_rxs) = ToExecutors::<P>::new(
+    let (mut to_executors, to_executors_rxs) = ToExecutors::<P>::new(
         "to_executors",
         process_channel_buffer_size,
         executors,
     );
+    to_executors.set_key_to_executor(key_to_executor);
 
     // connect to all processes
     let (ips, to_writers) = task::server::connect_to_all::<A, P>(
@@ -350,9 +359,6 @@ where
             (None, None)
         };
 
-    // create process
-    let (mut process, process_events) = P::new(process_id, shard_id, config);
-
     // discover processes
     let (connect_ok, closest_shard_process) =
         process.discover(sorted_processes);
@@ -745,6 +751,7 @@ pub mod tests {
                     client_channel_buffer_size,
                     workers,
                     executors,
+                    KeyToExecutor::from_config(&config),
                     multiplexing,
                     execution_log,
                     ping_interval,
@@ -1,14 +1,36 @@
+use crate::executor::KeyToExecutor;
 use crate::run::chan::{self, ChannelReceiver, ChannelSender};
 use color_eyre::Report;
 use std::fmt::Debug;
 
 pub trait PoolIndex {
     fn index(&self) -> Option<(usize, usize)>;
+
+    fn indexes(&self) -> Vec<(usize, usize)> {
+        self.index().into_iter().collect()
+    }
+
+    fn index_with(
+        &self,
+        _key_to_executor: &KeyToExecutor,
+    ) -> Option<(usize, usize)> {
+        self.index()
+    }
+
+    fn indexes_with(
+        &self,
+        _key_to_executor: &KeyToExecutor,
+    ) -> Vec<(usize, usize)> {
+        self.indexes()
+    }
 }
 
 #[derive(Clone)]
 pub struct ToPool<M> {
     pool: Vec<ChannelSender<M>>,
+    // policy used to route keys to pool workers (only relevant for pools of
+    // executors)
+    key_to_executor: KeyToExecutor,
 }
 
 impl<M> ToPool<M>
@@ -38,10 +60,19 @@ where
             })
             .collect();
         // create pool
-        let to_pool = Self { pool };
+        let to_pool = Self {
+            pool,
+            key_to_executor: KeyToExecutor::default(),
+        };
         (to_pool, rxs)
     }
 
+    /// Sets the policy used to route keys to pool workers.
+    pub fn set_key_to_executor(&mut self, key_to_executor: KeyToExecutor) {
+        key_to_executor.validate();
+        self.key_to_executor = key_to_executor;
+    }
+
     /// Returns the size of the pool.
     pub fn pool_size(&self) -> usize {
         self.pool.len()
@@ -59,13 +90,22 @@ where
     }
 
     /// Forwards message `msg` to the pool worker with id `msg.index() %
-    /// pool_size`.
+    /// pool_size`. If the message has more than one index (see
+    /// `PoolIndex::indexes`), it is forwarded to each of the pool workers.
     pub async fn forward(&mut self, msg: M) -> Result<(), Report>
     where
         M: PoolIndex,
     {
-        let index = self.index(&msg);
-        self.do_forward(index, msg).await
+        let mut indexes = self.indexes(&msg);
+        if indexes.len() > 1 {
+            let last = indexes.pop().expect("there should be a last index");
+            for index in indexes {
+                self.pool[index].send(msg.clone()).await?;
+            }
+            self.pool[last].send(msg).await
+        } else {
+            self.do_forward(indexes.pop(), msg).await
+        }
     }
 
     /// Forwards message `map(value)` to the pool worker with id `value.index()
@@ -102,9 +142,28 @@ where
     where
         T: PoolIndex,
     {
-        msg.index().map(|(reserved, index)| {
-            Self::do_index(reserved, index, self.pool_size())
-        })
+        msg.index_with(&self.key_to_executor)
+            .map(|(reserved, index)| {
+                Self::do_index(reserved, index, self.pool_size())
+            })
+    }
+
+    /// Returns the (distinct) indexes of the pool workers `msg` should be
+    /// forwarded to. If empty, it should be sent to all workers.
+    fn indexes<T>(&self, msg: &T) -> Vec<usize>
+    where
+        T: PoolIndex,
+    {
+        let mut indexes: Vec<_> = msg
+            .indexes_with(&self.key_to_executor)
+            .into_iter()
+            .map(|(reserved, index)| {
+                Self::do_index(reserved, index, self.pool_size())
+            })
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes
     }
 
     fn do_index(reserved: usize, index: usize, pool_size: usize) -> usize {
@@ -208,4 +267,83 @@ mod tests {
         assert_eq!(do_index(4, 2, pool_size), 2);
         assert_eq!(do_index(4, 3, pool_size), 0);
     }
+
+    #[test]
+    fn multi_key_indexes() {
+        use crate::executor::MessageKey;
+        use crate::kvs::Key;
+        use crate::util;
+
+        // execution info spanning several keys
+        #[derive(Debug, Clone)]
+        struct MultiKeyInfo {
+            keys: Vec<Key>,
+        }
+
+        impl MessageKey for MultiKeyInfo {
+            fn key(&self) -> &Key {
+                &self.keys[0]
+            }
+
+            fn keys(&self) -> Vec<&Key> {
+                self.keys.iter().collect()
+            }
+        }
+
+        let pool_size = 4;
+        let (to_pool, _rxs) =
+            ToPool::<MultiKeyInfo>::new("executors", 10, pool_size);
+
+        // a single-key info is forwarded to a single executor
+        let key_index =
+            |key: &Key| do_index(0, util::key_hash(key) as usize, pool_size);
+        let single = MultiKeyInfo {
+            keys: vec![String::from("A")],
+        };
+        assert_eq!(to_pool.indexes(&single), vec![key_index(&single.keys[0])]);
+
+        // a multi-key info is forwarded to all the executors its keys map to
+        let multi = MultiKeyInfo {
+            keys: (0..16).map(|i| format!("K{}", i)).collect(),
+        };
+        let mut expected: Vec<_> = multi.keys.iter().map(key_index).collect();
+        expected.sort_unstable();
+        expected.dedup();
+        assert!(expected.len() > 1);
+        assert_eq!(to_pool.indexes(&multi), expected);
+    }
+
+    #[test]
+    fn key_to_executor() {
+        use crate::executor::{BasicExecutionInfo, KeyToExecutor};
+        use crate::id::{Dot, Rifl};
+
+        let pool_size = 2;
+        let info = |key: &str| {
+            BasicExecutionInfo::new(
+                Dot::new(1, 1),
+                Rifl::new(1, 1),
+                key.to_string(),
+                std::sync::Arc::new(vec![]),
+            )
+        };
+
+        // each pool routes keys with its own policy
+        let (mut ranged, _rxs) =
+            ToPool::<BasicExecutionInfo>::new("ranged", 10, pool_size);
+        ranged.set_key_to_executor(KeyToExecutor::Range {
+            keys_per_executor: 10,
+        });
+        assert_eq!(ranged.index(&info("1")), Some(0));
+        assert_eq!(ranged.index(&info("10")), Some(0));
+        assert_eq!(ranged.index(&info("11")), Some(1));
+        assert_eq!(ranged.indexes(&info("20")), vec![1]);
+
+        let mut executors = crate::HashMap::new();
+        executors.insert(String::from("11"), 0);
+        let (mut explicit, _rxs) =
+            ToPool::<BasicExecutionInfo>::new("explicit", 10, pool_size);
+        explicit.set_key_to_executor(KeyToExecutor::Explicit(executors));
+        assert_eq!(explicit.index(&info("11")), Some(0));
+    }
 }
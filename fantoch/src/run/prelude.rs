@@ -1,8 +1,10 @@
 use super::chan::{ChannelReceiver, ChannelSender};
 use super::pool;
 use crate::command::{Command, CommandResult};
-use crate::executor::{Executor, ExecutorMetrics, ExecutorResult};
-use crate::id::{ClientId, Dot, ProcessId, ShardId};
+use crate::executor::{
+    Executor, ExecutorMetrics, ExecutorResult, KeyToExecutor,
+};
+use crate::id::{ClientId, Dot, ProcessId, Rifl, ShardId};
 use crate::load_balance::*;
 use crate::protocol::{
     CommittedAndExecuted, MessageIndex, Protocol, ProtocolMetrics,
@@ -71,7 +73,9 @@ pub type ServerToClientSender = ChannelSender<CommandResult>;
 pub type ExecutorResultReceiver = ChannelReceiver<ExecutorResult>;
 pub type ExecutorResultSender = ChannelSender<ExecutorResult>;
 pub type ExecutedReceiver = ChannelReceiver<CommittedAndExecuted>;
-pub type SubmitReceiver = ChannelReceiver<(Option<Dot>, Command)>;
+pub type SubmitReceiver =
+    ChannelReceiver<(Option<Dot>, Command, RejectedSender)>;
+pub type RejectedSender = ChannelSender<Rifl>;
 pub type ExecutionInfoReceiver<P> =
     ChannelReceiver<<<P as Protocol>::Executor as Executor>::ExecutionInfo>;
 pub type ExecutionInfoSender<P> =
@@ -89,9 +93,10 @@ pub type ProtocolMetricsSender = ChannelSender<(usize, ProtocolMetrics)>;
 pub type ExecutorMetricsReceiver = ChannelReceiver<(usize, ExecutorMetrics)>;
 pub type ExecutorMetricsSender = ChannelSender<(usize, ExecutorMetrics)>;
 
-// 1. workers receive messages from clients
-pub type ClientToWorkers = pool::ToPool<(Option<Dot>, Command)>;
-impl pool::PoolIndex for (Option<Dot>, Command) {
+// 1. workers receive messages from clients (along with the channel where the
+// command should be notified as rejected, in case the worker is overloaded)
+pub type ClientToWorkers = pool::ToPool<(Option<Dot>, Command, RejectedSender)>;
+impl pool::PoolIndex for (Option<Dot>, Command, RejectedSender) {
     fn index(&self) -> Option<(usize, usize)> {
         // if there's a `Dot`, then the protocol is leaderless; otherwise, it is
         // leader-based and the command should always be forwarded to the leader
@@ -179,4 +184,22 @@ where
     fn index(&self) -> Option<(usize, usize)> {
         self.index()
     }
+
+    fn indexes(&self) -> Vec<(usize, usize)> {
+        MessageIndex::indexes(self)
+    }
+
+    fn index_with(
+        &self,
+        key_to_executor: &KeyToExecutor,
+    ) -> Option<(usize, usize)> {
+        MessageIndex::index_with(self, key_to_executor)
+    }
+
+    fn indexes_with(
+        &self,
+        key_to_executor: &KeyToExecutor,
+    ) -> Vec<(usize, usize)> {
+        MessageIndex::indexes_with(self, key_to_executor)
+    }
 }
@@ -68,18 +68,22 @@ where
         V: Serialize,
     {
         let bytes = serialize(value);
-        futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_ready(cx))
-            .await
-            .wrap_err("error while polling sink ready")?;
+        futures::future::poll_fn(|cx| {
+            Sink::<Bytes>::poll_ready(Pin::new(&mut self.rw), cx)
+        })
+        .await
+        .wrap_err("error while polling sink ready")?;
         Pin::new(&mut self.rw)
             .start_send(bytes)
             .wrap_err("error while starting send to sink")
     }
 
     pub async fn flush(&mut self) -> Result<(), Report> {
-        futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_flush(cx))
-            .await
-            .wrap_err("error while flushing sink")
+        futures::future::poll_fn(|cx| {
+            Sink::<Bytes>::poll_flush(Pin::new(&mut self.rw), cx)
+        })
+        .await
+        .wrap_err("error while flushing sink")
     }
 }
 
@@ -26,6 +26,7 @@ use crate::HashSet;
 use crate::{info, trace, warn};
 use color_eyre::Report;
 use futures::stream::{FuturesUnordered, StreamExt};
+use pending::Outcome;
 use std::fmt::Debug;
 use std::time::Duration;
 use tokio::net::ToSocketAddrs;
@@ -155,9 +156,15 @@ where
     // track which clients are workload finished
     let mut workload_finished = HashSet::with_capacity(clients.len());
 
-    // generate the first message of each client
+    // generate the first messages of each client
     for client in clients.values_mut() {
-        cmd_send(client, &time, &mut batcher_tx, &mut workload_finished).await;
+        cmd_send_while_can_submit(
+            client,
+            &time,
+            &mut batcher_tx,
+            &mut workload_finished,
+        )
+        .await;
     }
 
     // wait for results and generate/submit new commands while there are
@@ -165,15 +172,25 @@ where
     while finished.len() < clients.len() {
         // and wait for next result
         let from_unbatcher = unbatcher_rx.recv().await;
-        let ready_clients =
-            cmd_recv(&mut clients, &time, from_unbatcher, &mut finished);
+        let ready_clients = cmd_recv(
+            &mut clients,
+            &time,
+            from_unbatcher,
+            &mut finished,
+            &mut workload_finished,
+        );
         for client_id in ready_clients {
             let client = clients
                 .get_mut(&client_id)
                 .expect("[client] ready client should exist");
-            // if client hasn't finished, issue a new command
-            cmd_send(client, &time, &mut batcher_tx, &mut workload_finished)
-                .await;
+            // if client hasn't finished, issue new commands
+            cmd_send_while_can_submit(
+                client,
+                &time,
+                &mut batcher_tx,
+                &mut workload_finished,
+            )
+            .await;
         }
     }
     assert_eq!(workload_finished.len(), finished.len());
@@ -235,6 +252,7 @@ where
                     &time,
                     from_unbatcher,
                     &mut finished,
+                    &mut workload_finished,
                 );
             }
             _ = interval.tick() => {
@@ -272,7 +290,7 @@ async fn client_setup<A>(
     status_frequency: Option<usize>,
 ) -> Option<(
     HashMap<ClientId, Client>,
-    ChannelReceiver<Vec<Rifl>>,
+    ChannelReceiver<(Vec<Rifl>, Outcome)>,
     ChannelSender<(ShardId, Command)>,
 )>
 where
@@ -369,7 +387,7 @@ async fn spawn_batcher_and_unbatcher(
     shard_to_writer: HashMap<ShardId, ChannelSender<ClientToServer>>,
 ) -> Option<(
     HashMap<ClientId, Client>,
-    ChannelReceiver<Vec<Rifl>>,
+    ChannelReceiver<(Vec<Rifl>, Outcome)>,
     ChannelSender<(ShardId, Command)>,
 )> {
     let (mut batcher_tx, batcher_rx) = chan::channel(channel_buffer_size);
@@ -424,16 +442,30 @@ async fn cmd_send(
     }
 }
 
+/// Issues new commands while the client is below its workload's concurrency
+/// and there are still commands to be generated.
+async fn cmd_send_while_can_submit(
+    client: &mut Client,
+    time: &dyn SysTime,
+    to_batcher: &mut ChannelSender<(ShardId, Command)>,
+    workload_finished: &mut HashSet<ClientId>,
+) {
+    while client.can_submit() && !workload_finished.contains(&client.id()) {
+        cmd_send(client, time, to_batcher, workload_finished).await;
+    }
+}
+
 /// Handles new ready rifls. Returns the client ids of clients with a new
 /// command finished.
 fn cmd_recv(
     clients: &mut HashMap<ClientId, Client>,
     time: &dyn SysTime,
-    from_unbatcher: Option<Vec<Rifl>>,
+    from_unbatcher: Option<(Vec<Rifl>, Outcome)>,
     finished: &mut HashSet<ClientId>,
+    workload_finished: &mut HashSet<ClientId>,
 ) -> Vec<ClientId> {
-    if let Some(rifls) = from_unbatcher {
-        do_cmd_recv(clients, time, rifls, finished)
+    if let Some((rifls, outcome)) = from_unbatcher {
+        do_cmd_recv(clients, time, rifls, outcome, finished, workload_finished)
     } else {
         panic!("[client] error while receiving message from client read-write task");
     }
@@ -443,7 +475,9 @@ fn do_cmd_recv(
     clients: &mut HashMap<ClientId, Client>,
     time: &dyn SysTime,
     rifls: Vec<Rifl>,
+    outcome: Outcome,
     finished: &mut HashSet<ClientId>,
+    workload_finished: &mut HashSet<ClientId>,
 ) -> Vec<ClientId> {
     rifls
         .into_iter()
@@ -454,8 +488,17 @@ fn do_cmd_recv(
                 .get_mut(&client_id)
                 .expect("[client] command result should belong to a client");
 
-            // handle command results
-            client.cmd_recv(rifl, time);
+            // handle command results (or their rejection or abort)
+            match outcome {
+                Outcome::Completed => client.cmd_recv(rifl, time),
+                Outcome::Rejected => client.cmd_rejected(rifl, time),
+                Outcome::Aborted => {
+                    client.cmd_aborted(rifl, time);
+                    // the client has a command to retry, so it may have to
+                    // submit again even if its workload has finished
+                    workload_finished.remove(&client_id);
+                }
+            }
 
             // check if client is finished
             if client.finished() {
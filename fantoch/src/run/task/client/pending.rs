@@ -3,6 +3,14 @@ use crate::hash_map::{Entry, HashMap};
 use crate::id::Rifl;
 use crate::trace;
 
+/// How the commands in a batch ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Rejected,
+    Aborted,
+}
+
 struct Expected {
     shard_count: usize,
     total_key_count: usize,
@@ -41,14 +49,31 @@ impl ShardsPending {
 
     // Add new `CommandResult`.
     // If some command got the `CommandResult`s from each of the shards
-    // accessed, then return all the `Rifl`s in that batch.
-    pub fn add(&mut self, result: CommandResult) -> Option<Vec<Rifl>> {
+    // accessed, then return all the `Rifl`s in that batch, along with whether
+    // the command was aborted (by any of the shards) or completed. If the
+    // command was rejected, the `Rifl`s in that batch are returned right away.
+    pub fn add(
+        &mut self,
+        result: CommandResult,
+    ) -> Option<(Vec<Rifl>, Outcome)> {
         let rifl = result.rifl();
         trace!("c{}: received {:?}", rifl.source(), rifl);
 
         // check if command is ready
         match self.pending.entry(rifl) {
             Entry::Occupied(mut entry) => {
+                // if the command was rejected (by the shard it was submitted
+                // to), then no other shard will reply
+                if result.is_rejected() {
+                    trace!("c{}: {:?} rejected", rifl.source(), rifl);
+                    entry.remove();
+                    let batch_rifls =
+                        self.rifl_to_batch_rifls.remove(&rifl).expect(
+                            "each rifl should be mapped to their batch rifls",
+                        );
+                    return Some((batch_rifls, Outcome::Rejected));
+                }
+
                 let (expected, results) = entry.get_mut();
                 // add new result
                 results.push(result);
@@ -66,12 +91,19 @@ impl ShardsPending {
                 //   `CommandResult` came from different shards, and are not
                 //   sent by the same shard
                 if results.len() == expected.shard_count {
+                    // aborted results carry no results; if there are none,
                     // assert that all keys accessed got a result
-                    let results_key_count: usize = results
-                        .into_iter()
-                        .map(|cmd_result| cmd_result.results().len())
-                        .sum();
-                    assert_eq!(results_key_count, expected.total_key_count);
+                    let outcome = if results.iter().any(|r| r.is_aborted()) {
+                        trace!("c{}: {:?} aborted", rifl.source(), rifl);
+                        Outcome::Aborted
+                    } else {
+                        let results_key_count: usize = results
+                            .iter()
+                            .map(|cmd_result| cmd_result.results().len())
+                            .sum();
+                        assert_eq!(results_key_count, expected.total_key_count);
+                        Outcome::Completed
+                    };
 
                     // remove command from pending
                     entry.remove();
@@ -81,7 +113,7 @@ impl ShardsPending {
                         self.rifl_to_batch_rifls.remove(&rifl).expect(
                             "each rifl should be mapped to their batch rifls",
                         );
-                    Some(batch_rifls)
+                    Some((batch_rifls, outcome))
                 } else {
                     None
                 }
@@ -1,5 +1,5 @@
 use super::batch::Batch;
-use super::pending::ShardsPending;
+use super::pending::{Outcome, ShardsPending};
 use crate::command::CommandResult;
 use crate::id::{Rifl, ShardId};
 use crate::run::chan::{ChannelReceiver, ChannelSender};
@@ -10,7 +10,7 @@ use color_eyre::eyre::{eyre, Report};
 
 pub async fn unbatcher(
     mut from: ChannelReceiver<Batch>,
-    mut to: ChannelSender<Vec<Rifl>>,
+    mut to: ChannelSender<(Vec<Rifl>, Outcome)>,
     mut read: ChannelReceiver<CommandResult>,
     mut shard_to_writer: HashMap<ShardId, ChannelSender<ClientToServer>>,
 ) {
@@ -92,7 +92,7 @@ async fn send_to_shard(
 
 async fn handle_from_server(
     cmd_result: Option<CommandResult>,
-    to: &mut ChannelSender<Vec<Rifl>>,
+    to: &mut ChannelSender<(Vec<Rifl>, Outcome)>,
     pending: &mut ShardsPending,
 ) -> Result<(), Report> {
     if let Some(cmd_result) = cmd_result {
@@ -105,11 +105,11 @@ async fn handle_from_server(
 
 async fn handle_cmd_result(
     cmd_result: CommandResult,
-    to: &mut ChannelSender<Vec<Rifl>>,
+    to: &mut ChannelSender<(Vec<Rifl>, Outcome)>,
     pending: &mut ShardsPending,
 ) {
-    if let Some(rifls) = pending.add(cmd_result) {
-        if let Err(e) = to.send(rifls).await {
+    if let Some(ready) = pending.add(cmd_result) {
+        if let Err(e) = to.send(ready).await {
             warn!("[unbatcher] error while sending message to client: {:?}", e);
         }
     }
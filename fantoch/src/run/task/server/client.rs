@@ -1,6 +1,6 @@
 use crate::command::Command;
 use crate::executor::{AggregatePending, ExecutorResult};
-use crate::id::{AtomicDotGen, ClientId, ProcessId, ShardId};
+use crate::id::{AtomicDotGen, ClientId, ProcessId, Rifl, ShardId};
 use crate::run::chan;
 use crate::run::prelude::*;
 use crate::run::rw::Connection;
@@ -100,6 +100,14 @@ async fn client_server_task(
     }
     let (client_ids, mut executor_results) = client.unwrap();
 
+    // create channel where the workers will notify rejected commands
+    let (mut rejected_tx, mut rejected_rx) =
+        chan::channel(client_channel_buffer_size);
+    rejected_tx.set_name(format!(
+        "client_server_rejected_{}",
+        task::util::ids_repr(&client_ids)
+    ));
+
     // create pending
     let mut pending = AggregatePending::new(process_id, shard_id);
 
@@ -109,9 +117,13 @@ async fn client_server_task(
                 trace!("[client_server] new executor result: {:?}", executor_result);
                 client_server_task_handle_executor_result(executor_result, &mut connection, &mut pending).await;
             }
+            rejected = rejected_rx.recv() => {
+                trace!("[client_server] rejected: {:?}", rejected);
+                client_server_task_handle_rejected(rejected, &mut connection, &mut pending).await;
+            }
             from_client = connection.recv() => {
                 trace!("[client_server] from client: {:?}", from_client);
-                if !client_server_task_handle_from_client(from_client, &client_ids, &atomic_dot_gen, &mut client_to_workers, &mut client_to_executors, &mut pending).await {
+                if !client_server_task_handle_from_client(from_client, &client_ids, &atomic_dot_gen, &mut client_to_workers, &mut client_to_executors, &rejected_tx, &mut pending).await {
                     return;
                 }
             }
@@ -176,6 +188,7 @@ async fn client_server_task_handle_from_client(
     atomic_dot_gen: &Option<AtomicDotGen>,
     client_to_workers: &mut ClientToWorkers,
     client_to_executors: &mut ClientToExecutors,
+    rejected: &RejectedSender,
     pending: &mut AggregatePending,
 ) -> bool {
     if let Some(from_client) = from_client {
@@ -183,6 +196,7 @@ async fn client_server_task_handle_from_client(
             from_client,
             atomic_dot_gen,
             client_to_workers,
+            rejected,
             pending,
         )
         .await;
@@ -207,6 +221,7 @@ async fn client_server_task_handle_cmd(
     from_client: ClientToServer,
     atomic_dot_gen: &Option<AtomicDotGen>,
     client_to_workers: &mut ClientToWorkers,
+    rejected: &RejectedSender,
     pending: &mut AggregatePending,
 ) {
     match from_client {
@@ -222,8 +237,10 @@ async fn client_server_task_handle_cmd(
             let dot = atomic_dot_gen
                 .as_ref()
                 .map(|atomic_dot_gen| atomic_dot_gen.next_id());
-            // forward command to worker process
-            if let Err(e) = client_to_workers.forward((dot, cmd)).await {
+            // forward command to worker process (along with the channel
+            // where it should be notified in case it's rejected)
+            let msg = (dot, cmd, rejected.clone());
+            if let Err(e) = client_to_workers.forward(msg).await {
                 warn!(
                     "[client_server] error while sending new command to protocol worker: {:?}",
                     e
@@ -260,3 +277,24 @@ async fn client_server_task_handle_executor_result(
         warn!("[client_server] error while receiving new executor result from executor");
     }
 }
+
+async fn client_server_task_handle_rejected(
+    rejected: Option<Rifl>,
+    connection: &mut Connection,
+    pending: &mut AggregatePending,
+) {
+    if let Some(rifl) = rejected {
+        if let Some(cmd_result) = pending.rejected(rifl) {
+            if let Err(e) = connection.send(&cmd_result).await {
+                warn!(
+                    "[client_server] error while sending command rejection: {:?}",
+                    e
+                );
+            }
+        }
+    } else {
+        warn!(
+            "[client_server] error while receiving new rejection from workers"
+        );
+    }
+}
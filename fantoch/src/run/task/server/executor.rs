@@ -205,7 +205,7 @@ async fn fetch_new_command_results<P>(
     // are waiting for them
     for executor_result in executor.to_clients_iter() {
         // get client id
-        let client_id = executor_result.rifl.source();
+        let client_id = executor_result.client();
 
         // send executor result to client (in case it is registered)
         if let Some(executor_results_tx) = to_clients.to_client(&client_id) {
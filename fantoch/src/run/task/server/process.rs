@@ -326,7 +326,7 @@ pub async fn send_to_one_writer<P>(
 
 async fn selected_from_clients<P>(
     worker_index: usize,
-    cmd: Option<(Option<Dot>, Command)>,
+    cmd: Option<(Option<Dot>, Command, RejectedSender)>,
     process: &mut P,
     to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     reader_to_workers: &mut ReaderToWorkers<P>,
@@ -337,11 +337,12 @@ async fn selected_from_clients<P>(
     P: Protocol + 'static,
 {
     trace!("[server] from clients: {:?}", cmd);
-    if let Some((dot, cmd)) = cmd {
+    if let Some((dot, cmd, rejected)) = cmd {
         handle_from_clients(
             worker_index,
             dot,
             cmd,
+            rejected,
             process,
             to_writers,
             reader_to_workers,
@@ -359,6 +360,7 @@ async fn handle_from_clients<P>(
     worker_index: usize,
     dot: Option<Dot>,
     cmd: Command,
+    mut rejected: RejectedSender,
     process: &mut P,
     to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     reader_to_workers: &mut ReaderToWorkers<P>,
@@ -368,6 +370,16 @@ async fn handle_from_clients<P>(
 ) where
     P: Protocol + 'static,
 {
+    // if the process is overloaded or hasn't discovered the other processes
+    // yet, reject the command
+    if process.overloaded() || !process.discovered() {
+        trace!("[server] rejecting command {:?}", cmd.rifl());
+        if let Err(e) = rejected.send(cmd.rifl()).await {
+            warn!("[server] error while rejecting command: {:?}", e);
+        }
+        return;
+    }
+
     // submit command in process
     process.submit(dot, cmd, time);
     send_to_processes_and_executors(
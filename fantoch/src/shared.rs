@@ -30,6 +30,14 @@ where
         Self { shared }
     }
 
+    // Create a `Shared` instance able to hold at least `capacity` elements
+    // without reallocating. Since elements are not evenly distributed across
+    // shards, each shard is pre-sized to hold twice its share.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let shared = DashMap::with_capacity(capacity * 2);
+        Self { shared }
+    }
+
     pub fn get(&self, key: &K) -> Option<SharedMapRef<'_, K, V>> {
         self.shared.get(key)
     }
@@ -90,6 +98,16 @@ where
         self.shared.remove(key)
     }
 
+    // Removes the entry associated with `key` if `f` returns true. Since `f`
+    // runs while holding the lock on the entry, the check and the removal are
+    // atomic.
+    pub fn remove_if<F>(&self, key: &K, f: F) -> Option<(K, V)>
+    where
+        F: FnOnce(&K, &V) -> bool,
+    {
+        self.shared.remove_if(key, f)
+    }
+
     pub fn iter(&self) -> SharedMapIter<'_, K, V> {
         self.shared.iter()
     }
@@ -112,4 +130,8 @@ where
     pub fn len(&self) -> usize {
         self.shared.len()
     }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
 }
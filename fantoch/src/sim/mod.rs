@@ -8,6 +8,8 @@ pub mod schedule;
 pub mod runner;
 
 // Re-exports.
-pub use runner::Runner;
+pub use runner::{
+    MessageCounts, Runner, RunnerOptions, SlaError, SlaViolation, StepResult,
+};
 pub use schedule::Schedule;
 pub use simulation::Simulation;
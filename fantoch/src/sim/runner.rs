@@ -1,28 +1,156 @@
 use crate::client::{Client, Workload};
-use crate::command::{Command, CommandResult, DEFAULT_SHARD_ID};
+use crate::command::{self, Command, CommandResult};
 use crate::config::Config;
 use crate::executor::{ExecutionOrderMonitor, Executor, ExecutorMetrics};
-use crate::id::{ClientId, ProcessId, ShardId};
+use crate::id::{ClientId, ProcessId, Rifl, ShardId};
 use crate::metrics::Histogram;
 use crate::planet::{Planet, Region};
-use crate::protocol::{Action, Protocol, ProtocolMetrics};
+use crate::protocol::{
+    Action, CommitCallback, Protocol, ProtocolMetrics, StableCallback,
+};
 use crate::sim::{Schedule, Simulation};
 use crate::time::SysTime;
 use crate::util;
-use crate::HashMap;
-use rand::Rng;
+use crate::warn;
+use crate::{HashMap, HashSet};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::fmt;
 use std::fmt::Debug;
 use std::time::{Duration, Instant};
 
 #[derive(PartialEq, Eq)]
-enum ScheduleAction<Message, PeriodicEvent> {
+enum ScheduleAction<Message, PeriodicEvent, ExecutionInfo> {
     SubmitToProc(ProcessId, Command),
     SendToProc(ProcessId, ShardId, ProcessId, Message),
+    SendToExecutor(ProcessId, ExecutionInfo),
     SendToClient(ClientId, CommandResult),
+    CmdTimeout(ClientId, Rifl, u64),
     PeriodicProcessEvent(ProcessId, PeriodicEvent, Duration),
     PeriodicExecutedNotification(ProcessId, Duration),
+    PeriodicExecutorCleanup(ProcessId, Duration),
 }
+
+// actions scheduled by the runner of protocol `P`
+type RunnerAction<P> = ScheduleAction<
+    <P as Protocol>::Message,
+    <P as Protocol>::PeriodicEvent,
+    <<P as Protocol>::Executor as Executor>::ExecutionInfo,
+>;
+
+/// Function that computes the one-way delay (in milliseconds) between two
+/// regions given their ping latency (in milliseconds).
+pub type DistanceFn = fn(&Region, &Region, u64) -> u64;
+
+// by default, the distance is half the ping latency
+fn half_ping(_from: &Region, _to: &Region, ping: u64) -> u64 {
+    ping / 2
+}
+
+// Creates a planet where the latency from each of the `from` regions to each
+// of the `to` regions is their distance, as computed by `distance_fn`.
+fn distance_planet<'a>(
+    planet: &Planet,
+    distance_fn: DistanceFn,
+    from: impl Iterator<Item = &'a Region>,
+    to: &[Region],
+) -> Planet {
+    let pairs = from
+        .flat_map(|from| {
+            to.iter().map(move |to| {
+                let ping = planet
+                    .ping_latency(from, to)
+                    .expect("both regions should exist on the planet");
+                let distance = distance_fn(from, to, ping);
+                ((from.clone(), to.clone()), distance)
+            })
+        })
+        .collect();
+    Planet::from_pairs(pairs)
+}
+
+/// Settings used when creating a `Runner` (see `Runner::with_options`), which
+/// can't be changed once processes are created.
+#[derive(Debug, Clone, Default)]
+pub struct RunnerOptions {
+    // function that computes the distance between regions (by default, half
+    // the ping latency)
+    distance_fn: Option<DistanceFn>,
+    // shards replicated by each process (by default, its own shard)
+    replicated_shards: HashMap<ProcessId, HashSet<ShardId>>,
+}
+
+impl RunnerOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the function used to compute the distance between two regions
+    /// from their ping latency (by default, half the ping latency). Processes
+    /// and clients discover processes sorted by this distance.
+    pub fn set_distance_fn(&mut self, distance_fn: DistanceFn) {
+        self.distance_fn = Some(distance_fn);
+    }
+
+    /// Sets the shards replicated by process `process_id`: its executor will
+    /// ignore commands that don't access any of these shards (if supported by
+    /// the executor).
+    pub fn set_replicated_shards(
+        &mut self,
+        process_id: ProcessId,
+        shards: HashSet<ShardId>,
+    ) {
+        self.replicated_shards.insert(process_id, shards);
+    }
+}
+
+/// Error returned by `Runner::assert_sla`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlaError {
+    /// there are no clients in the region provided
+    NoClients(Region),
+    /// the latency of the region exceeds the one allowed
+    Violation(SlaViolation),
+}
+
+impl fmt::Display for SlaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoClients(region) => {
+                write!(f, "there are no clients in {:?}", region)
+            }
+            Self::Violation(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+impl std::error::Error for SlaError {}
+
+/// The latency of some region exceeds the one allowed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlaViolation {
+    pub region: Region,
+    pub percentile: f64,
+    pub max_ms: u64,
+    pub observed_ms: f64,
+}
+
+impl fmt::Display for SlaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SLA violation in {:?}: p{} latency is {}ms, but it should be at most {}ms",
+            self.region,
+            self.percentile * 100.0,
+            self.observed_ms,
+            self.max_ms
+        )
+    }
+}
+
+impl std::error::Error for SlaViolation {}
+
 #[derive(Clone)]
 enum MessageRegion {
     Process(ProcessId),
@@ -32,9 +160,12 @@ enum MessageRegion {
 pub struct Runner<P: Protocol> {
     planet: Planet,
     simulation: Simulation<P>,
-    schedule: Schedule<ScheduleAction<P::Message, P::PeriodicEvent>>,
+    schedule: Schedule<RunnerAction<P>>,
     // mapping from process identifier to its region
     process_to_region: HashMap<ProcessId, Region>,
+    // mapping from process identifier to the closest process of each of the
+    // other shards
+    closest_shard_process: HashMap<ProcessId, HashMap<ShardId, ProcessId>>,
     // mapping from client identifier to its region
     client_to_region: HashMap<ClientId, Region>,
     // total number of clients
@@ -44,10 +175,64 @@ pub struct Runner<P: Protocol> {
     make_distances_symmetric: bool,
     // boolean indicating whether the runner should reoder messages
     reorder_messages: bool,
+    // percentage of messages between processes that are lost
+    message_loss: usize,
+    // bandwidth (in bytes per millisecond) of all links, and of specific
+    // links (which takes precedence); if set, messages take longer to be
+    // delivered the larger they are
+    bandwidth: Option<u64>,
+    link_bandwidth: HashMap<(Region, Region), u64>,
+    // function that computes the distance between regions
+    distance_fn: DistanceFn,
+    // commands completing before this (simulation) time are not recorded in
+    // the clients' latency histograms
+    warmup: Option<Duration>,
+    // state of the simulation loop
+    simulation_status: SimulationStatus,
+    extra_sim_time: Option<Duration>,
+    clients_done: usize,
+    simulation_final_time: u64,
+    // number of messages sent and received by each process
+    message_counts: HashMap<ProcessId, MessageCounts>,
+    // serialized size (in bytes) of the messages sent by each process
+    message_sizes: HashMap<ProcessId, Histogram>,
+    // commands submitted by clients that have not (yet) produced a result
+    unresolved: HashSet<Rifl>,
+    // identifier of the latest submission of each pending command (so that
+    // the timeout of a previous submission of a command, e.g. before it was
+    // aborted and retried, is ignored), and the next identifier to be used
+    submissions: HashMap<Rifl, u64>,
+    next_submission: u64,
+    // number of per-shard results still missing for commands that access
+    // more than one shard, and whether any of the results received so far was
+    // an abort
+    missing_shard_results: HashMap<Rifl, (usize, bool)>,
+    // source of randomness for message loss and reordering
+    rng: StdRng,
+}
+
+/// Number of messages sent to (and received from) other processes by a
+/// single process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageCounts {
+    /// number of messages sent
+    pub sent: u64,
+    /// number of messages received
+    pub received: u64,
+}
+
+/// Summary of a single `Runner::step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    /// number of scheduled actions executed
+    pub actions: usize,
+    /// simulation time (in milliseconds) after the step
+    pub time_millis: u64,
 }
 
 #[derive(PartialEq)]
 enum SimulationStatus {
+    NotStarted,
     ClientsRunning,
     ExtraSimulationTime,
     Done,
@@ -59,7 +244,9 @@ where
 {
     /// Create a new `Runner` from a `planet`, a `config`, and two lists of
     /// regions:
-    /// - `process_regions`: list of regions where processes are located
+    /// - `process_regions`: list of regions where processes are located (if
+    ///   there's more than one shard, each shard has a process in each of
+    ///   these regions)
     /// - `client_regions`: list of regions where clients are located
     pub fn new(
         planet: Planet,
@@ -68,6 +255,72 @@ where
         clients_per_process: usize,
         process_regions: Vec<Region>,
         client_regions: Vec<Region>,
+    ) -> Self {
+        let client_counts = client_regions
+            .into_iter()
+            .map(|region| (region, clients_per_process))
+            .collect();
+        let options = RunnerOptions::default();
+        Self::create(
+            planet,
+            config,
+            workload,
+            process_regions,
+            client_counts,
+            options,
+        )
+    }
+
+    /// Create a new `Runner` where the number of clients in each region is
+    /// given by `client_counts`, allowing some regions to host more clients
+    /// than others.
+    pub fn with_client_counts(
+        planet: Planet,
+        config: Config,
+        workload: Workload,
+        process_regions: Vec<Region>,
+        client_counts: HashMap<Region, usize>,
+    ) -> Self {
+        let options = RunnerOptions::default();
+        Self::with_options(
+            planet,
+            config,
+            workload,
+            process_regions,
+            client_counts,
+            options,
+        )
+    }
+
+    /// Same as `with_client_counts`, but with the given `options`.
+    pub fn with_options(
+        planet: Planet,
+        config: Config,
+        workload: Workload,
+        process_regions: Vec<Region>,
+        client_counts: HashMap<Region, usize>,
+        options: RunnerOptions,
+    ) -> Self {
+        // sort regions so that client identifiers are deterministic
+        let mut client_counts: Vec<_> = client_counts.into_iter().collect();
+        client_counts.sort();
+        Self::create(
+            planet,
+            config,
+            workload,
+            process_regions,
+            client_counts,
+            options,
+        )
+    }
+
+    fn create(
+        planet: Planet,
+        config: Config,
+        workload: Workload,
+        process_regions: Vec<Region>,
+        client_counts: Vec<(Region, usize)>,
+        options: RunnerOptions,
     ) -> Self {
         // check that we have the correct number of `process_regions`
         assert_eq!(process_regions.len(), config.n());
@@ -80,14 +333,20 @@ where
         let mut processes = Vec::with_capacity(config.n());
         let mut periodic_process_events = Vec::new();
         let mut periodic_executed_notifications = Vec::new();
-
-        // there's a single shard
-        let shard_id = 0;
-
-        let to_discover: Vec<_> = process_regions
-            .into_iter()
-            .zip(util::process_ids(shard_id, config.n()))
-            .map(|(region, process_id)| {
+        let mut periodic_executor_cleanups = Vec::new();
+
+        // each shard has a process in each of the `process_regions`
+        let to_discover: Vec<_> = (0..config.shard_count() as ShardId)
+            .flat_map(|shard_id| {
+                process_regions
+                    .clone()
+                    .into_iter()
+                    .zip(util::process_ids(shard_id, config.n()))
+                    .map(move |(region, process_id)| {
+                        (process_id, shard_id, region)
+                    })
+            })
+            .map(|(process_id, shard_id, region)| {
                 // create process and save it
                 let (process, process_events) =
                     P::new(process_id, shard_id, config);
@@ -106,6 +365,19 @@ where
                 periodic_executed_notifications
                     .push((process_id, executed_notification_interval));
 
+                // executors only need to be cleaned up if there's an execution
+                // delay or a write rate limit (in which case cleanups release
+                // delayed results), or if there's more than one shard (in
+                // which case cleanups retry requests to other shards)
+                if config.executor_execution_delay().is_some()
+                    || config.executor_max_writes_per_sec().is_some()
+                    || config.shard_count() > 1
+                {
+                    let cleanup_interval = config.executor_cleanup_interval();
+                    periodic_executor_cleanups
+                        .push((process_id, cleanup_interval));
+                }
+
                 (process_id, shard_id, region)
             })
             .collect();
@@ -117,33 +389,77 @@ where
             .map(|(process_id, _, region)| (process_id, region))
             .collect();
 
+        // processes (and clients) discover processes sorted by distance: if
+        // there's a distance function, distances are computed by it
+        let discovery_planet = match options.distance_fn {
+            Some(distance_fn) => {
+                let from_regions = process_regions
+                    .iter()
+                    .chain(client_counts.iter().map(|(region, _)| region));
+                distance_planet(
+                    &planet,
+                    distance_fn,
+                    from_regions,
+                    &process_regions,
+                )
+            }
+            None => planet.clone(),
+        };
+
         // register processes
+        let mut closest_shard_process = HashMap::new();
         processes.into_iter().for_each(|(region, mut process)| {
-            // discover
-            let sorted = util::sort_processes_by_distance(
+            // discover all processes from its shard and the closest process
+            // from each of the other shards
+            let shard_id = process.shard_id();
+            let (same_shard, other_shards): (Vec<_>, Vec<_>) =
+                to_discover.clone().into_iter().partition(
+                    |(_, process_shard_id, _)| *process_shard_id == shard_id,
+                );
+            let mut sorted = util::sort_processes_by_distance(
                 &region,
-                &planet,
-                to_discover.clone(),
+                &discovery_planet,
+                same_shard,
             );
-            let (connect_ok, _) = process.discover(sorted);
+            let mut closest: Vec<_> = util::closest_process_per_shard(
+                &region,
+                &discovery_planet,
+                other_shards,
+            )
+            .into_iter()
+            .map(|(shard_id, process_id)| (process_id, shard_id))
+            .collect();
+            closest.sort_unstable();
+            sorted.extend(closest);
+            let (connect_ok, closest) = process.discover(sorted);
             assert!(connect_ok);
+            closest_shard_process.insert(process.id(), closest);
 
             // create executor for this process
-            let executor = <P::Executor as Executor>::new(
+            let mut executor = <P::Executor as Executor>::new(
                 process.id(),
                 process.shard_id(),
                 config,
             );
+            if let Some(shards) = options.replicated_shards.get(&process.id()) {
+                executor.set_replicated_shards(shards.clone());
+            }
 
             // and register both
             simulation.register_process(process, executor);
         });
 
+        // if there's a seed, make the workload deterministic
+        let mut workload = workload;
+        if let Some(seed) = config.seed() {
+            workload.deterministic(seed);
+        }
+
         // register clients and create client to region mapping
         let mut client_id = 0;
         let mut client_to_region = HashMap::new();
-        for region in client_regions {
-            for _ in 1..=clients_per_process {
+        for (region, client_count) in client_counts {
+            for _ in 1..=client_count {
                 // create client
                 client_id += 1;
                 let status_frequency = None;
@@ -152,7 +468,7 @@ where
                 // discover
                 let closest = util::closest_process_per_shard(
                     &region,
-                    &planet,
+                    &discovery_planet,
                     to_discover.clone(),
                 );
                 client.connect(closest);
@@ -168,12 +484,32 @@ where
             simulation,
             schedule: Schedule::new(),
             process_to_region,
+            closest_shard_process,
             client_to_region,
             // since we start ids in 1, the last id is the same as the number of
             // clients
             client_count: client_id as usize,
             make_distances_symmetric: false,
             reorder_messages: false,
+            message_loss: 0,
+            bandwidth: None,
+            link_bandwidth: HashMap::new(),
+            distance_fn: options.distance_fn.unwrap_or(half_ping),
+            warmup: None,
+            simulation_status: SimulationStatus::NotStarted,
+            extra_sim_time: None,
+            clients_done: 0,
+            simulation_final_time: 0,
+            message_counts: HashMap::new(),
+            message_sizes: HashMap::new(),
+            unresolved: HashSet::new(),
+            submissions: HashMap::new(),
+            next_submission: 0,
+            missing_shard_results: HashMap::new(),
+            rng: match config.seed() {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
         };
 
         // schedule periodic process events
@@ -186,6 +522,11 @@ where
             runner.schedule_periodic_executed_notification(process_id, delay)
         }
 
+        // schedule periodic executor cleanups
+        for (process_id, delay) in periodic_executor_cleanups {
+            runner.schedule_periodic_executor_cleanup(process_id, delay)
+        }
+
         runner
     }
 
@@ -197,6 +538,83 @@ where
         self.reorder_messages = true;
     }
 
+    /// Sets the percentage of messages between (different) processes that are
+    /// lost. Clients should set a command timeout, as commands may never
+    /// complete.
+    pub fn set_message_loss(&mut self, percentage: usize) {
+        assert!(
+            percentage <= 100,
+            "the message loss must be less or equal to 100"
+        );
+        self.message_loss = percentage;
+    }
+
+    /// Sets the bandwidth (in bytes per millisecond) of all links: a message
+    /// takes `size / bandwidth` milliseconds longer to be delivered. By
+    /// default, there's no bandwidth limit.
+    pub fn set_bandwidth<I: Into<Option<u64>>>(&mut self, bandwidth: I) {
+        let bandwidth = bandwidth.into();
+        assert_ne!(bandwidth, Some(0), "the bandwidth must be non-zero");
+        self.bandwidth = bandwidth;
+    }
+
+    /// Sets the bandwidth (in bytes per millisecond) of the link from region
+    /// `from` to region `to`, overriding the one set with `set_bandwidth`.
+    pub fn set_link_bandwidth(
+        &mut self,
+        from: Region,
+        to: Region,
+        bandwidth: u64,
+    ) {
+        assert_ne!(bandwidth, 0, "the bandwidth must be non-zero");
+        self.link_bandwidth.insert((from, to), bandwidth);
+    }
+
+    /// Sets the clock skew of all processes in `region`: these processes will
+    /// perceive time shifted by `skew`.
+    pub fn set_clock_skew(&mut self, region: &Region, skew: Duration) {
+        for (process_id, process_region) in self.process_to_region.iter() {
+            if process_region == region {
+                self.simulation.set_clock_skew(*process_id, skew);
+            }
+        }
+    }
+
+    /// Sets a warmup period: commands completing before `warmup` are excluded
+    /// from the clients' latency histograms, which avoids cold-start effects.
+    pub fn set_warmup<I: Into<Option<Duration>>>(&mut self, warmup: I) {
+        self.warmup = warmup.into();
+    }
+
+    /// Sets a timeout on each command issued by clients: commands that don't
+    /// complete within `timeout` are accounted for as timed out, and clients
+    /// proceed to their next command.
+    pub fn set_cmd_timeout<I: Into<Option<Duration>>>(&mut self, timeout: I) {
+        let timeout = timeout.into();
+        for client_id in self.client_to_region.keys() {
+            let (client, _) = self.simulation.get_client(*client_id);
+            client.set_cmd_timeout(timeout);
+        }
+    }
+
+    /// Registers a callback invoked each time a command is committed at some
+    /// process (if supported by the protocol).
+    pub fn set_on_commit(&mut self, callback: CommitCallback) {
+        for process_id in self.process_to_region.keys() {
+            let (process, _, _, _) = self.simulation.get_process(*process_id);
+            process.set_on_commit(callback.clone());
+        }
+    }
+
+    /// Registers a callback invoked each time the stable frontier of some
+    /// process advances (if supported by the protocol).
+    pub fn set_on_stable(&mut self, callback: StableCallback) {
+        for process_id in self.process_to_region.keys() {
+            let (process, _, _, _) = self.simulation.get_process(*process_id);
+            process.set_on_stable(callback.clone());
+        }
+    }
+
     /// Run the simulation. `extra_sim_time` indicates how much longer should
     /// the simulation run after clients are finished.
     pub fn run(
@@ -209,24 +627,24 @@ where
         Duration
     ) {
         // start clients
-        self.simulation.start_clients().into_iter().for_each(
-            |(client_id, process_id, cmd)| {
-                // schedule client commands
-                self.schedule_submit(
-                    MessageRegion::Client(client_id),
-                    process_id,
-                    cmd,
-                )
-            },
-        );
+        self.start(extra_sim_time);
 
         let start_time = Instant::now();
         // run simulation loop
-        self.simulation_loop(extra_sim_time);
+        self.simulation_loop();
         let end_time = Instant::now();
 
         let elapsed_time = end_time - start_time;
 
+        // check that every command submitted produced a result
+        if let Err(unresolved) = self.reconcile() {
+            warn!(
+                "{} commands submitted never produced a result: {:?}",
+                unresolved.len(),
+                unresolved
+            );
+        }
+
         // return metrics and client latencies
         (
             self.metrics(),
@@ -236,85 +654,141 @@ where
         )
     }
 
-    fn simulation_loop(&mut self, extra_sim_time: Option<Duration>) {
-        let mut simulation_status = SimulationStatus::ClientsRunning;
-        let mut clients_done = 0;
-        let mut simulation_final_time = 0;
+    /// Checks that every command submitted by clients produced a result (i.e.
+    /// that it wasn't only accounted for as timed out). If not, returns the
+    /// (sorted) identifiers of the commands that never did.
+    pub fn reconcile(&self) -> Result<(), Vec<Rifl>> {
+        if self.unresolved.is_empty() {
+            Ok(())
+        } else {
+            let mut unresolved: Vec<_> =
+                self.unresolved.iter().cloned().collect();
+            unresolved.sort();
+            Err(unresolved)
+        }
+    }
 
-        while simulation_status != SimulationStatus::Done {
-            let action = self.schedule
-                .next_action(self.simulation.time())
-                .expect("there should be a new action since stability is always running");
+    /// Checks that the `percentile` (between 0 and 1) latency of clients in
+    /// `region` is at most `max_ms`. This should be called after `run`.
+    pub fn assert_sla(
+        &mut self,
+        region: &Region,
+        percentile: f64,
+        max_ms: u64,
+    ) -> Result<(), SlaError> {
+        let (_, histogram) = self
+            .clients_latencies()
+            .remove(region)
+            .ok_or_else(|| SlaError::NoClients(region.clone()))?;
+        let observed_ms = histogram.percentile(percentile).value();
+        if observed_ms <= max_ms as f64 {
+            Ok(())
+        } else {
+            Err(SlaError::Violation(SlaViolation {
+                region: region.clone(),
+                percentile,
+                max_ms,
+                observed_ms,
+            }))
+        }
+    }
 
-            match action {
-                ScheduleAction::PeriodicProcessEvent(
-                    process_id,
-                    event,
-                    delay,
-                ) => {
-                    self.handle_periodic_process_event(process_id, event, delay)
-                }
-                ScheduleAction::PeriodicExecutedNotification(
-                    process_id,
-                    delay,
-                ) => self
-                    .handle_periodic_executed_notification(process_id, delay),
-                ScheduleAction::SubmitToProc(process_id, cmd) => {
-                    self.handle_submit_to_proc(process_id, cmd);
-                }
-                ScheduleAction::SendToProc(
-                    from,
-                    from_shard_id,
-                    process_id,
-                    msg,
-                ) => {
-                    self.handle_send_to_proc(
-                        from,
-                        from_shard_id,
-                        process_id,
-                        msg,
-                    );
-                }
-                ScheduleAction::SendToClient(client_id, cmd_result) => {
-                    // handle new command result in client
-                    let submit = self.simulation.forward_to_client(cmd_result);
-                    if let Some((process_id, cmd)) = submit {
-                        self.schedule_submit(
-                            MessageRegion::Client(client_id),
-                            process_id,
-                            cmd,
-                        );
-                    } else {
-                        clients_done += 1;
-                        // if all clients are done, enter the next phase
-                        if clients_done == self.client_count {
-                            simulation_status = match extra_sim_time {
-                                Some(extra) => {
-                                    // if there's extra time, compute the
-                                    // final simulation time
-                                    simulation_final_time =
-                                        self.simulation.time().millis()
-                                            + extra.as_millis() as u64;
-                                    SimulationStatus::ExtraSimulationTime
-                                }
-                                None => {
-                                    // otherwise, end the simulation
-                                    SimulationStatus::Done
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    /// Starts the clients without running the simulation, which can then be
+    /// run one action at a time with `step`. `extra_sim_time` indicates how
+    /// much longer should the simulation run after clients are finished.
+    pub fn start(&mut self, extra_sim_time: Option<Duration>) {
+        assert!(
+            self.simulation_status == SimulationStatus::NotStarted,
+            "the simulation has already started"
+        );
+        self.extra_sim_time = extra_sim_time;
+        self.simulation_status = SimulationStatus::ClientsRunning;
+
+        // start clients in order so that the simulation is reproducible
+        let mut submits = self.simulation.start_clients();
+        submits.sort_by_key(|(client_id, _, _)| *client_id);
+        for (client_id, process_id, cmd) in submits {
+            // schedule client commands
+            let from_region = MessageRegion::Client(client_id);
+            self.schedule_submit(from_region, process_id, cmd)
+        }
+    }
+
+    /// Returns a boolean indicating whether the simulation is done.
+    pub fn is_done(&self) -> bool {
+        self.simulation_status == SimulationStatus::Done
+    }
+
+    fn simulation_loop(&mut self) {
+        while !self.is_done() {
+            self.step();
+        }
+    }
 
-            // check if we're in extra simulation time; if yes, finish the
-            // simulation if we're past the final simulation time
-            let should_end_sim = simulation_status
-                == SimulationStatus::ExtraSimulationTime
-                && self.simulation.time().millis() > simulation_final_time;
-            if should_end_sim {
-                simulation_status = SimulationStatus::Done;
+    /// Processes the next scheduled action. Once the simulation is done, no
+    /// more actions are processed.
+    pub fn step(&mut self) -> StepResult {
+        assert!(
+            self.simulation_status != SimulationStatus::NotStarted,
+            "the simulation should be started before stepping it"
+        );
+        if self.is_done() {
+            return StepResult {
+                actions: 0,
+                time_millis: self.simulation.time().millis(),
+            };
+        }
+
+        let action = self.schedule.next_action(self.simulation.time()).expect(
+            "there should be a new action since stability is always running",
+        );
+
+        match action {
+            ScheduleAction::PeriodicProcessEvent(process_id, event, delay) => {
+                self.handle_periodic_process_event(process_id, event, delay)
+            }
+            ScheduleAction::PeriodicExecutedNotification(process_id, delay) => {
+                self.handle_periodic_executed_notification(process_id, delay)
+            }
+            ScheduleAction::PeriodicExecutorCleanup(process_id, delay) => {
+                self.handle_periodic_executor_cleanup(process_id, delay)
+            }
+            ScheduleAction::SubmitToProc(process_id, cmd) => {
+                self.handle_submit_to_proc(process_id, cmd);
+            }
+            ScheduleAction::SendToProc(
+                from,
+                from_shard_id,
+                process_id,
+                msg,
+            ) => {
+                self.message_counts.entry(process_id).or_default().received +=
+                    1;
+                self.handle_send_to_proc(from, from_shard_id, process_id, msg);
             }
+            ScheduleAction::SendToExecutor(process_id, info) => {
+                self.handle_send_to_executor(process_id, info);
+            }
+            ScheduleAction::SendToClient(client_id, cmd_result) => {
+                self.handle_send_to_client(client_id, cmd_result);
+            }
+            ScheduleAction::CmdTimeout(client_id, rifl, submission) => {
+                self.handle_cmd_timeout(client_id, rifl, submission);
+            }
+        }
+
+        // check if we're in extra simulation time; if yes, finish the
+        // simulation if we're past the final simulation time
+        let should_end_sim = self.simulation_status
+            == SimulationStatus::ExtraSimulationTime
+            && self.simulation.time().millis() > self.simulation_final_time;
+        if should_end_sim {
+            self.simulation_status = SimulationStatus::Done;
+        }
+
+        StepResult {
+            actions: 1,
+            time_millis: self.simulation.time().millis(),
         }
     }
 
@@ -354,15 +828,72 @@ where
         self.schedule_periodic_executed_notification(process_id, delay);
     }
 
-    fn handle_submit_to_proc(&mut self, process_id: ProcessId, cmd: Command) {
-        // get process and executor
-        let (process, _executor, pending, time) =
+    fn handle_periodic_executor_cleanup(
+        &mut self,
+        process_id: ProcessId,
+        delay: Duration,
+    ) {
+        // get executor
+        let (_, executor, pending, time) =
             self.simulation.get_process(process_id);
 
+        // cleanup executor and handle all partial results in pending
+        executor.cleanup(time);
+        let to_executors: Vec<_> = executor.to_executors_iter().collect();
+        let ready: Vec<_> = executor
+            .to_clients_iter()
+            .filter_map(|executor_result| {
+                pending.add_executor_result(executor_result)
+            })
+            .collect();
+
+        // schedule new command results
+        self.schedule_to_clients(MessageRegion::Process(process_id), ready);
+
+        // send new executor messages
+        self.send_to_executors(process_id, to_executors);
+
+        // schedule the next periodic event
+        self.schedule_periodic_executor_cleanup(process_id, delay);
+    }
+
+    fn handle_submit_to_proc(&mut self, process_id: ProcessId, cmd: Command) {
+        // get process
+        let (process, _, pending, _) = self.simulation.get_process(process_id);
+
+        // if the process is overloaded or hasn't discovered the other
+        // processes yet, reject the command
+        if process.overloaded() || !process.discovered() {
+            let cmd_result = CommandResult::new_rejected(cmd.rifl());
+            self.schedule_to_client(
+                MessageRegion::Process(process_id),
+                cmd_result,
+            );
+            return;
+        }
+
         // register command in pending
         pending.wait_for(&cmd);
+        let shard_id = process.shard_id();
+
+        // register command in the pending of the processes (closest to the
+        // client) of the remaining shards accessed
+        let client_id = cmd.rifl().source();
+        let other_shards: Vec<_> = cmd
+            .shards()
+            .filter(|cmd_shard_id| **cmd_shard_id != shard_id)
+            .cloned()
+            .collect();
+        for other_shard_id in other_shards {
+            let (client, _) = self.simulation.get_client(client_id);
+            let other_process_id = client.shard_process(&other_shard_id);
+            let (_, _, pending, _) =
+                self.simulation.get_process(other_process_id);
+            pending.wait_for(&cmd);
+        }
 
         // submit to process and schedule new actions
+        let (process, _, _, time) = self.simulation.get_process(process_id);
         process.submit(None, cmd, time);
         self.send_to_processes_and_executors(process_id);
     }
@@ -382,6 +913,95 @@ where
         self.send_to_processes_and_executors(process_id);
     }
 
+    fn handle_send_to_client(
+        &mut self,
+        client_id: ClientId,
+        mut cmd_result: CommandResult,
+    ) {
+        // results not synthesized by a timeout resolve the command
+        if !cmd_result.is_timed_out() {
+            self.unresolved.remove(&cmd_result.rifl());
+        }
+
+        // commands that access more than one shard only complete once there's
+        // a result from each of these shards (and are aborted if any of the
+        // shards aborted them, since the command is retried with the same
+        // rifl); timed-out and rejected results complete the command right
+        // away
+        let rifl = cmd_result.rifl();
+        if cmd_result.is_timed_out() || cmd_result.is_rejected() {
+            self.missing_shard_results.remove(&rifl);
+        } else if let Some((missing, aborted)) =
+            self.missing_shard_results.get_mut(&rifl)
+        {
+            *missing -= 1;
+            *aborted |= cmd_result.is_aborted();
+            if *missing > 0 {
+                return;
+            }
+            if *aborted {
+                cmd_result = CommandResult::new_aborted(rifl);
+            }
+            self.missing_shard_results.remove(&rifl);
+        }
+
+        // ignore results of commands that are no longer pending (i.e. results
+        // of commands that have timed out)
+        let (client, _) = self.simulation.get_client(client_id);
+        if !client.is_pending(&cmd_result.rifl()) {
+            return;
+        }
+
+        // handle new command result in client; if the command is no longer
+        // pending, the timeout of its last submission can be ignored
+        let submit = self.simulation.forward_to_client(cmd_result);
+        if !self.simulation.get_client(client_id).0.is_pending(&rifl) {
+            self.submissions.remove(&rifl);
+        }
+        if let Some((process_id, cmd)) = submit {
+            self.schedule_submit(
+                MessageRegion::Client(client_id),
+                process_id,
+                cmd,
+            );
+        } else if self.simulation.get_client(client_id).0.finished() {
+            // the client is done once it has no more commands to submit and
+            // all of its commands have completed
+            self.clients_done += 1;
+            // if all clients are done, enter the next phase
+            if self.clients_done == self.client_count {
+                self.simulation_status = match self.extra_sim_time {
+                    Some(extra) => {
+                        // if there's extra time, compute the final
+                        // simulation time
+                        self.simulation_final_time =
+                            self.simulation.time().millis()
+                                + extra.as_millis() as u64;
+                        SimulationStatus::ExtraSimulationTime
+                    }
+                    None => {
+                        // otherwise, end the simulation
+                        SimulationStatus::Done
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_cmd_timeout(
+        &mut self,
+        client_id: ClientId,
+        rifl: Rifl,
+        submission: u64,
+    ) {
+        // only time out the command if this is the timeout of its latest
+        // submission
+        if self.submissions.get(&rifl) == Some(&submission) {
+            let cmd_result = CommandResult::new_timed_out(rifl);
+            self.handle_send_to_client(client_id, cmd_result);
+        }
+    }
+
     // (maybe) Schedules a new submit from a client.
     fn schedule_submit(
         &mut self,
@@ -389,6 +1009,30 @@ where
         process_id: ProcessId,
         cmd: Command,
     ) {
+        // if the submit is from a client with a command timeout, schedule a
+        // timeout for this submission that times out the command if it hasn't
+        // completed (nor been submitted again) by then
+        if let MessageRegion::Client(client_id) = &from_region {
+            self.unresolved.insert(cmd.rifl());
+            if cmd.shard_count() > 1 {
+                self.missing_shard_results
+                    .insert(cmd.rifl(), (cmd.shard_count(), false));
+            }
+            let submission = self.next_submission;
+            self.next_submission += 1;
+            self.submissions.insert(cmd.rifl(), submission);
+            let (client, _) = self.simulation.get_client(*client_id);
+            if let Some(timeout) = client.cmd_timeout() {
+                let action = ScheduleAction::CmdTimeout(
+                    *client_id,
+                    cmd.rifl(),
+                    submission,
+                );
+                self.schedule
+                    .schedule(self.simulation.time(), timeout, action);
+            }
+        }
+
         // create action and schedule it
         let action = ScheduleAction::SubmitToProc(process_id, cmd);
         self.schedule_message(
@@ -409,16 +1053,20 @@ where
         let protocol_actions = process.to_processes_iter().collect();
 
         // handle new execution info in the executor
+        let mut to_other_shards = Vec::new();
         let ready: Vec<_> = process
             .to_executors_iter()
             .flat_map(|info| {
                 executor.handle(info, time);
-                // handle executor messages to self
-                let to_executors =
-                    executor.to_executors_iter().collect::<Vec<_>>();
-                for (shard_id, info) in to_executors {
-                    assert_eq!(shard_id, DEFAULT_SHARD_ID);
-                    executor.handle(info, time);
+                // handle executor messages to self (including the ones
+                // generated while handling them), and save the ones to other
+                // shards
+                while let Some((to_shard_id, info)) = executor.to_executors() {
+                    if to_shard_id == shard_id {
+                        executor.handle(info, time);
+                    } else {
+                        to_other_shards.push((to_shard_id, info));
+                    }
                 }
                 // TODO remove collect
                 executor.to_clients_iter().collect::<Vec<_>>()
@@ -438,26 +1086,87 @@ where
         );
 
         // schedule new command results
-        ready.into_iter().for_each(|cmd_result| {
-            self.schedule_to_client(
-                MessageRegion::Process(process_id),
-                cmd_result,
-            )
-        });
+        self.schedule_to_clients(MessageRegion::Process(process_id), ready);
+
+        // send executor messages to other shards
+        self.send_to_executors(process_id, to_other_shards);
     }
 
-    /// (maybe) Schedules a new send from some process.
-    fn schedule_protocol_actions(
+    /// Sends executor messages from the executor of process `process_id` to
+    /// the executors of the closest process of each shard. Messages to the
+    /// process's own shard are handled immediately, while the remaining ones
+    /// are scheduled like any other message.
+    fn send_to_executors(
         &mut self,
         process_id: ProcessId,
-        shard_id: ShardId,
-        from_region: MessageRegion,
+        to_executors: Vec<(ShardId, <P::Executor as Executor>::ExecutionInfo)>,
+    ) {
+        for (shard_id, info) in to_executors {
+            // find the target process: either the process itself, or the
+            // closest process from the target shard
+            let (process, _, _, _) = self.simulation.get_process(process_id);
+            if process.shard_id() == shard_id {
+                self.handle_send_to_executor(process_id, info);
+            } else {
+                let to = *self
+                    .closest_shard_process
+                    .get(&process_id)
+                    .and_then(|closest| closest.get(&shard_id))
+                    .expect("closest process from each shard should be known");
+                let action = ScheduleAction::SendToExecutor(to, info);
+                self.schedule_message(
+                    MessageRegion::Process(process_id),
+                    MessageRegion::Process(to),
+                    action,
+                );
+            }
+        }
+    }
+
+    fn handle_send_to_executor(
+        &mut self,
+        process_id: ProcessId,
+        info: <P::Executor as Executor>::ExecutionInfo,
+    ) {
+        // get executor
+        let (_, executor, pending, time) =
+            self.simulation.get_process(process_id);
+
+        // handle the message in the executor
+        executor.handle(info, time);
+        let to_executors: Vec<_> = executor.to_executors_iter().collect();
+        let ready: Vec<_> = executor
+            .to_clients_iter()
+            .filter_map(|executor_result| {
+                pending.add_executor_result(executor_result)
+            })
+            .collect();
+
+        // schedule new command results
+        self.schedule_to_clients(MessageRegion::Process(process_id), ready);
+
+        // send new executor messages
+        self.send_to_executors(process_id, to_executors);
+    }
+
+    /// (maybe) Schedules a new send from some process.
+    fn schedule_protocol_actions(
+        &mut self,
+        process_id: ProcessId,
+        shard_id: ShardId,
+        from_region: MessageRegion,
         protocol_actions: Vec<Action<P>>,
     ) {
         for protocol_action in protocol_actions {
             match protocol_action {
                 Action::ToSend { target, msg } => {
-                    // for each process in target, schedule message delivery
+                    // for each process in target (in order, so that the
+                    // simulation is reproducible), schedule message delivery
+                    let mut target: Vec<_> = target.into_iter().collect();
+                    target.sort_unstable();
+                    // the message is the same for all targets, and so it's
+                    // only sized once
+                    let size = command::serialized_size(&msg);
                     target.into_iter().for_each(|to| {
                         // if message to self, deliver immediately
                         if to == process_id {
@@ -469,6 +1178,18 @@ where
                             )
                         } else {
                             // otherwise, create action and schedule it
+                            self.message_counts
+                                .entry(process_id)
+                                .or_default()
+                                .sent += 1;
+                            self.message_sizes
+                                .entry(process_id)
+                                .or_default()
+                                .increment(size);
+                            // drop the message if it's lost
+                            if self.lose_message() {
+                                return;
+                            }
                             let action = ScheduleAction::SendToProc(
                                 process_id,
                                 shard_id,
@@ -493,7 +1214,26 @@ where
         }
     }
 
+    // Returns a boolean indicating whether the next message should be lost.
+    fn lose_message(&mut self) -> bool {
+        self.message_loss > 0 && self.rng.gen_range(0..100) < self.message_loss
+    }
+
     /// Schedules a new command result.
+    fn schedule_to_clients(
+        &mut self,
+        from_region: MessageRegion,
+        mut cmd_results: Vec<CommandResult>,
+    ) {
+        // the order in which commands are executed within the same step may
+        // depend on the iteration order of their keys; so that the simulation
+        // is reproducible, results are sent in the order of their identifier
+        cmd_results.sort_by_key(|cmd_result| cmd_result.rifl());
+        for cmd_result in cmd_results {
+            self.schedule_to_client(from_region.clone(), cmd_result);
+        }
+    }
+
     fn schedule_to_client(
         &mut self,
         from_region: MessageRegion,
@@ -514,21 +1254,36 @@ where
         &mut self,
         from_region: MessageRegion,
         to_region: MessageRegion,
-        action: ScheduleAction<P::Message, P::PeriodicEvent>,
+        action: RunnerAction<P>,
     ) {
+        // check if we should reorder messages; if so, the distance is
+        // multiplied by some random number between 0 and 10
+        let multiplier: Option<f64> = if self.reorder_messages {
+            Some(self.rng.gen_range(0.0..10.0))
+        } else {
+            None
+        };
+
         // get actual regions
         let from = self.compute_region(from_region);
         let to = self.compute_region(to_region);
         // compute distance between regions
         let mut distance = self.distance(from, to);
-
-        // check if we should reorder messages
-        if self.reorder_messages {
-            // if so, multiply distance by some random number between 0 and 10
-            let multiplier: f64 = rand::thread_rng().gen_range(0.0..10.0);
+        if let Some(multiplier) = multiplier {
             distance = (distance as f64 * multiplier) as u64;
         }
 
+        // if there's a bandwidth limit on this link, account for the time it
+        // takes to transmit the message
+        let bandwidth = self
+            .link_bandwidth
+            .get(&(from.clone(), to.clone()))
+            .cloned()
+            .or(self.bandwidth);
+        if let Some(bandwidth) = bandwidth {
+            distance += Self::message_size(&action) / bandwidth;
+        }
+
         // schedule action
         let distance = Duration::from_millis(distance);
         self.schedule
@@ -562,6 +1317,18 @@ where
             .schedule(self.simulation.time(), delay, action);
     }
 
+    /// Schedules the next periodic executor cleanup.
+    fn schedule_periodic_executor_cleanup(
+        &mut self,
+        process_id: ProcessId,
+        delay: Duration,
+    ) {
+        // create action
+        let action = ScheduleAction::PeriodicExecutorCleanup(process_id, delay);
+        self.schedule
+            .schedule(self.simulation.time(), delay, action);
+    }
+
     /// Retrieves the region of some process/client.
     fn compute_region(&self, message_region: MessageRegion) -> &Region {
         match message_region {
@@ -576,8 +1343,32 @@ where
         }
     }
 
-    /// Computes the distance between two regions which is half the ping
-    /// latency.
+    // Computes the size (in bytes) of a scheduled message: commands submitted
+    // by clients are estimated, while the size of the remaining messages is
+    // computed by `command::serialized_size` (which accounts for the payload
+    // of the commands they carry, if any). This is only called if there's a
+    // bandwidth limit.
+    fn message_size(action: &RunnerAction<P>) -> u64 {
+        match action {
+            ScheduleAction::SubmitToProc(_, cmd) => {
+                cmd.estimated_serialized_size() as u64
+            }
+            ScheduleAction::SendToProc(_, _, _, msg) => {
+                command::serialized_size(msg)
+            }
+            ScheduleAction::SendToExecutor(_, info) => {
+                command::serialized_size(info)
+            }
+            ScheduleAction::SendToClient(_, cmd_result) => {
+                command::serialized_size(cmd_result)
+            }
+            // periodic events are not sent through the network
+            _ => 0,
+        }
+    }
+
+    /// Computes the distance between two regions using `distance_fn` (by
+    /// default, half the ping latency).
     fn distance(&self, from: &Region, to: &Region) -> u64 {
         let from_to = self
             .planet
@@ -595,14 +1386,12 @@ where
             from_to
         };
 
-        // distance is half the ping latency
-        let ms = ping / 2;
-        ms
+        (self.distance_fn)(from, to, ping)
     }
 
     /// Get metrics from processes and executors.
     /// TODO does this need to be mut?
-    fn metrics(
+    pub fn metrics(
         &mut self,
     ) -> HashMap<ProcessId, (ProtocolMetrics, ExecutorMetrics)> {
         self.check_processes_and_executors(|process, executor| {
@@ -612,6 +1401,29 @@ where
         })
     }
 
+    /// Returns the number of messages sent and received by each process.
+    /// Messages sent by a process to itself are not counted.
+    pub fn processes_message_counts(
+        &self,
+    ) -> HashMap<ProcessId, MessageCounts> {
+        self.message_counts.clone()
+    }
+
+    /// Returns, for each process, an histogram with the serialized size (in
+    /// bytes) of the messages it sent to other processes.
+    pub fn processes_message_sizes(&self) -> HashMap<ProcessId, Histogram> {
+        self.message_sizes.clone()
+    }
+
+    /// Returns, for each process, the result of inspecting its executor with
+    /// `f`.
+    pub fn inspect_executors<F, R>(&mut self, f: F) -> HashMap<ProcessId, R>
+    where
+        F: Fn(&P::Executor) -> R,
+    {
+        self.check_processes_and_executors(|_process, executor| f(executor))
+    }
+
     fn executors_monitors(
         &mut self,
     ) -> HashMap<ProcessId, Option<ExecutionOrderMonitor>> {
@@ -622,14 +1434,19 @@ where
 
     /// Get client's stats.
     /// TODO does this need to be mut?
-    fn clients_latencies(&mut self) -> HashMap<Region, (usize, Histogram)> {
+    pub fn clients_latencies(&mut self) -> HashMap<Region, (usize, Histogram)> {
+        // only record commands that completed after the warmup period
+        let warmup = self
+            .warmup
+            .map(|warmup| warmup.as_millis() as u64)
+            .unwrap_or(0);
         self.check_clients(
             |client, (commands, histogram): &mut (usize, Histogram)| {
                 // update issued commands with this client's issued commands
                 *commands += client.issued_commands();
 
                 // update region's histogram with this client's histogram
-                for latency in client.data().latency_data() {
+                for latency in client.data().latency_data_since(warmup) {
                     // since the simulation assumes WAN, use milliseconds for
                     // latency precision
                     let ms = latency.as_millis() as u64;
@@ -639,6 +1456,40 @@ where
         )
     }
 
+    /// Returns, for each region, the number of commands that timed out.
+    pub fn clients_timed_out(&mut self) -> HashMap<Region, usize> {
+        self.check_clients(|client, timed_out: &mut usize| {
+            *timed_out += client.data().timed_out();
+        })
+    }
+
+    /// Returns, for each region, the number of commands that were rejected by
+    /// overloaded processes.
+    pub fn clients_rejected(&mut self) -> HashMap<Region, usize> {
+        self.check_clients(|client, rejected: &mut usize| {
+            *rejected += client.data().rejected();
+        })
+    }
+
+    /// Returns, for each region, a histogram with the time clients waited for
+    /// their commands to be committed and a histogram with the time they
+    /// waited for them to be executed after that. Commands are only accounted
+    /// for if the executor records when they were committed and executed.
+    pub fn clients_phase_latencies(
+        &mut self,
+    ) -> HashMap<Region, (Histogram, Histogram)> {
+        self.check_clients(
+            |client, (commit, execute): &mut (Histogram, Histogram)| {
+                for latency in client.data().commit_latency_data() {
+                    commit.increment(latency.as_millis() as u64);
+                }
+                for latency in client.data().execute_latency_data() {
+                    execute.increment(latency.as_millis() as u64);
+                }
+            },
+        )
+    }
+
     fn check_processes_and_executors<F, R>(
         &mut self,
         f: F,
@@ -687,8 +1538,8 @@ where
     }
 }
 
-impl<Message: Debug, PeriodicEvent: Debug> fmt::Debug
-    for ScheduleAction<Message, PeriodicEvent>
+impl<Message: Debug, PeriodicEvent: Debug, ExecutionInfo: Debug> fmt::Debug
+    for ScheduleAction<Message, PeriodicEvent, ExecutionInfo>
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -705,9 +1556,19 @@ impl<Message: Debug, PeriodicEvent: Debug> fmt::Debug
                 "SendToProc({}, {}, {}, {:?})",
                 from_process_id, from_shard_id, to, msg
             ),
+            ScheduleAction::SendToExecutor(process_id, info) => {
+                write!(f, "SendToExecutor({}, {:?})", process_id, info)
+            }
             ScheduleAction::SendToClient(client_id, cmd_result) => {
                 write!(f, "SendToClient({}, {:?})", client_id, cmd_result)
             }
+            ScheduleAction::CmdTimeout(client_id, rifl, submission) => {
+                write!(
+                    f,
+                    "CmdTimeout({}, {:?}, {})",
+                    client_id, rifl, submission
+                )
+            }
             ScheduleAction::PeriodicProcessEvent(process_id, event, delay) => {
                 write!(
                     f,
@@ -722,6 +1583,13 @@ impl<Message: Debug, PeriodicEvent: Debug> fmt::Debug
                     process_id, delay
                 )
             }
+            ScheduleAction::PeriodicExecutorCleanup(process_id, delay) => {
+                write!(
+                    f,
+                    "PeriodicExecutorCleanup({}, {:?})",
+                    process_id, delay
+                )
+            }
         }
     }
 }
@@ -734,54 +1602,24 @@ mod tests {
     use crate::protocol::{Basic, ProtocolMetricsKind};
 
     fn run(f: usize, clients_per_process: usize) -> (Histogram, Histogram) {
-        // planet
-        let planet = Planet::new();
-
-        // config
-        let n = 3;
-        let mut config = Config::new(n, f);
-
-        // make sure stability is running
-        config.set_gc_interval(Duration::from_millis(100));
+        run_with_distance_fn(f, clients_per_process, None)
+    }
 
-        // clients workload
-        let shard_count = 1;
-        let keys_per_command = 1;
-        let pool_size = 1;
-        let conflict_rate = 100;
-        let key_gen = KeyGen::ConflictPool {
-            pool_size,
-            conflict_rate,
-        };
+    fn run_with_distance_fn(
+        f: usize,
+        clients_per_process: usize,
+        distance_fn: Option<DistanceFn>,
+    ) -> (Histogram, Histogram) {
         let commands_per_client = 1000;
-        let payload_size = 100;
-        let workload = Workload::new(
-            shard_count,
-            key_gen,
-            keys_per_command,
-            commands_per_client,
-            payload_size,
-        );
-
-        // process regions
-        let process_regions = vec![
-            Region::new("asia-east1"),
-            Region::new("us-central1"),
-            Region::new("us-west1"),
-        ];
-
-        // client regions
-        let client_regions =
-            vec![Region::new("us-west1"), Region::new("us-west2")];
-
-        // create runner
-        let mut runner: Runner<Basic> = Runner::new(
-            planet,
-            config,
-            workload,
+        let mut options = RunnerOptions::new();
+        if let Some(distance_fn) = distance_fn {
+            options.set_distance_fn(distance_fn);
+        }
+        let mut runner = create_runner_with_options(
+            f,
             clients_per_process,
-            process_regions,
-            client_regions,
+            commands_per_client,
+            options,
         );
 
         // run simulation until the clients end + another second second
@@ -821,6 +1659,162 @@ mod tests {
         (us_west1, us_west2)
     }
 
+    fn create_runner(
+        f: usize,
+        clients_per_process: usize,
+        commands_per_client: usize,
+    ) -> Runner<Basic> {
+        create_runner_with_config(
+            f,
+            clients_per_process,
+            commands_per_client,
+            |_| {},
+        )
+    }
+
+    fn create_runner_with_config<F>(
+        f: usize,
+        clients_per_process: usize,
+        commands_per_client: usize,
+        update_config: F,
+    ) -> Runner<Basic>
+    where
+        F: FnOnce(&mut Config),
+    {
+        let payload_size = 100;
+        create_runner_with_payload_size(
+            f,
+            clients_per_process,
+            commands_per_client,
+            payload_size,
+            update_config,
+        )
+    }
+
+    fn create_runner_with_payload_size<F>(
+        f: usize,
+        clients_per_process: usize,
+        commands_per_client: usize,
+        payload_size: usize,
+        update_config: F,
+    ) -> Runner<Basic>
+    where
+        F: FnOnce(&mut Config),
+    {
+        create_runner_with_all(
+            f,
+            clients_per_process,
+            commands_per_client,
+            payload_size,
+            update_config,
+            RunnerOptions::default(),
+        )
+    }
+
+    fn create_runner_with_options(
+        f: usize,
+        clients_per_process: usize,
+        commands_per_client: usize,
+        options: RunnerOptions,
+    ) -> Runner<Basic> {
+        let payload_size = 100;
+        create_runner_with_all(
+            f,
+            clients_per_process,
+            commands_per_client,
+            payload_size,
+            |_| {},
+            options,
+        )
+    }
+
+    fn create_runner_with_all<F>(
+        f: usize,
+        clients_per_process: usize,
+        commands_per_client: usize,
+        payload_size: usize,
+        update_config: F,
+        options: RunnerOptions,
+    ) -> Runner<Basic>
+    where
+        F: FnOnce(&mut Config),
+    {
+        // planet
+        let planet = Planet::new();
+
+        // config
+        let n = 3;
+        let config = create_config(n, f, update_config);
+
+        // clients workload
+        let workload = create_workload(commands_per_client, payload_size);
+
+        // process regions
+        let process_regions = create_process_regions();
+
+        // client regions
+        let client_regions =
+            vec![Region::new("us-west1"), Region::new("us-west2")];
+        let client_counts = client_regions
+            .into_iter()
+            .map(|region| (region, clients_per_process))
+            .collect();
+
+        // create runner
+        Runner::with_options(
+            planet,
+            config,
+            workload,
+            process_regions,
+            client_counts,
+            options,
+        )
+    }
+
+    fn create_config<F>(n: usize, f: usize, update_config: F) -> Config
+    where
+        F: FnOnce(&mut Config),
+    {
+        let mut config = Config::new(n, f);
+
+        // make sure stability is running
+        config.set_gc_interval(Duration::from_millis(100));
+
+        // apply test-specific changes
+        update_config(&mut config);
+        config
+    }
+
+    // Creates a workload in which all commands conflict with each other.
+    fn create_workload(
+        commands_per_client: usize,
+        payload_size: usize,
+    ) -> Workload {
+        let shard_count = 1;
+        let keys_per_command = 1;
+        let pool_size = 1;
+        let conflict_rate = 100;
+        let key_gen = KeyGen::ConflictPool {
+            pool_size,
+            conflict_rate,
+        };
+        Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        )
+    }
+
+    fn create_process_regions() -> Vec<Region> {
+        vec![
+            Region::new("asia-east1"),
+            Region::new("us-central1"),
+            Region::new("us-west1"),
+        ]
+    }
+
     #[test]
     fn runner_single_client_per_process() {
         // expected stats:
@@ -848,6 +1842,52 @@ mod tests {
         assert_eq!(us_west2.mean(), F64::new(58.0));
     }
 
+    #[test]
+    fn runner_distance_fn() {
+        // add 5ms of processing delay to every message
+        let distance_fn: DistanceFn = |_, _, ping| ping / 2 + 5;
+
+        // with f = 0, commands only take two messages (client to coordinator,
+        // and coordinator to client), and thus latencies should increase by
+        // 10ms
+        let f = 0;
+        let clients_per_process = 1;
+        let (us_west1, us_west2) =
+            run_with_distance_fn(f, clients_per_process, Some(distance_fn));
+        assert_eq!(us_west1.mean(), F64::new(10.0));
+        assert_eq!(us_west2.mean(), F64::new(34.0));
+    }
+
+    #[test]
+    fn runner_distance_fn_process_ordering() {
+        // asia-east1 is 1ms away from both us-west1 and us-west2, while the
+        // remaining distances are half the ping latency
+        let distance_fn: DistanceFn = |from, to, ping| {
+            let close = |region: &Region| {
+                region == &Region::new("us-west1")
+                    || region == &Region::new("us-west2")
+            };
+            let asia = Region::new("asia-east1");
+            if (from == &asia && close(to)) || (close(from) && to == &asia) {
+                1
+            } else {
+                ping / 2
+            }
+        };
+
+        // with f = 1, the fast quorum of us-west1 is itself and its closest
+        // process, now asia-east1 (instead of us-central1), and thus its
+        // clients wait 2ms for the quorum; clients in us-west2 are now closest
+        // to asia-east1, whose fast quorum is itself and us-west1, and thus
+        // they wait 2ms to reach their coordinator plus 2ms for the quorum
+        let f = 1;
+        let clients_per_process = 1;
+        let (us_west1, us_west2) =
+            run_with_distance_fn(f, clients_per_process, Some(distance_fn));
+        assert_eq!(us_west1.mean(), F64::new(2.0));
+        assert_eq!(us_west2.mean(), F64::new(4.0));
+    }
+
     #[test]
     fn runner_multiple_clients_per_process() {
         // 1 client per region
@@ -868,4 +1908,739 @@ mod tests {
         assert_eq!(us_west2_with_one.mean(), us_west2_with_ten.mean());
         assert_eq!(us_west2_with_one.cov(), us_west2_with_ten.cov());
     }
+
+    #[test]
+    fn runner_assert_sla() {
+        // with f = 0, clients in us-west2 observe 24ms (see
+        // `runner_single_client_per_process`)
+        let f = 0;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.run(None);
+
+        let region = Region::new("us-west2");
+        let percentile = 0.99;
+
+        // a generous SLA passes
+        assert!(runner.assert_sla(&region, percentile, 100).is_ok());
+
+        // a tight SLA fails, reporting the latency observed
+        let error = runner
+            .assert_sla(&region, percentile, 10)
+            .expect_err("the SLA should be violated");
+        assert!(error.to_string().contains("24ms"));
+        match error {
+            SlaError::Violation(violation) => {
+                assert_eq!(violation.observed_ms, 24.0);
+                assert_eq!(violation.max_ms, 10);
+            }
+            error => panic!("unexpected error: {:?}", error),
+        }
+
+        // a region without clients is reported as such
+        let region = Region::new("europe-west1");
+        assert_eq!(
+            runner.assert_sla(&region, percentile, 100),
+            Err(SlaError::NoClients(region))
+        );
+    }
+
+    #[test]
+    fn runner_warmup() {
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let region = Region::new("us-west1");
+
+        // without warmup
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        let (_, _, mut clients_latencies, _) = runner.run(None);
+        let (_, without_warmup) = clients_latencies.remove(&region).unwrap();
+
+        // with a warmup of 1 second
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.set_warmup(Duration::from_secs(1));
+        let (_, _, mut clients_latencies, _) = runner.run(None);
+        let (issued, with_warmup) = clients_latencies.remove(&region).unwrap();
+
+        // all commands are issued, but since in us-west1 each command takes
+        // 34ms, only the ones ending at 34 * 30 = 1020ms or later are recorded
+        assert_eq!(issued, commands_per_client);
+        assert_eq!(without_warmup.count(), commands_per_client);
+        assert_eq!(with_warmup.count(), commands_per_client - 29);
+
+        // `Basic` has no cold-start effects, but in general the warmup should
+        // never inflate latencies
+        assert!(with_warmup.mean() <= without_warmup.mean());
+    }
+
+    #[test]
+    fn runner_step() {
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let extra_sim_time = Some(Duration::from_secs(1));
+
+        // run the simulation in one go
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        let (_, _, run_latencies, _) = runner.run(extra_sim_time);
+
+        // step through the simulation
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.start(extra_sim_time);
+        let mut last_time = 0;
+        while !runner.is_done() {
+            let step = runner.step();
+            assert_eq!(step.actions, 1);
+            // simulation time never goes backwards
+            assert!(step.time_millis >= last_time);
+            last_time = step.time_millis;
+        }
+        // once done, steps do nothing
+        assert_eq!(runner.step().actions, 0);
+
+        // both runs produce the same histograms
+        assert_eq!(runner.clients_latencies(), run_latencies);
+    }
+
+    #[test]
+    fn runner_execution_delay() {
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let execution_delay = 10;
+
+        // without execution delay
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        let (_, _, without_delay, _) = runner.run(None);
+
+        // with execution delay; cleanups happen every millisecond so that
+        // delayed results are released as soon as the delay expires
+        let mut runner = create_runner_with_config(
+            f,
+            clients_per_process,
+            commands_per_client,
+            |config| {
+                config.set_executor_execution_delay(Duration::from_millis(
+                    execution_delay,
+                ));
+                config.set_executor_cleanup_interval(Duration::from_millis(1));
+            },
+        );
+        let (_, _, with_delay, _) = runner.run(None);
+
+        // latencies in all regions increase by the execution delay
+        for (region, (_, without_delay)) in without_delay {
+            let (issued, with_delay) = with_delay
+                .get(&region)
+                .expect("there should be stats for all regions");
+            assert_eq!(*issued, commands_per_client);
+            assert_eq!(
+                with_delay.mean().value(),
+                without_delay.mean().value() + execution_delay as f64
+            );
+        }
+    }
+
+    #[test]
+    fn runner_cmd_timeout() {
+        // with heavy message loss, some commands never complete; with a
+        // timeout of 1 second (much higher than the latency of any command
+        // that completes), clients give up on those and move on
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let timeout = Duration::from_secs(1);
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.set_message_loss(50);
+        runner.set_cmd_timeout(timeout);
+        let (_, _, clients_latencies, _) = runner.run(None);
+        let mut clients_timed_out = runner.clients_timed_out();
+
+        let mut total_timed_out = 0;
+        for (region, (issued, latencies)) in clients_latencies {
+            let timed_out = clients_timed_out
+                .remove(&region)
+                .expect("there should be timed out counts for all regions");
+            total_timed_out += timed_out;
+
+            // all commands are issued, and timed out commands are not
+            // accounted for in the latency histogram
+            assert_eq!(issued, commands_per_client);
+            assert_eq!(latencies.count() + timed_out, commands_per_client);
+            assert!(latencies.max().value() < timeout.as_millis() as f64);
+        }
+        assert!(total_timed_out > 0);
+    }
+
+    #[test]
+    fn runner_abort() {
+        // every command is aborted the first time it's executed, and then
+        // retried (as is) by its client; since latencies are measured from
+        // the retry, they're the same as without aborts (see
+        // `runner_single_client_per_process`)
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let mut runner = create_runner_with_config(
+            f,
+            clients_per_process,
+            commands_per_client,
+            |config| config.set_executor_abort_percentage(100),
+        );
+        let (_, _, mut clients_latencies, _) = runner.run(None);
+
+        // all commands complete exactly once
+        for (region, expected_mean) in
+            vec![("us-west1", 34.0), ("us-west2", 58.0)]
+        {
+            let (issued, latencies) = clients_latencies
+                .remove(&Region::new(region))
+                .expect("there should be stats from all regions");
+            assert_eq!(issued, commands_per_client);
+            assert_eq!(latencies.count(), commands_per_client);
+            assert_eq!(latencies.mean(), F64::new(expected_mean));
+        }
+    }
+
+    #[test]
+    fn runner_abort_with_cmd_timeout() {
+        // every command is aborted the first time it's executed; the timeout
+        // is higher than the latency of each submission, but lower than the
+        // latency of both the first submission and the retry in us-west2 (see
+        // `runner_abort`), so commands time out only if the timeout of the
+        // first submission is not ignored once the command is retried
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let mut runner = create_runner_with_config(
+            f,
+            clients_per_process,
+            commands_per_client,
+            |config| config.set_executor_abort_percentage(100),
+        );
+        runner.set_cmd_timeout(Duration::from_millis(100));
+        let (_, _, mut clients_latencies, _) = runner.run(None);
+
+        // no command times out, and all complete exactly once
+        assert!(runner
+            .clients_timed_out()
+            .values()
+            .all(|timed_out| *timed_out == 0));
+        for (region, expected_mean) in
+            vec![("us-west1", 34.0), ("us-west2", 58.0)]
+        {
+            let (issued, latencies) = clients_latencies
+                .remove(&Region::new(region))
+                .expect("there should be stats from all regions");
+            assert_eq!(issued, commands_per_client);
+            assert_eq!(latencies.count(), commands_per_client);
+            assert_eq!(latencies.mean(), F64::new(expected_mean));
+        }
+    }
+
+    #[test]
+    fn runner_on_commit() {
+        use crate::id::Dot;
+        use std::sync::{Arc, Mutex};
+
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 10;
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+
+        // count how many times each command is committed
+        let commits = Arc::new(Mutex::new(HashMap::new()));
+        let commits_clone = commits.clone();
+        runner.set_on_commit(Arc::new(move |dot: Dot, _cmd: &Command| {
+            *commits_clone.lock().unwrap().entry(dot).or_insert(0) += 1;
+        }));
+        // give enough time for the commits to reach all processes
+        runner.run(Some(Duration::from_secs(1)));
+
+        // with 2 clients, there are 20 commands, each committed exactly once at
+        // each of the 3 processes
+        let commits = commits.lock().unwrap();
+        assert_eq!(commits.len(), 2 * commands_per_client);
+        assert!(commits.values().all(|count| *count == 3));
+    }
+
+    #[test]
+    fn runner_on_stable() {
+        use crate::id::{Dot, ProcessId};
+        use std::sync::{Arc, Mutex};
+
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 10;
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+
+        // record the committed dots and each advancement of the stable
+        // frontier at each process
+        let committed = Arc::new(Mutex::new(HashSet::new()));
+        let committed_clone = committed.clone();
+        runner.set_on_commit(Arc::new(move |dot: Dot, _cmd: &Command| {
+            committed_clone.lock().unwrap().insert(dot);
+        }));
+        let advances = Arc::new(Mutex::new(HashMap::new()));
+        let advances_clone = advances.clone();
+        runner.set_on_stable(Arc::new(
+            move |process_id: ProcessId,
+                  time: u64,
+                  frontier: &HashMap<ProcessId, u64>| {
+                advances_clone
+                    .lock()
+                    .unwrap()
+                    .entry(process_id)
+                    .or_insert_with(Vec::new)
+                    .push((time, frontier.clone()));
+            },
+        ));
+        // give enough time for all commands to become stable
+        runner.run(Some(Duration::from_secs(5)));
+
+        let committed = committed.lock().unwrap();
+        let advances = advances.lock().unwrap();
+        assert_eq!(committed.len(), 2 * commands_per_client);
+        assert_eq!(advances.len(), 3);
+        for process_advances in advances.values() {
+            // both the timestamps and the frontier are monotonic
+            for window in process_advances.windows(2) {
+                let (time_a, frontier_a) = &window[0];
+                let (time_b, frontier_b) = &window[1];
+                assert!(time_a <= time_b);
+                assert!(frontier_a.iter().all(|(process_id, seq)| {
+                    frontier_b.get(process_id).map_or(false, |s| s >= seq)
+                }));
+            }
+
+            // the final frontier covers all committed dots
+            let (_, frontier) =
+                process_advances.last().expect("frontier should advance");
+            assert!(committed.iter().all(|dot| {
+                frontier
+                    .get(&dot.source())
+                    .map_or(false, |seq| *seq >= dot.sequence())
+            }));
+        }
+    }
+
+    #[test]
+    fn runner_pending_acks() {
+        // fraction of pending acks samples where the command had all the
+        // quorum acks; the simulation is seeded so that the messages lost are
+        // always the same
+        let full_acks_fraction = |message_loss: usize| {
+            let f = 1;
+            let clients_per_process = 1;
+            let commands_per_client = 100;
+            let seed = 42;
+            let mut runner = create_runner_with_config(
+                f,
+                clients_per_process,
+                commands_per_client,
+                |config| config.set_seed(Some(seed)),
+            );
+            runner.set_message_loss(message_loss);
+            runner.set_cmd_timeout(Duration::from_secs(1));
+            let (metrics, _, _, _) = runner.run(None);
+
+            // with f = 1, the quorum has size 2
+            let quorum_size = 2;
+            let mut pending_acks = Histogram::new();
+            for (process_metrics, _) in metrics.values() {
+                if let Some(histogram) = process_metrics
+                    .get_collected(ProtocolMetricsKind::PendingAcks)
+                {
+                    pending_acks.merge(histogram);
+                }
+            }
+            assert!(pending_acks.count() > 0);
+            let full_acks =
+                pending_acks.values().filter(|acks| *acks == quorum_size);
+            full_acks.count() as f64 / pending_acks.count() as f64
+        };
+
+        // without message loss, most in-flight commands have all the quorum
+        // acks and are just waiting to become stable
+        assert!(full_acks_fraction(0) > 0.5);
+
+        // with message loss, commands get stuck waiting for acks
+        assert!(full_acks_fraction(50) < 0.5);
+    }
+
+    #[test]
+    fn runner_admission_control() {
+        // max number of outstanding commands at any process and number of
+        // commands rejected
+        let run = |max_outstanding: Option<usize>| {
+            let f = 1;
+            let clients_per_process = 10;
+            let commands_per_client = 20;
+            let mut runner = create_runner_with_config(
+                f,
+                clients_per_process,
+                commands_per_client,
+                |config| config.set_max_outstanding(max_outstanding),
+            );
+            let (metrics, _, _, _) = runner.run(None);
+
+            let mut outstanding = Histogram::new();
+            for (process_metrics, _) in metrics.values() {
+                if let Some(histogram) = process_metrics
+                    .get_collected(ProtocolMetricsKind::Outstanding)
+                {
+                    outstanding.merge(histogram);
+                }
+            }
+            let max_outstanding = outstanding.max().value() as usize;
+            let rejected: usize = runner.clients_rejected().values().sum();
+            (max_outstanding, rejected)
+        };
+
+        // without a cap, all clients of a process have commands outstanding
+        // at the same time, and no command is rejected
+        let (max_outstanding, rejected) = run(None);
+        assert!(max_outstanding > 2);
+        assert_eq!(rejected, 0);
+
+        // with a cap, the number of outstanding commands stays bounded and
+        // clients see rejections instead
+        let (max_outstanding, rejected) = run(Some(2));
+        assert!(max_outstanding <= 2);
+        assert!(rejected > 0);
+    }
+
+    #[test]
+    fn runner_bandwidth() {
+        // mean latency of clients in us-west1
+        let mean_latency = |payload_size: usize| {
+            let f = 1;
+            let clients_per_process = 1;
+            let commands_per_client = 100;
+            let mut runner = create_runner_with_payload_size(
+                f,
+                clients_per_process,
+                commands_per_client,
+                payload_size,
+                |_| {},
+            );
+            // 1KB per millisecond
+            runner.set_bandwidth(1000);
+            let (_, _, mut clients_latencies, _) = runner.run(None);
+            let (_, latency) = clients_latencies
+                .remove(&Region::new("us-west1"))
+                .expect("there should be stats for us-west1");
+            latency.mean().value()
+        };
+
+        // a payload of 100KB takes (at least) 100ms to be submitted, while a
+        // payload of 100 bytes takes no time
+        let small = mean_latency(100);
+        let large = mean_latency(100_000);
+        assert!(large >= small + 100.0);
+    }
+
+    #[test]
+    fn runner_message_sizes() {
+        // mean size of the messages sent by all processes
+        let mean_size = |payload_size: usize| {
+            let f = 1;
+            let clients_per_process = 1;
+            let commands_per_client = 10;
+            let mut runner = create_runner_with_payload_size(
+                f,
+                clients_per_process,
+                commands_per_client,
+                payload_size,
+                |_| {},
+            );
+            runner.run(None);
+            let mut sizes = Histogram::new();
+            for histogram in runner.processes_message_sizes().values() {
+                sizes.merge(histogram);
+            }
+            sizes.mean().value()
+        };
+
+        // messages carry commands, and thus their payload
+        let small = mean_size(100);
+        let large = mean_size(10_000);
+        assert!(small > 0.0);
+        assert!(large > small);
+    }
+
+    #[test]
+    fn runner_reconcile() {
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+
+        // without message loss, all commands produce a result
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.run(None);
+        assert_eq!(runner.reconcile(), Ok(()));
+
+        // with heavy message loss, some commands only time out
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.set_message_loss(50);
+        runner.set_cmd_timeout(Duration::from_secs(1));
+        runner.run(None);
+        let lost = runner
+            .reconcile()
+            .expect_err("some commands should be lost");
+        assert!(!lost.is_empty());
+
+        // lost commands are a subset of the ones that timed out
+        let timed_out: usize = runner.clients_timed_out().values().sum();
+        assert!(lost.len() <= timed_out);
+    }
+
+    #[test]
+    fn runner_seed() {
+        let run = |seed: u64| {
+            let f = 1;
+            let clients_per_process = 2;
+            let commands_per_client = 50;
+            let mut runner = create_runner_with_config(
+                f,
+                clients_per_process,
+                commands_per_client,
+                |config| config.set_seed(Some(seed)),
+            );
+            runner.set_message_loss(10);
+            runner.reorder_messages();
+            runner.set_cmd_timeout(Duration::from_secs(1));
+            let (metrics, _, clients_latencies, _) = runner.run(None);
+            (metrics, clients_latencies)
+        };
+
+        // the same seed produces the same simulation
+        let (metrics_a, latencies_a) = run(42);
+        let (metrics_b, latencies_b) = run(42);
+        assert_eq!(latencies_a, latencies_b);
+        assert_eq!(metrics_a, metrics_b);
+    }
+
+    #[test]
+    fn runner_with_client_counts() {
+        let config = create_config(3, 1, |_| {});
+        let commands_per_client = 10;
+        let workload = create_workload(commands_per_client, 100);
+        let process_regions = create_process_regions();
+
+        // most clients are in us-west1
+        let client_counts: HashMap<_, _> =
+            vec![(Region::new("us-west1"), 3), (Region::new("asia-east1"), 1)]
+                .into_iter()
+                .collect();
+        let mut runner: Runner<Basic> = Runner::with_client_counts(
+            Planet::new(),
+            config,
+            workload,
+            process_regions,
+            client_counts.clone(),
+        );
+        let (_, _, clients_latencies, _) = runner.run(None);
+
+        // check the commands issued in each region
+        assert_eq!(clients_latencies.len(), client_counts.len());
+        for (region, client_count) in client_counts {
+            let (issued, _) = clients_latencies
+                .get(&region)
+                .expect("there should be stats for the region");
+            assert_eq!(*issued, client_count * commands_per_client);
+        }
+    }
+
+    #[test]
+    fn runner_concurrency() {
+        let commands_per_client = 20;
+        let run = |concurrency: usize| {
+            let config = create_config(3, 1, |_| {});
+            let mut workload = create_workload(commands_per_client, 100);
+            workload.set_concurrency(concurrency);
+            let process_regions = create_process_regions();
+            // a single client
+            let client_region = Region::new("us-west1");
+            let mut runner: Runner<Basic> = Runner::new(
+                Planet::new(),
+                config,
+                workload,
+                1,
+                process_regions,
+                vec![client_region.clone()],
+            );
+
+            // track the maximum number of commands in flight
+            let client_id = 1;
+            let mut max_pending = 0;
+            runner.start(None);
+            while !runner.is_done() {
+                runner.step();
+                let (client, _) = runner.simulation.get_client(client_id);
+                max_pending =
+                    std::cmp::max(max_pending, client.pending_count());
+            }
+            let (issued, latencies) = runner
+                .clients_latencies()
+                .remove(&client_region)
+                .expect("there should be stats for the client region");
+            (max_pending, issued, latencies.count())
+        };
+
+        // by default, there's a single command in flight
+        let (max_pending, issued, completed) = run(1);
+        assert_eq!(max_pending, 1);
+        assert_eq!(issued, commands_per_client);
+        assert_eq!(completed, commands_per_client);
+
+        // with concurrency, up to 4 commands are in flight, and the same
+        // number of commands completes
+        let (max_pending, issued, completed) = run(4);
+        assert_eq!(max_pending, 4);
+        assert_eq!(issued, commands_per_client);
+        assert_eq!(completed, commands_per_client);
+    }
+
+    #[cfg(feature = "command_spans")]
+    #[test]
+    fn runner_command_spans() {
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+        use tracing::span::{Attributes, Id};
+        use tracing::Subscriber;
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+        // layer that records the name of every span created
+        #[derive(Clone, Default)]
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+        impl<S: Subscriber> Layer<S> for SpanNames {
+            fn on_new_span(
+                &self,
+                attrs: &Attributes<'_>,
+                _id: &Id,
+                _ctx: Context<'_, S>,
+            ) {
+                self.0.lock().push(attrs.metadata().name());
+            }
+        }
+
+        let names = SpanNames::default();
+        let subscriber = tracing_subscriber::registry().with(names.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            // a single client issuing a single command
+            let config = create_config(3, 1, |_| {});
+            let workload = create_workload(1, 100);
+            let process_regions = create_process_regions();
+            let client_regions = vec![Region::new("us-west1")];
+            let mut runner: Runner<Basic> = Runner::new(
+                Planet::new(),
+                config,
+                workload,
+                1,
+                process_regions,
+                client_regions,
+            );
+            // give enough time for the commit to reach all processes
+            runner.run(Some(Duration::from_secs(1)));
+        });
+
+        // the command is submitted once, and then committed and executed at
+        // each process
+        let names = names.0.lock().clone();
+        let count = |phase| names.iter().filter(|name| **name == phase).count();
+        assert_eq!(count("submit"), 1);
+        assert_eq!(count("commit"), 3);
+        assert_eq!(count("execute"), 3);
+
+        // the first span of each phase is in the expected order
+        let mut phases = Vec::new();
+        for name in names {
+            if !phases.contains(&name) {
+                phases.push(name);
+            }
+        }
+        assert_eq!(phases, vec!["submit", "commit", "execute"]);
+    }
+
+    #[test]
+    fn runner_phase_latencies() {
+        // all commands conflict with each other
+        let f = 1;
+        let clients_per_process = 1;
+        let commands_per_client = 100;
+        let region = Region::new("us-west2");
+
+        // the basic executor executes commands as soon as they're committed
+        let mut runner =
+            create_runner(f, clients_per_process, commands_per_client);
+        runner.run(None);
+        let (commit, execute) = runner
+            .clients_phase_latencies()
+            .remove(&region)
+            .expect("there should be stats for us-west2");
+        assert_eq!(commit.count(), commands_per_client);
+        assert_eq!(execute.count(), commands_per_client);
+        assert_eq!(execute.mean(), F64::new(0.0));
+    }
+
+    #[test]
+    fn runner_from_pairs_planet() {
+        // planet with two regions 20ms apart
+        let region_a = Region::new("a");
+        let region_b = Region::new("b");
+        let mut pairs = HashMap::new();
+        pairs.insert((region_a.clone(), region_b.clone()), 20);
+        pairs.insert((region_b.clone(), region_a.clone()), 20);
+        let planet = Planet::from_pairs(pairs);
+
+        // config
+        let n = 2;
+        let f = 1;
+        let config = create_config(n, f, |_| {});
+
+        // clients workload
+        let commands_per_client = 10;
+        let payload_size = 100;
+        let workload = create_workload(commands_per_client, payload_size);
+
+        // one process in each region and clients in region a
+        let clients_per_process = 1;
+        let process_regions = vec![region_a.clone(), region_b.clone()];
+        let client_regions = vec![region_a.clone()];
+        let mut runner: Runner<Basic> = Runner::new(
+            planet,
+            config,
+            workload,
+            clients_per_process,
+            process_regions,
+            client_regions,
+        );
+
+        // by default, the distance is half the ping latency
+        assert_eq!(runner.distance(&region_a, &region_b), 10);
+        assert_eq!(runner.distance(&region_a, &region_a), 0);
+
+        // with f = 1, each command takes a round-trip to region b
+        let (_, _, mut clients_latencies, _) = runner.run(None);
+        let (issued, histogram) = clients_latencies
+            .remove(&region_a)
+            .expect("there should be stats for region a");
+        assert_eq!(issued, commands_per_client);
+        assert_eq!(histogram.mean(), F64::new(20.0));
+    }
 }
@@ -6,11 +6,15 @@ use crate::protocol::{Action, Protocol};
 use crate::time::SimTime;
 use crate::HashMap;
 use std::cell::Cell;
+use std::time::Duration;
 
 pub struct Simulation<P: Protocol> {
     time: SimTime,
     processes: HashMap<ProcessId, Cell<(P, P::Executor, AggregatePending)>>,
     clients: HashMap<ClientId, Cell<Client>>,
+    // mapping from process identifier to its clock skew (and the time
+    // perceived by that process)
+    skews: HashMap<ProcessId, (Duration, SimTime)>,
 }
 
 impl<P> Simulation<P>
@@ -24,6 +28,7 @@ where
             time: SimTime::new(),
             processes: HashMap::new(),
             clients: HashMap::new(),
+            skews: HashMap::new(),
         }
     }
 
@@ -48,6 +53,13 @@ where
         assert!(res.is_none());
     }
 
+    /// Sets the clock skew of some process: the process will perceive time
+    /// shifted by `skew`. By default, processes have no skew.
+    pub fn set_clock_skew(&mut self, process_id: ProcessId, skew: Duration) {
+        self.skews
+            .insert(process_id, (skew, self.time.with_skew(skew)));
+    }
+
     /// Registers a `Client` in the `Simulation` by storing it in a `Cell`.
     pub fn register_client(&mut self, client: Client) {
         // get identifier
@@ -58,21 +70,32 @@ where
         assert!(res.is_none());
     }
 
-    /// Starts all clients registered in the router.
+    /// Starts all clients registered in the router. Each client submits as
+    /// many commands as its workload's concurrency.
     pub fn start_clients(&mut self) -> Vec<(ClientId, ProcessId, Command)> {
         let time = &self.time;
-        self.clients
-            .iter_mut()
-            .map(|(_, client)| {
-                let client = client.get_mut();
-                // start client
-                let (target_shard, cmd) = client
-                    .cmd_send(time)
-                    .expect("clients should submit at least one command");
-                let process_id = client.shard_process(&target_shard);
-                (client.id(), process_id, cmd)
-            })
-            .collect()
+        let mut submits = Vec::new();
+        for (_, client) in self.clients.iter_mut() {
+            let client = client.get_mut();
+            // start client
+            let (target_shard, cmd) = client
+                .cmd_send(time)
+                .expect("clients should submit at least one command");
+            let process_id = client.shard_process(&target_shard);
+            submits.push((client.id(), process_id, cmd));
+
+            // keep submitting while below the workload's concurrency
+            while client.can_submit() {
+                match client.cmd_send(time) {
+                    Some((target_shard, cmd)) => {
+                        let process_id = client.shard_process(&target_shard);
+                        submits.push((client.id(), process_id, cmd));
+                    }
+                    None => break,
+                }
+            }
+        }
+        submits
     }
 
     /// Forward a `ToSend`.
@@ -137,11 +160,26 @@ where
         let client_id = cmd_result.rifl().source();
         // find client
         let (client, time) = self.get_client(client_id);
-        // handle command result
-        // TODO: we should aggregate command results if we have more than one
-        // shard in simulation
-        client.cmd_recv(cmd_result.rifl(), time);
-        // and generate the next command
+        // handle command result (the runner only forwards results of commands
+        // that access more than one shard once all shards have replied)
+        if cmd_result.is_timed_out() {
+            client.cmd_timed_out(cmd_result.rifl(), time);
+        } else if cmd_result.is_rejected() {
+            client.cmd_rejected(cmd_result.rifl(), time);
+        } else if cmd_result.is_aborted() {
+            client.cmd_aborted(cmd_result.rifl(), time);
+        } else {
+            client.cmd_recv_with_phases(
+                cmd_result.rifl(),
+                cmd_result.phase_times(),
+                time,
+            );
+        }
+        // and generate the next command (if below the workload's
+        // concurrency)
+        if !client.can_submit() {
+            return None;
+        }
         client.cmd_send(time).map(|(target_shard, cmd)| {
             let target = client.shard_process(&target_shard);
             (target, cmd)
@@ -164,7 +202,15 @@ where
                 );
             })
             .get_mut();
-        (process, executor, pending, &self.time)
+        // compute the time perceived by this process
+        let time = match self.skews.get_mut(&process_id) {
+            Some((skew, skewed_time)) => {
+                *skewed_time = self.time.with_skew(*skew);
+                skewed_time
+            }
+            None => &self.time,
+        };
+        (process, executor, pending, time)
     }
 
     /// Returns the client registered with this identifier.
@@ -50,6 +50,13 @@ impl SimTime {
         self.micros = new_time_micros;
     }
 
+    /// Creates a copy of this simulation time shifted by `skew`.
+    pub fn with_skew(&self, skew: Duration) -> Self {
+        Self {
+            micros: self.micros + skew.as_micros() as u64,
+        }
+    }
+
     fn millis_to_micros(millis: u64) -> u64 {
         millis * 1000
     }
@@ -94,6 +101,21 @@ mod tests {
         assert_eq!(time.millis(), 20);
     }
 
+    #[test]
+    fn sim_time_with_skew() {
+        let mut time = SimTime::new();
+        time.set_millis(20);
+
+        // skewed time is ahead of time
+        let skewed = time.with_skew(Duration::from_millis(50));
+        assert_eq!(skewed.millis(), 70);
+        assert_eq!(time.millis(), 20);
+
+        // no skew
+        let skewed = time.with_skew(Duration::from_millis(0));
+        assert_eq!(skewed.millis(), 20);
+    }
+
     #[test]
     #[should_panic]
     fn sim_time_should_be_monotonic() {
@@ -2,6 +2,7 @@ use crate::id::{Dot, ProcessId, ShardId};
 use crate::kvs::Key;
 use crate::planet::{Planet, Region};
 use crate::HashMap;
+use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 
 // warn and info logs are always enabled!
@@ -41,9 +42,47 @@ macro_rules! trace {
 #[macro_export]
 #[cfg(not(feature = "max_level_trace"))]
 macro_rules! trace {
+    // arguments are never evaluated, but are still considered to be used
     ($( $args:expr ),*) => {
+        if false {
+            $( let _ = &$args; )*
+        }
+    };
+}
+
+/// Emits a `tracing` span for the `phase` of the command identified by `rifl`
+/// (which serves as correlation id), at time `time` (a `SysTime`). If `body`
+/// is given, it's evaluated (and its value returned) inside the span, so that
+/// the span also times it. All protocols emit `submit` and `commit` spans, and
+/// all executors emit `execute` spans around the execution of each command.
+#[macro_export]
+#[cfg(feature = "command_spans")]
+macro_rules! command_span {
+    ($phase:literal, $rifl:expr, $time:expr) => {
+        $crate::command_span!($phase, $rifl, $time, ())
+    };
+    ($phase:literal, $rifl:expr, $time:expr, $body:expr) => {{
+        let span =
+            tracing::info_span!($phase, rifl = ?$rifl, time = $time.micros());
+        let _enter = span.enter();
+        $body
+    }};
+}
+
+#[macro_export]
+#[cfg(not(feature = "command_spans"))]
+macro_rules! command_span {
+    ($phase:literal, $rifl:expr, $time:expr) => {
         ()
     };
+    // arguments are never evaluated, but are still considered to be used
+    ($phase:literal, $rifl:expr, $time:expr, $body:expr) => {{
+        if false {
+            let _ = &$rifl;
+            let _ = &$time;
+        }
+        $body
+    }};
 }
 
 /// create a singleton hash set
@@ -119,6 +158,40 @@ pub fn key_hash(key: &Key) -> u64 {
     hasher.finish()
 }
 
+/// Computes the shard of a key using consistent hashing (more precisely, the
+/// jump consistent hash by Lamping and Veach): when the number of shards grows
+/// from `shard_count` to `shard_count + 1`, only `1 / (shard_count + 1)` of the
+/// keys move to the new shard, and the remaining keep their shard.
+#[allow(clippy::ptr_arg)]
+pub fn consistent_key_shard(key: &Key, shard_count: u64) -> ShardId {
+    assert!(shard_count >= 1);
+    let mut hash = key_hash(key);
+    let mut shard: i64 = -1;
+    let mut next: i64 = 0;
+    while next < shard_count as i64 {
+        shard = next;
+        hash = hash.wrapping_mul(2862933555777941757).wrapping_add(1);
+        let r = ((hash >> 33) + 1) as f64;
+        next = ((shard + 1) as f64 * ((1u64 << 31) as f64 / r)) as i64;
+    }
+    shard as ShardId
+}
+
+/// Computes the shard of a key, either using consistent hashing (see
+/// `consistent_key_shard`) or by taking its hash modulo `shard_count`.
+#[allow(clippy::ptr_arg)]
+pub fn key_shard(
+    key: &Key,
+    shard_count: u64,
+    consistent_hashing: bool,
+) -> ShardId {
+    if consistent_hashing {
+        consistent_key_shard(key, shard_count)
+    } else {
+        key_hash(key) % shard_count
+    }
+}
+
 /// Returns an iterator with all process identifiers in this shard in a system
 /// with `n` processes.
 pub fn process_ids(
@@ -154,26 +227,21 @@ pub fn sort_processes_by_distance(
     mut processes: Vec<(ProcessId, ShardId, Region)>,
 ) -> Vec<(ProcessId, ShardId)> {
     // TODO the following computation could be cached on `planet`
-    let indexes: HashMap<_, _> = planet
+    let distances: HashMap<_, _> = planet
         // get all regions sorted by distance from `region`
         .sorted(region)
         .expect("region should be part of planet")
         .iter()
-        // create a mapping from region to its index
-        .enumerate()
-        .map(|(index, (_distance, region))| (region, index))
+        // create a mapping from region to its distance
+        .map(|(distance, region)| (region, *distance))
         .collect();
 
-    // use the region order index (based on distance) to order `processes`
-    // - if two `processes` are from the same region, they're sorted by id
-    processes.sort_unstable_by(|(id_a, _, a), (id_b, _, b)| {
-        if a == b {
-            id_a.cmp(id_b)
-        } else {
-            let index_a = indexes.get(a).expect("region should exist");
-            let index_b = indexes.get(b).expect("region should exist");
-            index_a.cmp(index_b)
-        }
+    // use the region distance to order `processes`
+    // - if two `processes` are at the same distance (e.g. if they're from the
+    //   same region), they're sorted by id, so that the order is deterministic
+    processes.sort_unstable_by_key(|(id, _, region)| {
+        let distance = distances.get(region).expect("region should exist");
+        (*distance, *id)
     });
 
     processes
@@ -198,6 +266,47 @@ pub fn closest_process_per_shard(
     processes
 }
 
+/// Returns the entries of `map` to be shown in `Debug` output. If the
+/// `debug_sorted` feature is enabled, entries are sorted by (the `Debug`
+/// representation of) their key, so that the output is deterministic.
+pub fn debug_entries<K, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)>
+where
+    K: Debug,
+{
+    #[cfg(feature = "debug_sorted")]
+    {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by_cached_key(|(key, _)| format!("{:?}", key));
+        entries
+    }
+    #[cfg(not(feature = "debug_sorted"))]
+    {
+        map.iter().collect()
+    }
+}
+
+/// Wrapper that shows a map in `Debug` output with its entries ordered as in
+/// `debug_entries`.
+pub struct DebugMap<'a, K, V> {
+    map: &'a HashMap<K, V>,
+}
+
+impl<'a, K, V> DebugMap<'a, K, V> {
+    pub fn new(map: &'a HashMap<K, V>) -> Self {
+        Self { map }
+    }
+}
+
+impl<'a, K, V> Debug for DebugMap<'a, K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(debug_entries(self.map)).finish()
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -262,4 +371,30 @@ pub mod tests {
 
         assert_eq!(expected, sorted);
     }
+
+    #[test]
+    fn sort_processes_by_distance_tie_test() {
+        // regions `b` and `c` are equidistant from `a`
+        let a = Region::new("a");
+        let b = Region::new("b");
+        let c = Region::new("c");
+        let pairs =
+            vec![((a.clone(), b.clone()), 10), ((a.clone(), c.clone()), 10)]
+                .into_iter()
+                .collect();
+        let planet = Planet::from_pairs(pairs);
+
+        // process 4 is in `c` and process 5 is in `b`; regardless of the input
+        // order, the lower id should sort first
+        let shard_id = 0;
+        let expected = vec![(3, shard_id), (4, shard_id), (5, shard_id)];
+        let processes =
+            vec![(5, shard_id, b), (4, shard_id, c), (3, shard_id, a.clone())];
+        let mut reversed = processes.clone();
+        reversed.reverse();
+        for processes in vec![processes, reversed] {
+            let sorted = sort_processes_by_distance(&a, &planet, processes);
+            assert_eq!(sorted, expected);
+        }
+    }
 }
@@ -30,6 +30,45 @@ impl Protocol {
         }
     }
 
+    /// Computes the effective fault tolerance, i.e., how many of the `n`
+    /// regions can fail while a quorum is still available. Note that this
+    /// can be higher than `f` as it only accounts for the quorum size.
+    pub fn effective_fault_tolerance(&self, n: usize, f: usize) -> usize {
+        n - self.quorum_size(n, f)
+    }
+
+    /// Computes the probability that a quorum is available, given the
+    /// probability of failure of each of the `n` regions (with one process
+    /// per region), assuming regions fail independently.
+    pub fn quorum_availability(
+        &self,
+        n: usize,
+        f: usize,
+        region_failure_probs: &[f64],
+    ) -> f64 {
+        assert_eq!(region_failure_probs.len(), n);
+        // compute the probability of exactly `up` regions being up, one region
+        // at a time
+        let mut up_probs = vec![0.0; n + 1];
+        up_probs[0] = 1.0;
+        for (i, failure_prob) in region_failure_probs.iter().enumerate() {
+            assert!((0.0..=1.0).contains(failure_prob));
+            for up in (0..=i + 1).rev() {
+                let down_prob = up_probs[up] * failure_prob;
+                let up_prob = if up > 0 {
+                    up_probs[up - 1] * (1.0 - failure_prob)
+                } else {
+                    0.0
+                };
+                up_probs[up] = down_prob + up_prob;
+            }
+        }
+        // a quorum is available if at most `effective_fault_tolerance`
+        // regions are down
+        let max_down = self.effective_fault_tolerance(n, f);
+        up_probs[n - max_down..].iter().sum()
+    }
+
     fn minority(n: usize) -> usize {
         n / 2
     }
@@ -133,6 +172,47 @@ mod tests {
         assert_eq!(Protocol::Atlas.quorum_size(5, 2), 4);
     }
 
+    #[test]
+    fn effective_fault_tolerance() {
+        // FPaxos tolerates more than `f` failures with `n = 5`, as its
+        // quorums only have `f + 1` processes
+        assert_eq!(Protocol::FPaxos.effective_fault_tolerance(3, 1), 1);
+        assert_eq!(Protocol::FPaxos.effective_fault_tolerance(5, 1), 3);
+        assert_eq!(Protocol::FPaxos.effective_fault_tolerance(5, 2), 2);
+        // EPaxos ignores `f`
+        assert_eq!(Protocol::EPaxos.effective_fault_tolerance(3, 0), 1);
+        assert_eq!(Protocol::EPaxos.effective_fault_tolerance(5, 0), 2);
+        assert_eq!(Protocol::EPaxos.effective_fault_tolerance(5, 2), 2);
+        // Atlas tolerates fewer failures as `f` increases
+        assert_eq!(Protocol::Atlas.effective_fault_tolerance(3, 1), 1);
+        assert_eq!(Protocol::Atlas.effective_fault_tolerance(5, 1), 2);
+        assert_eq!(Protocol::Atlas.effective_fault_tolerance(5, 2), 1);
+    }
+
+    #[test]
+    fn quorum_availability() {
+        let n = 5;
+        let probs = vec![0.1; n];
+        let check = |availability: f64, expected: f64| {
+            assert!((availability - expected).abs() < 1e-9);
+        };
+
+        // quorum of 2: unavailable if 4 or 5 regions fail
+        // - 1 - (0.1^5 + 5 * 0.9 * 0.1^4)
+        let availability = Protocol::FPaxos.quorum_availability(n, 1, &probs);
+        check(availability, 0.99954);
+
+        // quorum of 4: available if 4 or 5 regions are up
+        // - 0.9^5 + 5 * 0.9^4 * 0.1
+        let availability = Protocol::Atlas.quorum_availability(n, 2, &probs);
+        check(availability, 0.91854);
+
+        // if regions never fail, a quorum is always available
+        let availability =
+            Protocol::EPaxos.quorum_availability(n, 0, &vec![0.0; n]);
+        check(availability, 1.0);
+    }
+
     #[test]
     fn protocol_stats() {
         let stats = Histogram::from(vec![10, 20, 40, 10]);
@@ -1,6 +1,8 @@
 #[cfg(feature = "exp")]
 use crate::args;
 use crate::{FantochFeature, Protocol, RunMode, Testbed};
+#[cfg(feature = "exp")]
+use fantoch::client::KeyGen;
 use fantoch::client::Workload;
 use fantoch::config::Config;
 use fantoch::id::{ProcessId, ShardId};
@@ -125,6 +127,31 @@ impl ProtocolConfig {
         }
     }
 
+    /// Overrides the interval (in milliseconds) between flushes of this
+    /// process' tcp sockets. If `None`, sockets are flushed on every write.
+    pub fn set_tcp_flush_interval(&mut self, interval: Option<usize>) {
+        self.tcp_flush_interval =
+            interval.map(|interval| Duration::from_millis(interval as u64));
+    }
+
+    /// Overrides the number of workers.
+    pub fn set_workers(&mut self, workers: usize) {
+        assert!(workers >= 1, "there should be at least one worker");
+        self.workers = workers;
+    }
+
+    /// Overrides the number of executors.
+    pub fn set_executors(&mut self, executors: usize) {
+        assert!(executors >= 1, "there should be at least one executor");
+        self.executors = executors;
+    }
+
+    /// Overrides the number of connections between each pair of processes.
+    pub fn set_multiplexing(&mut self, multiplexing: usize) {
+        assert!(multiplexing >= 1, "multiplexing should be at least 1");
+        self.multiplexing = multiplexing;
+    }
+
     pub fn to_args(&self) -> Vec<String> {
         let mut args = args![
             "--id",
@@ -170,6 +197,13 @@ impl ProtocolConfig {
                 interval.as_millis()
             ]);
         }
+        if let Some(keys_per_range) = self.config.executor_keys_per_range() {
+            args.extend(args!["--executor_keys_per_range", keys_per_range]);
+        }
+        args.extend(args![
+            "--executor_fifo_ordering",
+            self.config.executor_fifo_ordering()
+        ]);
         if let Some(interval) = self.config.gc_interval() {
             args.extend(args!["--gc_interval", interval.as_millis()]);
         }
@@ -230,6 +264,16 @@ impl ProtocolConfig {
         args
     }
 
+    /// Joins the arguments generated by `to_args` into a single (shell-escaped)
+    /// string, so that a process launch can be reproduced by hand.
+    pub fn to_command_line(&self) -> String {
+        self.to_args()
+            .iter()
+            .map(|arg| shell_escape(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     fn ips_to_addresses(&self) -> String {
         self.ips
             .iter()
@@ -246,6 +290,19 @@ impl ProtocolConfig {
     }
 }
 
+// quotes `arg` unless it only contains characters that the shell does not
+// interpret; single quotes inside `arg` are closed, escaped and reopened
+#[cfg(feature = "exp")]
+fn shell_escape(arg: &str) -> String {
+    let is_safe =
+        |c: char| c.is_ascii_alphanumeric() || "-_.,:/=@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
 fn workers_executors_and_leader(
     protocol: Protocol,
     config: &mut Config,
@@ -277,6 +334,8 @@ pub struct ClientConfig {
     id_end: usize,
     ips: Vec<(ProcessId, String)>,
     workload: Workload,
+    key_gen: KeyGen,
+    read_only_percentage: usize,
     batch_max_size: usize,
     batch_max_delay: Duration,
     tcp_nodelay: bool,
@@ -300,11 +359,17 @@ impl ClientConfig {
         metrics_file: String,
         log_file: String,
     ) -> Self {
+        // by default, use the key generator and read-only percentage set in
+        // the workload
+        let key_gen = workload.key_gen();
+        let read_only_percentage = workload.read_only_percentage();
         Self {
             id_start,
             id_end,
             ips,
             workload,
+            key_gen,
+            read_only_percentage,
             batch_max_size,
             batch_max_delay,
             tcp_nodelay: CLIENT_TCP_NODELAY,
@@ -317,20 +382,21 @@ impl ClientConfig {
         }
     }
 
+    /// Overrides the key generator used by clients.
+    pub fn set_key_gen(&mut self, key_gen: KeyGen) {
+        self.key_gen = key_gen;
+    }
+
+    /// Overrides the percentage of read-only commands issued by clients.
+    pub fn set_read_only_percentage(&mut self, read_only_percentage: usize) {
+        assert!(
+            read_only_percentage <= 100,
+            "the percentage of read-only commands must be less or equal to 100"
+        );
+        self.read_only_percentage = read_only_percentage;
+    }
+
     pub fn to_args(&self) -> Vec<String> {
-        use fantoch::client::KeyGen;
-        let key_gen = match self.workload.key_gen() {
-            KeyGen::ConflictPool {
-                conflict_rate,
-                pool_size,
-            } => {
-                format!("conflict_pool,{},{}", conflict_rate, pool_size)
-            }
-            KeyGen::Zipf {
-                coefficient,
-                total_keys_per_shard,
-            } => format!("zipf,{},{}", coefficient, total_keys_per_shard),
-        };
         let mut args = args![
             "--ids",
             format!("{}-{}", self.id_start, self.id_end),
@@ -338,16 +404,38 @@ impl ClientConfig {
             self.ips_to_addresses(),
             "--shard_count",
             self.workload.shard_count(),
-            "--key_gen",
-            key_gen,
+        ];
+        match self.key_gen {
+            KeyGen::ConflictPool {
+                conflict_rate,
+                pool_size,
+            } => args.extend(args![
+                "--key_gen",
+                "conflict_pool",
+                "--conflict_rate",
+                conflict_rate,
+                "--pool_size",
+                pool_size
+            ]),
+            KeyGen::Zipf {
+                coefficient,
+                total_keys_per_shard,
+            } => args.extend(args![
+                "--key_gen",
+                "zipf",
+                "--zipf_coefficient",
+                coefficient,
+                "--keys_per_shard",
+                total_keys_per_shard
+            ]),
+        }
+        args.extend(args![
             "--keys_per_command",
             self.workload.keys_per_command(),
             "--commands_per_client",
             self.workload.commands_per_client(),
             "--payload_size",
             self.workload.payload_size(),
-            "--read_only_percentage",
-            self.workload.read_only_percentage(),
             "--batch_max_size",
             self.batch_max_size,
             "--batch_max_delay",
@@ -357,8 +445,15 @@ impl ClientConfig {
             "--channel_buffer_size",
             self.channel_buffer_size,
             "--metrics_file",
-            self.metrics_file,
-        ];
+            self.metrics_file
+        ]);
+        // the client defaults to no read-only commands
+        if self.read_only_percentage > 0 {
+            args.extend(args![
+                "--read_only_percentage",
+                self.read_only_percentage
+            ]);
+        }
         if let Some(status_frequency) = self.status_frequency {
             args.extend(args!["--status_frequency", status_frequency]);
         }
@@ -503,3 +598,182 @@ pub fn port(process_id: ProcessId) -> usize {
 pub fn client_port(process_id: ProcessId) -> usize {
     process_id as usize + CLIENT_PORT
 }
+
+#[cfg(all(test, feature = "exp"))]
+mod tests {
+    use super::*;
+
+    fn protocol_config() -> ProtocolConfig {
+        let process_id = 1;
+        let shard_id = 0;
+        let config = Config::new(3, 1);
+        let ips = vec![
+            (2, String::from("10.0.0.2"), None),
+            (3, String::from("10.0.0.3"), Some(10)),
+        ];
+        ProtocolConfig::new(
+            Protocol::Basic,
+            process_id,
+            shard_id,
+            config,
+            None,
+            ips,
+            String::from("server_1.metrics"),
+            1,
+            String::from("server_1.log"),
+        )
+    }
+
+    fn client_config() -> ClientConfig {
+        let ips = vec![(1, String::from("10.0.0.1"))];
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 10,
+            pool_size: 1,
+        };
+        let workload = Workload::new(1, key_gen, 1, 1000, 100);
+        ClientConfig::new(
+            1,
+            10,
+            ips,
+            workload,
+            1,
+            Duration::from_millis(5),
+            String::from("client_1.metrics"),
+            String::from("client_1.log"),
+        )
+    }
+
+    // splits a command line produced by `to_command_line`; only single quotes
+    // and backslash escapes are supported
+    fn shell_split(command_line: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current = None;
+        let mut in_quotes = false;
+        let mut chars = command_line.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => {
+                    in_quotes = !in_quotes;
+                    current.get_or_insert_with(String::new);
+                }
+                '\\' if !in_quotes => {
+                    let escaped = chars.next().expect("dangling escape");
+                    current.get_or_insert_with(String::new).push(escaped);
+                }
+                ' ' if !in_quotes => {
+                    args.extend(current.take());
+                }
+                c => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        assert!(!in_quotes, "unterminated quote");
+        args.extend(current.take());
+        args
+    }
+
+    fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|index| args[index + 1].as_str())
+    }
+
+    #[test]
+    fn tcp_flush_interval() {
+        let mut protocol_config = protocol_config();
+
+        // by default, the global flush interval is used
+        let args = protocol_config.to_args();
+        let expected = PROCESS_TCP_FLUSH_INTERVAL
+            .map(|interval| interval.as_millis().to_string());
+        assert_eq!(
+            flag_value(&args, "--tcp_flush_interval"),
+            expected.as_deref()
+        );
+
+        // override it
+        protocol_config.set_tcp_flush_interval(Some(2));
+        let args = protocol_config.to_args();
+        assert_eq!(flag_value(&args, "--tcp_flush_interval"), Some("2"));
+
+        // with `None`, the flag is omitted
+        protocol_config.set_tcp_flush_interval(None);
+        let args = protocol_config.to_args();
+        assert!(!args.iter().any(|arg| arg == "--tcp_flush_interval"));
+    }
+
+    #[test]
+    fn workers_executors_multiplexing() {
+        let mut protocol_config = protocol_config();
+        protocol_config.set_workers(4);
+        protocol_config.set_executors(8);
+        protocol_config.set_multiplexing(2);
+
+        let args = protocol_config.to_args();
+        assert_eq!(flag_value(&args, "--workers"), Some("4"));
+        assert_eq!(flag_value(&args, "--executors"), Some("8"));
+        assert_eq!(flag_value(&args, "--multiplexing"), Some("2"));
+    }
+
+    #[test]
+    fn to_command_line() {
+        let mut protocol_config = protocol_config();
+        protocol_config.metrics_file = String::from("it's a metrics file");
+
+        let command_line = protocol_config.to_command_line();
+        assert!(command_line.contains("--id 1"));
+        assert!(
+            command_line.contains("--addresses 10.0.0.2:3002,10.0.0.3:3003-10")
+        );
+        assert!(command_line.contains("'it'\\''s a metrics file'"));
+
+        // parsing the command line should give back the original args
+        assert_eq!(shell_split(&command_line), protocol_config.to_args());
+    }
+
+    #[test]
+    fn read_only_percentage() {
+        let mut client_config = client_config();
+
+        // by default, no read-only percentage is set
+        let args = client_config.to_args();
+        assert!(!args.iter().any(|arg| arg == "--read_only_percentage"));
+
+        client_config.set_read_only_percentage(20);
+        let args = client_config.to_args();
+        assert_eq!(flag_value(&args, "--read_only_percentage"), Some("20"));
+    }
+
+    #[test]
+    fn key_gen() {
+        let mut client_config = client_config();
+
+        // by default, the workload's key generator is used
+        let args = client_config.to_args();
+        assert_eq!(flag_value(&args, "--key_gen"), Some("conflict_pool"));
+        assert_eq!(flag_value(&args, "--conflict_rate"), Some("10"));
+        assert_eq!(flag_value(&args, "--pool_size"), Some("1"));
+        assert!(!args.iter().any(|arg| arg == "--zipf_coefficient"));
+
+        client_config.set_key_gen(KeyGen::Zipf {
+            coefficient: 1.2,
+            total_keys_per_shard: 1_000_000,
+        });
+        let args = client_config.to_args();
+        assert_eq!(flag_value(&args, "--key_gen"), Some("zipf"));
+        assert_eq!(flag_value(&args, "--zipf_coefficient"), Some("1.2"));
+        assert_eq!(flag_value(&args, "--keys_per_shard"), Some("1000000"));
+        assert!(!args.iter().any(|arg| arg == "--conflict_rate"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_workers() {
+        protocol_config().set_workers(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_executors() {
+        protocol_config().set_executors(0);
+    }
+}
@@ -134,6 +134,64 @@ impl Protocol {
             Protocol::Basic => "basic",
         }
     }
+
+    /// Returns all protocols.
+    pub fn all() -> [Protocol; 8] {
+        // this match is exhaustive so that adding a protocol doesn't compile
+        // until it's also added below
+        match Protocol::Basic {
+            Protocol::AtlasLocked
+            | Protocol::EPaxosLocked
+            | Protocol::EPaxos
+            | Protocol::CaesarLocked
+            | Protocol::FPaxos
+            | Protocol::TempoAtomic
+            | Protocol::TempoLocked
+            | Protocol::Basic => {}
+        }
+        [
+            Protocol::AtlasLocked,
+            Protocol::EPaxosLocked,
+            Protocol::EPaxos,
+            Protocol::CaesarLocked,
+            Protocol::FPaxos,
+            Protocol::TempoAtomic,
+            Protocol::TempoLocked,
+            Protocol::Basic,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseProtocolError(pub String);
+
+impl std::fmt::Display for ParseProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown protocol {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseProtocolError {}
+
+impl std::str::FromStr for Protocol {
+    type Err = ParseProtocolError;
+
+    /// Parses a protocol from either its plot name (as in
+    /// `fantoch_plot::PlotFmt::protocol_name`) or its binary name (as in
+    /// `Protocol::binary`), ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "atlas" | "atlas_locked" => Ok(Protocol::AtlasLocked),
+            "epaxoslocked" | "epaxos_locked" => Ok(Protocol::EPaxosLocked),
+            "epaxos" => Ok(Protocol::EPaxos),
+            "caesar" | "caesar_locked" => Ok(Protocol::CaesarLocked),
+            "fpaxos" => Ok(Protocol::FPaxos),
+            "tempo" | "tempo_atomic" => Ok(Protocol::TempoAtomic),
+            "tempo-l" | "tempo_locked" => Ok(Protocol::TempoLocked),
+            "inconsistent" | "basic" => Ok(Protocol::Basic),
+            _ => Err(ParseProtocolError(s.to_string())),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
@@ -1,4 +1,8 @@
-use crate::db::{Dstat, DstatCompress, MicrosHistogramCompress};
+use crate::db::{
+    Dstat, DstatCompress, LatencyPrecision, MicrosHistogramCompress,
+};
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
 use fantoch::client::ClientData;
 use fantoch::executor::ExecutorMetrics;
 use fantoch::id::ProcessId;
@@ -6,8 +10,11 @@ use fantoch::metrics::Histogram;
 use fantoch::planet::Region;
 use fantoch::protocol::ProtocolMetrics;
 use fantoch::run::task::server::metrics_logger::ProcessMetrics;
+use fantoch_exp::ExperimentConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
 use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,9 +112,201 @@ impl ExperimentData {
         }
     }
 
+    /// Returns the global client throughput divided by the number of shards in
+    /// the experiment, so that sharded and unsharded deployments can be
+    /// compared. A shard count of 0 is handled as a single shard.
+    pub fn throughput_per_shard(&self, exp_config: &ExperimentConfig) -> f64 {
+        let shard_count = std::cmp::max(exp_config.config.shard_count(), 1);
+        self.global_client_throughput / shard_count as f64
+    }
+
+    /// Writes the client summary of each region, followed by the global
+    /// client summary, to `path`, one JSON object per line.
+    pub fn to_jsonl(&self, path: impl AsRef<Path>) -> Result<(), Report> {
+        // sort regions so that the output is deterministic
+        let mut regions: Vec<_> = self.client_latency.keys().collect();
+        regions.sort();
+        let summaries = regions
+            .into_iter()
+            .map(|region| {
+                ClientSummary::new(
+                    Some(region.clone()),
+                    &self.client_latency[region],
+                    self.client_throughput
+                        .get(region)
+                        .cloned()
+                        .unwrap_or_default(),
+                )
+            })
+            .chain(std::iter::once(ClientSummary::new(
+                None,
+                &self.global_client_latency,
+                self.global_client_throughput,
+            )));
+
+        let file = std::fs::File::create(path).wrap_err("jsonl create file")?;
+        let mut buf = std::io::BufWriter::new(file);
+        for summary in summaries {
+            serde_json::to_writer(&mut buf, &summary)
+                .wrap_err("jsonl serialize")?;
+            writeln!(buf).wrap_err("jsonl write")?;
+        }
+        buf.flush().wrap_err("jsonl flush")?;
+        Ok(())
+    }
+
     fn extract_micros(
         latency_data: impl Iterator<Item = Duration>,
     ) -> impl Iterator<Item = u64> {
         latency_data.map(move |duration| duration.as_micros() as u64)
     }
 }
+
+/// Summary of the latency (in milliseconds) and throughput of the clients in
+/// some region, as exported by `ExperimentData::to_jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientSummary {
+    /// region of the clients, or `None` if this is the global summary
+    pub region: Option<Region>,
+    pub min: f64,
+    pub mean: f64,
+    pub stddev: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub throughput: f64,
+}
+
+impl ClientSummary {
+    fn new(
+        region: Option<Region>,
+        latency: &MicrosHistogramCompress,
+        throughput: f64,
+    ) -> Self {
+        let precision = LatencyPrecision::Millis;
+        Self {
+            region,
+            min: latency.min(precision),
+            mean: latency.mean(precision),
+            stddev: latency.stddev(precision),
+            p95: latency.percentile(0.95, precision),
+            p99: latency.percentile(0.99, precision),
+            max: latency.max(precision),
+            throughput,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::client::{KeyGen, Workload};
+    use fantoch::config::Config;
+    use fantoch_exp::{Protocol, RunMode, Testbed};
+
+    fn exp_config(shard_count: usize) -> ExperimentConfig {
+        let mut config = Config::new(3, 1);
+        config.set_shard_count(shard_count);
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 10,
+            pool_size: 1,
+        };
+        let keys_per_command = 1;
+        let commands_per_client = 100;
+        let payload_size = 100;
+        let workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        ExperimentConfig::new(
+            Default::default(),
+            None,
+            RunMode::Release,
+            Vec::new(),
+            Testbed::Local,
+            Protocol::Basic,
+            config,
+            1,
+            workload,
+            1,
+            Duration::from_millis(0),
+            8,
+        )
+    }
+
+    #[test]
+    fn to_jsonl() {
+        // clients in two regions, with all operations completing at time 500
+        let client_data = |latencies: Vec<u64>| {
+            let mut client_data = ClientData::new();
+            for latency in latencies {
+                client_data.record(Duration::from_millis(latency), 500);
+            }
+            client_data
+        };
+        let us_west1 = Region::new("us-west1");
+        let us_west2 = Region::new("us-west2");
+        let mut client_metrics = HashMap::new();
+        client_metrics.insert(us_west1.clone(), client_data(vec![10, 20]));
+        client_metrics.insert(us_west2.clone(), client_data(vec![40, 50]));
+        let global_client_metrics = client_data(vec![10, 20, 40, 50]);
+        let exp_data = ExperimentData::new(
+            HashMap::new(),
+            HashMap::new(),
+            client_metrics,
+            HashMap::new(),
+            global_client_metrics,
+        );
+
+        // write and re-read the summaries
+        let path = std::env::temp_dir().join("fantoch_plot_to_jsonl.jsonl");
+        exp_data.to_jsonl(&path).expect("jsonl should be written");
+        let content =
+            std::fs::read_to_string(&path).expect("jsonl should be read");
+        std::fs::remove_file(&path).expect("jsonl should be removed");
+        let summaries: Vec<ClientSummary> = content
+            .lines()
+            .map(|line| {
+                serde_json::from_str(line).expect("line should be a summary")
+            })
+            .collect();
+
+        // one line per region plus the global line
+        let regions_and_means: Vec<_> = summaries
+            .iter()
+            .map(|summary| (summary.region.clone(), summary.mean))
+            .collect();
+        assert_eq!(
+            regions_and_means,
+            vec![(Some(us_west1), 15.0), (Some(us_west2), 45.0), (None, 30.0)]
+        );
+        let throughputs: Vec<_> =
+            summaries.iter().map(|summary| summary.throughput).collect();
+        assert_eq!(throughputs, vec![2.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn throughput_per_shard() {
+        // 800 operations, all completing within the same second
+        let mut global_client_metrics = ClientData::new();
+        for _ in 0..800 {
+            global_client_metrics.record(Duration::from_millis(10), 500);
+        }
+        let exp_data = ExperimentData::new(
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            global_client_metrics,
+        );
+        assert_eq!(exp_data.global_client_throughput, 800.0);
+
+        // with a single shard, the throughput is not normalized
+        assert_eq!(exp_data.throughput_per_shard(&exp_config(1)), 800.0);
+        // with 4 shards, each shard contributes with a fourth of it
+        assert_eq!(exp_data.throughput_per_shard(&exp_config(4)), 200.0);
+    }
+}
@@ -6,7 +6,7 @@ mod results_db;
 // Re-exports.
 pub use compress::{DstatCompress, LatencyPrecision, MicrosHistogramCompress};
 pub use dstat::Dstat;
-pub use exp_data::ExperimentData;
+pub use exp_data::{ClientSummary, ExperimentData};
 pub use results_db::ResultsDB;
 
 use fantoch::client::KeyGen;
@@ -1,4 +1,4 @@
-use fantoch::planet::Region;
+use fantoch::planet::{Continent, Region};
 use fantoch_exp::Protocol;
 
 pub struct PlotFmt;
@@ -17,6 +17,19 @@ impl PlotFmt {
         }
     }
 
+    pub fn continent_color(continent: Continent) -> &'static str {
+        match continent {
+            Continent::NorthAmerica => "#2980b9",
+            Continent::SouthAmerica => "#27ae60",
+            Continent::Europe => "#c0392b",
+            Continent::Asia => "#f1c40f",
+            Continent::Oceania => "#8e44ad",
+            Continent::Africa => "#e67e22",
+            Continent::MiddleEast => "#16a085",
+            Continent::Unknown => "#576574",
+        }
+    }
+
     pub fn protocol_name(protocol: Protocol) -> &'static str {
         match protocol {
             Protocol::AtlasLocked => "Atlas",
@@ -137,4 +150,73 @@ impl PlotFmt {
     pub fn linewidth(_f: usize) -> String {
         "1.6".to_string()
     }
+
+    // Color of each protocol message kind (e.g. "MCollect") in stacked
+    // message-count bars; unknown kinds get a color derived from their name,
+    // so that they're also consistent across plots.
+    pub fn message_type_color(kind: &str) -> String {
+        match kind {
+            "MCollect" => "#27ae60",
+            "MCollectAck" => "#2ecc71",
+            "MCommit" => "#2980b9",
+            "MConsensus" => "#f1c40f",
+            "MConsensusAck" => "#e67e22",
+            "MForwardSubmit" => "#8e44ad",
+            "MAccept" => "#c0392b",
+            "MAccepted" => "#e74c3c",
+            "MChosen" => "#34495e",
+            "MStable" => "#95a5a6",
+            _ => return Self::hash_color(kind),
+        }
+        .to_string()
+    }
+
+    fn hash_color(name: &str) -> String {
+        // FNV-1a hash, which (unlike `DefaultHasher`) is guaranteed to be the
+        // same across Rust versions
+        let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("#{:06x}", hash & 0xffffff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_name_round_trip() {
+        for protocol in Protocol::all() {
+            let name = PlotFmt::protocol_name(protocol);
+            assert_eq!(name.parse::<Protocol>(), Ok(protocol));
+            assert_eq!(name.to_lowercase().parse::<Protocol>(), Ok(protocol));
+            assert_eq!(protocol.binary().parse::<Protocol>(), Ok(protocol));
+        }
+        assert!("Paxos".parse::<Protocol>().is_err());
+    }
+
+    #[test]
+    fn message_type_color() {
+        let kinds = vec!["MCollect", "MCommit", "MFoo", "MBar"];
+
+        // the same kind always maps to the same color
+        for kind in kinds.iter() {
+            assert_eq!(
+                PlotFmt::message_type_color(kind),
+                PlotFmt::message_type_color(kind)
+            );
+        }
+        assert_eq!(PlotFmt::message_type_color("MCollect"), "#27ae60");
+        assert_eq!(PlotFmt::message_type_color("MFoo"), "#548060");
+
+        // and distinct kinds map to distinct colors
+        let mut colors: Vec<_> = kinds
+            .iter()
+            .map(|kind| PlotFmt::message_type_color(kind))
+            .collect();
+        colors.sort();
+        colors.dedup();
+        assert_eq!(colors.len(), kinds.len());
+    }
 }
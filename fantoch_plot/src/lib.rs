@@ -6,7 +6,9 @@ mod fmt;
 pub mod plot;
 
 // Re-exports.
-pub use db::{ExperimentData, LatencyPrecision, ResultsDB, Search};
+pub use db::{
+    ClientSummary, ExperimentData, LatencyPrecision, ResultsDB, Search,
+};
 pub use fmt::PlotFmt;
 
 #[cfg(feature = "pyo3")]
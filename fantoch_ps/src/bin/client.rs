@@ -10,10 +10,9 @@ use std::time::Duration;
 const RANGE_SEP: &str = "-";
 const DEFAULT_KEYS_PER_COMMAND: usize = 1;
 const DEFAULT_SHARD_COUNT: usize = 1;
-const DEFAULT_KEY_GEN: KeyGen = KeyGen::ConflictPool {
-    conflict_rate: 100,
-    pool_size: 1,
-};
+const DEFAULT_KEY_GEN: &str = "conflict_pool";
+const DEFAULT_CONFLICT_RATE: usize = 100;
+const DEFAULT_POOL_SIZE: usize = 1;
 const DEFAULT_COMMANDS_PER_CLIENT: usize = 1000;
 const DEFAULT_READ_ONLY_PERCENTAGE: usize = 0;
 const DEFAULT_PAYLOAD_SIZE: usize = 100;
@@ -105,7 +104,35 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
             Arg::new("key_gen")
                 .long("key_gen")
                 .value_name("KEY_GEN")
-                .help("representation of a key generator; possible values 'conflict_pool,100,1' where 100 is the conflict rate and 1 the pool size, or 'zipf,1.3,10000' where 1.3 is the zipf coefficient (which should be non-zero) and 10000 the number of keys (per shard) in the distribution; default: 'conflict_rate,100,1'")
+                .help("representation of a key generator; possible values 'conflict_pool' (configured with '--conflict_rate' and '--pool_size') or 'zipf' (configured with '--zipf_coefficient' and '--keys_per_shard'); the inline forms 'conflict_pool,100,1' where 100 is the conflict rate and 1 the pool size, or 'zipf,1.3,10000' where 1.3 is the zipf coefficient (which should be non-zero) and 10000 the number of keys (per shard) in the distribution are also supported (in which case the dedicated arguments can't be set); default: 'conflict_pool'")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("conflict_rate")
+                .long("conflict_rate")
+                .value_name("CONFLICT_RATE")
+                .help("conflict rate of the 'conflict_pool' key generator (also used if no key generator is set); default: 100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("pool_size")
+                .long("pool_size")
+                .value_name("POOL_SIZE")
+                .help("pool size of the 'conflict_pool' key generator (also used if no key generator is set); default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("zipf_coefficient")
+                .long("zipf_coefficient")
+                .value_name("ZIPF_COEFFICIENT")
+                .help("coefficient of the 'zipf' key generator (which should be non-zero); required if the key generator is 'zipf'")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("keys_per_shard")
+                .long("keys_per_shard")
+                .value_name("KEYS_PER_SHARD")
+                .help("number of keys (per shard) in the distribution of the 'zipf' key generator; required if the key generator is 'zipf'")
                 .takes_value(true),
         )
         .arg(
@@ -211,9 +238,16 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
     let ids = parse_id_range(matches.value_of("ids"));
     let addresses = parse_addresses(matches.value_of("addresses"));
     let interval = parse_interval(matches.value_of("interval"));
+    let key_gen = parse_key_gen(
+        matches.value_of("key_gen"),
+        matches.value_of("conflict_rate"),
+        matches.value_of("pool_size"),
+        matches.value_of("zipf_coefficient"),
+        matches.value_of("keys_per_shard"),
+    );
     let workload = parse_workload(
         matches.value_of("shard_count"),
-        matches.value_of("key_gen"),
+        key_gen,
         matches.value_of("keys_per_command"),
         matches.value_of("commands_per_client"),
         matches.value_of("read_only_percentage"),
@@ -306,14 +340,13 @@ fn parse_interval(interval: Option<&str>) -> Option<Duration> {
 
 fn parse_workload(
     shard_count: Option<&str>,
-    key_gen: Option<&str>,
+    key_gen: KeyGen,
     keys_per_command: Option<&str>,
     commands_per_client: Option<&str>,
     read_only_percentage: Option<&str>,
     payload_size: Option<&str>,
 ) -> Workload {
     let shard_count = parse_shard_count(shard_count);
-    let key_gen = parse_key_gen(key_gen);
     let keys_per_command = parse_keys_per_command(keys_per_command);
     let commands_per_client = parse_commands_per_client(commands_per_client);
     let read_only_percentage = parse_read_only_percentage(read_only_percentage);
@@ -349,45 +382,87 @@ fn parse_shard_count(number: Option<&str>) -> usize {
         .unwrap_or(DEFAULT_SHARD_COUNT)
 }
 
-fn parse_key_gen(key_gen: Option<&str>) -> KeyGen {
-    key_gen
-        .map(|key_gen| {
-            let parts: Vec<_>= key_gen.split(',').collect();
-            match parts.len() {
-                2 | 3 => (),
-                _ => panic!("invalid specification of key generator: {:?}", key_gen)
-            };
-            match parts[0] {
-                "conflict_pool" => {
-                    if parts.len() != 3 {
-                        panic!("conflict_pool key generator takes two arguments");
-                    }
-                    let conflict_rate = parts[1]
-                        .parse::<usize>()
-                        .expect("conflict rate should be a number");
-                    let pool_size = parts[2]
+fn parse_key_gen(
+    key_gen: Option<&str>,
+    conflict_rate: Option<&str>,
+    pool_size: Option<&str>,
+    zipf_coefficient: Option<&str>,
+    keys_per_shard: Option<&str>,
+) -> KeyGen {
+    // if no key generator is set, the arguments configure the default one
+    let key_gen = key_gen.unwrap_or(DEFAULT_KEY_GEN);
+    let parts: Vec<_> = key_gen.split(',').collect();
+    let kgen = parts[0];
+    // the key generator is configured either with its dedicated arguments or
+    // inline, but not both
+    let (first, second) = match parts.len() {
+        1 => match kgen {
+            "conflict_pool" => {
+                assert!(
+                    zipf_coefficient.is_none() && keys_per_shard.is_none(),
+                    "conflict_pool key generator takes no zipf arguments"
+                );
+                (conflict_rate, pool_size)
+            }
+            "zipf" => {
+                assert!(
+                    conflict_rate.is_none() && pool_size.is_none(),
+                    "zipf key generator takes no conflict_pool arguments"
+                );
+                (zipf_coefficient, keys_per_shard)
+            }
+            kgen => panic!("invalid key generator type: {}", kgen),
+        },
+        3 => {
+            assert!(
+                conflict_rate.is_none()
+                    && pool_size.is_none()
+                    && zipf_coefficient.is_none()
+                    && keys_per_shard.is_none(),
+                "key generator {:?} is already configured inline",
+                key_gen
+            );
+            (Some(parts[1]), Some(parts[2]))
+        }
+        _ => panic!("invalid specification of key generator: {:?}", key_gen),
+    };
+    match kgen {
+        "conflict_pool" => {
+            let conflict_rate = first
+                .map(|conflict_rate| {
+                    conflict_rate
                         .parse::<usize>()
-                        .expect("pool size should be a number");
-                    KeyGen::ConflictPool { conflict_rate, pool_size }
-                }
-                "zipf" => {
-                    if parts.len() != 3 {
-                        panic!("zipf key generator takes two arguments");
-                    }
-                    let coefficient = parts[1]
-                        .parse::<f64>()
-                        .expect("zipf coefficient should be a float");
-                    let keys_per_shard = parts[2]
+                        .expect("conflict rate should be a number")
+                })
+                .unwrap_or(DEFAULT_CONFLICT_RATE);
+            let pool_size = second
+                .map(|pool_size| {
+                    pool_size
                         .parse::<usize>()
-                        .expect("number of keys (per shard) in the zipf distribution should be a number");
-                        KeyGen::Zipf {
-                            coefficient, total_keys_per_shard: keys_per_shard
-                        }
-                }
-                kgen => panic!("invalid key generator type: {}", kgen),
+                        .expect("pool size should be a number")
+                })
+                .unwrap_or(DEFAULT_POOL_SIZE);
+            KeyGen::ConflictPool {
+                conflict_rate,
+                pool_size,
             }
-        })
-        .unwrap_or(DEFAULT_KEY_GEN)
+        }
+        "zipf" => {
+            let coefficient = first
+                .expect("zipf key generator requires a zipf coefficient")
+                .parse::<f64>()
+                .expect("zipf coefficient should be a float");
+            let total_keys_per_shard = second
+                .expect("zipf key generator requires the number of keys per shard")
+                .parse::<usize>()
+                .expect("number of keys (per shard) in the zipf distribution should be a number");
+            KeyGen::Zipf {
+                coefficient,
+                total_keys_per_shard,
+            }
+        }
+        kgen => panic!("invalid key generator type: {}", kgen),
+    }
 }
 
 fn parse_commands_per_client(number: Option<&str>) -> usize {
@@ -1,9 +1,12 @@
 use clap::{Arg, Command};
 use color_eyre::Report;
 use fantoch::config::Config;
+use fantoch::executor::KeyToExecutor;
 use fantoch::id::{ProcessId, ShardId};
 use fantoch::info;
+use fantoch::kvs::Key;
 use fantoch::protocol::Protocol;
+use fantoch::HashMap;
 use std::net::IpAddr;
 use std::time::Duration;
 
@@ -17,6 +20,7 @@ const DEFAULT_PORT: u16 = 3000;
 const DEFAULT_CLIENT_PORT: u16 = 4000;
 
 const DEFAULT_EXECUTE_AT_COMMIT: bool = false;
+const DEFAULT_EXECUTOR_FIFO_ORDERING: bool = false;
 const DEFAULT_EXECUTOR_CLEANUP_INTERVAL: Duration = Duration::from_millis(5);
 
 const DEFAULT_WORKERS: usize = 1;
@@ -52,6 +56,7 @@ type ProtocolArgs = (
     usize,
     usize,
     usize,
+    KeyToExecutor,
     usize,
     Option<String>,
     Option<Duration>,
@@ -82,6 +87,7 @@ where
         client_channel_buffer_size,
         workers,
         executors,
+        key_to_executor,
         multiplexing,
         execution_log,
         ping_interval,
@@ -106,6 +112,7 @@ where
         client_channel_buffer_size,
         workers,
         executors,
+        key_to_executor,
         multiplexing,
         execution_log,
         ping_interval,
@@ -217,6 +224,27 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("executor monitor pending interval (in milliseconds); if no value if set, pending commands are not monitored")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("executor_keys_per_range")
+                .long("executor_keys_per_range")
+                .value_name("EXECUTOR_KEYS_PER_RANGE")
+                .help("number of (numeric) keys in each range of keys routed to the same executor; if no value is set, keys are routed by their hash")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("executor_keys")
+                .long("executor_keys")
+                .value_name("EXECUTOR_KEYS")
+                .help("comma-separated list of keys routed to an explicitly given executor, in the form KEY-INDEX; keys not in the list are routed as if this wasn't set")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("executor_fifo_ordering")
+                .long("executor_fifo_ordering")
+                .value_name("EXECUTOR_FIFO_ORDERING")
+                .help("bool indicating whether commands that become ready at the same time are executed in the order they were added to the dependency graph; default: false")
+                .takes_value(true),
+        )
         .arg(
             Arg::new("gc_interval")
                 .long("gc_interval")
@@ -387,7 +415,7 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
     let addresses = parse_addresses(matches.value_of("addresses"));
 
     // parse config
-    let config = build_config(
+    let mut config = build_config(
         parse_n(matches.value_of("n")),
         parse_f(matches.value_of("f")),
         parse_shard_count(matches.value_of("shard_count")),
@@ -398,6 +426,12 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
         parse_executor_monitor_pending_interval(
             matches.value_of("executor_monitor_pending_interval"),
         ),
+        parse_executor_keys_per_range(
+            matches.value_of("executor_keys_per_range"),
+        ),
+        parse_executor_fifo_ordering(
+            matches.value_of("executor_fifo_ordering"),
+        ),
         parse_gc_interval(matches.value_of("gc_interval")),
         parse_leader(matches.value_of("leader")),
         parse_nfr(matches.value_of("nfr")),
@@ -424,7 +458,15 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
         matches.value_of("client_channel_buffer_size"),
     );
     let workers = parse_workers(matches.value_of("workers"));
+    // key dependencies picked at runtime are parallel if there's more than
+    // one worker
+    config.set_parallel_key_deps(workers > 1);
     let executors = parse_executors(matches.value_of("executors"));
+    let key_to_executor =
+        match parse_executor_keys(matches.value_of("executor_keys")) {
+            Some(executors) => KeyToExecutor::Explicit(executors),
+            None => KeyToExecutor::from_config(&config),
+        };
     let multiplexing = parse_multiplexing(matches.value_of("multiplexing"));
     let execution_log = parse_execution_log(matches.value_of("execution_log"));
     let ping_interval = parse_ping_interval(matches.value_of("ping_interval"));
@@ -452,6 +494,7 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
     );
     info!("workers: {:?}", workers);
     info!("executors: {:?}", executors);
+    info!("key to executor: {:?}", key_to_executor);
     info!("multiplexing: {:?}", multiplexing);
     info!("execution log: {:?}", execution_log);
     info!("ping_interval: {:?}", ping_interval);
@@ -474,6 +517,7 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
         client_channel_buffer_size,
         workers,
         executors,
+        key_to_executor,
         multiplexing,
         execution_log,
         ping_interval,
@@ -570,6 +614,8 @@ pub fn build_config(
     execute_at_commit: bool,
     executor_cleanup_interval: Duration,
     executor_monitor_pending_interval: Option<Duration>,
+    executor_keys_per_range: Option<u64>,
+    executor_fifo_ordering: bool,
     gc_interval: Option<Duration>,
     leader: Option<ProcessId>,
     nfr: bool,
@@ -586,6 +632,8 @@ pub fn build_config(
     if let Some(interval) = executor_monitor_pending_interval {
         config.set_executor_monitor_pending_interval(interval);
     }
+    config.set_executor_keys_per_range(executor_keys_per_range);
+    config.set_executor_fifo_ordering(executor_fifo_ordering);
     if let Some(interval) = gc_interval {
         config.set_gc_interval(interval);
     }
@@ -657,6 +705,26 @@ pub fn parse_executor_monitor_pending_interval(
     })
 }
 
+pub fn parse_executor_keys_per_range(
+    keys_per_range: Option<&str>,
+) -> Option<u64> {
+    keys_per_range.map(|keys_per_range| {
+        keys_per_range
+            .parse::<u64>()
+            .expect("executor_keys_per_range should be a number")
+    })
+}
+
+pub fn parse_executor_fifo_ordering(fifo_ordering: Option<&str>) -> bool {
+    fifo_ordering
+        .map(|fifo_ordering| {
+            fifo_ordering
+                .parse::<bool>()
+                .expect("executor_fifo_ordering should be a bool")
+        })
+        .unwrap_or(DEFAULT_EXECUTOR_FIFO_ORDERING)
+}
+
 pub fn parse_gc_interval(gc_interval: Option<&str>) -> Option<Duration> {
     gc_interval.map(|gc_interval| {
         let ms = gc_interval
@@ -735,6 +803,25 @@ fn parse_executors(executors: Option<&str>) -> usize {
         .unwrap_or(DEFAULT_EXECUTORS)
 }
 
+fn parse_executor_keys(
+    executor_keys: Option<&str>,
+) -> Option<HashMap<Key, usize>> {
+    executor_keys.map(|executor_keys| {
+        executor_keys
+            .split(LIST_SEP)
+            .map(|entry| match entry.rsplit_once('-') {
+                Some((key, index)) => {
+                    let index = index
+                        .parse::<usize>()
+                        .expect("executor index should be a number");
+                    (key.to_string(), index)
+                }
+                None => panic!("invalid executor key: {:?}", entry),
+            })
+            .collect()
+    })
+}
+
 fn parse_multiplexing(multiplexing: Option<&str>) -> usize {
     multiplexing
         .map(|multiplexing| {
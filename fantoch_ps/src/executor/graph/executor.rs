@@ -1,20 +1,23 @@
 use crate::executor::graph::DependencyGraph;
 use crate::protocol::common::graph::Dependency;
-use fantoch::command::Command;
+use fantoch::command::{Command, PhaseTimes};
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::executor::{
     ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
 };
-use fantoch::id::{Dot, ProcessId, ShardId};
-use fantoch::kvs::KVStore;
+use fantoch::hash_map::Entry;
+use fantoch::id::{Dot, ProcessId, Rifl, ShardId};
+use fantoch::kvs::{KVOp, KVStore, Key};
 use fantoch::protocol::MessageIndex;
 use fantoch::time::SysTime;
-use fantoch::HashSet;
 use fantoch::{debug, trace};
+use fantoch::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt;
 use std::iter::FromIterator;
+use threshold::VClock;
 
 #[derive(Clone)]
 pub struct GraphExecutor {
@@ -24,8 +27,23 @@ pub struct GraphExecutor {
     config: Config,
     graph: DependencyGraph,
     store: KVStore,
+    // if set, commands are projected on these shards (and ignored if they
+    // don't access any of them)
+    replicated_shards: Option<HashSet<ShardId>>,
     to_clients: VecDeque<ExecutorResult>,
     to_executors: Vec<(ShardId, GraphExecutionInfo)>,
+    // if writes are rate limited, the time (in micros) after which each key
+    // can be written again
+    next_write_micros: HashMap<Key, u64>,
+    // commands delayed by the write rate limit (indexed by the order in which
+    // they were delayed), along with their dot and whether they were executed
+    // at commit time, and the delayed commands on each key, in that order
+    delayed: BTreeMap<u64, (Dot, Command, bool)>,
+    delayed_per_key: HashMap<Key, VecDeque<u64>>,
+    next_delayed_id: u64,
+    // time (in millis) at which each command not yet executed was committed
+    // (i.e. handed to this executor)
+    commit_times: HashMap<Rifl, u64>,
 }
 
 impl Executor for GraphExecutor {
@@ -34,8 +52,16 @@ impl Executor for GraphExecutor {
     fn new(process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
         // this value will be overwritten
         let executor_index = 0;
-        let graph = DependencyGraph::new(process_id, shard_id, &config);
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let graph = match config.executor_graph_capacity() {
+            Some(capacity) => DependencyGraph::with_capacity(
+                process_id, shard_id, &config, capacity,
+            ),
+            None => DependencyGraph::new(process_id, shard_id, &config),
+        };
+        let store = KVStore::with_mode(
+            config.executor_monitor_execution_order(),
+            config.storage_mode(),
+        );
         let to_clients = Default::default();
         let to_executors = Default::default();
         Self {
@@ -45,8 +71,14 @@ impl Executor for GraphExecutor {
             config,
             graph,
             store,
+            replicated_shards: None,
             to_clients,
             to_executors,
+            next_write_micros: HashMap::new(),
+            delayed: BTreeMap::new(),
+            delayed_per_key: HashMap::new(),
+            next_delayed_id: 0,
+            commit_times: HashMap::new(),
         }
     }
 
@@ -56,9 +88,13 @@ impl Executor for GraphExecutor {
     }
 
     fn cleanup(&mut self, time: &dyn SysTime) {
+        self.execute_delayed(time);
         if self.config.shard_count() > 1 {
             self.graph.cleanup(time);
             self.fetch_actions(time);
+        } else if self.config.executor_execute_chunk_size().is_some() {
+            // execute the next chunk of ready commands
+            self.fetch_commands_to_execute(time);
         }
     }
 
@@ -66,11 +102,35 @@ impl Executor for GraphExecutor {
         self.graph.monitor_pending(time);
     }
 
+    fn set_replicated_shards(&mut self, shards: HashSet<ShardId>) {
+        self.replicated_shards = Some(shards);
+    }
+
     fn handle(&mut self, info: GraphExecutionInfo, time: &dyn SysTime) {
         match info {
             GraphExecutionInfo::Add { dot, cmd, deps } => {
-                if self.config.execute_at_commit() {
-                    self.execute(cmd);
+                let cmd = if let Some(cmd) = self.project(cmd) {
+                    cmd
+                } else {
+                    trace!(
+                        "p{}: @{} GraphExecutor::handle ignoring {:?} | time = {}",
+                        self.process_id,
+                        self.executor_index,
+                        dot,
+                        time.millis()
+                    );
+                    return;
+                };
+                // ignore duplicate deliveries of commands already added (or
+                // executed at commit time)
+                if self.graph.is_duplicate(dot) {
+                    return;
+                }
+                self.commit_times.insert(cmd.rifl(), time.millis());
+                if self.config.execute_at_commit() && !cmd.is_snapshot_read() {
+                    // snapshot reads are still ordered by the graph, so it's
+                    // told once this command is executed (see `do_execute`)
+                    self.execute(dot, cmd, true, time);
                 } else {
                     // handle new command
                     let deps = Vec::from_iter(deps);
@@ -108,12 +168,65 @@ impl Executor for GraphExecutor {
         &self.graph.metrics()
     }
 
+    fn reset_metrics(&mut self) {
+        self.graph.reset_metrics();
+    }
+
     fn monitor(&self) -> Option<ExecutionOrderMonitor> {
         self.store.monitor().cloned()
     }
 }
 
 impl GraphExecutor {
+    /// Returns the number of commands that were committed but not yet
+    /// executed (see `DependencyGraph::pending_count`), including the ones
+    /// delayed by the write rate limit.
+    pub fn pending_count(&self) -> usize {
+        self.graph.pending_count() + self.delayed.len()
+    }
+
+    /// Returns the dependency edges between commands pending in the graph
+    /// (see `DependencyGraph::dependency_edges`).
+    pub fn dependency_edges(&self) -> Vec<(Dot, Dot)> {
+        self.graph.dependency_edges()
+    }
+
+    /// Returns the commands pending in the graph that access `key` (see
+    /// `DependencyGraph::pending_on_key`).
+    pub fn pending_on_key(&self, key: &Key) -> Vec<Dot> {
+        self.graph.pending_on_key(key)
+    }
+
+    /// Returns the commands that were committed but never executed (see
+    /// `DependencyGraph::drain_remaining`), followed by the ones still delayed
+    /// by the write rate limit.
+    pub fn drain_remaining(&mut self) -> Vec<Command> {
+        let mut remaining = self.graph.drain_remaining();
+        self.delayed_per_key.clear();
+        self.commit_times.clear();
+        remaining.extend(
+            std::mem::take(&mut self.delayed)
+                .into_values()
+                .map(|(_, cmd, _)| cmd),
+        );
+        remaining
+    }
+
+    /// Compacts the executed clock up to `below` (see
+    /// `DependencyGraph::prune_executed`).
+    pub fn prune_executed(&mut self, below: &VClock<ProcessId>) {
+        self.graph.prune_executed(below)
+    }
+
+    // Projects `cmd` on the shards replicated by this executor, returning
+    // `None` if it doesn't access any of them.
+    fn project(&self, cmd: Command) -> Option<Command> {
+        match &self.replicated_shards {
+            Some(shards) => cmd.project_shards(shards),
+            None => Some(cmd),
+        }
+    }
+
     fn fetch_actions(&mut self, time: &dyn SysTime) {
         self.fetch_commands_to_execute(time);
         if self.config.shard_count() > 1 {
@@ -123,20 +236,31 @@ impl GraphExecutor {
         }
     }
 
-    fn fetch_commands_to_execute(&mut self, _time: &dyn SysTime) {
-        // get more commands that are ready to be executed
-        while let Some(cmd) = self.graph.command_to_execute() {
-            trace!(
-                "p{}: @{} GraphExecutor::comands_to_execute {:?} | time = {}",
-                self.process_id,
-                self.executor_index,
-                cmd.rifl(),
-                _time.millis()
-            );
-            self.execute(cmd);
+    fn fetch_commands_to_execute(&mut self, time: &dyn SysTime) {
+        // get more commands that are ready to be executed; if there's a
+        // maximum chunk size, the remaining ones are executed on cleanup
+        if let Some(chunk_size) = self.config.executor_execute_chunk_size() {
+            for (dot, cmd) in self.graph.to_execute_chunked(chunk_size) {
+                self.execute_ready(dot, cmd, time);
+            }
+        } else {
+            while let Some((dot, cmd)) = self.graph.command_to_execute() {
+                self.execute_ready(dot, cmd, time);
+            }
         }
     }
 
+    fn execute_ready(&mut self, dot: Dot, cmd: Command, time: &dyn SysTime) {
+        trace!(
+            "p{}: @{} GraphExecutor::comands_to_execute {:?} | time = {}",
+            self.process_id,
+            self.executor_index,
+            cmd.rifl(),
+            time.millis()
+        );
+        self.execute(dot, cmd, false, time);
+    }
+
     fn fetch_to_executors(&mut self, _time: &dyn SysTime) {
         if let Some(added) = self.graph.to_executors() {
             debug!(
@@ -161,8 +285,24 @@ impl GraphExecutor {
                 dots,
                 _time.millis()
             );
-            let request = GraphExecutionInfo::request(self.shard_id, dots);
-            self.to_executors.push((to, request));
+            // requests to the same shard are already coalesced by the graph;
+            // here we only split them if there's a maximum batch size
+            match self.config.executor_max_request_batch() {
+                Some(max_batch) if dots.len() > max_batch => {
+                    let dots = Vec::from_iter(dots);
+                    for batch in dots.chunks(max_batch) {
+                        let batch = batch.iter().cloned().collect();
+                        let request =
+                            GraphExecutionInfo::request(self.shard_id, batch);
+                        self.to_executors.push((to, request));
+                    }
+                }
+                _ => {
+                    let request =
+                        GraphExecutionInfo::request(self.shard_id, dots);
+                    self.to_executors.push((to, request));
+                }
+            }
         }
     }
 
@@ -181,10 +321,169 @@ impl GraphExecutor {
         }
     }
 
-    fn execute(&mut self, cmd: Command) {
-        // execute the command
-        let results = cmd.execute(self.shard_id, &mut self.store);
-        self.to_clients.extend(results);
+    // Executes the command with this `dot`. If the command was not added to
+    // the graph (i.e. if it's executed at commit time), the graph is told once
+    // it's executed.
+    fn execute(
+        &mut self,
+        dot: Dot,
+        cmd: Command,
+        at_commit: bool,
+        time: &dyn SysTime,
+    ) {
+        if self.config.executor_max_writes_per_sec().is_some() {
+            // if writes are rate limited, the command may have to be delayed
+            let id = self.delay(dot, cmd, at_commit);
+            self.execute_delayed_from(BTreeSet::from_iter(vec![id]), time);
+        } else {
+            self.do_execute(dot, cmd, at_commit, time);
+        }
+    }
+
+    // Delays a command until its writes are within the per-key write rate
+    // limit, returning its identifier.
+    fn delay(&mut self, dot: Dot, cmd: Command, at_commit: bool) -> u64 {
+        let id = self.next_delayed_id;
+        self.next_delayed_id += 1;
+        for (key, _) in cmd.iter(self.shard_id) {
+            self.delayed_per_key
+                .entry(key.clone())
+                .or_default()
+                .push_back(id);
+        }
+        self.delayed.insert(id, (dot, cmd, at_commit));
+        id
+    }
+
+    // Executes the delayed commands whose writes are within the per-key write
+    // rate limit. Each key has a bucket with a single token, refilled every
+    // `1 / max_writes_per_sec` seconds. Commands that access a key on which
+    // an earlier command is delayed are also delayed, so that commands on the
+    // same key are still executed in order; thus, only the first delayed
+    // command on each key can be executed.
+    fn execute_delayed(&mut self, time: &dyn SysTime) {
+        let first = self
+            .delayed_per_key
+            .values()
+            .filter_map(|ids| ids.front())
+            .cloned()
+            .collect();
+        self.execute_delayed_from(first, time);
+    }
+
+    // Executes the delayed commands in `candidates`, and then the ones that
+    // become the first delayed command on some key, in the order in which
+    // they were delayed.
+    fn execute_delayed_from(
+        &mut self,
+        mut candidates: BTreeSet<u64>,
+        time: &dyn SysTime,
+    ) {
+        let max_writes = self.config.executor_max_writes_per_sec();
+        let write_interval_micros = match max_writes {
+            Some(max_writes) => 1_000_000 / max_writes,
+            None => return,
+        };
+        let now = time.micros();
+
+        while let Some(id) = candidates.iter().next().cloned() {
+            candidates.remove(&id);
+            let cmd = match self.delayed.get(&id) {
+                Some((_, cmd, _)) => cmd,
+                None => continue,
+            };
+
+            // check whether the command can be executed now
+            let can_execute = cmd.iter(self.shard_id).all(|(key, ops)| {
+                let first = self
+                    .delayed_per_key
+                    .get(key)
+                    .map_or(false, |ids| ids.front() == Some(&id));
+                let write = !ops.iter().all(KVOp::is_read);
+                let next_write = self
+                    .next_write_micros
+                    .get(key)
+                    .cloned()
+                    .unwrap_or_default();
+                first && !(write && next_write > now)
+            });
+            if !can_execute {
+                trace!(
+                    "p{}: @{} GraphExecutor::execute_delayed delaying {:?} | time = {}",
+                    self.process_id,
+                    self.executor_index,
+                    cmd.rifl(),
+                    time.millis()
+                );
+                continue;
+            }
+
+            let (dot, cmd, at_commit) = self
+                .delayed
+                .remove(&id)
+                .expect("delayed command should exist");
+            for (key, ops) in cmd.iter(self.shard_id) {
+                // consume the token of each key written
+                if !ops.iter().all(KVOp::is_read) {
+                    self.next_write_micros
+                        .insert(key.clone(), now + write_interval_micros);
+                }
+                // the next delayed command on this key (if any) may now be
+                // executed
+                if let Entry::Occupied(mut ids) =
+                    self.delayed_per_key.entry(key.clone())
+                {
+                    ids.get_mut().pop_front();
+                    match ids.get().front() {
+                        Some(next) => {
+                            candidates.insert(*next);
+                        }
+                        None => {
+                            ids.remove();
+                        }
+                    }
+                }
+            }
+            self.do_execute(dot, cmd, at_commit, time);
+        }
+    }
+
+    fn do_execute(
+        &mut self,
+        dot: Dot,
+        cmd: Command,
+        at_commit: bool,
+        time: &dyn SysTime,
+    ) {
+        // commands fetched from other shards were never committed here, so
+        // they're committed as they're executed
+        let execute_time = time.millis();
+        let commit_time = self
+            .commit_times
+            .remove(&cmd.rifl())
+            .unwrap_or(execute_time);
+        let phase_times = PhaseTimes::new(commit_time, execute_time);
+
+        // execute the command (which only happens as its results are
+        // collected)
+        let rifl = cmd.rifl();
+        let results =
+            cmd.execute_from(self.shard_id, dot.source(), &mut self.store);
+        command_span!(
+            "execute",
+            rifl,
+            time,
+            self.to_clients.extend(
+                results.map(|result| result.with_phase_times(phase_times))
+            )
+        );
+
+        // if the command was not added to the graph, let the graph know that
+        // it has been executed; if a snapshot read is pending on it, the read
+        // may now be executed
+        if at_commit && self.graph.mark_executed(dot, time) {
+            self.fetch_actions(time);
+        }
     }
 }
 
@@ -252,3 +551,493 @@ impl MessageIndex for GraphExecutionInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::common::graph::AccessKind;
+    use fantoch::executor::ExecutorMetricsKind;
+    use fantoch::id::Rifl;
+    use fantoch::kvs::KVOpResult;
+    use fantoch::time::SimTime;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn replicated_shards() {
+        let process_id = 1;
+        let shard_id = 0;
+        let config = Config::new(3, 1);
+        let time = SimTime::new();
+
+        // command only on shard 1 that depends on a command never added (so
+        // that, if not ignored, it stays pending)
+        let dot = Dot::new(2, 1);
+        let missing = Dependency {
+            dot: Dot::new(3, 1),
+            shards: Some(BTreeSet::from_iter(vec![shard_id])),
+            kind: AccessKind::default(),
+        };
+        let shard_1_ops =
+            HashMap::from_iter(vec![(String::from("A"), vec![KVOp::Put(10)])]);
+        let cmd = Command::new(
+            Rifl::new(1, 1),
+            HashMap::from_iter(vec![(1, shard_1_ops)]),
+        );
+        let add = GraphExecutionInfo::add(
+            dot,
+            cmd.clone(),
+            HashSet::from_iter(vec![missing.clone()]),
+        );
+
+        // by default, the command is added
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        executor.handle(add.clone(), &time);
+        assert_eq!(executor.drain_remaining(), vec![cmd]);
+
+        // but it's ignored if only shard 0 is replicated
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        executor.set_replicated_shards(HashSet::from_iter(vec![0]));
+        executor.handle(add, &time);
+        assert!(executor.drain_remaining().is_empty());
+
+        // a command on shards 0 and 1 is projected on shard 0
+        let shard_0_ops =
+            HashMap::from_iter(vec![(String::from("B"), vec![KVOp::Put(20)])]);
+        let shard_1_ops =
+            HashMap::from_iter(vec![(String::from("A"), vec![KVOp::Put(10)])]);
+        let cmd = Command::new(
+            Rifl::new(1, 2),
+            HashMap::from_iter(vec![(0, shard_0_ops), (1, shard_1_ops)]),
+        );
+        let add = GraphExecutionInfo::add(
+            Dot::new(2, 2),
+            cmd.clone(),
+            HashSet::from_iter(vec![missing]),
+        );
+        executor.handle(add, &time);
+        let remaining = executor.drain_remaining();
+        assert_eq!(remaining, vec![cmd.project(0).unwrap()]);
+        assert!(!remaining[0].replicated_by(&1));
+    }
+
+    #[test]
+    fn max_writes_per_sec() {
+        let process_id = 1;
+        let shard_id = 0;
+        let max_writes_per_sec = 100;
+        let mut config = Config::new(3, 1);
+        config.set_executor_max_writes_per_sec(max_writes_per_sec);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let mut time = SimTime::new();
+
+        // times (in millis) at which writes on each key were executed
+        let mut executed: HashMap<Key, Vec<u64>> = HashMap::new();
+        let record = |executor: &mut GraphExecutor,
+                      executed: &mut HashMap<Key, Vec<u64>>,
+                      time: &SimTime| {
+            while let Some(result) = executor.to_clients() {
+                executed
+                    .entry(result.unwrap_ready().key)
+                    .or_default().push(time.millis());
+            }
+        };
+
+        // during one second, key A is written every millisecond (which is
+        // above the limit), while key B is written every 100 milliseconds
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let mut sequence = 0;
+        let mut write =
+            |executor: &mut GraphExecutor, key: &Key, time: &SimTime| {
+                sequence += 1;
+                let dot = Dot::new(2, sequence);
+                let cmd = Command::from(
+                    Rifl::new(1, sequence),
+                    vec![(key.clone(), KVOp::Put(10))],
+                );
+                let add = GraphExecutionInfo::add(dot, cmd, HashSet::new());
+                executor.handle(add, time);
+            };
+        for millis in 0..1000 {
+            time.set_millis(millis);
+            write(&mut executor, &key_a, &time);
+            if millis % 100 == 0 {
+                write(&mut executor, &key_b, &time);
+            }
+            executor.cleanup(&time);
+            record(&mut executor, &mut executed, &time);
+        }
+
+        // keep cleaning up until all writes on key A are executed
+        let mut millis = 1000;
+        while executed.get(&key_a).map(Vec::len) != Some(1000) {
+            time.set_millis(millis);
+            executor.cleanup(&time);
+            record(&mut executor, &mut executed, &time);
+            millis += 1;
+        }
+
+        // writes on key A never exceed the limit
+        let times_a = executed.get(&key_a).expect("key A should be written");
+        assert_eq!(
+            times_a.iter().filter(|time| **time < 1000).count(),
+            max_writes_per_sec as usize
+        );
+        assert!(times_a.windows(2).all(|times| times[1] - times[0] >= 10));
+
+        // while writes on key B are not delayed
+        let times_b = executed.get(&key_b).expect("key B should be written");
+        assert_eq!(times_b, &(0..1000).step_by(100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn execute_chunk_size() {
+        let process_id = 1;
+        let shard_id = 0;
+        let chunk_size = 7;
+        let config = Config::new(3, 1);
+        let mut chunked_config = config;
+        chunked_config.set_executor_execute_chunk_size(chunk_size);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let mut chunked =
+            GraphExecutor::new(process_id, shard_id, chunked_config);
+        let time = SimTime::new();
+
+        // create a large SCC: each command depends on the next one, and the
+        // last one depends on the first one
+        let scc_size = 100;
+        let dots: Vec<_> = (1..=scc_size).map(|seq| Dot::new(2, seq)).collect();
+        for (index, dot) in dots.iter().enumerate() {
+            let next = Dependency {
+                dot: dots[(index + 1) % dots.len()],
+                shards: Some(BTreeSet::from_iter(vec![shard_id])),
+                kind: AccessKind::default(),
+            };
+            let cmd = Command::from(
+                Rifl::new(1, dot.sequence()),
+                vec![(String::from("A"), KVOp::Put(10))],
+            );
+            let add = GraphExecutionInfo::add(
+                *dot,
+                cmd,
+                HashSet::from_iter(vec![next]),
+            );
+            executor.handle(add.clone(), &time);
+            chunked.handle(add, &time);
+        }
+
+        // without a chunk size, the whole SCC is executed at once
+        let expected: Vec<_> = executor
+            .to_clients_iter()
+            .map(|result| result.rifl())
+            .collect();
+        assert_eq!(expected.len(), scc_size as usize);
+
+        // with a chunk size, at most that many commands are executed at once,
+        // and the remaining ones on the next cleanups
+        let mut executed = Vec::new();
+        loop {
+            let chunk: Vec<_> = chunked
+                .to_clients_iter()
+                .map(|result| result.rifl())
+                .collect();
+            assert!(chunk.len() <= chunk_size);
+            if chunk.is_empty() {
+                break;
+            }
+            executed.extend(chunk);
+            chunked.cleanup(&time);
+        }
+        assert_eq!(executed, expected);
+        assert_eq!(chunked.pending_count(), 0);
+    }
+
+    #[test]
+    fn dependency_edges() {
+        let process_id = 1;
+        let shard_id = 0;
+        let config = Config::new(3, 1);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+
+        // command 2 depends on command 1, which depends on command 3 (that
+        // is never added)
+        let dot_1 = Dot::new(1, 1);
+        let dot_2 = Dot::new(2, 1);
+        let dot_3 = Dot::new(3, 1);
+        let dep = |dot| Dependency {
+            dot,
+            shards: Some(BTreeSet::from_iter(vec![shard_id])),
+            kind: AccessKind::default(),
+        };
+        for (dot, dep_dot) in vec![(dot_2, dot_1), (dot_1, dot_3)] {
+            let cmd = Command::from(
+                Rifl::new(dot.source() as u64, dot.sequence()),
+                vec![(String::from("A"), KVOp::Put(10))],
+            );
+            let deps = HashSet::from_iter(vec![dep(dep_dot)]);
+            executor.handle(GraphExecutionInfo::add(dot, cmd, deps), &time);
+        }
+
+        // both commands are pending, but only the edge between them is
+        // reported
+        assert_eq!(executor.pending_count(), 2);
+        assert_eq!(executor.dependency_edges(), vec![(dot_2, dot_1)]);
+    }
+
+    #[test]
+    fn pending_on_key() {
+        let process_id = 1;
+        let shard_id = 0;
+        let config = Config::new(3, 1);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+
+        // command 1 accesses key A and command 2 keys A and B; both depend on
+        // command 3 (that is never added)
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let dot_1 = Dot::new(1, 1);
+        let dot_2 = Dot::new(2, 1);
+        let dot_3 = Dot::new(3, 1);
+        let dep = Dependency {
+            dot: dot_3,
+            shards: Some(BTreeSet::from_iter(vec![shard_id])),
+            kind: AccessKind::default(),
+        };
+        let adds = vec![
+            (dot_1, vec![key_a.clone()]),
+            (dot_2, vec![key_a.clone(), key_b.clone()]),
+        ];
+        for (dot, keys) in adds {
+            let cmd = Command::from(
+                Rifl::new(dot.source() as u64, dot.sequence()),
+                keys.into_iter().map(|key| (key, KVOp::Put(10))),
+            );
+            let deps = HashSet::from_iter(vec![dep.clone()]);
+            executor.handle(GraphExecutionInfo::add(dot, cmd, deps), &time);
+        }
+
+        let on_key = |executor: &GraphExecutor, key: &Key| {
+            BTreeSet::from_iter(executor.pending_on_key(key))
+        };
+        assert_eq!(
+            on_key(&executor, &key_a),
+            BTreeSet::from_iter(vec![dot_1, dot_2])
+        );
+        assert_eq!(on_key(&executor, &key_b), BTreeSet::from_iter(vec![dot_2]));
+        assert!(on_key(&executor, &String::from("C")).is_empty());
+    }
+
+    #[test]
+    fn drain_remaining_delayed() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        config.set_executor_max_writes_per_sec(1);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+
+        // two writes on the same key: the second one is delayed
+        let write = |sequence| {
+            Command::from(
+                Rifl::new(1, sequence),
+                vec![(String::from("A"), KVOp::Put(10))],
+            )
+        };
+        for sequence in 1..=2 {
+            let dot = Dot::new(2, sequence);
+            let add =
+                GraphExecutionInfo::add(dot, write(sequence), HashSet::new());
+            executor.handle(add, &time);
+        }
+        assert_eq!(executor.to_clients_iter().count(), 1);
+
+        // the delayed write is reported as remaining
+        assert_eq!(executor.drain_remaining(), vec![write(2)]);
+        executor.cleanup(&time);
+        assert_eq!(executor.to_clients_iter().count(), 0);
+    }
+
+    #[test]
+    fn snapshot_read_at_commit() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        config.set_execute_at_commit(true);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let value = 10;
+        let write = |sequence, key: &Key| {
+            Command::from(
+                Rifl::new(1, sequence),
+                vec![(key.clone(), KVOp::Put(value))],
+            )
+        };
+        let dep = |dot| Dependency {
+            dot,
+            shards: Some(BTreeSet::from_iter(vec![shard_id])),
+            kind: AccessKind::default(),
+        };
+
+        // the write on A is executed at commit time
+        let write_a_dot = Dot::new(2, 1);
+        let add = GraphExecutionInfo::add(
+            write_a_dot,
+            write(1, &key_a),
+            HashSet::new(),
+        );
+        executor.handle(add, &time);
+        assert_eq!(executor.to_clients_iter().count(), 1);
+
+        // but a snapshot read that depends on the writes on A and B waits for
+        // the write on B
+        let write_b_dot = Dot::new(2, 2);
+        let read_rifl = Rifl::new(3, 1);
+        let read = Command::snapshot_read(
+            read_rifl,
+            vec![key_a.clone(), key_b.clone()],
+        );
+        let deps = HashSet::from_iter(vec![dep(write_a_dot), dep(write_b_dot)]);
+        let add = GraphExecutionInfo::add(Dot::new(3, 1), read, deps);
+        executor.handle(add, &time);
+        assert_eq!(executor.to_clients_iter().count(), 0);
+        assert_eq!(executor.pending_count(), 1);
+
+        // once the write on B is executed, the read observes both writes
+        let add = GraphExecutionInfo::add(
+            write_b_dot,
+            write(2, &key_b),
+            HashSet::new(),
+        );
+        executor.handle(add, &time);
+        let read_results: HashMap<_, _> = executor
+            .to_clients_iter()
+            .filter(|result| result.rifl() == read_rifl)
+            .map(|result| result.unwrap_ready())
+            .map(|result| (result.key, result.partial_results))
+            .collect();
+        assert_eq!(read_results.len(), 2);
+        let expected = vec![KVOpResult::Value(Some(value))];
+        assert!(read_results
+            .values()
+            .all(|partial_results| partial_results == &expected));
+        assert_eq!(executor.pending_count(), 0);
+    }
+
+    #[test]
+    fn duplicate_at_commit() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        config.set_execute_at_commit(true);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+
+        // deliver the same command twice
+        let dot = Dot::new(2, 1);
+        let rifl = Rifl::new(1, 1);
+        let cmd = Command::from(rifl, vec![(String::from("A"), KVOp::Add(10))]);
+        for _ in 0..2 {
+            let add = GraphExecutionInfo::add(dot, cmd.clone(), HashSet::new());
+            executor.handle(add, &time);
+        }
+
+        // it's only executed once
+        let rifls: Vec<_> = executor
+            .to_clients_iter()
+            .map(|result| result.rifl())
+            .collect();
+        assert_eq!(rifls, vec![rifl]);
+        assert_eq!(
+            executor
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::Duplicate),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn executed_at_commit_notified_on_cleanup() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        config.set_shard_count(2);
+        config.set_execute_at_commit(true);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+
+        // a write executed at commit time on which nothing is pending
+        let dot = Dot::new(2, 1);
+        let cmd = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Put(10))],
+        );
+        let add = GraphExecutionInfo::add(dot, cmd, HashSet::new());
+        executor.handle(add, &time);
+        assert_eq!(executor.to_clients_iter().count(), 1);
+
+        // doesn't notify the other executors right away, but on cleanup
+        assert!(executor.to_executors().is_none());
+        executor.cleanup(&time);
+        let executed =
+            GraphExecutionInfo::executed(HashSet::from_iter(vec![dot]));
+        assert_eq!(executor.to_executors(), Some((shard_id, executed)));
+    }
+
+    #[test]
+    fn snapshot_read_waits_for_delayed_write_at_commit() {
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        config.set_execute_at_commit(true);
+        config.set_executor_max_writes_per_sec(1);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let mut time = SimTime::new();
+
+        let key = String::from("A");
+        let write = |sequence| {
+            Command::from(
+                Rifl::new(1, sequence),
+                vec![(key.clone(), KVOp::Put(10))],
+            )
+        };
+
+        // two writes on the same key are executed at commit time, but the
+        // second one is delayed
+        for sequence in 1..=2 {
+            let dot = Dot::new(2, sequence);
+            let deps = HashSet::new();
+            let add = GraphExecutionInfo::add(dot, write(sequence), deps);
+            executor.handle(add, &time);
+        }
+        assert_eq!(executor.to_clients_iter().count(), 1);
+
+        // a snapshot read that depends on the delayed write waits for it
+        let read_rifl = Rifl::new(3, 1);
+        let read = Command::snapshot_read(read_rifl, vec![key.clone()]);
+        let dep = Dependency {
+            dot: Dot::new(2, 2),
+            shards: Some(BTreeSet::from_iter(vec![shard_id])),
+            kind: AccessKind::default(),
+        };
+        let deps = HashSet::from_iter(vec![dep]);
+        let add = GraphExecutionInfo::add(Dot::new(3, 1), read, deps);
+        executor.handle(add, &time);
+        assert_eq!(executor.to_clients_iter().count(), 0);
+        assert_eq!(executor.pending_count(), 2);
+
+        // once the write is executed, so is the read
+        time.set_millis(1000);
+        executor.cleanup(&time);
+        let rifls: Vec<_> = executor
+            .to_clients_iter()
+            .map(|result| result.rifl())
+            .collect();
+        assert_eq!(rifls, vec![Rifl::new(1, 2), read_rifl]);
+        assert_eq!(executor.pending_count(), 0);
+    }
+}
@@ -0,0 +1,300 @@
+use super::DependencyGraph;
+use crate::protocol::common::graph::{AccessKind, Dependency};
+use fantoch::command::Command;
+use fantoch::config::Config;
+use fantoch::id::{ClientId, Dot, Rifl, ShardId};
+use fantoch::kvs::{KVOp, Key};
+use fantoch::time::RunTime;
+use fantoch::{HashMap, HashSet};
+use permutator::{Combination, Permutation};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::FromIterator;
+
+/// Commands to be added to the queue: their dot, the keys they access (a
+/// single `CONF` key if none), and the dots they depend on.
+pub type Adds = Vec<(Dot, Option<BTreeSet<Key>>, HashSet<Dot>)>;
+
+/// How the dependencies of commands are pruned, assuming that conflicts are
+/// transitive (see `transitive_conflicts_assumption_regression_test_2`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pruning {
+    /// All dependencies are kept.
+    None,
+    /// Only the highest dependency from each process is kept. This makes the
+    /// total order depend on the order in which commands are added.
+    HighestPerProcess,
+    /// Only the highest dependency from each process on each key is kept.
+    /// Since commands from the same process on the same key depend on each
+    /// other, the dependencies dropped are still reachable.
+    HighestPerKey,
+}
+
+pub fn dep(dot: Dot, shard_id: ShardId) -> Dependency {
+    Dependency {
+        dot,
+        shards: Some(BTreeSet::from_iter(vec![shard_id])),
+        kind: AccessKind::default(),
+    }
+}
+
+/// Generates (using `seed`) random commands with dependencies consistent with
+/// the conflicts between them, and checks that all permutations of their
+/// insertion in the queue yield the same total order. On failure, the seed used
+/// is reported so that it can be re-run.
+pub fn fuzz_queue(n: usize, events_per_process: usize, seed: u64) {
+    if let Err(e) = try_fuzz_queue(n, events_per_process, seed, Pruning::None) {
+        panic!("fuzz_queue failed with seed {}: {}", seed, e);
+    }
+}
+
+/// Same as `fuzz_queue`, but returns an error (instead of panicking) if some
+/// permutation yields a different total order. Dependencies are pruned as
+/// set by `pruning`.
+pub fn try_fuzz_queue(
+    n: usize,
+    events_per_process: usize,
+    seed: u64,
+    pruning: Pruning,
+) -> Result<(), String> {
+    let shard_id = 0;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let args = random_adds(shard_id, n, events_per_process, pruning, &mut rng);
+    try_shuffle_it(n, args)
+}
+
+pub fn random_adds(
+    shard_id: ShardId,
+    n: usize,
+    events_per_process: usize,
+    pruning: Pruning,
+    rng: &mut impl Rng,
+) -> Adds {
+    let mut possible_keys: Vec<_> =
+        ('A'..='D').map(|key| key.to_string()).collect();
+
+    // create dots
+    let dots: Vec<_> = fantoch::util::process_ids(shard_id, n)
+        .flat_map(|process_id| {
+            (1..=events_per_process)
+                .map(move |event| Dot::new(process_id, event as u64))
+        })
+        .collect();
+
+    // compute keys and empty deps
+    let deps: HashMap<_, _> = dots
+        .clone()
+        .into_iter()
+        .map(|dot| {
+            // select two random keys from the set of possible keys:
+            // - this makes sure that the conflict relation is not transitive
+            possible_keys.shuffle(rng);
+            let mut keys = BTreeSet::new();
+            assert!(keys.insert(possible_keys[0].clone()));
+            assert!(keys.insert(possible_keys[1].clone()));
+            // create empty deps
+            let deps = HashSet::new();
+            (dot, (Some(keys), RefCell::new(deps)))
+        })
+        .collect();
+
+    // for each pair of dots
+    dots.combination(2).for_each(|dots| {
+        let left = dots[0];
+        let right = dots[1];
+
+        // find their data
+        let (left_keys, left_clock) =
+            deps.get(left).expect("left dot data must exist");
+        let (right_keys, right_clock) =
+            deps.get(right).expect("right dot data must exist");
+
+        // unwrap keys
+        let left_keys = left_keys.as_ref().expect("left keys should exist");
+        let right_keys = right_keys.as_ref().expect("right keys should exist");
+
+        // check if the commands conflict (i.e. if the keys being accessed
+        // intersect)
+        let conflict = left_keys.intersection(&right_keys).next().is_some();
+
+        // if the commands conflict, then make sure at least one is a
+        // dependency of the other
+        if conflict {
+            // borrow their clocks mutably
+            let mut left_deps = left_clock.borrow_mut();
+            let mut right_deps = right_clock.borrow_mut();
+
+            if left.source() == right.source() {
+                // if dots belong to the same process, make the latest depend
+                // on the oldest
+                match left.sequence().cmp(&right.sequence()) {
+                    Ordering::Less => right_deps.insert(*left),
+                    Ordering::Greater => left_deps.insert(*right),
+                    _ => unreachable!("dots must be different"),
+                };
+            } else {
+                // otherwise, make them depend on each other (maybe both ways)
+                match rng.gen_range(0..3) {
+                    0 => {
+                        // left depends on right
+                        left_deps.insert(*right);
+                    }
+                    1 => {
+                        // right depends on left
+                        right_deps.insert(*left);
+                    }
+                    2 => {
+                        // both
+                        left_deps.insert(*right);
+                        right_deps.insert(*left);
+                    }
+                    _ => panic!("out-of-bounds random number"),
+                }
+            }
+        }
+    });
+
+    // keys accessed by each command
+    let keys: HashMap<_, _> = deps
+        .iter()
+        .map(|(dot, (keys, _))| {
+            (*dot, keys.clone().expect("keys should exist"))
+        })
+        .collect();
+    let mut args: Vec<_> = deps
+        .into_iter()
+        .map(|(dot, (dot_keys, deps_cell))| {
+            let deps = deps_cell.into_inner();
+            let dot_keys_ref = dot_keys.as_ref().expect("keys should exist");
+            // checks whether `other` is a higher dependency than `dep` from
+            // the same process
+            let higher = |dep: &Dot, other: &Dot| {
+                other.source() == dep.source()
+                    && other.sequence() > dep.sequence()
+            };
+            let deps = match pruning {
+                Pruning::None => deps,
+                Pruning::HighestPerProcess => deps
+                    .iter()
+                    .filter(|dep| !deps.iter().any(|other| higher(dep, other)))
+                    .cloned()
+                    .collect(),
+                Pruning::HighestPerKey => deps
+                    .iter()
+                    .filter(|dep| {
+                        keys[dep].intersection(dot_keys_ref).any(|key| {
+                            !deps.iter().any(|other| {
+                                higher(dep, other) && keys[other].contains(key)
+                            })
+                        })
+                    })
+                    .cloned()
+                    .collect(),
+            };
+            (dot, dot_keys, deps)
+        })
+        .collect();
+
+    // sort by dot so that the same seed always produces the same output
+    args.sort_by_key(|(dot, _, _)| *dot);
+    args
+}
+
+/// Checks that all permutations of `args` yield the same total order.
+pub fn shuffle_it(n: usize, args: Adds) {
+    if let Err(e) = try_shuffle_it(n, args) {
+        panic!("{}", e);
+    }
+}
+
+fn try_shuffle_it(n: usize, mut args: Adds) -> Result<(), String> {
+    let total_order = try_check_termination(n, args.clone())?;
+    for permutation in args.permutation() {
+        let sorted = try_check_termination(n, permutation.clone())?;
+        if total_order != sorted {
+            return Err(format!(
+                "permutation {:?} yields {:?} instead of {:?}",
+                permutation, sorted, total_order
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Adds `args` to a new queue (in the order given), and returns the order in
+/// which commands are executed on each key.
+pub fn check_termination(n: usize, args: Adds) -> BTreeMap<Key, Vec<Rifl>> {
+    try_check_termination(n, args).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn try_check_termination(
+    n: usize,
+    args: Adds,
+) -> Result<BTreeMap<Key, Vec<Rifl>>, String> {
+    // create queue
+    let process_id = 1;
+    let shard_id = 0;
+    let f = 1;
+    let config = Config::new(n, f);
+    let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+    let time = RunTime;
+    let mut all_rifls = HashSet::new();
+    let mut sorted = BTreeMap::new();
+
+    for (dot, keys, dep_dots) in args {
+        // transform dep dots into deps
+        let deps = dep_dots
+            .into_iter()
+            .map(|dep_dot| dep(dep_dot, shard_id))
+            .collect();
+        // create command rifl from its dot
+        let rifl = Rifl::new(dot.source() as ClientId, dot.sequence());
+
+        // create command:
+        // - set single CONF key if no keys were provided
+        let keys = keys
+            .unwrap_or_else(|| BTreeSet::from_iter(vec![String::from("CONF")]));
+        let ops = keys.into_iter().map(|key| {
+            let value = 10;
+            (key, KVOp::Put(value))
+        });
+        let cmd = Command::from(rifl, ops);
+
+        // add to the set of all rifls
+        if !all_rifls.insert(rifl) {
+            return Err(format!("command {:?} added twice", rifl));
+        }
+
+        // add it to the queue
+        queue.handle_add(dot, cmd, deps, &time);
+
+        // for each command ready to be executed
+        for cmd in queue.commands_to_execute() {
+            // remove it from the set of rifls
+            let rifl = cmd.rifl();
+            if !all_rifls.remove(&rifl) {
+                return Err(format!("command {:?} executed twice", rifl));
+            }
+
+            // and add it to the sorted results
+            cmd.keys(shard_id).for_each(|key| {
+                sorted
+                    .entry(key.clone())
+                    .or_insert_with(Vec::new)
+                    .push(rifl);
+            })
+        }
+    }
+
+    // the set of all rifls should be empty
+    if !all_rifls.is_empty() {
+        return Err(format!("commands {:?} were never executed", all_rifls));
+    }
+
+    // return sorted commands
+    Ok(sorted)
+}
@@ -4,6 +4,7 @@ use fantoch::config::Config;
 use fantoch::hash_map::{Entry, HashMap};
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::info;
+use fantoch::kvs::Key;
 use fantoch::shared::{SharedMap, SharedMapRef};
 use fantoch::time::SysTime;
 use fantoch::HashSet;
@@ -19,6 +20,8 @@ pub type VertexRef<'a> = SharedMapRef<'a, Dot, RwLock<Vertex>>;
 pub struct VertexIndex {
     process_id: ProcessId,
     index: Arc<SharedMap<Dot, RwLock<Vertex>>>,
+    // mapping from each key to the pending vertices accessing it
+    per_key: Arc<SharedMap<(ShardId, Key), RwLock<HashSet<Dot>>>>,
 }
 
 impl VertexIndex {
@@ -26,17 +29,58 @@ impl VertexIndex {
         Self {
             process_id,
             index: Arc::new(SharedMap::new()),
+            per_key: Arc::new(SharedMap::new()),
         }
     }
 
+    /// Creates a new `VertexIndex` pre-sized to hold `capacity` vertices, each
+    /// accessing (at least) a key on each of the `shards` shards.
+    pub fn with_capacity(
+        process_id: ProcessId,
+        shards: usize,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            process_id,
+            index: Arc::new(SharedMap::with_capacity(capacity)),
+            per_key: Arc::new(SharedMap::with_capacity(capacity * shards)),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        self.index.capacity()
+    }
+
     /// Indexes a new vertex, returning any previous vertex indexed.
     pub fn index(&mut self, vertex: Vertex) -> Option<Vertex> {
         let dot = vertex.dot;
+        // index the vertex on each of its keys
+        for (shard_id, key) in vertex.cmd.all_keys() {
+            self.per_key
+                .get_or(&(*shard_id, key.clone()), || {
+                    RwLock::new(HashSet::new())
+                })
+                .write()
+                .insert(dot);
+        }
         let cell = RwLock::new(vertex);
         self.index.insert(dot, cell).map(|cell| cell.into_inner())
     }
 
-    #[allow(dead_code)]
+    /// Returns the pending vertices that access `key` on `shard_id`.
+    pub fn vertices_on_key(
+        &self,
+        shard_id: ShardId,
+        key: &Key,
+    ) -> impl Iterator<Item = Dot> {
+        self.per_key
+            .get(&(shard_id, key.clone()))
+            .map(|dots| dots.read().iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+    }
+
     pub fn dots(&self) -> impl Iterator<Item = Dot> + '_ {
         self.index.iter().map(|entry| *entry.key())
     }
@@ -45,9 +89,42 @@ impl VertexIndex {
         self.index.get(dot)
     }
 
+    /// Returns, for each pending vertex, an edge to each of its dependencies
+    /// that is also pending.
+    pub fn dependency_edges(&self) -> Vec<(Dot, Dot)> {
+        self.index
+            .iter()
+            .flat_map(|vertex_ref| {
+                let vertex = vertex_ref.read();
+                vertex
+                    .deps
+                    .iter()
+                    .filter(|dep| self.index.contains_key(&dep.dot))
+                    .map(|dep| (vertex.dot, dep.dot))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     /// Removes a vertex from the index.
     pub fn remove(&mut self, dot: &Dot) -> Option<Vertex> {
-        self.index.remove(dot).map(|(_, cell)| cell.into_inner())
+        let vertex = self.index.remove(dot).map(|(_, cell)| cell.into_inner());
+        if let Some(vertex) = vertex.as_ref() {
+            // remove the vertex from each of its keys
+            for (shard_id, key) in vertex.cmd.all_keys() {
+                let key = (*shard_id, key.clone());
+                // remove the dot and, if no vertex accesses the key anymore,
+                // the key itself; doing both while holding the lock on the
+                // entry ensures that no vertex is indexed on the key between
+                // the emptiness check and the removal
+                self.per_key.remove_if(&key, |_, dots| {
+                    let mut dots = dots.write();
+                    dots.remove(dot);
+                    dots.is_empty()
+                });
+            }
+        }
+        vertex
     }
 
     pub fn monitor_pending(
@@ -198,6 +275,11 @@ impl PendingIndex {
         None
     }
 
+    /// Returns whether some dot is pending on a given dependency dot.
+    pub fn contains(&self, dep_dot: &Dot) -> bool {
+        self.index.contains_key(dep_dot)
+    }
+
     /// Finds all pending dots for a given dependency dot.
     pub fn remove(&mut self, dep_dot: &Dot) -> Option<HashSet<Dot>> {
         self.index.remove(dep_dot)
@@ -8,6 +8,10 @@ mod index;
 /// `GraphExecutionInfo`.
 mod executor;
 
+/// This module contains a fuzzer for `DependencyGraph`.
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
+
 // Re-exports.
 pub use executor::{GraphExecutionInfo, GraphExecutor};
 
@@ -18,6 +22,7 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{ExecutorMetrics, ExecutorMetricsKind};
 use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::kvs::Key;
 use fantoch::time::SysTime;
 use fantoch::util;
 use fantoch::{debug, trace};
@@ -26,7 +31,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fmt;
 use std::time::Duration;
-use threshold::AEClock;
+use threshold::{AEClock, EventSet, VClock};
 
 const MONITOR_PENDING_THRESHOLD: Duration = Duration::from_secs(1);
 
@@ -42,10 +47,33 @@ pub enum RequestReply {
     },
 }
 
+/// Order in which commands that become ready at the same time are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionOrdering {
+    /// Commands are returned in the order their SCCs are found.
+    SccCompletion,
+    /// Commands are returned in the order they were added to the graph,
+    /// as long as that respects their dependencies. This bounds the latency
+    /// of commands that become ready late.
+    Fifo,
+}
+
+// SCC found while in `ExecutionOrdering::Fifo`, which is kept until all SCCs
+// found in the same batch can be ordered
+#[derive(Clone)]
+struct ReadySCC {
+    dots: Vec<Dot>,
+    deps: HashSet<Dot>,
+    insert_time_ms: u64,
+    cmds: Vec<(Dot, Command)>,
+}
+
 #[derive(Clone)]
 pub struct DependencyGraph {
     executor_index: usize,
     process_id: ProcessId,
+    shard_id: ShardId,
+    config: Config,
     executed_clock: AEClock<ProcessId>,
     vertex_index: VertexIndex,
     pending_index: PendingIndex,
@@ -56,10 +84,13 @@ pub struct DependencyGraph {
     // - `out_requests` dependencies to be able to order commands
     // - notifies remaining workers about what's been executed through
     //   `added_to_executed_clock`
-    to_execute: VecDeque<Command>,
+    to_execute: VecDeque<(Dot, Command)>,
+    ordering: ExecutionOrdering,
+    ready_sccs: Vec<ReadySCC>,
     out_requests: HashMap<ShardId, HashSet<Dot>>,
     added_to_executed_clock: HashSet<Dot>,
-    // auxiliary workers (handles requests):
+    // auxiliary workers (handles requests; the main worker does it if it is
+    // the only one):
     // - may have `buffered_in_requests` when doesn't have the command yet
     // - produces `out_request_replies` when it has the command
     buffered_in_requests: HashMap<ShardId, HashSet<Dot>>,
@@ -83,6 +114,29 @@ impl DependencyGraph {
         process_id: ProcessId,
         shard_id: ShardId,
         config: &Config,
+    ) -> Self {
+        let vertex_index = VertexIndex::new(process_id);
+        Self::with_vertex_index(process_id, shard_id, config, vertex_index)
+    }
+
+    /// Create a new `Graph` pre-sized to hold `capacity` pending commands.
+    pub fn with_capacity(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: &Config,
+        capacity: usize,
+    ) -> Self {
+        let shards = config.shard_count();
+        let vertex_index =
+            VertexIndex::with_capacity(process_id, shards, capacity);
+        Self::with_vertex_index(process_id, shard_id, config, vertex_index)
+    }
+
+    fn with_vertex_index(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: &Config,
+        vertex_index: VertexIndex,
     ) -> Self {
         // this value will be overwritten
         let executor_index = 0;
@@ -92,14 +146,20 @@ impl DependencyGraph {
                 .map(|(process_id, _)| process_id)
                 .collect();
         let executed_clock = AEClock::with(ids.clone());
-        // create indexes
-        let vertex_index = VertexIndex::new(process_id);
+        // create pending index
         let pending_index = PendingIndex::new(shard_id, *config);
         // create finder
         let finder = TarjanSCCFinder::new(process_id, *config);
         let metrics = ExecutorMetrics::new();
         // create to execute
         let to_execute = Default::default();
+        // order commands that become ready at the same time as configured
+        let ordering = if config.executor_fifo_ordering() {
+            ExecutionOrdering::Fifo
+        } else {
+            ExecutionOrdering::SccCompletion
+        };
+        let ready_sccs = Vec::new();
         // create requests and request replies
         let out_requests = Default::default();
         // only track what's added to the executed clock if partial replication
@@ -109,12 +169,16 @@ impl DependencyGraph {
         DependencyGraph {
             executor_index,
             process_id,
+            shard_id,
+            config: *config,
             executed_clock,
             vertex_index,
             pending_index,
             finder,
             metrics,
             to_execute,
+            ordering,
+            ready_sccs,
             out_requests,
             added_to_executed_clock,
             buffered_in_requests,
@@ -126,12 +190,22 @@ impl DependencyGraph {
         self.executor_index = index;
     }
 
-    /// Returns a new command ready to be executed.
+    /// Returns a new command ready to be executed, along with its dot.
     #[must_use]
-    pub fn command_to_execute(&mut self) -> Option<Command> {
+    pub fn command_to_execute(&mut self) -> Option<(Dot, Command)> {
         self.to_execute.pop_front()
     }
 
+    /// Returns at most `max` commands ready to be executed (along with their
+    /// dots), in execution order. This allows callers to bound the work done
+    /// per tick when a large SCC becomes ready; the remaining commands are
+    /// kept (in order) for subsequent calls.
+    #[must_use]
+    pub fn to_execute_chunked(&mut self, max: usize) -> Vec<(Dot, Command)> {
+        let count = std::cmp::min(max, self.to_execute.len());
+        self.to_execute.drain(..count).collect()
+    }
+
     /// Returns which dots have been added to the executed clock.
     #[must_use]
     pub fn to_executors(&mut self) -> Option<HashSet<Dot>> {
@@ -154,15 +228,75 @@ impl DependencyGraph {
         std::mem::take(&mut self.out_request_replies)
     }
 
-    #[cfg(test)]
+    /// Returns the dependency edges between pending commands, which can be
+    /// used to analyze the shape of the dependency graph.
+    pub fn dependency_edges(&self) -> Vec<(Dot, Dot)> {
+        self.vertex_index.dependency_edges()
+    }
+
+    /// Returns the commands that have been added but not yet executed that
+    /// access `key` on this shard.
+    pub fn pending_on_key(&self, key: &Key) -> Vec<Dot> {
+        self.vertex_index
+            .vertices_on_key(self.shard_id, key)
+            .collect()
+    }
+
+    /// Returns the number of commands that have been added but not yet
+    /// executed.
+    pub fn pending_count(&self) -> usize {
+        self.vertex_index.dots().count()
+    }
+
+    /// Removes and returns (ordered by dot) the commands that have been added
+    /// but not yet executed, e.g. because they're part of an incomplete SCC.
+    /// This is meant to be called at shutdown to report the work left undone.
+    pub fn drain_remaining(&mut self) -> Vec<Command> {
+        let mut dots: Vec<_> = self.vertex_index.dots().collect();
+        dots.sort();
+        dots.into_iter()
+            .filter_map(|dot| self.vertex_index.remove(&dot))
+            .map(|vertex| vertex.cmd)
+            .collect()
+    }
+
+    /// Compacts the executed clock up to `below`, i.e. marks as executed all
+    /// dots below it. Dots that are never executed locally (e.g. commands
+    /// from other shards) leave gaps in the executed clock that make it grow
+    /// unboundedly; pruning below a stable clock (one whose dots are known to
+    /// have been executed, or to be irrelevant here) bounds its size.
+    pub fn prune_executed(&mut self, below: &VClock<ProcessId>) {
+        for (process_id, event_set) in below.iter() {
+            let frontier = event_set.frontier();
+            if frontier > 0 {
+                self.executed_clock.add_range(process_id, 1, frontier);
+            }
+        }
+        trace!(
+            "p{}: @{} Graph::prune_executed {:?} | executed = {:?}",
+            self.process_id,
+            self.executor_index,
+            below,
+            self.executed_clock
+        );
+    }
+
+    #[cfg(any(test, feature = "fuzz"))]
     fn commands_to_execute(&mut self) -> VecDeque<Command> {
         std::mem::take(&mut self.to_execute)
+            .into_iter()
+            .map(|(_, cmd)| cmd)
+            .collect()
     }
 
     fn metrics(&self) -> &ExecutorMetrics {
         &self.metrics
     }
 
+    fn reset_metrics(&mut self) {
+        self.metrics.clear();
+    }
+
     fn cleanup(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: @{} Graph::cleanup | time = {}",
@@ -170,10 +304,9 @@ impl DependencyGraph {
             self.executor_index,
             time.millis()
         );
-        if self.executor_index > 0 {
-            // if not main executor, check pending remote requests
-            self.check_pending_requests(time);
-        }
+        // check pending remote requests (the main executor only has them if
+        // it's the only executor)
+        self.check_pending_requests(time);
     }
 
     fn monitor_pending(&self, time: &dyn SysTime) {
@@ -227,6 +360,22 @@ impl DependencyGraph {
             time.millis()
         );
 
+        // ignore duplicate deliveries of commands already added
+        if self.is_duplicate(dot) {
+            return;
+        }
+
+        // record the number of dependencies of this command
+        self.metrics
+            .collect(ExecutorMetricsKind::DepCount, deps.len() as u64);
+
+        // if the command has no dependencies, there are no ordering
+        // constraints, and thus it can be executed right away
+        if deps.is_empty() {
+            self.execute_without_deps(dot, cmd, time);
+            return;
+        }
+
         // create new vertex for this command
         let vertex = Vertex::new(dot, cmd, deps, time);
 
@@ -249,6 +398,17 @@ impl DependencyGraph {
                 self.check_pending(dots, &mut total_scc_count, time);
             }
             FinderInfo::MissingDependencies(dots, _visited, missing_deps) => {
+                // record whether the command waits on a dependency from
+                // another shard
+                let cross_shard = missing_deps.iter().any(|dep| {
+                    dep.shards.as_ref().map_or(false, |shards| {
+                        !shards.contains(&self.shard_id)
+                    })
+                });
+                if cross_shard {
+                    self.metrics
+                        .aggregate(ExecutorMetricsKind::CrossShardWait, 1);
+                }
                 // update the pending
                 self.index_pending(dot, missing_deps, time);
                 // try to execute other commands if new SCCs were found
@@ -259,6 +419,9 @@ impl DependencyGraph {
             }
         }
 
+        // order the SCCs found, if needed
+        self.flush_ready_sccs();
+
         // check that all newly ready commands have been incorporated
         assert_eq!(self.to_execute.len(), initial_ready + total_scc_count);
 
@@ -274,13 +437,111 @@ impl DependencyGraph {
         );
     }
 
+    // Executes a command with no dependencies without indexing it, which saves
+    // running the SCC finder on it.
+    fn execute_without_deps(
+        &mut self,
+        dot: Dot,
+        cmd: Command,
+        time: &dyn SysTime,
+    ) {
+        trace!(
+            "p{}: @{} Graph::execute_without_deps {:?} | time = {}",
+            self.process_id,
+            self.executor_index,
+            dot,
+            time.millis()
+        );
+        assert!(
+            self.vertex_index.find(&dot).is_none(),
+            "p{}: @{} Graph::execute_without_deps {:?} is already indexed",
+            self.process_id,
+            self.executor_index,
+            dot
+        );
+
+        // the command forms an SCC on its own, and it's executed with no delay
+        self.metrics.collect(ExecutorMetricsKind::ChainSize, 1);
+        self.metrics.collect(ExecutorMetricsKind::ExecutionDelay, 0);
+        if self.ordering == ExecutionOrdering::Fifo {
+            // in fifo mode, it's ordered (by insertion time) along with the
+            // SCCs that become ready once it's executed
+            self.ready_sccs.push(ReadySCC {
+                dots: vec![dot],
+                deps: HashSet::new(),
+                insert_time_ms: time.millis(),
+                cmds: vec![(dot, cmd)],
+            });
+        } else {
+            self.to_execute.push_back((dot, cmd));
+        }
+        self.executed(dot, time);
+    }
+
+    /// Returns whether the command with this `dot` has already been added
+    /// or executed, in which case its delivery is recorded as a duplicate.
+    pub fn is_duplicate(&mut self, dot: Dot) -> bool {
+        let known = self.vertex_index.find(&dot).is_some()
+            || self.executed_clock.contains(&dot.source(), dot.sequence());
+        if known {
+            self.metrics.aggregate(ExecutorMetricsKind::Duplicate, 1);
+        }
+        known
+    }
+
+    /// Records that a command was executed without being added to the graph
+    /// (e.g. because it was executed at commit time). Returns whether some
+    /// command (i.e. a snapshot read) is pending on it, in which case that
+    /// command may now be executed (and so the graph should be polled).
+    #[must_use]
+    pub fn mark_executed(&mut self, dot: Dot, time: &dyn SysTime) -> bool {
+        assert_eq!(self.executor_index, 0);
+        trace!(
+            "p{}: @{} Graph::mark_executed {:?} | time = {}",
+            self.process_id,
+            self.executor_index,
+            dot,
+            time.millis()
+        );
+        if self.pending_index.contains(&dot) {
+            self.executed(dot, time);
+            true
+        } else {
+            // if nothing is pending on it, there's no need to look for
+            // commands to execute; the other executors are told that it has
+            // been executed on the next cleanup
+            self.add_to_executed_clock(dot);
+            false
+        }
+    }
+
+    // Adds a command executed outside of an SCC to the executed clock, and
+    // tries to execute the commands that depend on it.
+    fn executed(&mut self, dot: Dot, time: &dyn SysTime) {
+        self.add_to_executed_clock(dot);
+
+        // try to execute commands that depend on this one
+        let dots = vec![dot];
+        let mut total_scc_count = 0;
+        self.check_pending(dots, &mut total_scc_count, time);
+        self.flush_ready_sccs();
+    }
+
+    fn add_to_executed_clock(&mut self, dot: Dot) {
+        self.executed_clock.add(&dot.source(), dot.sequence());
+        if self.config.shard_count() > 1 {
+            self.added_to_executed_clock.insert(dot);
+        }
+    }
+
     fn handle_request(
         &mut self,
         from: ShardId,
         dots: HashSet<Dot>,
         time: &dyn SysTime,
     ) {
-        assert!(self.executor_index > 0);
+        // requests are handled by the auxiliary workers, or by the main one if
+        // it's the only worker (as in the simulator)
         trace!(
             "p{}: @{} Graph::handle_request {:?} from {:?} | time = {}",
             self.process_id,
@@ -301,7 +562,6 @@ impl DependencyGraph {
         dots: impl Iterator<Item = Dot>,
         time: &dyn SysTime,
     ) {
-        assert!(self.executor_index > 0);
         for dot in dots {
             if let Some(vertex) = self.vertex_index.find(&dot) {
                 let vertex = vertex.read();
@@ -400,6 +660,7 @@ impl DependencyGraph {
                     let dots = vec![dot];
                     let mut total_scc_count = 0;
                     self.check_pending(dots, &mut total_scc_count, time);
+                    self.flush_ready_sccs();
                 }
             }
         }
@@ -492,6 +753,16 @@ impl DependencyGraph {
         self.metrics
             .collect(ExecutorMetricsKind::ChainSize, scc.len() as u64);
 
+        // in fifo mode, commands are kept in a `ReadySCC` until the batch of
+        // SCCs is flushed
+        let fifo = self.ordering == ExecutionOrdering::Fifo;
+        let mut ready_scc = ReadySCC {
+            dots: Vec::with_capacity(scc.len()),
+            deps: HashSet::new(),
+            insert_time_ms: u64::MAX,
+            cmds: Vec::with_capacity(scc.len()),
+        };
+
         scc.into_iter().for_each(|dot| {
             trace!(
                 "p{}: @{} Graph::save_scc removing {:?} from indexes | time = {}",
@@ -510,6 +781,13 @@ impl DependencyGraph {
             // update the set of ready dots
             dots.push(dot);
 
+            if fifo {
+                ready_scc.dots.push(dot);
+                ready_scc.deps.extend(vertex.deps.iter().map(|dep| dep.dot));
+                ready_scc.insert_time_ms =
+                    std::cmp::min(ready_scc.insert_time_ms, vertex.start_time_ms);
+            }
+
             // get command
             let (duration_ms, cmd) = vertex.into_command(time);
 
@@ -518,8 +796,47 @@ impl DependencyGraph {
                 .collect(ExecutorMetricsKind::ExecutionDelay, duration_ms);
 
             // add command to commands to be executed
-            self.to_execute.push_back(cmd);
-        })
+            if fifo {
+                ready_scc.cmds.push((dot, cmd));
+            } else {
+                self.to_execute.push_back((dot, cmd));
+            }
+        });
+
+        if fifo {
+            self.ready_sccs.push(ready_scc);
+        }
+    }
+
+    // Moves the SCCs found (in `ExecutionOrdering::Fifo`) to the commands to
+    // be executed: the next SCC is always the one added the earliest among
+    // those whose dependencies have already been moved.
+    fn flush_ready_sccs(&mut self) {
+        let mut ready_sccs = std::mem::take(&mut self.ready_sccs);
+        let batch: HashSet<_> = ready_sccs
+            .iter()
+            .flat_map(|scc| scc.dots.iter().cloned())
+            .collect();
+        let mut flushed = HashSet::with_capacity(batch.len());
+
+        while !ready_sccs.is_empty() {
+            let index = ready_sccs
+                .iter()
+                .enumerate()
+                .filter(|(_, scc)| {
+                    scc.deps.iter().all(|dep| {
+                        scc.dots.contains(dep)
+                            || !batch.contains(dep)
+                            || flushed.contains(dep)
+                    })
+                })
+                .min_by_key(|(index, scc)| (scc.insert_time_ms, *index))
+                .map(|(index, _)| index)
+                .expect("some SCC should have its dependencies flushed");
+            let scc = ready_sccs.remove(index);
+            flushed.extend(scc.dots);
+            self.to_execute.extend(scc.cmds);
+        }
     }
 
     fn index_pending(
@@ -689,28 +1006,26 @@ impl fmt::Debug for DependencyGraph {
 
 #[cfg(test)]
 mod tests {
+    use super::fuzz::{
+        check_termination, dep, fuzz_queue, random_adds, shuffle_it,
+        try_fuzz_queue, Pruning,
+    };
     use super::*;
+    use crate::protocol::common::graph::{KeyDeps, SequentialKeyDeps};
     use crate::util;
-    use fantoch::id::{ClientId, Rifl, ShardId};
-    use fantoch::kvs::{KVOp, Key};
-    use fantoch::time::RunTime;
+    use fantoch::client::{KeyGen, Workload};
+    use fantoch::executor::Executor;
+    use fantoch::id::{ClientId, Rifl, RiflGen};
+    use fantoch::kvs::{KVOp, KVOpResult, KVStore, Key};
+    use fantoch::metrics::F64;
+    use fantoch::time::{RunTime, SimTime};
     use fantoch::HashMap;
-    use permutator::{Combination, Permutation};
-    use rand::seq::SliceRandom;
-    use rand::Rng;
-    use std::cell::RefCell;
-    use std::cmp::Ordering;
-    use std::collections::{BTreeMap, BTreeSet};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeSet;
     use std::iter::FromIterator;
     use threshold::{AEClock, AboveExSet, EventSet};
 
-    fn dep(dot: Dot, shard_id: ShardId) -> Dependency {
-        Dependency {
-            dot,
-            shards: Some(BTreeSet::from_iter(vec![shard_id])),
-        }
-    }
-
     #[test]
     fn simple() {
         // create queue
@@ -753,132 +1068,1055 @@ mod tests {
         assert_eq!(queue.commands_to_execute(), vec![cmd_0, cmd_1]);
     }
 
-    /// We have 5 commands by the same process (process A) that access the same
-    /// key. We have `n = 5` and `f = 1` and thus the fast quorum size of 3.
-    /// The fast quorum used by process A is `{A, B, C}`. We have the
-    /// following order of commands in the 3 processes:
-    /// - A: (1,1) (1,2) (1,3) (1,4) (1,5)
-    /// - B: (1,3) (1,4) (1,1) (1,2) (1,5)
-    /// - C: (1,1) (1,2) (1,4) (1,5) (1,3)
-    ///
-    /// The above results in the following final dependencies:
-    /// - dep[(1,1)] = {(1,4)}
-    /// - dep[(1,2)] = {(1,4)}
-    /// - dep[(1,3)] = {(1,5)}
-    /// - dep[(1,4)] = {(1,3)}
-    /// - dep[(1,5)] = {(1,4)}
-    ///
-    /// The executor then receives the commit notifications of (1,3) (1,4) and
-    /// (1,5) and, if transitive conflicts are assumed, these 3 commands
-    /// form an SCC. This is because with this assumption we only check the
-    /// highest conflicting command per replica, and thus (1,3) (1,4) and
-    /// (1,5) have "all the dependencies".
-    ///
-    /// Then, the executor executes whichever missing command comes first, since
-    /// (1,1) and (1,2) "only depend on an SCC already formed". This means that
-    /// if two executors receive (1,3) (1,4) (1,5), and then one receives (1,1)
-    /// and (1,2) while the other receives (1,2) and (1,1), they will execute
-    /// (1,1) and (1,2) in differents order, leading to an inconsistency.
-    ///
-    /// This example is impossible if commands from the same process are
-    /// processed (on the replicas computing dependencies) in their submission
-    /// order. With this, a command never depends on later commands from the
-    /// same process, which seems to be enough to prevent this issue. This means
-    /// that parallelizing the processing of messages needs to be on a
-    /// per-process basis, i.e. commands by the same process are always
-    /// processed by the same worker.
     #[test]
-    fn transitive_conflicts_assumption_regression_test_1() {
-        // config
-        let n = 5;
-
-        // dots
-        let dot_1 = Dot::new(1, 1);
-        let dot_2 = Dot::new(1, 2);
-        let dot_3 = Dot::new(1, 3);
-        let dot_4 = Dot::new(1, 4);
-        let dot_5 = Dot::new(1, 5);
-
-        // deps
-        let deps_1 = HashSet::from_iter(vec![dot_4]);
-        let deps_2 = HashSet::from_iter(vec![dot_4]);
-        let deps_3 = HashSet::from_iter(vec![dot_5]);
-        let deps_4 = HashSet::from_iter(vec![dot_3]);
-        let deps_5 = HashSet::from_iter(vec![dot_4]);
+    fn dep_count() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
 
-        let order_a = vec![
-            (dot_3, None, deps_3.clone()),
-            (dot_4, None, deps_4.clone()),
-            (dot_5, None, deps_5.clone()),
-            (dot_1, None, deps_1.clone()),
-            (dot_2, None, deps_2.clone()),
-        ];
-        let order_b = vec![
-            (dot_3, None, deps_3),
-            (dot_4, None, deps_4),
-            (dot_5, None, deps_5),
-            (dot_2, None, deps_2),
-            (dot_1, None, deps_1),
-        ];
-        let order_a = check_termination(n, order_a);
-        let order_b = check_termination(n, order_b);
-        assert!(order_a != order_b);
+        // add 4 commands on the same key, each depending on all the previous
+        // ones
+        let dots: Vec<_> = (1..=4).map(|seq| Dot::new(1, seq)).collect();
+        for (i, dot) in dots.iter().enumerate() {
+            let cmd = Command::from(
+                Rifl::new(1, i as u64 + 1),
+                vec![(String::from("A"), KVOp::Put(10))],
+            );
+            let deps =
+                dots[..i].iter().map(|dot| dep(*dot, shard_id)).collect();
+            queue.handle_add(*dot, cmd, deps, &time);
+        }
+        assert_eq!(queue.commands_to_execute().len(), 4);
+
+        // dependency counts are 0, 1, 2 and 3
+        let dep_count = queue
+            .metrics()
+            .get_collected(ExecutorMetricsKind::DepCount)
+            .expect("dep count should be collected");
+        assert_eq!(dep_count.count(), 4);
+        assert_eq!(dep_count.mean(), F64::new(1.5));
     }
 
-    /// Simple example showing why encoding of dependencies matters for the
-    /// `transitive_conflicts` optimization to be correct (which, makes the name
-    /// of the optimization not great):
-    /// - 3 replicas (A, B, C), and 3 commands
-    ///   * command (A, 1), keys = {x}
-    ///   * command (A, 2), keys = {y}
-    ///   * command (B, 1), keys = {x, y}
-    ///
-    /// First, (A, 1) is submitted and gets no dependencies:
-    /// - {A -> 0, B -> 0, C -> 0}
-    /// Then, (A, 2) is submitted and also gets no dependencies:
-    /// - {A -> 0, B -> 0, C -> 0}
-    /// Finally, (B, 1) is submitted and gets (A, 2) as a dependency:
-    /// - {A -> 2, B -> 0, C -> 0}
-    /// It only gets (A, 2) because we only return the highest conflicting
-    /// command from each replica.
-    ///
-    /// With the optimization, the order in which commands are received by the
-    /// ordering component affects results:
-    /// - (A, 1), (A, 2), (B, 1): commands are executed in the order they're
-    ///   received, producing correct results
-    /// - (A, 2), (B, 1), (A, 1): (B, 1) is executed before (A, 1) and shouldn't
-    ///
-    /// Without the optimization, (B, 1) would be forced to wait for (A, 1) in
-    /// the last case, producing a correct result.
-    ///
-    /// It looks like the optimization would be correct if, instead of returning
-    /// the highest conflicting command per replica, we would return the highest
-    /// conflict command per replica *per key*.
     #[test]
-    fn transitive_conflicts_assumption_regression_test_2() {
-        // config
+    fn dependency_chain_workload() {
+        let process_id = 1;
+        let shard_id = 0;
         let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
 
-        let keys = |keys: Vec<&str>| {
-            keys.into_iter()
-                .map(|key| key.to_string())
-                .collect::<BTreeSet<_>>()
+        // generate a single chain of mutually-conflicting commands
+        let chain_length = 10;
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 0,
+            pool_size: 1,
         };
+        let mut workload = Workload::new(1, key_gen, 1, chain_length, 0);
+        workload.set_dependency_chain(chain_length);
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen_state(client_id);
+        let mut cmds = Vec::new();
+        while let Some((_, cmd)) =
+            workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+        {
+            cmds.push(cmd);
+        }
 
-        // cmd 1,1
-        let dot_1_1 = Dot::new(1, 1);
-        let keys_1_1 = keys(vec!["A"]);
-        let deps_1_1 = HashSet::new();
+        // the commands are submitted concurrently, and thus two replicas may
+        // see them in opposite orders: the dependencies of each command are
+        // the union of the ones computed by each replica
+        let dots: Vec<_> = (1..=chain_length)
+            .map(|seq| Dot::new(process_id, seq as u64))
+            .collect();
+        let nfr = false;
+        let mut replica_a = SequentialKeyDeps::new(shard_id, nfr);
+        let mut replica_b = SequentialKeyDeps::new(shard_id, nfr);
+        let mut deps: HashMap<Dot, HashSet<Dependency>> = HashMap::new();
+        for (dot, cmd) in dots.iter().zip(cmds.iter()) {
+            let cmd_deps = replica_a.add_cmd(*dot, cmd, None);
+            deps.entry(*dot).or_default().extend(cmd_deps);
+        }
+        for (dot, cmd) in dots.iter().zip(cmds.iter()).rev() {
+            let cmd_deps = replica_b.add_cmd(*dot, cmd, None);
+            deps.entry(*dot).or_default().extend(cmd_deps);
+        }
 
-        // cmd 1,2
-        let dot_1_2 = Dot::new(1, 2);
-        let keys_1_2 = keys(vec!["B"]);
-        let deps_1_2 = HashSet::new();
+        for (dot, cmd) in dots.into_iter().zip(cmds) {
+            let deps = deps.remove(&dot).unwrap().into_iter().collect();
+            queue.handle_add(dot, cmd, deps, &time);
+        }
+        assert_eq!(queue.commands_to_execute().len(), chain_length);
+
+        // a single SCC with all the commands was found
+        let chain_size = queue
+            .metrics()
+            .get_collected(ExecutorMetricsKind::ChainSize)
+            .expect("chain size should be collected");
+        assert_eq!(chain_size.count(), 1);
+        assert_eq!(chain_size.max(), F64::new(chain_length as f64));
+    }
 
-        // cmd 2,1
-        let dot_2_1 = Dot::new(2, 1);
-        let keys_2_1 = keys(vec!["A", "B"]);
-        let deps_2_1 = HashSet::from_iter(vec![dot_1_2]);
+    #[test]
+    fn duplicate() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        let dot_0 = Dot::new(1, 1);
+        let dot_1 = Dot::new(1, 2);
+        let cmd = |seq: u64| {
+            Command::from(
+                Rifl::new(1, seq),
+                vec![(String::from("A"), KVOp::Put(10))],
+            )
+        };
+        let cmd_0 = cmd(1);
+        let cmd_1 = cmd(2);
+
+        // cmd 1 (pending) is delivered twice
+        for _ in 0..2 {
+            let deps = vec![dep(dot_0, shard_id)];
+            queue.handle_add(dot_1, cmd_1.clone(), deps, &time);
+        }
+        assert!(queue.commands_to_execute().is_empty());
+
+        // cmd 0 (executed) is delivered twice
+        for _ in 0..2 {
+            queue.handle_add(dot_0, cmd_0.clone(), vec![], &time);
+        }
+        assert_eq!(queue.commands_to_execute(), vec![cmd_0, cmd_1]);
+
+        // both duplicates were detected
+        assert_eq!(
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::Duplicate),
+            Some(&2)
+        );
+    }
+
+    #[test]
+    fn drain_remaining() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // cmd 0 has no dependencies and cmd 1 depends on a command that is
+        // never added
+        let dot_0 = Dot::new(1, 1);
+        let dot_1 = Dot::new(1, 2);
+        let missing = Dot::new(2, 1);
+        let cmd_0 = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Put(10))],
+        );
+        let cmd_1 = Command::from(
+            Rifl::new(1, 2),
+            vec![(String::from("B"), KVOp::Put(10))],
+        );
+        let deps_1 = vec![dep(missing, shard_id)];
+        queue.handle_add(dot_0, cmd_0.clone(), vec![], &time);
+        queue.handle_add(dot_1, cmd_1.clone(), deps_1, &time);
+        assert_eq!(queue.commands_to_execute(), vec![cmd_0]);
+
+        // cmd 1 is reported as never executed, and only once
+        assert_eq!(queue.drain_remaining(), vec![cmd_1]);
+        assert!(queue.drain_remaining().is_empty());
+    }
+
+    #[test]
+    fn no_conflicts_fast_track() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // commands on disjoint keys have no dependencies
+        let cmd = |seq: u64| {
+            Command::from(
+                Rifl::new(1, seq),
+                vec![(format!("K{}", seq), KVOp::Put(10))],
+            )
+        };
+
+        // they're never indexed, and are executed in arrival order
+        let cmds: Vec<_> = (1..=100).map(cmd).collect();
+        for (index, cmd) in cmds.iter().enumerate() {
+            let source = (index % n) as ProcessId + 1;
+            let dot = Dot::new(source, (index / n) as u64 + 1);
+            queue.handle_add(dot, cmd.clone(), vec![], &time);
+            assert_eq!(queue.vertex_index.dots().count(), 0);
+        }
+        assert_eq!(queue.commands_to_execute(), cmds);
+
+        // commands waiting on a command with no dependencies are executed
+        // once it arrives
+        let dot_a = Dot::new(1, 100);
+        let dot_b = Dot::new(1, 101);
+        queue.handle_add(dot_b, cmd(101), vec![dep(dot_a, shard_id)], &time);
+        assert!(queue.commands_to_execute().is_empty());
+        queue.handle_add(dot_a, cmd(102), vec![], &time);
+        assert_eq!(queue.commands_to_execute(), vec![cmd(102), cmd(101)]);
+        assert_eq!(queue.vertex_index.dots().count(), 0);
+
+        // with a single shard, executed commands are not tracked for other
+        // shards
+        assert!(queue.to_executors().is_none());
+    }
+
+    #[test]
+    fn to_execute_chunked() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let mut chunked = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // create a large SCC: each command depends on the next one, and the
+        // last one depends on the first one
+        let scc_size = 100;
+        let dots: Vec<_> = (1..=scc_size).map(|seq| Dot::new(1, seq)).collect();
+        for (index, dot) in dots.iter().enumerate() {
+            let next = dots[(index + 1) % dots.len()];
+            let cmd = Command::from(
+                Rifl::new(1, dot.sequence()),
+                vec![(String::from("A"), KVOp::Put(10))],
+            );
+            let deps = vec![dep(next, shard_id)];
+            queue.handle_add(*dot, cmd.clone(), deps.clone(), &time);
+            chunked.handle_add(*dot, cmd, deps, &time);
+        }
+        let expected: Vec<_> = queue.commands_to_execute().into();
+        assert_eq!(expected.len(), scc_size as usize);
+
+        // drain in chunks of at most 7 commands
+        let max = 7;
+        let mut drained = Vec::new();
+        loop {
+            let chunk = chunked.to_execute_chunked(max);
+            assert!(chunk.len() <= max);
+            if chunk.is_empty() {
+                break;
+            }
+            drained.extend(chunk.into_iter().map(|(_, cmd)| cmd));
+        }
+        assert_eq!(drained, expected);
+        assert!(chunked.command_to_execute().is_none());
+    }
+
+    #[test]
+    fn iterative_scc_finder() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        let mut recursive = DependencyGraph::new(process_id, shard_id, &config);
+        config.set_executor_iterative_scc_finder(true);
+        let mut iterative = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // both finders should produce the same output, no matter the order in
+        // which commands are added
+        let events_per_process = 5;
+        let pruning = Pruning::None;
+        let seed = 0;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let args =
+            random_adds(shard_id, n, events_per_process, pruning, &mut rng);
+        for (dot, keys, dep_dots) in args {
+            let deps: Vec<_> = dep_dots
+                .into_iter()
+                .map(|dep_dot| dep(dep_dot, shard_id))
+                .collect();
+            let rifl = Rifl::new(dot.source() as ClientId, dot.sequence());
+            let keys = keys.expect("keys should exist");
+            let cmd = Command::from(
+                rifl,
+                keys.into_iter().map(|key| (key, KVOp::Put(10))),
+            );
+            recursive.handle_add(dot, cmd.clone(), deps.clone(), &time);
+            iterative.handle_add(dot, cmd, deps, &time);
+            assert_eq!(
+                recursive.commands_to_execute(),
+                iterative.commands_to_execute()
+            );
+        }
+    }
+
+    #[test]
+    fn iterative_scc_finder_long_chain() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_executor_iterative_scc_finder(true);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // create a chain too long for the recursive finder: each command
+        // depends on the next one, and the last one depends on the first one
+        let chain_length = 50_000;
+        let dots: Vec<_> =
+            (1..=chain_length).map(|seq| Dot::new(1, seq)).collect();
+        for (index, dot) in dots.iter().enumerate() {
+            let next = dots[(index + 1) % dots.len()];
+            let cmd = Command::from(
+                Rifl::new(1, dot.sequence()),
+                vec![(String::from("A"), KVOp::Put(10))],
+            );
+            queue.handle_add(*dot, cmd, vec![dep(next, shard_id)], &time);
+        }
+        assert_eq!(queue.commands_to_execute().len(), chain_length as usize);
+
+        // a single SCC with all the commands was found
+        let chain_size = queue
+            .metrics()
+            .get_collected(ExecutorMetricsKind::ChainSize)
+            .expect("chain size should be collected");
+        assert_eq!(chain_size.count(), 1);
+        assert_eq!(chain_size.max(), F64::new(chain_length as f64));
+    }
+
+    #[test]
+    fn prune_executed() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let time = RunTime;
+
+        let cmd = |seq| {
+            Command::from(
+                Rifl::new(1, seq),
+                vec![(String::from("A"), KVOp::Put(10))],
+            )
+        };
+
+        // dot (2, 1) is never added (e.g. it's from another shard), leaving a
+        // gap in the executed clock
+        let dot_1 = Dot::new(2, 2);
+        let dot_2 = Dot::new(2, 3);
+        // the vertex index is shared between clones, so create two queues
+        // that go through the same adds
+        let new_queue = || {
+            let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+            queue.handle_add(dot_1, cmd(1), vec![], &time);
+            queue.handle_add(dot_2, cmd(2), vec![dep(dot_1, shard_id)], &time);
+            assert_eq!(queue.commands_to_execute(), vec![cmd(1), cmd(2)]);
+            assert_eq!(
+                queue.executed_clock.frontier(),
+                util::vclock(vec![0, 0, 0])
+            );
+            queue
+        };
+        let mut queue = new_queue();
+
+        // prune the other queue below a stable clock
+        let mut pruned = new_queue();
+        pruned.prune_executed(&util::vclock(vec![0, 3, 0]));
+        assert_eq!(
+            pruned.executed_clock.frontier(),
+            util::vclock(vec![0, 3, 0])
+        );
+
+        // future adds produce the same results in both queues
+        let dot_3 = Dot::new(1, 1);
+        let dot_4 = Dot::new(3, 1);
+        let dot_5 = Dot::new(2, 4);
+        let adds = [
+            (dot_4, cmd(4), vec![dep(dot_3, shard_id)]),
+            (
+                dot_5,
+                cmd(5),
+                vec![dep(dot_2, shard_id), dep(dot_4, shard_id)],
+            ),
+            (dot_3, cmd(3), vec![dep(dot_2, shard_id)]),
+        ];
+        for (dot, cmd, deps) in adds {
+            queue.handle_add(dot, cmd.clone(), deps.clone(), &time);
+            pruned.handle_add(dot, cmd, deps, &time);
+            assert_eq!(
+                queue.commands_to_execute(),
+                pruned.commands_to_execute()
+            );
+        }
+        for dot in [dot_1, dot_2, dot_3, dot_4, dot_5] {
+            assert!(pruned
+                .executed_clock
+                .contains(&dot.source(), dot.sequence()));
+            assert!(queue
+                .executed_clock
+                .contains(&dot.source(), dot.sequence()));
+        }
+    }
+
+    #[test]
+    fn fifo_ordering() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_executor_fifo_ordering(true);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let mut time = SimTime::new();
+
+        // create dots
+        let dot_a = Dot::new(1, 1);
+        let dot_e = Dot::new(1, 2);
+        let dot_b = Dot::new(2, 1);
+        let dot_c = Dot::new(2, 2);
+        let dot_d = Dot::new(1, 3);
+
+        // create commands
+        let cmd = |seq| {
+            Command::from(
+                Rifl::new(1, seq),
+                vec![(String::from("A"), KVOp::Put(10))],
+            )
+        };
+        let cmd_a = cmd(1);
+        let cmd_e = cmd(2);
+        let cmd_b = cmd(3);
+        let cmd_c = cmd(4);
+        let cmd_d = cmd(5);
+
+        // add (in this order):
+        // - A, which depends on E
+        // - E, which depends on C
+        // - B, which depends on C
+        // - D, with no dependencies
+        // - C, with no dependencies
+        let deps_a = vec![dep(dot_e, shard_id)];
+        let deps_e = vec![dep(dot_c, shard_id)];
+        let deps_b = vec![dep(dot_c, shard_id)];
+        queue.handle_add(dot_a, cmd_a.clone(), deps_a, &time);
+        time.add_millis(1);
+        queue.handle_add(dot_e, cmd_e.clone(), deps_e, &time);
+        time.add_millis(1);
+        queue.handle_add(dot_b, cmd_b.clone(), deps_b, &time);
+        assert!(queue.commands_to_execute().is_empty());
+        time.add_millis(1);
+
+        // D doesn't depend on the commands added before it, and so it's
+        // returned right away
+        queue.handle_add(dot_d, cmd_d.clone(), vec![], &time);
+        assert_eq!(queue.commands_to_execute(), vec![cmd_d]);
+        time.add_millis(1);
+        queue.handle_add(dot_c, cmd_c.clone(), vec![], &time);
+
+        // although A only becomes ready after E, it was added before B, and
+        // so it's returned before B
+        assert_eq!(
+            queue.commands_to_execute(),
+            vec![cmd_c, cmd_e, cmd_a, cmd_b]
+        );
+    }
+
+    #[test]
+    fn dependency_edges() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        let value = 10;
+
+        // create dots
+        let dot_0 = Dot::new(1, 1);
+        let dot_1 = Dot::new(2, 1);
+        let dot_2 = Dot::new(3, 1);
+
+        // cmd 0 and cmd 1 depend on each other, and both depend on cmd 2,
+        // which is never added; this keeps both commands pending
+        let cmd_0 = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Put(value))],
+        );
+        let deps_0 = vec![dep(dot_1, shard_id), dep(dot_2, shard_id)];
+        let cmd_1 = Command::from(
+            Rifl::new(2, 1),
+            vec![(String::from("A"), KVOp::Put(value))],
+        );
+        let deps_1 = vec![dep(dot_0, shard_id), dep(dot_2, shard_id)];
+
+        queue.handle_add(dot_0, cmd_0, deps_0, &time);
+        queue.handle_add(dot_1, cmd_1, deps_1, &time);
+        assert!(queue.commands_to_execute().is_empty());
+
+        // both directed edges are reported, but not the ones to cmd 2
+        let edges: BTreeSet<_> = queue.dependency_edges().into_iter().collect();
+        assert_eq!(
+            edges,
+            BTreeSet::from_iter(vec![(dot_0, dot_1), (dot_1, dot_0)])
+        );
+    }
+
+    #[test]
+    fn vertices_on_key() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let key_c = String::from("C");
+        let value = 10;
+
+        // create dots
+        let dot_0 = Dot::new(1, 1);
+        let dot_1 = Dot::new(2, 1);
+        let dot_2 = Dot::new(3, 1);
+
+        // cmd 0 and cmd 1 share key `A`, and both depend on cmd 2, which
+        // keeps them pending
+        let cmd_0 = Command::from(
+            Rifl::new(1, 1),
+            vec![
+                (key_a.clone(), KVOp::Put(value)),
+                (key_b.clone(), KVOp::Put(value)),
+            ],
+        );
+        let cmd_1 = Command::from(
+            Rifl::new(2, 1),
+            vec![(key_a.clone(), KVOp::Put(value))],
+        );
+        let cmd_2 = Command::from(
+            Rifl::new(3, 1),
+            vec![(key_c.clone(), KVOp::Put(value))],
+        );
+        queue.handle_add(dot_0, cmd_0, vec![dep(dot_2, shard_id)], &time);
+        queue.handle_add(dot_1, cmd_1, vec![dep(dot_2, shard_id)], &time);
+
+        let on_key = |queue: &DependencyGraph, key: &Key| {
+            queue
+                .pending_on_key(key)
+                .into_iter()
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(
+            on_key(&queue, &key_a),
+            HashSet::from_iter(vec![dot_0, dot_1])
+        );
+        assert_eq!(on_key(&queue, &key_b), HashSet::from_iter(vec![dot_0]));
+        assert!(on_key(&queue, &key_c).is_empty());
+
+        // once cmd 2 is added, all commands are executed and removed from the
+        // index
+        queue.handle_add(dot_2, cmd_2, vec![], &time);
+        assert_eq!(queue.commands_to_execute().len(), 3);
+        assert!(on_key(&queue, &key_a).is_empty());
+        assert!(on_key(&queue, &key_b).is_empty());
+        assert!(on_key(&queue, &key_c).is_empty());
+    }
+
+    #[test]
+    fn with_capacity() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let time = RunTime;
+        let capacity = 100;
+
+        let mut default = DependencyGraph::new(process_id, shard_id, &config);
+        let mut hinted = DependencyGraph::with_capacity(
+            process_id, shard_id, &config, capacity,
+        );
+        // the hinted index is pre-sized
+        assert!(hinted.vertex_index.capacity() >= capacity);
+
+        // each command depends on the next one, so all commands stay pending
+        // until the last one is added
+        let dots: Vec<_> = (1..=capacity as u64)
+            .map(|sequence| Dot::new(process_id, sequence))
+            .collect();
+        let adds: Vec<_> = dots
+            .iter()
+            .enumerate()
+            .map(|(i, dot)| {
+                let cmd = Command::from(
+                    Rifl::new(1, dot.sequence()),
+                    vec![(String::from("A"), KVOp::Put(10))],
+                );
+                let deps = dots
+                    .get(i + 1)
+                    .map(|next| vec![dep(*next, shard_id)])
+                    .unwrap_or_default();
+                (*dot, cmd, deps)
+            })
+            .collect();
+
+        for (dot, cmd, deps) in adds {
+            default.handle_add(dot, cmd.clone(), deps.clone(), &time);
+            hinted.handle_add(dot, cmd, deps, &time);
+        }
+
+        // both graphs execute the same commands in the same order
+        let executed = hinted.commands_to_execute();
+        assert_eq!(executed.len(), capacity);
+        assert_eq!(executed, default.commands_to_execute());
+
+    }
+
+    #[test]
+    fn vertex_index_with_capacity() {
+        let process_id = 1;
+        let shards = 1;
+        let time = RunTime;
+        let capacity = 1000;
+
+        let mut index =
+            VertexIndex::with_capacity(process_id, shards, capacity);
+        let initial_capacity = index.capacity();
+        assert!(initial_capacity >= capacity);
+
+        // inserting `capacity` vertices doesn't resize the index
+        for sequence in 1..=capacity as u64 {
+            let dot = Dot::new(process_id, sequence);
+            let cmd = Command::from(
+                Rifl::new(1, sequence),
+                vec![(sequence.to_string(), KVOp::Put(10))],
+            );
+            let vertex = Vertex::new(dot, cmd, vec![], &time);
+            assert!(index.index(vertex).is_none());
+        }
+        assert_eq!(index.capacity(), initial_capacity);
+    }
+
+    #[test]
+    fn max_request_batch() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_shard_count(2);
+        let max_batch = 3;
+        config.set_executor_max_request_batch(max_batch);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = RunTime;
+
+        // the command depends on many commands from process 4, which belongs
+        // to shard 1, and thus all of them need to be requested
+        let missing_count = 10;
+        let missing: HashSet<_> = (1..=missing_count)
+            .map(|sequence| Dot::new(4, sequence))
+            .collect();
+        let deps = missing
+            .iter()
+            .map(|dot| dep(*dot, 1))
+            .collect::<HashSet<_>>();
+        let cmd = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Put(10))],
+        );
+        let info = GraphExecutionInfo::add(Dot::new(1, 1), cmd, deps);
+        executor.handle(info, &time);
+
+        // check that the missing dots are requested in a bounded number of
+        // messages, all to shard 1
+        let mut request_count = 0;
+        let mut requested = HashSet::new();
+        for (to, info) in executor.to_executors_iter() {
+            if let GraphExecutionInfo::Request { from, dots } = info {
+                assert_eq!(to, 1);
+                assert_eq!(from, shard_id);
+                assert!(dots.len() <= max_batch);
+                request_count += 1;
+                requested.extend(dots);
+            }
+        }
+        let expected_count =
+            (missing_count as usize + max_batch - 1) / max_batch;
+        assert_eq!(request_count, expected_count);
+        assert_eq!(requested, missing);
+    }
+
+    #[test]
+    fn cross_shard_wait() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let time = RunTime;
+
+        let cross_shard_wait = |queue: &DependencyGraph| {
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::CrossShardWait)
+                .cloned()
+                .unwrap_or_default()
+        };
+        let cmd = |sequence| {
+            Command::from(
+                Rifl::new(1, sequence),
+                vec![(String::from("A"), KVOp::Put(10))],
+            )
+        };
+
+        // with two shards, a command waiting on a command from shard 1 (by
+        // process 4) is accounted for
+        let mut config = Config::new(n, f);
+        config.set_shard_count(2);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let deps = vec![dep(Dot::new(4, 1), 1)];
+        queue.handle_add(Dot::new(1, 1), cmd(1), deps, &time);
+        assert_eq!(cross_shard_wait(&queue), 1);
+
+        // but not one waiting on a command from its own shard
+        let deps = vec![dep(Dot::new(2, 1), shard_id)];
+        queue.handle_add(Dot::new(1, 2), cmd(2), deps, &time);
+        assert_eq!(cross_shard_wait(&queue), 1);
+
+        // with a single shard, commands never wait on other shards
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let deps = vec![dep(Dot::new(2, 1), shard_id)];
+        queue.handle_add(Dot::new(1, 1), cmd(1), deps, &time);
+        assert_eq!(cross_shard_wait(&queue), 0);
+    }
+
+    #[test]
+    fn reset_metrics() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_shard_count(2);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = RunTime;
+
+        let out_requests = |executor: &GraphExecutor| {
+            executor
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::OutRequests)
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        // a command that depends on a command from shard 1 generates a request
+        let dot_a = Dot::new(1, 1);
+        let dot_b = Dot::new(4, 1);
+        let cmd_a = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Put(10))],
+        );
+        let deps_a = HashSet::from_iter(vec![dep(dot_b, 1)]);
+        executor.handle(GraphExecutionInfo::add(dot_a, cmd_a, deps_a), &time);
+        assert_eq!(out_requests(&executor), 1);
+
+        // after the reset, the metric reads zero
+        executor.reset_metrics();
+        assert_eq!(out_requests(&executor), 0);
+
+        // execution still works after the reset
+        let dot_c = Dot::new(1, 2);
+        let cmd_c = Command::from(
+            Rifl::new(2, 1),
+            vec![(String::from("B"), KVOp::Put(10))],
+        );
+        let deps_c = HashSet::new();
+        executor.handle(GraphExecutionInfo::add(dot_c, cmd_c, deps_c), &time);
+        let results: Vec<_> = executor.to_clients_iter().collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].rifl(), Rifl::new(2, 1));
+        assert_eq!(out_requests(&executor), 0);
+    }
+
+    #[test]
+    fn snapshot_read() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let config = Config::new(n, f);
+        let time = RunTime;
+
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let value = 10;
+
+        // a write on A, a write on B, and a snapshot read of both keys that
+        // depends on the two writes (the latest writes on its keys)
+        let write_a_dot = Dot::new(1, 1);
+        let write_a = Command::from(
+            Rifl::new(1, 1),
+            vec![(key_a.clone(), KVOp::Put(value))],
+        );
+        let write_b_dot = Dot::new(1, 2);
+        let write_b = Command::from(
+            Rifl::new(1, 2),
+            vec![(key_b.clone(), KVOp::Put(value))],
+        );
+        let read_dot = Dot::new(2, 1);
+        let read_rifl = Rifl::new(2, 1);
+        let read = Command::snapshot_read(
+            read_rifl,
+            vec![key_a.clone(), key_b.clone()],
+        );
+        let read_deps =
+            vec![dep(write_a_dot, shard_id), dep(write_b_dot, shard_id)];
+
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let mut store = KVStore::new(false);
+        let mut execute = |queue: &mut DependencyGraph| {
+            let mut read_results = HashMap::new();
+            for cmd in queue.commands_to_execute() {
+                let rifl = cmd.rifl();
+                for result in cmd.execute(shard_id, &mut store) {
+                    let result = result.unwrap_ready();
+                    if rifl == read_rifl {
+                        read_results.insert(result.key, result.partial_results);
+                    }
+                }
+            }
+            read_results
+        };
+
+        // the write on A is executed right away
+        queue.handle_add(write_a_dot, write_a, vec![], &time);
+        assert!(execute(&mut queue).is_empty());
+
+        // the read is a single vertex that waits for the write on B, and
+        // thus it never observes the write on A without the one on B
+        queue.handle_add(read_dot, read, read_deps, &time);
+        assert!(execute(&mut queue).is_empty());
+        assert_eq!(queue.pending_count(), 1);
+
+        // once the write on B is added, the read observes both writes
+        queue.handle_add(write_b_dot, write_b, vec![], &time);
+        let read_results = execute(&mut queue);
+        assert_eq!(read_results.len(), 2);
+        let expected = vec![KVOpResult::Value(Some(value))];
+        assert_eq!(read_results.get(&key_a), Some(&expected));
+        assert_eq!(read_results.get(&key_b), Some(&expected));
+        assert_eq!(queue.pending_count(), 0);
+    }
+
+    #[test]
+    fn snapshot_read_concurrent_write() {
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let config = Config::new(n, f);
+        let time = RunTime;
+
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let value = 10;
+
+        // a write on both keys and a snapshot read of both keys
+        let write_dot = Dot::new(1, 1);
+        let write = Command::from(
+            Rifl::new(1, 1),
+            vec![
+                (key_a.clone(), KVOp::Put(value)),
+                (key_b.clone(), KVOp::Put(value)),
+            ],
+        );
+        let read_dot = Dot::new(2, 1);
+        let read_rifl = Rifl::new(2, 1);
+        let read = Command::snapshot_read(
+            read_rifl,
+            vec![key_a.clone(), key_b.clone()],
+        );
+
+        // since the commands are concurrent, the read may depend on the
+        // write, the write may depend on the read, or both
+        let all_deps = vec![
+            (vec![], vec![dep(write_dot, shard_id)]),
+            (vec![dep(read_dot, shard_id)], vec![]),
+            (
+                vec![dep(read_dot, shard_id)],
+                vec![dep(write_dot, shard_id)],
+            ),
+        ];
+
+        for (write_deps, read_deps) in all_deps {
+            let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+            queue.handle_add(write_dot, write.clone(), write_deps, &time);
+            queue.handle_add(read_dot, read.clone(), read_deps, &time);
+
+            // execute commands in the order they became ready
+            let mut store = KVStore::new(false);
+            let mut read_results = HashMap::new();
+            for cmd in queue.commands_to_execute() {
+                let rifl = cmd.rifl();
+                for result in cmd.execute(shard_id, &mut store) {
+                    let result = result.unwrap_ready();
+                    if rifl == read_rifl {
+                        read_results.insert(result.key, result.partial_results);
+                    }
+                }
+            }
+
+            // the read either observes the write on both keys or on none
+            assert_eq!(read_results.len(), 2);
+            assert_eq!(read_results.get(&key_a), read_results.get(&key_b));
+        }
+    }
+
+    /// We have 5 commands by the same process (process A) that access the same
+    /// key. We have `n = 5` and `f = 1` and thus the fast quorum size of 3.
+    /// The fast quorum used by process A is `{A, B, C}`. We have the
+    /// following order of commands in the 3 processes:
+    /// - A: (1,1) (1,2) (1,3) (1,4) (1,5)
+    /// - B: (1,3) (1,4) (1,1) (1,2) (1,5)
+    /// - C: (1,1) (1,2) (1,4) (1,5) (1,3)
+    ///
+    /// The above results in the following final dependencies:
+    /// - dep[(1,1)] = {(1,4)}
+    /// - dep[(1,2)] = {(1,4)}
+    /// - dep[(1,3)] = {(1,5)}
+    /// - dep[(1,4)] = {(1,3)}
+    /// - dep[(1,5)] = {(1,4)}
+    ///
+    /// The executor then receives the commit notifications of (1,3) (1,4) and
+    /// (1,5) and, if transitive conflicts are assumed, these 3 commands
+    /// form an SCC. This is because with this assumption we only check the
+    /// highest conflicting command per replica, and thus (1,3) (1,4) and
+    /// (1,5) have "all the dependencies".
+    ///
+    /// Then, the executor executes whichever missing command comes first, since
+    /// (1,1) and (1,2) "only depend on an SCC already formed". This means that
+    /// if two executors receive (1,3) (1,4) (1,5), and then one receives (1,1)
+    /// and (1,2) while the other receives (1,2) and (1,1), they will execute
+    /// (1,1) and (1,2) in differents order, leading to an inconsistency.
+    ///
+    /// This example is impossible if commands from the same process are
+    /// processed (on the replicas computing dependencies) in their submission
+    /// order. With this, a command never depends on later commands from the
+    /// same process, which seems to be enough to prevent this issue. This means
+    /// that parallelizing the processing of messages needs to be on a
+    /// per-process basis, i.e. commands by the same process are always
+    /// processed by the same worker.
+    #[test]
+    fn transitive_conflicts_assumption_regression_test_1() {
+        // config
+        let n = 5;
+
+        // dots
+        let dot_1 = Dot::new(1, 1);
+        let dot_2 = Dot::new(1, 2);
+        let dot_3 = Dot::new(1, 3);
+        let dot_4 = Dot::new(1, 4);
+        let dot_5 = Dot::new(1, 5);
+
+        // deps
+        let deps_1 = HashSet::from_iter(vec![dot_4]);
+        let deps_2 = HashSet::from_iter(vec![dot_4]);
+        let deps_3 = HashSet::from_iter(vec![dot_5]);
+        let deps_4 = HashSet::from_iter(vec![dot_3]);
+        let deps_5 = HashSet::from_iter(vec![dot_4]);
+
+        let order_a = vec![
+            (dot_3, None, deps_3.clone()),
+            (dot_4, None, deps_4.clone()),
+            (dot_5, None, deps_5.clone()),
+            (dot_1, None, deps_1.clone()),
+            (dot_2, None, deps_2.clone()),
+        ];
+        let order_b = vec![
+            (dot_3, None, deps_3),
+            (dot_4, None, deps_4),
+            (dot_5, None, deps_5),
+            (dot_2, None, deps_2),
+            (dot_1, None, deps_1),
+        ];
+        let order_a = check_termination(n, order_a);
+        let order_b = check_termination(n, order_b);
+        assert!(order_a != order_b);
+    }
+
+    /// Simple example showing why encoding of dependencies matters for the
+    /// `transitive_conflicts` optimization to be correct (which, makes the name
+    /// of the optimization not great):
+    /// - 3 replicas (A, B, C), and 3 commands
+    ///   * command (A, 1), keys = {x}
+    ///   * command (A, 2), keys = {y}
+    ///   * command (B, 1), keys = {x, y}
+    ///
+    /// First, (A, 1) is submitted and gets no dependencies:
+    /// - {A -> 0, B -> 0, C -> 0}
+    /// Then, (A, 2) is submitted and also gets no dependencies:
+    /// - {A -> 0, B -> 0, C -> 0}
+    /// Finally, (B, 1) is submitted and gets (A, 2) as a dependency:
+    /// - {A -> 2, B -> 0, C -> 0}
+    /// It only gets (A, 2) because we only return the highest conflicting
+    /// command from each replica.
+    ///
+    /// With the optimization, the order in which commands are received by the
+    /// ordering component affects results:
+    /// - (A, 1), (A, 2), (B, 1): commands are executed in the order they're
+    ///   received, producing correct results
+    /// - (A, 2), (B, 1), (A, 1): (B, 1) is executed before (A, 1) and shouldn't
+    ///
+    /// Without the optimization, (B, 1) would be forced to wait for (A, 1) in
+    /// the last case, producing a correct result.
+    ///
+    /// It looks like the optimization would be correct if, instead of returning
+    /// the highest conflicting command per replica, we would return the highest
+    /// conflict command per replica *per key*.
+    #[test]
+    fn transitive_conflicts_assumption_regression_test_2() {
+        // config
+        let n = 3;
+
+        let keys = |keys: Vec<&str>| {
+            keys.into_iter()
+                .map(|key| key.to_string())
+                .collect::<BTreeSet<_>>()
+        };
+
+        // cmd 1,1
+        let dot_1_1 = Dot::new(1, 1);
+        let keys_1_1 = keys(vec!["A"]);
+        let deps_1_1 = HashSet::new();
+
+        // cmd 1,2
+        let dot_1_2 = Dot::new(1, 2);
+        let keys_1_2 = keys(vec!["B"]);
+        let deps_1_2 = HashSet::new();
+
+        // cmd 2,1
+        let dot_2_1 = Dot::new(2, 1);
+        let keys_2_1 = keys(vec!["A", "B"]);
+        let deps_2_1 = HashSet::from_iter(vec![dot_1_2]);
 
         let order_a = vec![
             (dot_1_1, Some(keys_1_1.clone()), deps_1_1.clone()),
@@ -920,198 +2158,37 @@ mod tests {
 
     #[test]
     fn test_add_random() {
-        let shard_id = 0;
         let n = 2;
         let iterations = 10;
         let events_per_process = 3;
 
         (0..iterations).for_each(|_| {
-            let args = random_adds(shard_id, n, events_per_process);
-            shuffle_it(n, args);
-        });
-    }
-
-    fn random_adds(
-        shard_id: ShardId,
-        n: usize,
-        events_per_process: usize,
-    ) -> Vec<(Dot, Option<BTreeSet<Key>>, HashSet<Dot>)> {
-        let mut possible_keys: Vec<_> =
-            ('A'..='D').map(|key| key.to_string()).collect();
-
-        // create dots
-        let dots: Vec<_> = fantoch::util::process_ids(shard_id, n)
-            .flat_map(|process_id| {
-                (1..=events_per_process)
-                    .map(move |event| Dot::new(process_id, event as u64))
-            })
-            .collect();
-
-        // compute keys and empty deps
-        let deps: HashMap<_, _> = dots
-            .clone()
-            .into_iter()
-            .map(|dot| {
-                // select two random keys from the set of possible keys:
-                // - this makes sure that the conflict relation is not
-                //   transitive
-                possible_keys.shuffle(&mut rand::thread_rng());
-                let mut keys = BTreeSet::new();
-                assert!(keys.insert(possible_keys[0].clone()));
-                assert!(keys.insert(possible_keys[1].clone()));
-                // create empty deps
-                let deps = HashSet::new();
-                (dot, (Some(keys), RefCell::new(deps)))
-            })
-            .collect();
-
-        // for each pair of dots
-        dots.combination(2).for_each(|dots| {
-            let left = dots[0];
-            let right = dots[1];
-
-            // find their data
-            let (left_keys, left_clock) =
-                deps.get(left).expect("left dot data must exist");
-            let (right_keys, right_clock) =
-                deps.get(right).expect("right dot data must exist");
-
-            // unwrap keys
-            let left_keys = left_keys.as_ref().expect("left keys should exist");
-            let right_keys =
-                right_keys.as_ref().expect("right keys should exist");
-
-            // check if the commands conflict (i.e. if the keys being accessed
-            // intersect)
-            let conflict = left_keys.intersection(&right_keys).next().is_some();
-
-            // if the commands conflict, then make sure at least one is a
-            // dependency of the other
-            if conflict {
-                // borrow their clocks mutably
-                let mut left_deps = left_clock.borrow_mut();
-                let mut right_deps = right_clock.borrow_mut();
-
-                if left.source() == right.source() {
-                    // if dots belong to the same process, make the latest
-                    // depend on the oldest
-                    match left.sequence().cmp(&right.sequence()) {
-                        Ordering::Less => right_deps.insert(*left),
-                        Ordering::Greater => left_deps.insert(*right),
-                        _ => unreachable!("dots must be different"),
-                    };
-                } else {
-                    // otherwise, make them depend on each other (maybe both
-                    // ways)
-                    let mut rng = rand::thread_rng();
-                    match rng.gen_range(0..3) {
-                        0 => {
-                            // left depends on right
-                            left_deps.insert(*right);
-                        }
-                        1 => {
-                            // right depends on left
-                            right_deps.insert(*left);
-                        }
-                        2 => {
-                            // both
-                            left_deps.insert(*right);
-                            right_deps.insert(*left);
-                        }
-                        _ => panic!("out-of-bounds random number"),
-                    }
-                }
-            }
-        });
-
-        deps.into_iter()
-            .map(|(dot, (keys, deps_cell))| {
-                let deps = deps_cell.into_inner();
-                (dot, keys, deps)
-            })
-            .collect()
-    }
-
-    fn shuffle_it(
-        n: usize,
-        mut args: Vec<(Dot, Option<BTreeSet<Key>>, HashSet<Dot>)>,
-    ) {
-        let total_order = check_termination(n, args.clone());
-        args.permutation().for_each(|permutation| {
-            println!("permutation = {:?}", permutation);
-            let sorted = check_termination(n, permutation);
-            assert_eq!(total_order, sorted);
+            let seed = rand::thread_rng().gen();
+            fuzz_queue(n, events_per_process, seed);
         });
     }
 
-    fn check_termination(
-        n: usize,
-        args: Vec<(Dot, Option<BTreeSet<Key>>, HashSet<Dot>)>,
-    ) -> BTreeMap<Key, Vec<Rifl>> {
-        // create queue
-        let process_id = 1;
-        let shard_id = 0;
-        let f = 1;
-        let config = Config::new(n, f);
-        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
-        let time = RunTime;
-        let mut all_rifls = HashSet::new();
-        let mut sorted = BTreeMap::new();
-
-        args.into_iter().for_each(|(dot, keys, dep_dots)| {
-            // transform dep dots into deps
-            let deps = dep_dots
-                .into_iter()
-                .map(|dep_dot| dep(dep_dot, shard_id))
-                .collect();
-            // create command rifl from its dot
-            let rifl = Rifl::new(dot.source() as ClientId, dot.sequence());
-
-            // create command:
-            // - set single CONF key if no keys were provided
-            let keys = keys.unwrap_or_else(|| {
-                BTreeSet::from_iter(vec![String::from("CONF")])
-            });
-            let ops = keys.into_iter().map(|key| {
-                let value = 10;
-                (key, KVOp::Put(value))
-            });
-            let cmd = Command::from(rifl, ops);
-
-            // add to the set of all rifls
-            assert!(all_rifls.insert(rifl));
-
-            // add it to the queue
-            queue.handle_add(dot, cmd, deps, &time);
-
-            // get ready to execute
-            let to_execute = queue.commands_to_execute();
-
-            // for each command ready to be executed
-            to_execute.iter().for_each(|cmd| {
-                // get its rifl
-                let rifl = cmd.rifl();
-
-                // remove it from the set of rifls
-                assert!(all_rifls.remove(&cmd.rifl()));
-
-                // and add it to the sorted results
-                cmd.keys(shard_id).for_each(|key| {
-                    sorted
-                        .entry(key.clone())
-                        .or_insert_with(Vec::new)
-                        .push(rifl);
-                })
-            });
-        });
-
-        // the set of all rifls should be empty
-        if !all_rifls.is_empty() {
-            panic!("the set of all rifls should be empty");
-        }
+    #[test]
+    fn fuzz_queue_transitive_conflicts_regression() {
+        // with this seed, keeping only the highest conflicting command from
+        // each process (regardless of the key) makes the total order depend on
+        // the order in which commands are added (see
+        // `transitive_conflicts_assumption_regression_test_2`); keeping the
+        // highest one on each key doesn't
+        let n = 2;
+        let events_per_process = 3;
+        let seed = 13;
 
-        // return sorted commands
-        sorted
+        fuzz_queue(n, events_per_process, seed);
+        let per_process = Pruning::HighestPerProcess;
+        assert!(
+            try_fuzz_queue(n, events_per_process, seed, per_process).is_err()
+        );
+        let per_key = Pruning::HighestPerKey;
+        assert_eq!(
+            try_fuzz_queue(n, events_per_process, seed, per_key),
+            Ok(())
+        );
     }
 
     #[test]
@@ -22,6 +22,15 @@ pub enum FinderResult {
     NotFound,
 }
 
+// State of a vertex being visited by `strong_connect_iterative`.
+struct Frame<'a> {
+    dot: Dot,
+    vertex_ref: VertexRef<'a>,
+    // index of the next dependency to be visited
+    next_dep: usize,
+    missing_deps_count: usize,
+}
+
 #[derive(Clone)]
 pub struct TarjanSCCFinder {
     process_id: ProcessId,
@@ -100,6 +109,42 @@ impl TarjanSCCFinder {
         vertex_index: &VertexIndex,
         scc_count: &mut usize,
         missing_deps_count: &mut usize,
+    ) -> FinderResult {
+        if self.config.executor_iterative_scc_finder() {
+            self.strong_connect_iterative(
+                first_find,
+                dot,
+                executed_clock,
+                added_to_executed_clock,
+                vertex_index,
+                scc_count,
+                missing_deps_count,
+            )
+        } else {
+            self.strong_connect_recursive(
+                first_find,
+                dot,
+                vertex_ref,
+                executed_clock,
+                added_to_executed_clock,
+                vertex_index,
+                scc_count,
+                missing_deps_count,
+            )
+        }
+    }
+
+    // Recursive implementation of Tarjan's algorithm.
+    fn strong_connect_recursive(
+        &mut self,
+        first_find: bool,
+        dot: Dot,
+        vertex_ref: &VertexRef<'_>,
+        executed_clock: &mut AEClock<ProcessId>,
+        added_to_executed_clock: &mut HashSet<Dot>,
+        vertex_index: &VertexIndex,
+        scc_count: &mut usize,
+        missing_deps_count: &mut usize,
     ) -> FinderResult {
         // update id
         self.id += 1;
@@ -181,7 +226,7 @@ impl TarjanSCCFinder {
                         // OPTIMIZATION: passing the dep vertex ref as an
                         // argument to `strong_connect` avoids double look-up
                         let mut dep_missing_deps_count = 0;
-                        let result = self.strong_connect(
+                        let result = self.strong_connect_recursive(
                             first_find,
                             dep_dot,
                             &dep_vertex_ref,
@@ -228,88 +273,267 @@ impl TarjanSCCFinder {
         // vertex.low
         // - good news: the SCC members are on the stack
         if *missing_deps_count == 0 && vertex.id == vertex.low {
-            let mut scc = SCC::new();
-
             // drop guards
             drop(vertex);
             drop(vertex_ref);
 
-            loop {
-                // pop an element from the stack
-                let member_dot = self
-                    .stack
-                    .pop()
-                    .expect("there should be an SCC member on the stack");
+            self.pop_scc(
+                dot,
+                executed_clock,
+                added_to_executed_clock,
+                vertex_index,
+                scc_count,
+            );
+            FinderResult::Found
+        } else {
+            FinderResult::NotFound
+        }
+    }
 
-                debug!(
-                    "p{}: Finder::strong_connect new SCC member {:?}",
-                    self.process_id, member_dot
-                );
+    // Iterative implementation of Tarjan's algorithm: instead of recursing on
+    // each non-visited dependency, an explicit stack of frames is kept, which
+    // allows the traversal of arbitrarily long dependency chains. The SCCs
+    // found (and the order in which they're found) are the same as the ones
+    // found by the recursive implementation.
+    fn strong_connect_iterative(
+        &mut self,
+        first_find: bool,
+        dot: Dot,
+        executed_clock: &mut AEClock<ProcessId>,
+        added_to_executed_clock: &mut HashSet<Dot>,
+        vertex_index: &VertexIndex,
+        scc_count: &mut usize,
+        missing_deps_count: &mut usize,
+    ) -> FinderResult {
+        let vertex_ref =
+            vertex_index.find(&dot).expect("root vertex should exist");
+        let mut frames = vec![self.visit(dot, vertex_ref)];
+
+        loop {
+            let frame = frames.last_mut().expect("there should be a frame");
 
-                // get its vertex and change its `on_stack` value
-                let member_vertex_ref = vertex_index
-                    .find(&member_dot)
-                    .expect("stack member should exist");
-
-                // increment number of commands found
-                *scc_count += 1;
-
-                // get its vertex and change its `on_stack` value
-                let mut member_vertex = member_vertex_ref.write();
-                member_vertex.on_stack = false;
-
-                // add it to the SCC and check it wasn't there before
-                assert!(scc.insert(member_dot));
-
-                // drop guards
-                drop(member_vertex);
-                drop(member_vertex_ref);
-
-                // update executed clock:
-                // - this is a nice optimization (that I think we missed in
-                //   Atlas); instead of waiting for the root-level recursion to
-                //   finish in order to update `executed_clock` (which is
-                //   consulted to decide what are the dependencies of a
-                //   command), we can update it right here, possibly reducing a
-                //   few iterations
-
-                // TODO add this check back:
-                // check if the command is replicated by my shard
-                // let is_mine =
-                // member_vertex.cmd.replicated_by(&self.shard_id);
-                // if executed_clock.write("Finder::strong_connect", |clock| {
-                //     clock.add(&member_dot.source(), member_dot.sequence())
-                // })
-                // && is_mine
-                // {
-                //     panic!(
-                //         "p{}: Finder::strong_connect dot {:?} already
-                // executed",         self.process_id,
-                // member_dot     );
-                // }
-                executed_clock.add(&member_dot.source(), member_dot.sequence());
-                if self.config.shard_count() > 1 {
-                    added_to_executed_clock.insert(member_dot);
+            // find the next dependency that hasn't been visited yet (if any)
+            let mut vertex = frame.vertex_ref.write();
+            let mut next = None;
+            while next.is_none() && frame.next_dep < vertex.deps.len() {
+                // get dep dot
+                let dep_dot = vertex.deps[frame.next_dep].dot;
+                frame.next_dep += 1;
+
+                // ignore self or if already executed
+                if dep_dot == frame.dot
+                    || executed_clock
+                        .contains(&dep_dot.source(), dep_dot.sequence())
+                {
+                    trace!(
+                        "p{}: Finder::strong_connect ignoring dependency {:?}",
+                        self.process_id,
+                        dep_dot
+                    );
+                    continue;
                 }
 
+                match vertex_index.find(&dep_dot) {
+                    None => {
+                        let dep = vertex.deps[frame.next_dep - 1].clone();
+                        debug!(
+                            "p{}: Finder::strong_connect missing {:?}",
+                            self.process_id, dep
+                        );
+                        if self.config.shard_count() == 1 || !first_find {
+                            // give up, accounting for the missing deps found
+                            // so far
+                            drop(vertex);
+                            *missing_deps_count += frames
+                                .iter()
+                                .map(|frame| frame.missing_deps_count)
+                                .sum::<usize>();
+                            return FinderResult::MissingDependencies(
+                                singleton![dep],
+                            );
+                        } else {
+                            // see `strong_connect_recursive`
+                            self.missing_deps.insert(dep);
+                            frame.missing_deps_count += 1;
+                        }
+                    }
+                    Some(dep_vertex_ref) => {
+                        let dep_vertex = dep_vertex_ref.read();
+                        if dep_vertex.id == 0 {
+                            // if not visited, visit it next
+                            drop(dep_vertex);
+                            next = Some((dep_dot, dep_vertex_ref));
+                        } else if dep_vertex.on_stack {
+                            trace!("p{}: Finder::strong_connect dependency on stack {:?}", self.process_id, dep_dot);
+                            // min low with dep id
+                            vertex.low = cmp::min(vertex.low, dep_vertex.id);
+                        }
+                    }
+                }
+            }
+            drop(vertex);
+
+            if let Some((dep_dot, dep_vertex_ref)) = next {
                 trace!(
-                    "p{}: Finder::strong_connect executed clock {:?}",
+                    "p{}: Finder::strong_connect non-visited {:?}",
                     self.process_id,
-                    executed_clock
+                    dep_dot
                 );
+                let frame = self.visit(dep_dot, dep_vertex_ref);
+                frames.push(frame);
+                continue;
+            }
 
-                // quit if root is found
-                if member_dot == dot {
-                    break;
+            // if after visiting all neighbors, an SCC was found if vertex.id
+            // == vertex.low
+            let frame = frames.pop().expect("there should be a frame");
+            let (id, low) = {
+                let vertex = frame.vertex_ref.read();
+                (vertex.id, vertex.low)
+            };
+            let result = if frame.missing_deps_count == 0 && id == low {
+                drop(frame.vertex_ref);
+                self.pop_scc(
+                    frame.dot,
+                    executed_clock,
+                    added_to_executed_clock,
+                    vertex_index,
+                    scc_count,
+                );
+                FinderResult::Found
+            } else {
+                FinderResult::NotFound
+            };
+
+            match frames.last_mut() {
+                Some(parent) => {
+                    // update missing deps count with the number of missing
+                    // deps of our dep, and min low with dep low
+                    parent.missing_deps_count += frame.missing_deps_count;
+                    let mut parent_vertex = parent.vertex_ref.write();
+                    parent_vertex.low = cmp::min(parent_vertex.low, low);
+                }
+                None => {
+                    // the root was visited
+                    *missing_deps_count += frame.missing_deps_count;
+                    return result;
                 }
             }
+        }
+    }
 
-            // add scc to to the set of sccs
-            self.sccs.push(scc);
-            FinderResult::Found
-        } else {
-            FinderResult::NotFound
+    // Visits vertex `dot`, pushing it to the stack.
+    fn visit<'a>(&mut self, dot: Dot, vertex_ref: VertexRef<'a>) -> Frame<'a> {
+        // update id
+        self.id += 1;
+
+        // set id and low for vertex
+        let mut vertex = vertex_ref.write();
+        vertex.id = self.id;
+        vertex.low = self.id;
+
+        // add to the stack
+        vertex.on_stack = true;
+        self.stack.push(dot);
+        drop(vertex);
+
+        debug!(
+            "p{}: Finder::strong_connect {:?} with id {}",
+            self.process_id, dot, self.id
+        );
+
+        Frame {
+            dot,
+            vertex_ref,
+            next_dep: 0,
+            missing_deps_count: 0,
+        }
+    }
+
+    // Pops the SCC whose root is `dot` from the stack, marking its members as
+    // executed.
+    fn pop_scc(
+        &mut self,
+        dot: Dot,
+        executed_clock: &mut AEClock<ProcessId>,
+        added_to_executed_clock: &mut HashSet<Dot>,
+        vertex_index: &VertexIndex,
+        scc_count: &mut usize,
+    ) {
+        let mut scc = SCC::new();
+
+        loop {
+            // pop an element from the stack
+            let member_dot = self
+                .stack
+                .pop()
+                .expect("there should be an SCC member on the stack");
+
+            debug!(
+                "p{}: Finder::strong_connect new SCC member {:?}",
+                self.process_id, member_dot
+            );
+
+            // get its vertex and change its `on_stack` value
+            let member_vertex_ref = vertex_index
+                .find(&member_dot)
+                .expect("stack member should exist");
+
+            // increment number of commands found
+            *scc_count += 1;
+
+            // get its vertex and change its `on_stack` value
+            let mut member_vertex = member_vertex_ref.write();
+            member_vertex.on_stack = false;
+
+            // add it to the SCC and check it wasn't there before
+            assert!(scc.insert(member_dot));
+
+            // drop guards
+            drop(member_vertex);
+            drop(member_vertex_ref);
+
+            // update executed clock:
+            // - this is a nice optimization (that I think we missed in
+            //   Atlas); instead of waiting for the root-level recursion to
+            //   finish in order to update `executed_clock` (which is
+            //   consulted to decide what are the dependencies of a
+            //   command), we can update it right here, possibly reducing a
+            //   few iterations
+
+            // TODO add this check back:
+            // check if the command is replicated by my shard
+            // let is_mine =
+            // member_vertex.cmd.replicated_by(&self.shard_id);
+            // if executed_clock.write("Finder::strong_connect", |clock| {
+            //     clock.add(&member_dot.source(), member_dot.sequence())
+            // })
+            // && is_mine
+            // {
+            //     panic!(
+            //         "p{}: Finder::strong_connect dot {:?} already
+            // executed",         self.process_id,
+            // member_dot     );
+            // }
+            executed_clock.add(&member_dot.source(), member_dot.sequence());
+            if self.config.shard_count() > 1 {
+                added_to_executed_clock.insert(member_dot);
+            }
+
+            trace!(
+                "p{}: Finder::strong_connect executed clock {:?}",
+                self.process_id,
+                executed_clock
+            );
+
+            // quit if root is found
+            if member_dot == dot {
+                break;
+            }
         }
+
+        // add scc to to the set of sccs
+        self.sccs.push(scc);
     }
 }
 
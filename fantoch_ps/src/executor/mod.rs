@@ -11,6 +11,8 @@ mod pred;
 mod slot;
 
 // Re-exports.
+#[cfg(any(test, feature = "fuzz"))]
+pub use graph::fuzz as graph_fuzz;
 pub use graph::{GraphExecutionInfo, GraphExecutor};
 pub use pred::{PredecessorsExecutionInfo, PredecessorsExecutor};
 pub use slot::{SlotExecutionInfo, SlotExecutor};
@@ -1,15 +1,17 @@
 use crate::executor::pred::PredecessorsGraph;
 use crate::protocol::common::pred::{CaesarDeps, Clock};
-use fantoch::command::Command;
+use fantoch::command::{Command, PhaseTimes};
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::executor::{
     ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
 };
-use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::id::{Dot, ProcessId, Rifl, ShardId};
 use fantoch::kvs::KVStore;
 use fantoch::protocol::{CommittedAndExecuted, MessageIndex};
 use fantoch::time::SysTime;
 use fantoch::trace;
+use fantoch::HashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::sync::Arc;
@@ -21,6 +23,9 @@ pub struct PredecessorsExecutor {
     graph: PredecessorsGraph,
     store: KVStore,
     to_clients: VecDeque<ExecutorResult>,
+    // time (in millis) at which each command not yet executed was committed
+    // (i.e. handed to this executor)
+    commit_times: HashMap<Rifl, u64>,
 }
 
 impl Executor for PredecessorsExecutor {
@@ -28,7 +33,10 @@ impl Executor for PredecessorsExecutor {
 
     fn new(process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
         let graph = PredecessorsGraph::new(process_id, &config);
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let store = KVStore::with_mode(
+            config.executor_monitor_execution_order(),
+            config.storage_mode(),
+        );
         let to_clients = Default::default();
         Self {
             process_id,
@@ -36,23 +44,25 @@ impl Executor for PredecessorsExecutor {
             graph,
             store,
             to_clients,
+            commit_times: HashMap::new(),
         }
     }
 
     fn handle(&mut self, info: PredecessorsExecutionInfo, time: &dyn SysTime) {
         // handle new command
+        self.commit_times.insert(info.cmd.rifl(), time.millis());
         self.graph
             .add(info.dot, info.cmd, info.clock, info.deps, time);
 
         // get more commands that are ready to be executed
-        while let Some(cmd) = self.graph.command_to_execute() {
+        while let Some((dot, cmd)) = self.graph.command_to_execute() {
             trace!(
                 "p{}: PredecessorsExecutor::comands_to_execute {:?} | time = {}",
                 self.process_id,
                 cmd.rifl(),
                 time.millis()
             );
-            self.execute(cmd);
+            self.execute(dot, cmd, time);
         }
     }
 
@@ -88,14 +98,31 @@ impl Executor for PredecessorsExecutor {
 }
 
 impl PredecessorsExecutor {
-    fn execute(&mut self, cmd: Command) {
-        // execute the command
-        let results = cmd.execute(self.shard_id, &mut self.store);
-        self.to_clients.extend(results);
+    fn execute(&mut self, dot: Dot, cmd: Command, time: &dyn SysTime) {
+        let execute_time = time.millis();
+        let commit_time = self
+            .commit_times
+            .remove(&cmd.rifl())
+            .unwrap_or(execute_time);
+        let phase_times = PhaseTimes::new(commit_time, execute_time);
+
+        // execute the command (which only happens as its results are
+        // collected)
+        let rifl = cmd.rifl();
+        let results =
+            cmd.execute_from(self.shard_id, dot.source(), &mut self.store);
+        command_span!(
+            "execute",
+            rifl,
+            time,
+            self.to_clients.extend(
+                results.map(|result| result.with_phase_times(phase_times))
+            )
+        );
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PredecessorsExecutionInfo {
     dot: Dot,
     cmd: Command,
@@ -39,7 +39,7 @@ pub struct PredecessorsGraph {
     new_committed_dots: u64,
     // dots of new commands executed
     new_executed_dots: Vec<Dot>,
-    to_execute: VecDeque<Command>,
+    to_execute: VecDeque<(Dot, Command)>,
     execute_at_commit: bool,
 }
 
@@ -78,15 +78,18 @@ impl PredecessorsGraph {
         }
     }
 
-    /// Returns a new command ready to be executed.
+    /// Returns a new command ready to be executed, along with its dot.
     #[must_use]
-    pub fn command_to_execute(&mut self) -> Option<Command> {
+    pub fn command_to_execute(&mut self) -> Option<(Dot, Command)> {
         self.to_execute.pop_front()
     }
 
     #[cfg(test)]
     fn commands_to_execute(&mut self) -> VecDeque<Command> {
         std::mem::take(&mut self.to_execute)
+            .into_iter()
+            .map(|(_, cmd)| cmd)
+            .collect()
     }
 
     fn committed_and_executed(&mut self) -> CommittedAndExecuted {
@@ -379,7 +382,7 @@ impl PredecessorsGraph {
         assert!(self.executed_clock.add(&dot.source(), dot.sequence()));
 
         // add command to commands to be executed
-        self.to_execute.push_back(cmd);
+        self.to_execute.push_back((dot, cmd));
     }
 }
 
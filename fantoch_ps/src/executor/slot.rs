@@ -1,4 +1,5 @@
-use fantoch::command::Command;
+use fantoch::command::{Command, PhaseTimes};
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::executor::{
     ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
@@ -20,7 +21,8 @@ pub struct SlotExecutor {
     store: KVStore,
     next_slot: Slot,
     // TODO maybe BinaryHeap
-    to_execute: HashMap<Slot, Command>,
+    // mapping from slot to its command and the time at which it was committed
+    to_execute: HashMap<Slot, (Command, u64)>,
     metrics: ExecutorMetrics,
     to_clients: VecDeque<ExecutorResult>,
 }
@@ -28,8 +30,11 @@ pub struct SlotExecutor {
 impl Executor for SlotExecutor {
     type ExecutionInfo = SlotExecutionInfo;
 
-    fn new(_process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
-        let store = KVStore::new(config.executor_monitor_execution_order());
+    fn new(process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
+        let store = KVStore::with_mode(
+            config.executor_monitor_execution_order(),
+            config.storage_mode().for_process(process_id),
+        );
         // the next slot to be executed is 1
         let next_slot = 1;
         // there's nothing to execute in the beginning
@@ -47,24 +52,25 @@ impl Executor for SlotExecutor {
         }
     }
 
-    fn handle(&mut self, info: Self::ExecutionInfo, _time: &dyn SysTime) {
+    fn handle(&mut self, info: Self::ExecutionInfo, time: &dyn SysTime) {
         let SlotExecutionInfo { slot, cmd } = info;
         // we shouldn't receive execution info about slots already executed
         // TODO actually, if recovery is involved, then this may not be
         // necessarily true
         assert!(slot >= self.next_slot);
+        let commit_time = time.millis();
 
         if self.config.execute_at_commit() {
-            self.execute(cmd);
+            self.execute(cmd, commit_time, time);
         } else {
             // add received command to the commands to be executed and try to
             // execute commands
             // TODO here we could optimize and only insert the command if it
             // isn't the command that will be executed in the next
             // slot
-            let res = self.to_execute.insert(slot, cmd);
+            let res = self.to_execute.insert(slot, (cmd, commit_time));
             assert!(res.is_none());
-            self.try_next_slot();
+            self.try_next_slot(time);
         }
     }
 
@@ -86,20 +92,33 @@ impl Executor for SlotExecutor {
 }
 
 impl SlotExecutor {
-    fn try_next_slot(&mut self) {
+    fn try_next_slot(&mut self, time: &dyn SysTime) {
         // gather commands while the next command to be executed exists
-        while let Some(cmd) = self.to_execute.remove(&self.next_slot) {
-            self.execute(cmd);
+        while let Some((cmd, commit_time)) =
+            self.to_execute.remove(&self.next_slot)
+        {
+            self.execute(cmd, commit_time, time);
             // update the next slot to be executed
             self.next_slot += 1;
         }
     }
 
-    fn execute(&mut self, cmd: Command) {
-        // execute the command
+    fn execute(&mut self, cmd: Command, commit_time: u64, time: &dyn SysTime) {
+        let phase_times = PhaseTimes::new(commit_time, time.millis());
+
+        // execute the command (which only happens as its results are
+        // collected)
+        let rifl = cmd.rifl();
         let results = cmd.execute(self.shard_id, &mut self.store);
         // update results if this rifl is pending
-        self.to_clients.extend(results);
+        command_span!(
+            "execute",
+            rifl,
+            time,
+            self.to_clients.extend(
+                results.map(|result| result.with_phase_times(phase_times))
+            )
+        );
     }
 }
 
@@ -125,7 +144,7 @@ impl MessageIndex for SlotExecutionInfo {
 mod tests {
     use super::*;
     use fantoch::id::Rifl;
-    use fantoch::kvs::KVOp;
+    use fantoch::kvs::{KVOp, KVOpResult};
     use permutator::Permutation;
     use std::collections::BTreeMap;
 
@@ -161,12 +180,12 @@ mod tests {
         // - we don't expect rifl 1 because we will not wait for it in the
         //   executor
         let mut expected_results = BTreeMap::new();
-        expected_results.insert(rifl_1, vec![None]);
-        expected_results.insert(rifl_2, vec![Some(1)]);
-        expected_results.insert(rifl_3, vec![None]);
-        expected_results.insert(rifl_4, vec![Some(2)]);
-        expected_results.insert(rifl_5, vec![None]);
-        expected_results.insert(rifl_6, vec![Some(3)]);
+        expected_results.insert(rifl_1, vec![KVOpResult::Value(None)]);
+        expected_results.insert(rifl_2, vec![KVOpResult::Value(Some(1))]);
+        expected_results.insert(rifl_3, vec![KVOpResult::Value(None)]);
+        expected_results.insert(rifl_4, vec![KVOpResult::Value(Some(2))]);
+        expected_results.insert(rifl_5, vec![KVOpResult::Value(None)]);
+        expected_results.insert(rifl_6, vec![KVOpResult::Value(Some(3))]);
 
         // create execution info
         let ei_1 = SlotExecutionInfo::new(1, cmd_1);
@@ -194,6 +213,7 @@ mod tests {
                     executor.handle(info, &fantoch::time::RunTime);
                     executor
                         .to_clients_iter()
+                        .map(|executor_result| executor_result.unwrap_ready())
                         .map(|executor_result| {
                             assert_eq!(
                                 key, executor_result.key,
@@ -1,5 +1,7 @@
 use crate::executor::table::MultiVotesTable;
 use crate::protocol::common::table::VoteRange;
+use fantoch::command::PhaseTimes;
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::executor::{
     ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
@@ -39,20 +41,27 @@ struct PendingPerKey {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Pending {
     rifl: Rifl,
+    // process that coordinated the command
+    coordinator: ProcessId,
     shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
     // number of keys on being accessed on this shard
     shard_key_count: u64,
     // number of shards the key is not stable at yet
     missing_stable_shards: usize,
     ops: Arc<Vec<KVOp>>,
+    // time (in millis) at which the command was committed (i.e. handed to
+    // the executor)
+    commit_time: u64,
 }
 
 impl Pending {
     pub fn new(
         shard_id: ShardId,
         rifl: Rifl,
+        coordinator: ProcessId,
         shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
         ops: Arc<Vec<KVOp>>,
+        commit_time: u64,
     ) -> Self {
         let shard_key_count = shard_to_keys
             .get(&shard_id)
@@ -61,10 +70,12 @@ impl Pending {
         let missing_stable_shards = shard_to_keys.len();
         Self {
             rifl,
+            coordinator,
             shard_to_keys,
             shard_key_count,
             missing_stable_shards,
             ops,
+            commit_time,
         }
     }
 
@@ -86,7 +97,10 @@ impl Executor for TableExecutor {
             config.n(),
             stability_threshold,
         );
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let store = KVStore::with_mode(
+            config.executor_monitor_execution_order(),
+            config.storage_mode(),
+        );
         let metrics = ExecutorMetrics::new();
         let to_clients = Default::default();
         let to_executors = Default::default();
@@ -107,7 +121,7 @@ impl Executor for TableExecutor {
         }
     }
 
-    fn handle(&mut self, info: Self::ExecutionInfo, _time: &dyn SysTime) {
+    fn handle(&mut self, info: Self::ExecutionInfo, time: &dyn SysTime) {
         // handle each new info by updating the votes table and execute ready
         // commands
         match info {
@@ -120,25 +134,32 @@ impl Executor for TableExecutor {
                 ops,
                 votes,
             } => {
-                let pending =
-                    Pending::new(self.shard_id, rifl, shard_to_keys, ops);
+                let now = time.millis();
+                let pending = Pending::new(
+                    self.shard_id,
+                    rifl,
+                    dot.source(),
+                    shard_to_keys,
+                    ops,
+                    now,
+                );
                 if self.execute_at_commit {
-                    self.execute(key, pending);
+                    self.execute(key, pending, time);
                 } else {
                     let to_execute = self
                         .table
                         .add_attached_votes(dot, clock, &key, pending, votes);
-                    self.send_stable_or_execute(key, to_execute);
+                    self.send_stable_or_execute(key, to_execute, time);
                 }
             }
             TableExecutionInfo::DetachedVotes { key, votes } => {
                 if !self.execute_at_commit {
                     let to_execute = self.table.add_detached_votes(&key, votes);
-                    self.send_stable_or_execute(key, to_execute);
+                    self.send_stable_or_execute(key, to_execute, time);
                 }
             }
             TableExecutionInfo::StableAtShard { key, rifl } => {
-                self.handle_stable_msg(key, rifl)
+                self.handle_stable_msg(key, rifl, time)
             }
         }
     }
@@ -165,7 +186,7 @@ impl Executor for TableExecutor {
 }
 
 impl TableExecutor {
-    fn handle_stable_msg(&mut self, key: Key, rifl: Rifl) {
+    fn handle_stable_msg(&mut self, key: Key, rifl: Rifl, time: &dyn SysTime) {
         // get pending commands on this key
         let pending_per_key = self.pending.entry(key.clone()).or_default();
 
@@ -192,6 +213,7 @@ impl TableExecutor {
                         pending,
                         &mut self.store,
                         &mut self.to_clients,
+                        time,
                     );
 
                     // try to execute the remaining pending commands
@@ -207,6 +229,7 @@ impl TableExecutor {
                                 &mut self.to_executors,
                                 &mut pending_per_key.stable_shards_buffered,
                                 &self.rifl_to_stable_count,
+                                time,
                             );
                         if let Some(pending) = try_result {
                             // if this command cannot be executed, buffer it and
@@ -234,8 +257,12 @@ impl TableExecutor {
         }
     }
 
-    fn send_stable_or_execute<I>(&mut self, key: Key, mut to_execute: I)
-    where
+    fn send_stable_or_execute<I>(
+        &mut self,
+        key: Key,
+        mut to_execute: I,
+        time: &dyn SysTime,
+    ) where
         I: Iterator<Item = Pending>,
     {
         let pending_per_key = self.pending.entry(key.clone()).or_default();
@@ -263,6 +290,7 @@ impl TableExecutor {
                 &mut self.to_executors,
                 &mut pending_per_key.stable_shards_buffered,
                 &self.rifl_to_stable_count,
+                time,
             );
             if let Some(pending) = try_result {
                 // if this command cannot be executed, then add it (and all the
@@ -285,11 +313,12 @@ impl TableExecutor {
         to_executors: &mut Vec<(ShardId, TableExecutionInfo)>,
         stable_shards_buffered: &mut HashMap<Rifl, usize>,
         rifl_to_stable_count: &Arc<SharedMap<Rifl, Mutex<u64>>>,
+        time: &dyn SysTime,
     ) -> Option<Pending> {
         let rifl = pending.rifl;
         if pending.single_key_command() {
             // if the command is single-key, execute immediately
-            Self::do_execute(key.clone(), pending, store, to_clients);
+            Self::do_execute(key.clone(), pending, store, to_clients, time);
             None
         } else {
             // closure that sends the stable message
@@ -348,7 +377,7 @@ impl TableExecutor {
 
             if pending.missing_stable_shards == 0 {
                 // if the command is already stable at shards, then execute it
-                Self::do_execute(key.clone(), pending, store, to_clients);
+                Self::do_execute(key.clone(), pending, store, to_clients, time);
                 None
             } else {
                 // in this case, the command cannot be executed; so send it back
@@ -358,8 +387,14 @@ impl TableExecutor {
         }
     }
 
-    fn execute(&mut self, key: Key, stable: Pending) {
-        Self::do_execute(key, stable, &mut self.store, &mut self.to_clients)
+    fn execute(&mut self, key: Key, stable: Pending, time: &dyn SysTime) {
+        Self::do_execute(
+            key,
+            stable,
+            &mut self.store,
+            &mut self.to_clients,
+            time,
+        )
     }
 
     fn do_execute(
@@ -367,7 +402,9 @@ impl TableExecutor {
         stable: Pending,
         store: &mut KVStore,
         to_clients: &mut VecDeque<ExecutorResult>,
+        time: &dyn SysTime,
     ) {
+        let phase_times = PhaseTimes::new(stable.commit_time, time.millis());
         // take the ops inside the arc if we're the last with a reference to it
         // (otherwise, clone them)
         let rifl = stable.rifl;
@@ -375,8 +412,14 @@ impl TableExecutor {
         let ops =
             Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone());
         // execute ops in the `KVStore`
-        let partial_results = store.execute(&key, ops, rifl);
-        to_clients.push_back(ExecutorResult::new(rifl, key, partial_results));
+        let partial_results = command_span!(
+            "execute",
+            rifl,
+            time,
+            store.execute_from(&key, ops, rifl, stable.coordinator)
+        );
+        let executor_result = ExecutorResult::new(rifl, key, partial_results);
+        to_clients.push_back(executor_result.with_phase_times(phase_times));
     }
 }
 
@@ -307,7 +307,7 @@ mod tests {
                     .collect(),
             );
             let ops = Arc::new(vec![KVOp::Put(10)]);
-            Pending::new(DEFAULT_SHARD_ID, rifl, shard_to_keys, ops)
+            Pending::new(DEFAULT_SHARD_ID, rifl, 1, shard_to_keys, ops, 0)
         };
 
         // a1
@@ -502,7 +502,7 @@ mod tests {
                     .collect(),
             );
             let ops = Arc::new(vec![KVOp::Put(10)]);
-            Pending::new(DEFAULT_SHARD_ID, rifl, shard_to_keys, ops)
+            Pending::new(DEFAULT_SHARD_ID, rifl, 1, shard_to_keys, ops, 0)
         };
 
         // a1
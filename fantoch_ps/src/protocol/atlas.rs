@@ -1,15 +1,17 @@
 use crate::executor::{GraphExecutionInfo, GraphExecutor};
 use crate::protocol::common::graph::{
-    Dependency, KeyDeps, LockedKeyDeps, QuorumDeps, SequentialKeyDeps,
+    CommittedDeps, Dependency, KeyDeps, LockedKeyDeps, OracleKeyDeps,
+    QuorumDeps, SequentialKeyDeps,
 };
 use crate::protocol::common::synod::{Synod, SynodMessage};
 use crate::protocol::partial::{self, ShardsCommits};
-use fantoch::command::Command;
+use fantoch::command::{Command, CommutativityAwareOracle};
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    Action, BaseProcess, CommitCallback, Info, MessageIndex, Protocol,
+    ProtocolMetrics, SequentialCommandsInfo, StableCallback, VClockGCTrack,
 };
 use fantoch::time::SysTime;
 use fantoch::{singleton, trace};
@@ -20,9 +22,10 @@ use threshold::VClock;
 
 pub type AtlasSequential = Atlas<SequentialKeyDeps>;
 pub type AtlasLocked = Atlas<LockedKeyDeps>;
+pub type AtlasCommutative = Atlas<OracleKeyDeps<CommutativityAwareOracle>>;
 
 #[derive(Debug, Clone)]
-pub struct Atlas<KD: KeyDeps> {
+pub struct Atlas<KD: KeyDeps + Clone> {
     bp: BaseProcess,
     key_deps: KD,
     cmds: SequentialCommandsInfo<AtlasInfo>,
@@ -34,9 +37,12 @@ pub struct Atlas<KD: KeyDeps> {
     // commit notifications that arrived before the initial `MCollect` message
     // (this may be possible even without network failures due to multiplexing)
     buffered_commits: HashMap<Dot, (ProcessId, ConsensusValue)>,
+    // dependencies of committed commands (only tracked with transitive
+    // conflicts and gc)
+    committed_deps: CommittedDeps,
 }
 
-impl<KD: KeyDeps> Protocol for Atlas<KD> {
+impl<KD: KeyDeps + Clone> Protocol for Atlas<KD> {
     type Message = Message;
     type PeriodicEvent = PeriodicEvent;
     type Executor = GraphExecutor;
@@ -58,7 +64,7 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             fast_quorum_size,
             write_quorum_size,
         );
-        let key_deps = KD::new(shard_id, config.nfr());
+        let key_deps = KD::from_config(shard_id, &config);
         let cmds = SequentialCommandsInfo::new(
             process_id,
             shard_id,
@@ -73,6 +79,7 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
         let shard_processes =
             fantoch::util::process_ids(shard_id, config.n()).collect();
         let buffered_commits = HashMap::new();
+        let committed_deps = CommittedDeps::new(&config);
 
         // create `Atlas`
         let protocol = Self {
@@ -84,6 +91,7 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             to_executors,
             shard_processes,
             buffered_commits,
+            committed_deps,
         };
 
         // create periodic events
@@ -119,6 +127,7 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd, true)
     }
 
@@ -196,16 +205,36 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
         KD::parallel()
     }
 
+    fn is_parallel(&self) -> bool {
+        self.key_deps.is_parallel()
+    }
+
     fn leaderless() -> bool {
         true
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
 }
 
-impl<KD: KeyDeps> Atlas<KD> {
+impl<KD: KeyDeps + Clone> Atlas<KD> {
     /// Handles a submit operation by a client.
     fn handle_submit(
         &mut self,
@@ -216,6 +245,10 @@ impl<KD: KeyDeps> Atlas<KD> {
         // compute the command identifier
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
+        // track the command (and its quorum acks) until it's committed
+        self.bp.track_outstanding(dot);
+        self.bp.track_acks(dot);
+
         // create submit actions
         let create_mforward_submit =
             |dot, cmd| Message::MForwardSubmit { dot, cmd };
@@ -348,6 +381,9 @@ impl<KD: KeyDeps> Atlas<KD> {
             return;
         }
 
+        // record the quorum ack
+        self.bp.ack(dot);
+
         // update quorum deps
         info.quorum_deps.add(from, deps);
 
@@ -446,11 +482,20 @@ impl<KD: KeyDeps> Atlas<KD> {
             .as_ref()
             .expect("there should be a command payload");
 
-        // create execution info
-        let execution_info =
-            GraphExecutionInfo::add(dot, cmd.clone(), value.deps.clone());
+        // notify the on-commit callback (if any)
+        command_span!("commit", cmd.rifl(), _time);
+        self.bp.committed(dot, cmd);
+
+        // create execution info; if enabled, transitively reduce the
+        // dependencies first
+        let deps = self.committed_deps.commit(dot, &value.deps);
+        let execution_info = GraphExecutionInfo::add(dot, cmd.clone(), deps);
         self.to_executors.push(execution_info);
 
+        // the command is no longer outstanding
+        self.bp.untrack_outstanding(dot);
+        self.bp.untrack_acks(dot);
+
         // update command info:
         info.status = Status::COMMIT;
 
@@ -682,16 +727,22 @@ impl<KD: KeyDeps> Atlas<KD> {
         &mut self,
         from: ProcessId,
         stable: Vec<(ProcessId, u64, u64)>,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
         trace!(
             "p{}: MStable({:?}) from {} | time={}",
             self.id(),
             stable,
             from,
-            _time.micros()
+            time.micros()
         );
         assert_eq!(from, self.bp.process_id);
+        if self.bp.config.transitive_conflicts() {
+            for dot in fantoch::util::dots(stable.clone()) {
+                self.committed_deps.gc(&dot);
+            }
+        }
+        self.bp.advance_stable_frontier(&stable, time);
         let stable_count = self.cmds.gc(stable);
         self.bp.stable(stable_count);
     }
@@ -703,6 +754,9 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         // retrieve the committed clock
         let committed = self.gc_track.clock().frontier();
 
@@ -934,7 +988,7 @@ mod tests {
         atlas_flow::<LockedKeyDeps>()
     }
 
-    fn atlas_flow<KD: KeyDeps>() {
+    fn atlas_flow<KD: KeyDeps + Clone>() {
         // create simulation
         let mut simulation = Simulation::new();
 
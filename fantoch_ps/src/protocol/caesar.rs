@@ -3,12 +3,13 @@ use crate::protocol::common::pred::{
     CaesarDeps, Clock, KeyClocks, LockedKeyClocks, QuorumClocks, QuorumRetries,
 };
 use fantoch::command::Command;
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, BasicGCTrack, CommittedAndExecuted, Info,
-    LockedCommandsInfo, MessageIndex, Protocol, ProtocolMetrics,
-    ProtocolMetricsKind,
+    Action, BaseProcess, BasicGCTrack, CommitCallback, CommittedAndExecuted,
+    Info, LockedCommandsInfo, MessageIndex, Protocol, ProtocolMetrics,
+    ProtocolMetricsKind, StableCallback,
 };
 use fantoch::time::SysTime;
 use fantoch::{singleton, trace};
@@ -138,6 +139,7 @@ impl<KC: KeyClocks> Protocol for Caesar<KC> {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd);
     }
 
@@ -230,6 +232,22 @@ impl<KC: KeyClocks> Protocol for Caesar<KC> {
         true
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
@@ -246,6 +264,10 @@ impl<KC: KeyClocks> Caesar<KC> {
         // compute the command identifier
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
+        // track the command (and its quorum acks) until it's committed
+        self.bp.track_outstanding(dot);
+        self.bp.track_acks(dot);
+
         // compute its clock
         let clock = self.key_clocks.clock_next();
 
@@ -553,6 +575,9 @@ impl<KC: KeyClocks> Caesar<KC> {
             );
         }
 
+        // record the quorum ack
+        self.bp.ack(dot);
+
         // update quorum deps
         info.quorum_clocks.add(from, clock, deps, ok);
 
@@ -586,7 +611,12 @@ impl<KC: KeyClocks> Caesar<KC> {
                     msg: mcommit,
                 });
             } else {
-                // slow path: create `MRetry`
+                // slow path: restart the tracking of quorum acks, as the
+                // command now waits for the `MRetryAck`s
+                self.bp.untrack_acks(dot);
+                self.bp.track_acks(dot);
+
+                // create `MRetry`
                 let mconsensus = Message::MRetry {
                     dot,
                     clock: aggregated_clock,
@@ -674,12 +704,18 @@ impl<KC: KeyClocks> Caesar<KC> {
         info.deps = Arc::new(deps);
         Self::update_clock(&mut self.key_clocks, dot, &mut info, clock);
 
-        // create execution info
+        // notify the on-commit callback (if any) and create execution info
         let cmd = info.cmd.clone().expect("there should be a command payload");
+        command_span!("commit", cmd.rifl(), time);
+        self.bp.committed(dot, &cmd);
         let execution_info =
             PredecessorsExecutionInfo::new(dot, cmd, clock, info.deps.clone());
         self.to_executors.push(execution_info);
 
+        // the command is no longer outstanding
+        self.bp.untrack_outstanding(dot);
+        self.bp.untrack_acks(dot);
+
         // take the set of commands that this command is blocking and try to
         // unblock them
         let blocking = std::mem::take(&mut info.blocking);
@@ -799,6 +835,9 @@ impl<KC: KeyClocks> Caesar<KC> {
             );
         }
 
+        // record the quorum ack
+        self.bp.ack(dot);
+
         // update quorum retries
         info.quorum_retries.add(from, deps);
 
@@ -852,12 +891,11 @@ impl<KC: KeyClocks> Caesar<KC> {
         }
     }
 
-    fn handle_mgc_dot(&mut self, dot: Dot, _time: &dyn SysTime) {
-        trace!(
-            "p{}: MGCDot({:?}) | time={}",
-            self.id(),
-            dot,
-            _time.micros()
+    fn handle_mgc_dot(&mut self, dot: Dot, time: &dyn SysTime) {
+        trace!("p{}: MGCDot({:?}) | time={}", self.id(), dot, time.micros());
+        self.bp.advance_stable_frontier(
+            &[(dot.source(), dot.sequence(), dot.sequence())],
+            time,
         );
         self.gc_command(dot);
         self.bp.stable(1);
@@ -870,6 +908,9 @@ impl<KC: KeyClocks> Caesar<KC> {
             _time.micros()
         );
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         trace!(
             "p{}: COMMITTED {:>20} EXECUTED {:>20} EXISTING {:>20}",
             self.id(),
@@ -0,0 +1,227 @@
+use super::Dependency;
+use fantoch::config::Config;
+use fantoch::id::Dot;
+use fantoch::{HashMap, HashSet};
+
+/// Tracks the dependencies of committed commands, so that the dependencies of
+/// new commands can be transitively reduced: a dependency is dropped if it's
+/// already implied by another dependency (e.g. if C depends on A and B, and B
+/// depends on A, then C only needs to depend on B).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedDeps {
+    enabled: bool,
+    deps: HashMap<Dot, HashSet<Dot>>,
+}
+
+impl CommittedDeps {
+    /// Creates a new `CommittedDeps` instance. Dependencies are only reduced
+    /// with transitive conflicts and garbage collection: without garbage
+    /// collection, the dependencies of a command are forgotten as soon as it
+    /// commits, and thus they could never be used to reduce others.
+    pub fn new(config: &Config) -> Self {
+        let enabled =
+            config.transitive_conflicts() && config.gc_interval().is_some();
+        Self {
+            enabled,
+            deps: HashMap::new(),
+        }
+    }
+
+    /// Transitively reduces the dependencies `deps` of the committed command
+    /// `dot`, returning the reduced dependencies. These are also saved, so
+    /// that they can be used to reduce the dependencies of future commands.
+    /// If reductions are not enabled, the dependencies are returned as they
+    /// are.
+    pub fn commit(
+        &mut self,
+        dot: Dot,
+        deps: &HashSet<Dependency>,
+    ) -> HashSet<Dependency> {
+        if !self.enabled {
+            return deps.clone();
+        }
+        let reduced = self.reduce(deps);
+        let dots = reduced.iter().map(|dep| dep.dot).collect();
+        self.deps.insert(dot, dots);
+        reduced
+    }
+
+    /// Forgets the dependencies of command `dot`, which can no longer be used
+    /// to reduce the dependencies of future commands.
+    pub fn gc(&mut self, dot: &Dot) {
+        self.deps.remove(dot);
+    }
+
+    /// Returns the number of commands whose dependencies are known.
+    pub fn len(&self) -> usize {
+        self.deps.len()
+    }
+
+    /// Checks whether the dependencies of any command are known.
+    pub fn is_empty(&self) -> bool {
+        self.deps.is_empty()
+    }
+
+    fn reduce(&self, deps: &HashSet<Dependency>) -> HashSet<Dependency> {
+        // go through the dependencies in a deterministic order and drop each
+        // dependency that is implied by one of the dependencies not dropped
+        // so far; this ensures that a set of dependencies that imply each
+        // other (i.e. a cycle) is never dropped entirely
+        let mut sorted: Vec<_> = deps.iter().map(|dep| dep.dot).collect();
+        sorted.sort();
+        let mut kept: HashSet<_> = sorted.iter().cloned().collect();
+        for dot in sorted {
+            let implied = kept
+                .iter()
+                .any(|other| *other != dot && self.reaches(*other, dot));
+            if implied {
+                kept.remove(&dot);
+            }
+        }
+        deps.iter()
+            .filter(|dep| kept.contains(&dep.dot))
+            .cloned()
+            .collect()
+    }
+
+    // Checks whether `to` is a (transitive) dependency of `from`, using the
+    // dependencies known.
+    fn reaches(&self, from: Dot, to: Dot) -> bool {
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![from];
+        while let Some(dot) = to_visit.pop() {
+            if let Some(deps) = self.deps.get(&dot) {
+                for dep in deps {
+                    if *dep == to {
+                        return true;
+                    }
+                    if visited.insert(*dep) {
+                        to_visit.push(*dep);
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{GraphExecutionInfo, GraphExecutor};
+    use crate::protocol::common::graph::AccessKind;
+    use fantoch::command::Command;
+    use fantoch::config::Config;
+    use fantoch::executor::Executor;
+    use fantoch::id::Rifl;
+    use fantoch::kvs::KVOp;
+    use fantoch::time::SimTime;
+    use std::collections::BTreeSet;
+    use std::iter::FromIterator;
+    use std::time::Duration;
+
+    // reductions are only enabled with transitive conflicts and gc
+    fn config() -> Config {
+        let mut config = Config::new(3, 1);
+        config.set_transitive_conflicts(true);
+        config.set_gc_interval(Duration::from_millis(100));
+        config
+    }
+
+    fn dep(dot: Dot) -> Dependency {
+        Dependency {
+            dot,
+            shards: Some(BTreeSet::from_iter(vec![0])),
+            kind: AccessKind::default(),
+        }
+    }
+
+    #[test]
+    fn chain() {
+        // chain A <- B <- C
+        let dot_a = Dot::new(1, 1);
+        let dot_b = Dot::new(2, 1);
+        let dot_c = Dot::new(3, 1);
+        let deps_a = HashSet::new();
+        let deps_b = HashSet::from_iter(vec![dep(dot_a)]);
+        let deps_c = HashSet::from_iter(vec![dep(dot_a), dep(dot_b)]);
+
+        let mut committed = CommittedDeps::new(&config());
+        assert_eq!(committed.commit(dot_a, &deps_a), deps_a);
+        assert_eq!(committed.commit(dot_b, &deps_b), deps_b);
+        // C only needs to depend on B, since A is implied by B
+        let reduced_c = committed.commit(dot_c, &deps_c);
+        assert_eq!(reduced_c, HashSet::from_iter(vec![dep(dot_b)]));
+        assert_eq!(committed.len(), 3);
+
+        // if B is gc-ed, C's deps can no longer be reduced
+        committed.gc(&dot_b);
+        let dot_d = Dot::new(4, 1);
+        assert_eq!(committed.commit(dot_d, &deps_c), deps_c);
+
+        // the executor still orders the commands correctly, even if they
+        // arrive in the opposite order
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = config();
+        config.set_executor_monitor_execution_order(true);
+        let mut executor = GraphExecutor::new(process_id, shard_id, config);
+        let time = SimTime::new();
+        let key = String::from("A");
+        let adds = vec![(dot_c, reduced_c), (dot_b, deps_b), (dot_a, deps_a)];
+        for (dot, deps) in adds {
+            let rifl = Rifl::new(dot.source() as u64, dot.sequence());
+            let cmd = Command::from(rifl, vec![(key.clone(), KVOp::Put(1))]);
+            executor.handle(GraphExecutionInfo::add(dot, cmd, deps), &time);
+        }
+        let monitor = executor.monitor().expect("monitor should exist");
+        let order = monitor.get_order(&key).expect("key should be executed");
+        let expected: Vec<_> = vec![dot_a, dot_b, dot_c]
+            .into_iter()
+            .map(|dot| Rifl::new(dot.source() as u64, dot.sequence()))
+            .collect();
+        assert_eq!(order, &expected);
+    }
+
+    #[test]
+    fn cycle() {
+        // A and B depend on each other, and C depends on both
+        let dot_a = Dot::new(1, 1);
+        let dot_b = Dot::new(2, 1);
+        let dot_c = Dot::new(3, 1);
+        let deps_a = HashSet::from_iter(vec![dep(dot_b)]);
+        let deps_b = HashSet::from_iter(vec![dep(dot_a)]);
+        let deps_c = HashSet::from_iter(vec![dep(dot_a), dep(dot_b)]);
+
+        let mut committed = CommittedDeps::new(&config());
+        committed.commit(dot_a, &deps_a);
+        committed.commit(dot_b, &deps_b);
+        // one of the dependencies is kept
+        let reduced_c = committed.commit(dot_c, &deps_c);
+        assert_eq!(reduced_c, HashSet::from_iter(vec![dep(dot_b)]));
+    }
+
+    #[test]
+    fn disabled() {
+        // chain A <- B <- C
+        let dot_a = Dot::new(1, 1);
+        let dot_b = Dot::new(2, 1);
+        let dot_c = Dot::new(3, 1);
+        let deps_a = HashSet::new();
+        let deps_b = HashSet::from_iter(vec![dep(dot_a)]);
+        let deps_c = HashSet::from_iter(vec![dep(dot_a), dep(dot_b)]);
+
+        // without transitive conflicts, or without gc, nothing is reduced
+        let mut without_transitive_conflicts = config();
+        without_transitive_conflicts.set_transitive_conflicts(false);
+        let mut without_gc = Config::new(3, 1);
+        without_gc.set_transitive_conflicts(true);
+        for config in vec![without_transitive_conflicts, without_gc] {
+            let mut committed = CommittedDeps::new(&config);
+            assert_eq!(committed.commit(dot_a, &deps_a), deps_a);
+            assert_eq!(committed.commit(dot_b, &deps_b), deps_b);
+            assert_eq!(committed.commit(dot_c, &deps_c), deps_c);
+            assert!(committed.is_empty());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Dependency, KeyDeps, LockedKeyDeps, SequentialKeyDeps};
+use fantoch::command::Command;
+use fantoch::config::Config;
+use fantoch::id::{Dot, ShardId};
+use fantoch::HashSet;
+
+/// `KeyDeps` whose implementation is chosen at runtime.
+pub type AutoKeyDeps = Box<dyn KeyDeps>;
+
+impl dyn KeyDeps {
+    /// Creates a new `LockedKeyDeps` if `parallel` is set, and a new
+    /// `SequentialKeyDeps` otherwise. This allows a single binary to pick the
+    /// implementation at runtime, instead of at compile time.
+    pub fn new_auto(
+        shard_id: ShardId,
+        nfr: bool,
+        parallel: bool,
+    ) -> AutoKeyDeps {
+        if parallel {
+            Box::new(LockedKeyDeps::new(shard_id, nfr))
+        } else {
+            Box::new(SequentialKeyDeps::new(shard_id, nfr))
+        }
+    }
+}
+
+/// Allows boxed `KeyDeps` to be cloned.
+pub trait KeyDepsClone {
+    fn box_clone(&self) -> AutoKeyDeps;
+}
+
+impl<KD> KeyDepsClone for KD
+where
+    KD: KeyDeps + Clone + 'static,
+{
+    fn box_clone(&self) -> AutoKeyDeps {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for AutoKeyDeps {
+    fn clone(&self) -> Self {
+        (**self).box_clone()
+    }
+}
+
+impl KeyDeps for AutoKeyDeps {
+    /// Create a new `SequentialKeyDeps` instance; use `new_auto` (or
+    /// `from_config`) to pick the implementation.
+    fn new(shard_id: ShardId, nfr: bool) -> Self {
+        <dyn KeyDeps>::new_auto(shard_id, nfr, false)
+    }
+
+    /// Create a new `LockedKeyDeps` instance if `parallel_key_deps` is set in
+    /// the `Config`, and a new `SequentialKeyDeps` instance otherwise.
+    fn from_config(shard_id: ShardId, config: &Config) -> Self {
+        <dyn KeyDeps>::new_auto(
+            shard_id,
+            config.nfr(),
+            config.parallel_key_deps(),
+        )
+    }
+
+    fn add_cmd(
+        &mut self,
+        dot: Dot,
+        cmd: &Command,
+        past: Option<HashSet<Dependency>>,
+    ) -> HashSet<Dependency> {
+        (**self).add_cmd(dot, cmd, past)
+    }
+
+    fn add_noop(&mut self, dot: Dot) -> HashSet<Dependency> {
+        (**self).add_noop(dot)
+    }
+
+    #[cfg(test)]
+    fn cmd_deps(&self, cmd: &Command) -> HashSet<Dot> {
+        (**self).cmd_deps(cmd)
+    }
+
+    #[cfg(test)]
+    fn noop_deps(&self) -> HashSet<Dot> {
+        (**self).noop_deps()
+    }
+
+    fn parallel() -> bool {
+        // since the implementation is only known at runtime, we can't tell
+        // whether it supports parallelism; thus, we assume that it doesn't
+        // (use `is_parallel` to check the implementation picked)
+        false
+    }
+
+    fn is_parallel(&self) -> bool {
+        (**self).is_parallel()
+    }
+}
@@ -1,18 +1,26 @@
-use super::{Dependency, KeyDeps, LatestDep, LatestRWDep};
+use super::{AccessKind, Dependency, KeyDeps, LatestDep, LatestRWDep};
 use fantoch::command::Command;
 use fantoch::id::{Dot, ShardId};
 use fantoch::kvs::Key;
 use fantoch::shared::SharedMap;
-use fantoch::HashSet;
-use parking_lot::RwLock;
+use fantoch::{HashMap, HashSet};
+use parking_lot::{RwLock, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+type Stripes = Vec<RwLock<HashMap<Key, LatestRWDep>>>;
+
 #[derive(Debug, Clone)]
 pub struct LockedKeyDeps {
     shard_id: ShardId,
     nfr: bool,
     latest: Arc<SharedMap<Key, RwLock<LatestRWDep>>>,
+    // if set, each key is mapped to one of these locks (instead of having its
+    // own lock in `latest`)
+    stripes: Option<Arc<Stripes>>,
     latest_noop: Arc<RwLock<LatestDep>>,
+    // number of times a write lock couldn't be acquired without blocking
+    contention: Arc<AtomicU64>,
 }
 
 impl KeyDeps for LockedKeyDeps {
@@ -22,7 +30,9 @@ impl KeyDeps for LockedKeyDeps {
             shard_id,
             nfr,
             latest: Arc::new(SharedMap::new()),
+            stripes: None,
             latest_noop: Arc::new(RwLock::new(None)),
+            contention: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -49,7 +59,7 @@ impl KeyDeps for LockedKeyDeps {
     #[cfg(test)]
     fn cmd_deps(&self, cmd: &Command) -> HashSet<Dot> {
         let mut deps = HashSet::new();
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(cmd.read_only(), &mut deps);
         self.do_cmd_deps(cmd, &mut deps);
         super::extract_dots(deps)
     }
@@ -57,7 +67,7 @@ impl KeyDeps for LockedKeyDeps {
     #[cfg(test)]
     fn noop_deps(&self) -> HashSet<Dot> {
         let mut deps = HashSet::new();
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(false, &mut deps);
         self.do_noop_deps(&mut deps);
         super::extract_dots(deps)
     }
@@ -65,13 +75,54 @@ impl KeyDeps for LockedKeyDeps {
     fn parallel() -> bool {
         true
     }
+
+    fn is_parallel(&self) -> bool {
+        Self::parallel()
+    }
 }
 
 impl LockedKeyDeps {
-    fn maybe_add_noop_latest(&self, deps: &mut HashSet<Dependency>) {
+    /// Create a new `LockedKeyDeps` instance in which each key is mapped to
+    /// one of `stripes` locks. This trades some false sharing between keys
+    /// for less contention on the structure holding all keys.
+    pub fn with_stripes(shard_id: ShardId, nfr: bool, stripes: usize) -> Self {
+        assert!(stripes > 0, "the number of stripes must be positive");
+        let stripes = (0..stripes).map(|_| RwLock::default()).collect();
+        Self {
+            stripes: Some(Arc::new(stripes)),
+            ..Self::new(shard_id, nfr)
+        }
+    }
+
+    /// Returns the stripe `key` is mapped to, if lock-striping is enabled.
+    pub fn stripe_of(&self, key: &Key) -> Option<usize> {
+        self.stripes.as_ref().map(|stripes| {
+            (fantoch::util::key_hash(key) % stripes.len() as u64) as usize
+        })
+    }
+
+    /// Returns the number of times a write lock couldn't be acquired without
+    /// blocking.
+    pub fn contention(&self) -> u64 {
+        self.contention.load(Ordering::Relaxed)
+    }
+
+    fn write<'a, T>(&self, lock: &'a RwLock<T>) -> RwLockWriteGuard<'a, T> {
+        lock.try_write().unwrap_or_else(|| {
+            // if the lock is taken, record contention and wait for it
+            self.contention.fetch_add(1, Ordering::Relaxed);
+            lock.write()
+        })
+    }
+
+    fn maybe_add_noop_latest(
+        &self,
+        read_only: bool,
+        deps: &mut HashSet<Dependency>,
+    ) {
         // for this operation we only need a read lock
         if let Some(dep) = self.latest_noop.read().as_ref() {
-            deps.insert(dep.clone());
+            deps.insert(dep.with_kind(AccessKind::new(read_only, false)));
         }
     }
 
@@ -86,30 +137,25 @@ impl LockedKeyDeps {
 
         // flag indicating whether the command is read-only
         let read_only = cmd.read_only();
-        // we only support single-key read commands with NFR
-        assert!(if self.nfr && read_only {
-            cmd.total_key_count() == 1
-        } else {
-            true
-        });
+        // we only support single-key and snapshot read commands with NFR
+        assert!(super::nfr_supported(self.nfr, cmd));
 
         // iterate through all command keys, grab a write lock, get their
         // current latest and set ourselves to be the new latest
         cmd.keys(self.shard_id).for_each(|key| {
-            // get latest read and write on this key
-            let entry = self.latest.get_or(key, || RwLock::default());
-            // grab a write lock
-            let mut guard = entry.write();
-
-            super::maybe_add_deps(read_only, self.nfr, &guard, &mut deps);
-
-            // finally, store the command
-            if read_only {
-                // if a command is read-only, then added it as the latest read
-                guard.read = Some(cmd_dep.clone());
+            if let Some(stripe) = self.stripe_of(key) {
+                // grab a write lock on the key's stripe
+                let stripes = self.stripes.as_ref().expect("stripes exist");
+                let mut guard = self.write(&stripes[stripe]);
+                // get latest read and write on this key
+                let latest_rw = guard.entry(key.clone()).or_default();
+                self.update_latest(read_only, &cmd_dep, latest_rw, &mut deps);
             } else {
-                // otherwise, add it as the latest write
-                guard.write = Some(cmd_dep.clone());
+                // get latest read and write on this key
+                let entry = self.latest.get_or(key, || RwLock::default());
+                // grab a write lock
+                let mut guard = self.write(entry.value());
+                self.update_latest(read_only, &cmd_dep, &mut guard, &mut deps);
             }
         });
 
@@ -117,12 +163,31 @@ impl LockedKeyDeps {
         // TODO: when adding recovery, check that the interleaving of the
         // following and the previous loop, and how it interacts with
         // `do_add_noop` is correct
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(read_only, &mut deps);
 
         // and finally return the computed deps
         deps
     }
 
+    fn update_latest(
+        &self,
+        read_only: bool,
+        cmd_dep: &Dependency,
+        latest_rw: &mut LatestRWDep,
+        deps: &mut HashSet<Dependency>,
+    ) {
+        super::maybe_add_deps(read_only, self.nfr, latest_rw, deps);
+
+        // finally, store the command
+        if read_only {
+            // if a command is read-only, then added it as the latest read
+            latest_rw.read = Some(cmd_dep.clone());
+        } else {
+            // otherwise, add it as the latest write
+            latest_rw.write = Some(cmd_dep.clone());
+        }
+    }
+
     fn do_add_noop(
         &self,
         dot: Dot,
@@ -140,7 +205,7 @@ impl LockedKeyDeps {
             self.latest_noop.write().replace(Dependency::from_noop(dot))
         {
             // if there was a previous latest, then it's a dependency
-            deps.insert(dep);
+            deps.insert(dep.with_kind(AccessKind::WriteWrite));
         }
 
         // compute deps for this noop
@@ -152,16 +217,20 @@ impl LockedKeyDeps {
     fn do_noop_deps(&self, deps: &mut HashSet<Dependency>) {
         // iterate through all keys, grab a read lock, and include their latest
         // in the final `deps`
-        self.latest.iter().for_each(|entry| {
-            // grab a read lock and take the dots there as a dependency
-            let latest_rw = entry.value().read();
-            if let Some(rdep) = latest_rw.read.as_ref() {
-                deps.insert(rdep.clone());
-            }
-            if let Some(wdep) = latest_rw.write.as_ref() {
-                deps.insert(wdep.clone());
-            }
-        });
+        if let Some(stripes) = self.stripes.as_ref() {
+            stripes.iter().for_each(|stripe| {
+                // grab a read lock and take the dots there as a dependency
+                stripe.read().values().for_each(|latest_rw| {
+                    super::add_noop_deps(latest_rw, deps)
+                });
+            });
+        } else {
+            self.latest.iter().for_each(|entry| {
+                // grab a read lock and take the dots there as a dependency
+                let latest_rw = entry.value().read();
+                super::add_noop_deps(&latest_rw, deps);
+            });
+        }
     }
 
     #[cfg(test)]
@@ -170,12 +239,21 @@ impl LockedKeyDeps {
         let read_only = cmd.read_only();
 
         cmd.keys(self.shard_id).for_each(|key| {
-            // get latest read and write on this key
-            let entry = self.latest.get_or(key, || RwLock::default());
-            // grab a read lock
-            let guard = entry.read();
+            if let Some(stripe) = self.stripe_of(key) {
+                // grab a read lock on the key's stripe
+                let stripes = self.stripes.as_ref().expect("stripes exist");
+                let guard = stripes[stripe].read();
+                if let Some(latest_rw) = guard.get(key) {
+                    super::maybe_add_deps(read_only, self.nfr, latest_rw, deps);
+                }
+            } else {
+                // get latest read and write on this key
+                let entry = self.latest.get_or(key, || RwLock::default());
+                // grab a read lock
+                let guard = entry.read();
 
-            super::maybe_add_deps(read_only, self.nfr, &guard, deps);
+                super::maybe_add_deps(read_only, self.nfr, &guard, deps);
+            }
         });
     }
 }
@@ -7,24 +7,61 @@ mod sequential;
 // This module contains the definition of `LockedKeyDeps`.
 mod locked;
 
+// This module contains the definition of `AutoKeyDeps`.
+mod dynamic;
+
+// This module contains the definition of `OracleKeyDeps`.
+mod oracle;
+
 // Re-exports.
+pub use dynamic::{AutoKeyDeps, KeyDepsClone};
 pub use locked::LockedKeyDeps;
+pub use oracle::OracleKeyDeps;
 pub use sequential::SequentialKeyDeps;
 pub use mrvs::MultiRecordValues;
 pub use mrvs::Key_Deps_MRV;
 
 use fantoch::kvs::Value;
 use fantoch::command::Command;
+use fantoch::config::Config;
 use fantoch::id::{Dot, ShardId};
 use fantoch::HashSet;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fmt::Debug;
 
+/// Kind of a dependency edge: whether the command (first) and its dependency
+/// (second) read or write. This allows executors to let reads commute.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum AccessKind {
+    ReadRead,
+    ReadWrite,
+    WriteRead,
+    #[default]
+    WriteWrite,
+}
+
+impl AccessKind {
+    /// Computes the kind of the edge between a command and its dependency,
+    /// given whether each of them is read-only. Noops count as writes.
+    pub fn new(read_only: bool, dep_read_only: bool) -> Self {
+        match (read_only, dep_read_only) {
+            (true, true) => AccessKind::ReadRead,
+            (true, false) => AccessKind::ReadWrite,
+            (false, true) => AccessKind::WriteRead,
+            (false, false) => AccessKind::WriteWrite,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Dependency {
     pub dot: Dot,
     pub shards: Option<BTreeSet<ShardId>>,
+    #[serde(default)]
+    pub kind: AccessKind,
 }
 
 impl Dependency {
@@ -32,11 +69,23 @@ impl Dependency {
         Self {
             dot,
             shards: Some(cmd.shards().cloned().collect()),
+            kind: AccessKind::default(),
         }
     }
 
     pub fn from_noop(dot: Dot) -> Self {
-        Self { dot, shards: None }
+        Self {
+            dot,
+            shards: None,
+            kind: AccessKind::default(),
+        }
+    }
+
+    /// Returns a copy of this dependency with the kind of edge provided.
+    pub fn with_kind(&self, kind: AccessKind) -> Self {
+        let mut dep = self.clone();
+        dep.kind = kind;
+        dep
     }
 }
 
@@ -56,7 +105,7 @@ pub fn maybe_add_deps(
     // independently of whether the command is read-only or not, all commands
     // depend on writes
     if let Some(wdep) = latest_rw.write.as_ref() {
-        deps.insert(wdep.clone());
+        deps.insert(wdep.with_kind(AccessKind::new(read_only, false)));
     }
 
     // if the command is not read-only, and the NFR optimization is not enabled,
@@ -71,7 +120,7 @@ pub fn maybe_add_deps(
     //  ----------------------------------
     if !read_only && !nfr {
         if let Some(rdep) = latest_rw.read.as_ref() {
-            deps.insert(rdep.clone());
+            deps.insert(rdep.with_kind(AccessKind::new(read_only, true)));
         }
     }
     // in sum:
@@ -80,9 +129,40 @@ pub fn maybe_add_deps(
     //   they don't)
 }
 
-pub trait KeyDeps: Debug + Clone {
+// Checks whether `cmd` is supported with the NFR optimization: besides
+// single-key reads, only snapshot reads are, as these are a single vertex that
+// depends on the latest write on each of its keys.
+fn nfr_supported(nfr: bool, cmd: &Command) -> bool {
+    !(nfr && cmd.read_only()) || cmd.nfr_allowed() || cmd.is_snapshot_read()
+}
+
+pub fn add_noop_deps(latest_rw: &LatestRWDep, deps: &mut HashSet<Dependency>) {
+    // noops depend on all reads and writes
+    if let Some(rdep) = latest_rw.read.as_ref() {
+        deps.insert(rdep.with_kind(AccessKind::WriteRead));
+    }
+    if let Some(wdep) = latest_rw.write.as_ref() {
+        deps.insert(wdep.with_kind(AccessKind::WriteWrite));
+    }
+}
+
+// `KeyDeps` is object-safe, so that the implementation can be picked at
+// runtime (see `AutoKeyDeps`); for this reason, instead of requiring `Clone`,
+// it requires `KeyDepsClone`, which allows boxed `KeyDeps` to be cloned.
+pub trait KeyDeps: Debug + Send + Sync + KeyDepsClone {
     /// Create a new `KeyDeps` instance.
-    fn new(shard_id: ShardId, nfr: bool) -> Self;
+    fn new(shard_id: ShardId, nfr: bool) -> Self
+    where
+        Self: Sized;
+
+    /// Create a new `KeyDeps` instance given a `Config`; this allows
+    /// `AutoKeyDeps` to pick the implementation set in the `Config`.
+    fn from_config(shard_id: ShardId, config: &Config) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(shard_id, config.nfr())
+    }
 
     /// Sets the command's `Dot` as the latest command on each key touched by
     /// the command, returning the set of local conflicting commands
@@ -105,7 +185,13 @@ pub trait KeyDeps: Debug + Clone {
     #[cfg(test)]
     fn noop_deps(&self) -> HashSet<Dot>;
 
-    fn parallel() -> bool;
+    fn parallel() -> bool
+    where
+        Self: Sized;
+
+    /// Same as `parallel`, but can be called on trait objects; this allows
+    /// `AutoKeyDeps` to report the implementation picked at runtime.
+    fn is_parallel(&self) -> bool;
 }
 
 #[cfg(test)]
@@ -128,6 +214,8 @@ mod tests {
         key_deps_flow::<SequentialKeyDeps>();
         read_deps::<SequentialKeyDeps>(false);
         read_deps::<SequentialKeyDeps>(true);
+        snapshot_read_deps::<SequentialKeyDeps>(false);
+        snapshot_read_deps::<SequentialKeyDeps>(true);
     }
 
     #[test]
@@ -135,6 +223,186 @@ mod tests {
         key_deps_flow::<LockedKeyDeps>();
         read_deps::<LockedKeyDeps>(false);
         read_deps::<LockedKeyDeps>(true);
+        snapshot_read_deps::<LockedKeyDeps>(false);
+        snapshot_read_deps::<LockedKeyDeps>(true);
+    }
+
+    #[test]
+    fn auto_key_deps() {
+        key_deps_flow::<AutoKeyDeps>();
+        read_deps::<AutoKeyDeps>(false);
+        read_deps::<AutoKeyDeps>(true);
+        snapshot_read_deps::<AutoKeyDeps>(false);
+        snapshot_read_deps::<AutoKeyDeps>(true);
+    }
+
+    #[test]
+    fn access_kinds() {
+        // a read depending on a read (which key deps never produce, since
+        // reads don't depend on reads) and a read depending on a write
+        assert_eq!(AccessKind::new(true, true), AccessKind::ReadRead);
+        assert_eq!(AccessKind::new(true, false), AccessKind::ReadWrite);
+
+        let shard_id = 0;
+        let nfr = false;
+        let mut key_deps = SequentialKeyDeps::new(shard_id, nfr);
+        let mut dot_gen = DotGen::new(1);
+        let key = String::from("A");
+        let write =
+            Command::from(Rifl::new(1, 1), vec![(key.clone(), KVOp::Put(1))]);
+        let read =
+            Command::from(Rifl::new(1, 2), vec![(key.clone(), KVOp::Get)]);
+        let kinds = |deps: HashSet<Dependency>| {
+            deps.into_iter()
+                .map(|dep| (dep.dot, dep.kind))
+                .collect::<HashMap<_, _>>()
+        };
+
+        // a read after a write produces a read-write edge
+        let dot_w = dot_gen.next_id();
+        let dot_r = dot_gen.next_id();
+        key_deps.add_cmd(dot_w, &write, None);
+        let deps = key_deps.add_cmd(dot_r, &read, None);
+        assert_eq!(
+            kinds(deps),
+            HashMap::from_iter(vec![(dot_w, AccessKind::ReadWrite)])
+        );
+
+        // a write after both produces a write-read and a write-write edge
+        let dot_w2 = dot_gen.next_id();
+        let deps = key_deps.add_cmd(dot_w2, &write, None);
+        assert_eq!(
+            kinds(deps),
+            HashMap::from_iter(vec![
+                (dot_w, AccessKind::WriteWrite),
+                (dot_r, AccessKind::WriteRead),
+            ])
+        );
+
+        // a noop depends on the latest read and write as a write
+        let dot_noop = dot_gen.next_id();
+        let deps = key_deps.add_noop(dot_noop);
+        assert_eq!(
+            kinds(deps),
+            HashMap::from_iter(vec![
+                (dot_r, AccessKind::WriteRead),
+                (dot_w2, AccessKind::WriteWrite),
+            ])
+        );
+    }
+
+    #[test]
+    fn auto_key_deps_is_parallel() {
+        let shard_id = 0;
+        let deps_nfr = false;
+        // the implementation picked at runtime is reported
+        let locked = <dyn KeyDeps>::new_auto(shard_id, deps_nfr, true);
+        assert!(locked.is_parallel());
+        let sequential = <dyn KeyDeps>::new_auto(shard_id, deps_nfr, false);
+        assert!(!sequential.is_parallel());
+    }
+
+    #[test]
+    fn auto_key_deps_from_config() {
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        // by default, the implementation picked is sequential
+        let key_deps = AutoKeyDeps::from_config(shard_id, &config);
+        assert!(!key_deps.is_parallel());
+        // but that can be changed in the config
+        config.set_parallel_key_deps(true);
+        let key_deps = AutoKeyDeps::from_config(shard_id, &config);
+        assert!(key_deps.is_parallel());
+    }
+
+    #[test]
+    fn auto_key_deps_sequential() {
+        let shard_id = 0;
+        let deps_nfr = false;
+        let ops_number = 1000;
+        let max_keys_per_command = 2;
+        let keys_number = 4;
+        let noop_probability = 10;
+
+        // create both key deps
+        let parallel = false;
+        let mut auto_key_deps =
+            <dyn KeyDeps>::new_auto(shard_id, deps_nfr, parallel);
+        let mut sequential_key_deps =
+            SequentialKeyDeps::new(shard_id, deps_nfr);
+
+        // add the same sequence of commands to both
+        let mut dot_gen = DotGen::new(1);
+        for _ in 0..ops_number {
+            let dot = dot_gen.next_id();
+            let cmd = util::gen_cmd(
+                max_keys_per_command,
+                keys_number,
+                noop_probability,
+            );
+            let (auto_deps, sequential_deps) = match cmd.as_ref() {
+                Some(cmd) => (
+                    auto_key_deps.add_cmd(dot, cmd, None),
+                    sequential_key_deps.add_cmd(dot, cmd, None),
+                ),
+                None => (
+                    auto_key_deps.add_noop(dot),
+                    sequential_key_deps.add_noop(dot),
+                ),
+            };
+            // the dependencies computed are the same
+            assert_eq!(auto_deps, sequential_deps);
+        }
+        assert_eq!(auto_key_deps.noop_deps(), sequential_key_deps.noop_deps());
+
+        // clones of boxed key deps are independent
+        let mut clone = auto_key_deps.clone();
+        clone.add_noop(dot_gen.next_id());
+        assert_ne!(auto_key_deps.noop_deps(), clone.noop_deps());
+    }
+
+    #[test]
+    fn sequential_key_deps_skip_deps() {
+        let shard_id = 0;
+        let deps_nfr = false;
+        let mut key_deps = SequentialKeyDeps::new(shard_id, deps_nfr);
+
+        let process_id = 1;
+        let mut dot_gen = DotGen::new(process_id);
+
+        let key_a = String::from("A");
+        let value = 10;
+        let cmd_a = multi_put(Rifl::new(100, 1), vec![key_a.clone()], value);
+
+        // add A with {1,1} and then with {1,2}, skipping dependencies
+        let skip_deps = true;
+        key_deps.add_cmd(dot_gen.next_id(), &cmd_a, None);
+        let deps =
+            key_deps.add_cmd_with(dot_gen.next_id(), &cmd_a, None, skip_deps);
+
+        // the dependencies returned are bottom
+        assert!(deps.is_empty());
+
+        // but {1,2} is now the latest command on A
+        let deps_1_2 = HashSet::from_iter(vec![Dot::new(1, 2)]);
+        assert_eq!(key_deps.cmd_deps(&cmd_a), deps_1_2);
+        assert_eq!(key_deps.noop_deps(), deps_1_2);
+
+        // without skipping, dependencies are computed as usual
+        let skip_deps = false;
+        let deps =
+            key_deps.add_cmd_with(dot_gen.next_id(), &cmd_a, None, skip_deps);
+        assert_eq!(extract_dots(deps), deps_1_2);
+
+        // the dependencies of snapshot reads are never skipped
+        let skip_deps = true;
+        let read = Command::snapshot_read(Rifl::new(101, 1), vec![key_a]);
+        let deps =
+            key_deps.add_cmd_with(dot_gen.next_id(), &read, None, skip_deps);
+        assert_eq!(
+            extract_dots(deps),
+            HashSet::from_iter(vec![Dot::new(1, 3)])
+        );
     }
 
     fn get(rifl: Rifl, key: String) -> Command {
@@ -490,6 +758,65 @@ mod tests {
         }
     }
 
+    fn snapshot_read_deps<KD: KeyDeps>(deps_nfr: bool) {
+        // create key deps
+        let shard_id = 0;
+        let mut key_deps = KD::new(shard_id, deps_nfr);
+
+        // create dot gen
+        let process_id = 1;
+        let mut dot_gen = DotGen::new(process_id);
+
+        // keys
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let value = 10;
+
+        // add a write on A with {1,1} and a write on B with {1,2}
+        let write_a = multi_put(Rifl::new(100, 1), vec![key_a.clone()], value);
+        let write_b = multi_put(Rifl::new(101, 1), vec![key_b.clone()], value);
+        key_deps.add_cmd(dot_gen.next_id(), &write_a, None);
+        key_deps.add_cmd(dot_gen.next_id(), &write_b, None);
+
+        // a snapshot read of A and B (even with NFR) depends on the latest
+        // write on both keys
+        let read = Command::snapshot_read(
+            Rifl::new(102, 1),
+            vec![key_a.clone(), key_b.clone()],
+        );
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &read, None);
+        let deps_1_1_and_1_2 =
+            HashSet::from_iter(vec![Dot::new(1, 1), Dot::new(1, 2)]);
+        assert_eq!(extract_dots(deps), deps_1_1_and_1_2);
+
+        // and, unless NFR is enabled, a new write on A depends on it
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &write_a, None);
+        if deps_nfr {
+            assert_eq!(
+                extract_dots(deps),
+                HashSet::from_iter(vec![Dot::new(1, 1)])
+            );
+        } else {
+            assert_eq!(
+                extract_dots(deps),
+                HashSet::from_iter(vec![Dot::new(1, 1), Dot::new(1, 3)])
+            );
+        }
+
+        // add a write on both A and B with {1,5}
+        let write_ab = multi_put(
+            Rifl::new(103, 1),
+            vec![key_a.clone(), key_b.clone()],
+            value,
+        );
+        key_deps.add_cmd(dot_gen.next_id(), &write_ab, None);
+
+        // a snapshot read of A and B only depends on that write
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &read, None);
+        let deps_1_5 = HashSet::from_iter(vec![Dot::new(1, 5)]);
+        assert_eq!(extract_dots(deps), deps_1_5);
+    }
+
     #[test]
     fn concurrent_locked_test() {
         let nthreads = 2;
@@ -497,8 +824,20 @@ mod tests {
         let max_keys_per_command = 2;
         let keys_number = 4;
         let noop_probability = 50;
-        for _ in 0..10 {
-            concurrent_test::<LockedKeyDeps>(
+        let shard_id = 0;
+        let deps_nfr = false;
+        // the iterations are split between no lock-striping and lock-striping
+        // with a single stripe shared by all keys, and as many stripes as keys
+        let stripes = [None, Some(1), Some(keys_number)];
+        for i in 0..10 {
+            let key_deps = match stripes[i % stripes.len()] {
+                Some(stripes) => {
+                    LockedKeyDeps::with_stripes(shard_id, deps_nfr, stripes)
+                }
+                None => LockedKeyDeps::new(shard_id, deps_nfr),
+            };
+            concurrent_test(
+                key_deps,
                 nthreads,
                 ops_number,
                 max_keys_per_command,
@@ -508,18 +847,73 @@ mod tests {
         }
     }
 
-    fn concurrent_test<KD: KeyDeps + Send + Sync + 'static>(
+    #[test]
+    fn striped_locked_key_deps_contention() {
+        let shard_id = 0;
+        let deps_nfr = false;
+        let stripes = 4;
+        let ops_number = 3000;
+        let key_deps = LockedKeyDeps::with_stripes(shard_id, deps_nfr, stripes);
+
+        // find a key per stripe
+        let mut stripe_to_key = HashMap::new();
+        let mut key_index = 0;
+        while stripe_to_key.len() < stripes {
+            let key = format!("K{}", key_index);
+            key_index += 1;
+            let stripe = key_deps
+                .stripe_of(&key)
+                .expect("lock-striping should be enabled");
+            stripe_to_key.entry(stripe).or_insert(key);
+        }
+
+        // spawn a worker per key; each worker only accesses its own key, and
+        // thus its own stripe
+        let handles: Vec<_> = stripe_to_key
+            .into_values()
+            .enumerate()
+            .map(|(index, key)| {
+                let mut key_deps_clone = key_deps.clone();
+                thread::spawn(move || {
+                    let process_id = (index + 1) as ProcessId;
+                    let mut dot_gen = DotGen::new(process_id);
+                    let mut previous = None;
+                    for _ in 0..ops_number {
+                        let dot = dot_gen.next_id();
+                        let rifl = Rifl::new(process_id as u64, dot.sequence());
+                        let cmd = multi_put(rifl, vec![key.clone()], 10);
+                        // each command depends on the previous one
+                        let deps = key_deps_clone.add_cmd(dot, &cmd, None);
+                        assert_eq!(
+                            extract_dots(deps),
+                            HashSet::from_iter(previous)
+                        );
+                        previous = Some(dot);
+                    }
+                    (key, previous)
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let (key, latest) = handle.join().expect("worker should finish");
+            // the latest command on each key is the last one added
+            let cmd = get(Rifl::new(100, 1), key);
+            assert_eq!(key_deps.cmd_deps(&cmd), HashSet::from_iter(latest));
+        }
+
+        // since keys are on different stripes, workers never block each other
+        assert_eq!(key_deps.contention(), 0);
+    }
+
+    fn concurrent_test<KD: KeyDeps + Clone + 'static>(
+        key_deps: KD,
         nthreads: usize,
         ops_number: usize,
         max_keys_per_command: usize,
         keys_number: usize,
         noop_probability: usize,
     ) {
-        // create key deps
-        let shard_id = 0;
-        let deps_nfr = false;
-        let key_deps = KD::new(shard_id, deps_nfr);
-
         // spawn workers
         let handles: Vec<_> = (1..=nthreads)
             .map(|process_id| {
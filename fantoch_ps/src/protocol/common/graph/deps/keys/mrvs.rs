@@ -1,12 +1,10 @@
-
-use super::{Dependency, LatestDep, LatestRWDep};
+use super::{AccessKind, Dependency, LatestDep, LatestRWDep};
 use fantoch::command::Command;
 use fantoch::id::{Dot, ShardId};
-use fantoch::kvs::{KVOp, Key};
+use fantoch::kvs::{KVOp, Key, RecordSelector, RECORDS_PER_KEY};
 use fantoch::{HashMap, HashSet};
-use rand::Rng;
 
-const N: usize = 30;
+const N: usize = RECORDS_PER_KEY;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LatestRWDepArray {
@@ -33,21 +31,26 @@ impl Default for LatestRWDepArray {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct MultiRecordValues {
     shard_id: ShardId,
     nfr: bool,
     latest: HashMap<Key, LatestRWDepArray>,
     latest_noop: LatestDep,
+    selector: RecordSelector,
 }
 
 pub type Key_Deps_MRV = HashMap<Key, Vec<usize>>;
 
 impl MultiRecordValues {
 
-    fn maybe_add_noop_latest(&self, deps: &mut HashSet<Dependency>) {
+    fn maybe_add_noop_latest(
+        &self,
+        read_only: bool,
+        deps: &mut HashSet<Dependency>,
+    ) {
         if let Some(dep) = self.latest_noop.as_ref() {
-            deps.insert(dep.clone());
+            deps.insert(dep.with_kind(AccessKind::new(read_only, false)));
         }
     }
 
@@ -63,12 +66,8 @@ impl MultiRecordValues {
 
         // flag indicating whether the command is read-only
         let read_only = cmd.read_only();
-        // we only support single-key read commands with NFR
-        assert!(if self.nfr && read_only {
-            cmd.total_key_count() == 1
-        } else {
-            true
-        });
+        // we only support single-key and snapshot read commands with NFR
+        assert!(super::nfr_supported(self.nfr, cmd));
 
         // iterate through all command keys, get their current latest and set
         // ourselves to be the new latest
@@ -83,12 +82,26 @@ impl MultiRecordValues {
                    None => {
                     match op {
                         KVOp::Add(_) | KVOp::Subtract(_) => {
-                            let n = rand::thread_rng().gen_range(0..N);
+                            // with PN-counters, the op targets the record
+                            // owned by the process coordinating it
+                            let n = self
+                                .selector
+                                .for_process(dot.source())
+                                .select(cmd.rifl());
                             let vec = vec![n];
                             keys_deps.insert(key.clone(), vec.clone());
                             vec
                         },
-                        KVOp::Delete |  KVOp::Get | KVOp::Put(_) => {
+                        KVOp::PutRecords(values) => {
+                            // a put with one value per record writes the
+                            // records it has a value for (values beyond the
+                            // last record are ignored)
+                            let vec: Vec<usize> =
+                                (0..values.len().min(N)).collect();
+                            keys_deps.insert(key.clone(), vec.clone());
+                            vec
+                        }
+                        KVOp::Delete |  KVOp::Get | KVOp::GetRecords | KVOp::Put(_) | KVOp::AddReturningOld(_) | KVOp::DeleteIf(_) | KVOp::Rebalance => {
                             let mut vec = Vec::new();
                             for i in 0..N {
                                 vec.push(i);
@@ -133,7 +146,7 @@ impl MultiRecordValues {
         });
 
         // always include latest noop, if any
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(read_only, &mut deps);
 
         // and finally return the computed deps
         (deps, keys_deps)
@@ -145,12 +158,7 @@ impl MultiRecordValues {
         // in the final `deps`
         self.latest.values().for_each(|vec| {
             for latest_rw in &vec.data {
-                if let Some(rdep) = latest_rw.read.as_ref() {
-                    deps.insert(rdep.clone());
-                }
-                if let Some(wdep) = latest_rw.write.as_ref() {
-                    deps.insert(wdep.clone());
-                }
+                super::add_noop_deps(latest_rw, deps);
             }
         });
     }
@@ -163,8 +171,8 @@ impl MultiRecordValues {
         cmd.keys(self.shard_id).for_each(|key| {
             // get latest command on this key
             if let Some(vec) = self.latest.get(key) {
-                for latest_rw in vec.data {
-                    super::maybe_add_deps(read_only, self.nfr, &latest_rw, deps);
+                for latest_rw in &vec.data {
+                    super::maybe_add_deps(read_only, self.nfr, latest_rw, deps);
                 }
             }
         });
@@ -179,7 +187,7 @@ impl MultiRecordValues {
         if let Some(dep) = self.latest_noop.replace(Dependency::from_noop(dot))
         {
             // if there was a previous latest, then it's a dependency
-            deps.insert(dep);
+            deps.insert(dep.with_kind(AccessKind::WriteWrite));
         }
 
         // compute deps for this noop
@@ -195,9 +203,18 @@ impl MultiRecordValues {
             nfr,
             latest: HashMap::new(),
             latest_noop: None,
+            selector: RecordSelector::default(),
         }
     }
 
+    /// Changes the strategy used to pick the record accessed by `Add`s and
+    /// `Subtract`s. By default, records are picked at random. In order for
+    /// commands to be ordered on the records they change, this should be the
+    /// selector used by the `KVStore` (see `StorageMode::MultiRecord`).
+    pub fn set_record_selector(&mut self, selector: RecordSelector) {
+        self.selector = selector;
+    }
+
     pub fn add_cmd(
         &mut self,
         dot: Dot,
@@ -225,7 +242,7 @@ impl MultiRecordValues {
     #[cfg(test)]
     pub fn cmd_deps(&self, cmd: &Command) -> HashSet<Dot> {
         let mut deps = HashSet::new();
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(cmd.read_only(), &mut deps);
         self.do_cmd_deps(cmd, &mut deps);
         super::extract_dots(deps)
     }
@@ -233,12 +250,335 @@ impl MultiRecordValues {
     #[cfg(test)]
     pub fn noop_deps(&self) -> HashSet<Dot> {
         let mut deps = HashSet::new();
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(false, &mut deps);
         self.do_noop_deps(&mut deps);
         super::extract_dots(deps)
     }
 
+    /// Returns, for each record of `key`, the latest read and the latest
+    /// write on that record (in this order). Unlike reads of the whole key,
+    /// this exposes how commands are spread across records. The values of
+    /// the records are kept by the `KVStore` (see `KVStore::records`).
+    pub fn record_deps(&self, key: &Key) -> Vec<(Option<Dot>, Option<Dot>)> {
+        match self.latest.get(key) {
+            Some(vec) => vec
+                .data
+                .iter()
+                .map(|latest_rw| {
+                    let read = latest_rw.read.as_ref().map(|dep| dep.dot);
+                    let write = latest_rw.write.as_ref().map(|dep| dep.dot);
+                    (read, write)
+                })
+                .collect(),
+            None => vec![(None, None); N],
+        }
+    }
+
     pub fn parallel() -> bool {
         false
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::id::{DotGen, ProcessId, Rifl};
+    use fantoch::kvs::{KVOpResult, KVStore, StorageMode};
+    use std::iter::FromIterator;
+
+    #[test]
+    fn record_deps() {
+        let shard_id = 0;
+        let nfr = false;
+        let mut mrvs = MultiRecordValues::new(shard_id, nfr);
+        let key = String::from("A");
+
+        // initially, no record has been accessed
+        assert_eq!(mrvs.record_deps(&key), vec![(None, None); N]);
+
+        let mut dot_gen = DotGen::new(1);
+
+        // add to record 2 and then to record 5
+        let add =
+            Command::from(Rifl::new(1, 1), vec![(key.clone(), KVOp::Add(1))]);
+        let dot_a = dot_gen.next_id();
+        let keys_deps = HashMap::from_iter(vec![(key.clone(), vec![2])]);
+        mrvs.add_cmd(dot_a, &add, None, Some(keys_deps));
+        let dot_b = dot_gen.next_id();
+        let keys_deps = HashMap::from_iter(vec![(key.clone(), vec![5])]);
+        mrvs.add_cmd(dot_b, &add, None, Some(keys_deps));
+
+        // each write is only registered on its record
+        let records = mrvs.record_deps(&key);
+        assert_eq!(records.len(), N);
+        for (index, (read, write)) in records.into_iter().enumerate() {
+            assert_eq!(read, None);
+            match index {
+                2 => assert_eq!(write, Some(dot_a)),
+                5 => assert_eq!(write, Some(dot_b)),
+                _ => assert_eq!(write, None),
+            }
+        }
+
+        // a get reads all records
+        let get =
+            Command::from(Rifl::new(2, 1), vec![(key.clone(), KVOp::Get)]);
+        let dot_c = dot_gen.next_id();
+        mrvs.add_cmd(dot_c, &get, None, None);
+        assert!(mrvs
+            .record_deps(&key)
+            .into_iter()
+            .all(|(read, _)| read == Some(dot_c)));
+    }
+
+    // Adds `count` `Add`s to `key`, returning the record picked for each.
+    fn add_records(
+        mrvs: &mut MultiRecordValues,
+        key: &Key,
+        count: u64,
+    ) -> Vec<usize> {
+        let mut dot_gen = DotGen::new(1);
+        (1..=count)
+            .map(|sequence| {
+                let add = Command::from(
+                    Rifl::new(1, sequence),
+                    vec![(key.clone(), KVOp::Add(1))],
+                );
+                let (_, keys_deps) =
+                    mrvs.add_cmd(dot_gen.next_id(), &add, None, None);
+                let records = keys_deps.get(key).expect("key should exist");
+                assert_eq!(records.len(), 1);
+                records[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rebalance() {
+        let mut mrvs = MultiRecordValues::new(0, false);
+        mrvs.set_record_selector(RecordSelector::Fixed(2));
+        let key = String::from("A");
+
+        // add everything to record 2
+        add_records(&mut mrvs, &key, 1);
+        let add_dot = Dot::new(1, 1);
+
+        // rebalancing accesses all records, and thus it depends on the add
+        let rebalance = Command::from(
+            Rifl::new(2, 1),
+            vec![(key.clone(), KVOp::Rebalance)],
+        );
+        let rebalance_dot = Dot::new(2, 1);
+        let (deps, keys_deps) =
+            mrvs.add_cmd(rebalance_dot, &rebalance, None, None);
+        assert_eq!(
+            super::super::extract_dots(deps),
+            HashSet::from_iter(vec![add_dot])
+        );
+        assert_eq!(keys_deps.get(&key).map(|records| records.len()), Some(N));
+
+        // after it, all records were last written by the rebalance
+        assert!(mrvs
+            .record_deps(&key)
+            .into_iter()
+            .all(|(_, write)| write == Some(rebalance_dot)));
+    }
+
+    #[test]
+    fn put_records() {
+        let mut mrvs = MultiRecordValues::new(0, false);
+        let key = String::from("A");
+
+        // a put with a value per record writes all records
+        let values = (1..=N as u16).collect();
+        let put = Command::from(
+            Rifl::new(1, 1),
+            vec![(key.clone(), KVOp::PutRecords(values))],
+        );
+        let put_dot = Dot::new(1, 1);
+        let (_, keys_deps) = mrvs.add_cmd(put_dot, &put, None, None);
+        assert_eq!(keys_deps.get(&key).map(|records| records.len()), Some(N));
+        assert!(mrvs
+            .record_deps(&key)
+            .into_iter()
+            .all(|(_, write)| write == Some(put_dot)));
+
+        // while a put with fewer values only writes the first records
+        let put = Command::from(
+            Rifl::new(1, 2),
+            vec![(key.clone(), KVOp::PutRecords(vec![1, 2, 3]))],
+        );
+        let partial_put_dot = Dot::new(1, 2);
+        let (deps, _) = mrvs.add_cmd(partial_put_dot, &put, None, None);
+        assert_eq!(
+            super::super::extract_dots(deps),
+            HashSet::from_iter(vec![put_dot])
+        );
+        assert!(mrvs.record_deps(&key).into_iter().enumerate().all(
+            |(index, (_, write))| if index < 3 {
+                write == Some(partial_put_dot)
+            } else {
+                write == Some(put_dot)
+            }
+        ));
+    }
+
+    #[test]
+    fn fixed_record_selector() {
+        let mut mrvs = MultiRecordValues::new(0, false);
+        mrvs.set_record_selector(RecordSelector::Fixed(2));
+        let key = String::from("A");
+
+        // every add lands on record 2
+        let records = add_records(&mut mrvs, &key, 10);
+        assert!(records.into_iter().all(|index| index == 2));
+        assert!(mrvs
+            .record_deps(&key)
+            .into_iter()
+            .enumerate()
+            .all(|(index, (_, write))| write.is_some() == (index == 2)));
+    }
+
+    #[test]
+    fn pn_counter_record_selector() {
+        let key = String::from("A");
+        let process_ids: Vec<ProcessId> = vec![1, 2, 3];
+        let selector = RecordSelector::PnCounter { process_id: 0 };
+        let mut mrvs: Vec<_> = process_ids
+            .iter()
+            .map(|_| {
+                let mut mrvs = MultiRecordValues::new(0, false);
+                mrvs.set_record_selector(selector);
+                mrvs
+            })
+            .collect();
+
+        // each process coordinates an arithmetic op on the key, all issued by
+        // the same client
+        let ops = vec![KVOp::Add(10), KVOp::Add(20), KVOp::Subtract(5)];
+        let mut issued = Vec::new();
+        for (sequence, ((process_id, op), process_mrvs)) in
+            process_ids.iter().zip(ops).zip(mrvs.iter_mut()).enumerate()
+        {
+            let rifl = Rifl::new(1, sequence as u64 + 1);
+            let cmd = Command::from(rifl, vec![(key.clone(), op)]);
+            let dot = Dot::new(*process_id, 1);
+            let (deps, keys_deps) = process_mrvs.add_cmd(dot, &cmd, None, None);
+            assert!(deps.is_empty());
+            // the op lands on the record owned by the coordinator
+            let expected = vec![*process_id as usize - 1];
+            assert_eq!(keys_deps.get(&key), Some(&expected));
+            issued.push((dot, cmd));
+        }
+
+        // none of them conflicts with the ones coordinated by other
+        // processes, even when each process computes the dependencies itself
+        for (dot, cmd) in &issued {
+            for (index, process_mrvs) in mrvs.iter_mut().enumerate() {
+                if index + 1 != dot.source() as usize {
+                    let (deps, _) = process_mrvs.add_cmd(*dot, cmd, None, None);
+                    assert!(deps.is_empty());
+                }
+            }
+        }
+
+        // and the value of the counter is the sum of all ops in the store of
+        // every process (subtracts saturate on the record, so each record is
+        // funded first), which end up with the same records
+        let records: Vec<_> = process_ids
+            .into_iter()
+            .map(|_| {
+                let mode = StorageMode::MultiRecord(selector);
+                let mut store = KVStore::with_mode(false, mode);
+                let put = KVOp::PutRecords(vec![10; 3]);
+                let put =
+                    Command::from(Rifl::new(4, 1), vec![(key.clone(), put)]);
+                put.execute(0, &mut store).for_each(drop);
+                for (dot, cmd) in issued.clone() {
+                    cmd.execute_from(0, dot.source(), &mut store)
+                        .for_each(drop);
+                }
+                let get = KVOp::Get;
+                let value = store.execute(&key, vec![get], Rifl::new(4, 2));
+                let expected = Some(30 + 10 + 20 - 5);
+                assert_eq!(value, vec![KVOpResult::Value(expected)]);
+                store.records(&key).cloned()
+            })
+            .collect();
+        assert!(records.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn seeded_record_selector() {
+        let records = |seed: u64| {
+            let mut mrvs = MultiRecordValues::new(0, false);
+            mrvs.set_record_selector(RecordSelector::Random { seed });
+            let key = String::from("A");
+            add_records(&mut mrvs, &key, 100)
+        };
+
+        // the same seed picks the same records
+        assert_eq!(records(42), records(42));
+        assert_ne!(records(42), records(43));
+    }
+
+    #[test]
+    fn round_robin_record_selector() {
+        let mut mrvs = MultiRecordValues::new(0, false);
+        mrvs.set_record_selector(RecordSelector::RoundRobin);
+        let key = String::from("A");
+
+        // adds are evenly spread across records
+        let rounds = 3;
+        let records = add_records(&mut mrvs, &key, (N * rounds) as u64);
+        let mut counts = vec![0; N];
+        for index in records {
+            counts[index] += 1;
+        }
+        assert!(counts.into_iter().all(|count| count == rounds));
+    }
+
+    #[test]
+    fn store_applies_selected_records() {
+        let shard_id = 0;
+        let key = String::from("A");
+        let selectors = vec![
+            RecordSelector::Random { seed: 7 },
+            RecordSelector::RoundRobin,
+            RecordSelector::Fixed(2),
+            RecordSelector::PnCounter { process_id: 1 },
+        ];
+        for selector in selectors {
+            let mut mrvs = MultiRecordValues::new(shard_id, false);
+            mrvs.set_record_selector(selector);
+            let mode = StorageMode::MultiRecord(selector);
+            let mut store = KVStore::with_mode(false, mode);
+
+            let put = Command::from(
+                Rifl::new(1, 1),
+                vec![(key.clone(), KVOp::Put(0))],
+            );
+            put.execute(shard_id, &mut store).for_each(drop);
+
+            let mut dot_gen = DotGen::new(1);
+            for (client_id, sequence) in vec![(1, 2), (1, 3), (2, 1), (3, 1)] {
+                let add = Command::from(
+                    Rifl::new(client_id, sequence),
+                    vec![(key.clone(), KVOp::Add(1))],
+                );
+                let dot = dot_gen.next_id();
+                let (_, keys_deps) = mrvs.add_cmd(dot, &add, None, None);
+                let before = store.records(&key).cloned().unwrap();
+                add.execute_from(shard_id, dot.source(), &mut store)
+                    .for_each(drop);
+                let after = store.records(&key).cloned().unwrap();
+
+                // the store changed the record the deps were computed on
+                let changed: Vec<_> = (0..N)
+                    .filter(|index| before[*index] != after[*index])
+                    .collect();
+                assert_eq!(keys_deps.get(&key), Some(&changed));
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use super::{AccessKind, Dependency, KeyDeps, LatestDep};
+use fantoch::command::{Command, ConflictOracle};
+use fantoch::id::{Dot, ShardId};
+use fantoch::kvs::{KVOp, Key};
+use fantoch::{HashMap, HashSet};
+
+/// `KeyDeps` that consults a `ConflictOracle` to decide whether commands on
+/// the same key conflict. For each key, it keeps every command since the
+/// latest one that doesn't commute with itself (e.g. all the `Add`s since the
+/// latest `Put`), and a new command only depends on the ones that the oracle
+/// says conflict with it.
+///
+/// To bound the number of commands kept per key (e.g. with an endless stream
+/// of `Add`s), once there are `MAX_LATEST_OPS` of them, the next command
+/// depends on all of them and replaces them as a barrier: a command that
+/// every later command on the key depends on.
+#[derive(Debug, Clone)]
+pub struct OracleKeyDeps<O> {
+    shard_id: ShardId,
+    nfr: bool,
+    oracle: O,
+    latest: HashMap<Key, Vec<LatestOps>>,
+    latest_noop: LatestDep,
+}
+
+// Maximum number of commands kept per key.
+const MAX_LATEST_OPS: usize = 64;
+
+#[derive(Debug, Clone)]
+struct LatestOps {
+    ops: Vec<KVOp>,
+    read_only: bool,
+    dep: Dependency,
+    // whether this command stands for the commands it replaced when the
+    // bound was reached, and thus conflicts with every command
+    barrier: bool,
+}
+
+impl LatestOps {
+    fn conflicts<O: ConflictOracle>(&self, oracle: &O, ops: &[KVOp]) -> bool {
+        self.barrier || oracle.ops_conflict(ops, &self.ops)
+    }
+}
+
+impl<O> KeyDeps for OracleKeyDeps<O>
+where
+    O: ConflictOracle + Default + Clone + 'static,
+{
+    /// Create a new `OracleKeyDeps` instance.
+    fn new(shard_id: ShardId, nfr: bool) -> Self {
+        Self {
+            shard_id,
+            nfr,
+            oracle: O::default(),
+            latest: HashMap::new(),
+            latest_noop: None,
+        }
+    }
+
+    fn add_cmd(
+        &mut self,
+        dot: Dot,
+        cmd: &Command,
+        past: Option<HashSet<Dependency>>,
+    ) -> HashSet<Dependency> {
+        // we start with past in case there's one, or bottom otherwise
+        let mut deps = match past {
+            Some(past) => past,
+            None => HashSet::new(),
+        };
+        self.maybe_add_noop_latest(cmd.read_only(), &mut deps);
+        self.do_cmd_deps(cmd, &mut deps);
+
+        // add the command to the latest commands on each of its keys
+        let cmd_dep = Dependency::from_cmd(dot, cmd);
+        let read_only = cmd.read_only();
+        let nfr = self.nfr;
+        let oracle = &self.oracle;
+        for key in cmd.keys(self.shard_id) {
+            let ops: Vec<_> =
+                cmd.operations(self.shard_id, key).cloned().collect();
+            let key_latest = self.latest.entry(key.clone()).or_default();
+            // if the command doesn't commute with itself, then it conflicts
+            // with any command that conflicts with the ones it depends on, and
+            // thus it can replace them (with NFR, writes don't depend on
+            // reads, and reads can't replace writes, since later writes won't
+            // depend on them)
+            if oracle.ops_conflict(&ops, &ops) {
+                key_latest.retain(|latest| {
+                    let replaced = latest.conflicts(oracle, &ops)
+                        && !(nfr && read_only != latest.read_only);
+                    !replaced
+                });
+            }
+            // if the bound is reached, the command depends on (and replaces)
+            // all the commands kept; it's only read-only if all of them are
+            let barrier = key_latest.len() >= MAX_LATEST_OPS;
+            let mut latest_read_only = read_only;
+            if barrier {
+                for latest in key_latest.drain(..) {
+                    let kind = AccessKind::new(read_only, latest.read_only);
+                    deps.insert(latest.dep.with_kind(kind));
+                    latest_read_only &= latest.read_only;
+                }
+            }
+            key_latest.push(LatestOps {
+                ops,
+                read_only: latest_read_only,
+                dep: cmd_dep.clone(),
+                barrier,
+            });
+        }
+        deps
+    }
+
+    fn add_noop(&mut self, dot: Dot) -> HashSet<Dependency> {
+        let mut deps = HashSet::new();
+        // set self to be the new latest
+        if let Some(dep) = self.latest_noop.replace(Dependency::from_noop(dot))
+        {
+            // if there was a previous latest, then it's a dependency
+            deps.insert(dep.with_kind(AccessKind::WriteWrite));
+        }
+        self.do_noop_deps(&mut deps);
+        deps
+    }
+
+    #[cfg(test)]
+    fn cmd_deps(&self, cmd: &Command) -> HashSet<Dot> {
+        let mut deps = HashSet::new();
+        self.maybe_add_noop_latest(cmd.read_only(), &mut deps);
+        self.do_cmd_deps(cmd, &mut deps);
+        super::extract_dots(deps)
+    }
+
+    #[cfg(test)]
+    fn noop_deps(&self) -> HashSet<Dot> {
+        let mut deps = HashSet::new();
+        if let Some(dep) = self.latest_noop.as_ref() {
+            deps.insert(dep.clone());
+        }
+        self.do_noop_deps(&mut deps);
+        super::extract_dots(deps)
+    }
+
+    fn parallel() -> bool {
+        false
+    }
+
+    fn is_parallel(&self) -> bool {
+        Self::parallel()
+    }
+}
+
+impl<O: ConflictOracle> OracleKeyDeps<O> {
+    fn maybe_add_noop_latest(
+        &self,
+        read_only: bool,
+        deps: &mut HashSet<Dependency>,
+    ) {
+        if let Some(dep) = self.latest_noop.as_ref() {
+            deps.insert(dep.with_kind(AccessKind::new(read_only, false)));
+        }
+    }
+
+    fn do_cmd_deps(&self, cmd: &Command, deps: &mut HashSet<Dependency>) {
+        // flag indicating whether the command is read-only
+        let read_only = cmd.read_only();
+
+        for key in cmd.keys(self.shard_id) {
+            let ops: Vec<_> =
+                cmd.operations(self.shard_id, key).cloned().collect();
+            let key_latest = match self.latest.get(key) {
+                Some(key_latest) => key_latest,
+                None => continue,
+            };
+            for latest in key_latest {
+                // with NFR, writes don't depend on reads
+                let skip = self.nfr && !read_only && latest.read_only;
+                if !skip && latest.conflicts(&self.oracle, &ops) {
+                    let kind = AccessKind::new(read_only, latest.read_only);
+                    deps.insert(latest.dep.with_kind(kind));
+                }
+            }
+        }
+    }
+
+    fn do_noop_deps(&self, deps: &mut HashSet<Dependency>) {
+        // noops depend on the latest commands on all keys
+        for latest in self.latest.values().flatten() {
+            let kind = AccessKind::new(false, latest.read_only);
+            deps.insert(latest.dep.with_kind(kind));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::extract_dots;
+    use super::*;
+    use fantoch::command::{CommutativityAwareOracle, KeyOverlapOracle};
+    use fantoch::id::{DotGen, Rifl};
+    use std::iter::FromIterator;
+
+    fn add(rifl: Rifl, key: &str) -> Command {
+        Command::from(rifl, vec![(String::from(key), KVOp::Add(1))])
+    }
+
+    fn put(rifl: Rifl, key: &str) -> Command {
+        Command::from(rifl, vec![(String::from(key), KVOp::Put(10))])
+    }
+
+    #[test]
+    fn commuting_adds() {
+        let shard_id = 0;
+        let nfr = false;
+        let mut dot_gen = DotGen::new(1);
+        let dot_a = dot_gen.next_id();
+        let dot_b = dot_gen.next_id();
+        let dot_c = dot_gen.next_id();
+        let dot_d = dot_gen.next_id();
+        let add_a = add(Rifl::new(1, 1), "A");
+        let add_b = add(Rifl::new(2, 1), "A");
+        let put_c = put(Rifl::new(3, 1), "A");
+        let add_d = add(Rifl::new(4, 1), "A");
+
+        // under the default oracle, the second add depends on the first one
+        let mut key_deps =
+            OracleKeyDeps::<KeyOverlapOracle>::new(shard_id, nfr);
+        assert!(key_deps.add_cmd(dot_a, &add_a, None).is_empty());
+        let deps = extract_dots(key_deps.add_cmd(dot_b, &add_b, None));
+        assert_eq!(deps, HashSet::from_iter(vec![dot_a]));
+
+        // under the commutativity-aware oracle, it doesn't
+        let mut key_deps =
+            OracleKeyDeps::<CommutativityAwareOracle>::new(shard_id, nfr);
+        assert!(key_deps.add_cmd(dot_a, &add_a, None).is_empty());
+        assert!(key_deps.add_cmd(dot_b, &add_b, None).is_empty());
+
+        // but a put still depends on all the adds, and an add on the put
+        let deps = extract_dots(key_deps.add_cmd(dot_c, &put_c, None));
+        assert_eq!(deps, HashSet::from_iter(vec![dot_a, dot_b]));
+        let deps = extract_dots(key_deps.add_cmd(dot_d, &add_d, None));
+        assert_eq!(deps, HashSet::from_iter(vec![dot_c]));
+    }
+
+    #[test]
+    fn bounded_latest() {
+        let shard_id = 0;
+        let nfr = false;
+        let mut dot_gen = DotGen::new(1);
+        let mut key_deps =
+            OracleKeyDeps::<CommutativityAwareOracle>::new(shard_id, nfr);
+
+        // adds commute, and so they're all kept until the bound is reached
+        let adds: Vec<_> = (1..=MAX_LATEST_OPS as u64)
+            .map(|sequence| {
+                let dot = dot_gen.next_id();
+                let add = add(Rifl::new(1, sequence), "A");
+                assert!(key_deps.add_cmd(dot, &add, None).is_empty());
+                dot
+            })
+            .collect();
+        assert_eq!(key_deps.latest["A"].len(), MAX_LATEST_OPS);
+
+        // then, the next add depends on all of them and replaces them
+        let dot_barrier = dot_gen.next_id();
+        let add_barrier = add(Rifl::new(2, 1), "A");
+        let deps =
+            extract_dots(key_deps.add_cmd(dot_barrier, &add_barrier, None));
+        assert_eq!(deps, HashSet::from_iter(adds));
+        assert_eq!(key_deps.latest["A"].len(), 1);
+
+        // and later adds depend on it, even though adds commute
+        let dot = dot_gen.next_id();
+        let add = add(Rifl::new(2, 2), "A");
+        let deps = extract_dots(key_deps.add_cmd(dot, &add, None));
+        assert_eq!(deps, HashSet::from_iter(vec![dot_barrier]));
+        assert_eq!(key_deps.latest["A"].len(), 2);
+    }
+}
@@ -1,8 +1,9 @@
-use super::{Dependency, KeyDeps, LatestDep, LatestRWDep};
+use super::{AccessKind, Dependency, KeyDeps, LatestDep, LatestRWDep};
 use fantoch::command::Command;
-use fantoch::id::{Dot, ShardId};
+use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::kvs::Key;
 use fantoch::{HashMap, HashSet};
+use threshold::VClock;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SequentialKeyDeps {
@@ -29,12 +30,8 @@ impl KeyDeps for SequentialKeyDeps {
         cmd: &Command,
         past: Option<HashSet<Dependency>>,
     ) -> HashSet<Dependency> {
-        // we start with past in case there's one, or bottom otherwise
-        let deps = match past {
-            Some(past) => past,
-            None => HashSet::new(),
-        };
-        self.do_add_cmd(dot, cmd, deps)
+        let skip_deps = false;
+        self.add_cmd_with(dot, cmd, past, skip_deps)
     }
 
     fn add_noop(&mut self, dot: Dot) -> HashSet<Dependency> {
@@ -46,7 +43,7 @@ impl KeyDeps for SequentialKeyDeps {
     #[cfg(test)]
     fn cmd_deps(&self, cmd: &Command) -> HashSet<Dot> {
         let mut deps = HashSet::new();
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(cmd.read_only(), &mut deps);
         self.do_cmd_deps(cmd, &mut deps);
         super::extract_dots(deps)
     }
@@ -54,7 +51,7 @@ impl KeyDeps for SequentialKeyDeps {
     #[cfg(test)]
     fn noop_deps(&self) -> HashSet<Dot> {
         let mut deps = HashSet::new();
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(false, &mut deps);
         self.do_noop_deps(&mut deps);
         super::extract_dots(deps)
     }
@@ -62,12 +59,66 @@ impl KeyDeps for SequentialKeyDeps {
     fn parallel() -> bool {
         false
     }
+
+    fn is_parallel(&self) -> bool {
+        Self::parallel()
+    }
 }
 
 impl SequentialKeyDeps {
-    fn maybe_add_noop_latest(&self, deps: &mut HashSet<Dependency>) {
+    /// Same as `KeyDeps::add_cmd`, but if `skip_deps` is set, the command is
+    /// registered as the latest command on each of its keys without computing
+    /// its dependencies, and an empty set of dependencies is returned. This is
+    /// useful when commands are executed at commit time, as in that case
+    /// dependencies are not needed for execution.
+    pub fn add_cmd_with(
+        &mut self,
+        dot: Dot,
+        cmd: &Command,
+        past: Option<HashSet<Dependency>>,
+        skip_deps: bool,
+    ) -> HashSet<Dependency> {
+        // we start with past in case there's one, or bottom otherwise
+        let deps = match past {
+            Some(past) => past,
+            None => HashSet::new(),
+        };
+        self.do_add_cmd(dot, cmd, deps, skip_deps)
+    }
+
+    /// Forgets the latest commands (and noop) that are already covered by the
+    /// `stable` clock. Since these commands are stable, new commands no
+    /// longer need to depend on them, and keys whose latest commands are all
+    /// stable can be removed.
+    pub fn gc(&mut self, stable: &VClock<ProcessId>) {
+        let forget_if_stable = |latest: &mut LatestDep| {
+            let is_stable = match latest {
+                Some(dep) => {
+                    stable.contains(&dep.dot.source(), dep.dot.sequence())
+                }
+                None => false,
+            };
+            if is_stable {
+                *latest = None;
+            }
+        };
+
+        self.latest.retain(|_, latest_rw| {
+            forget_if_stable(&mut latest_rw.read);
+            forget_if_stable(&mut latest_rw.write);
+            // only keep the key if some of its latest commands is not stable
+            latest_rw.read.is_some() || latest_rw.write.is_some()
+        });
+        forget_if_stable(&mut self.latest_noop);
+    }
+
+    fn maybe_add_noop_latest(
+        &self,
+        read_only: bool,
+        deps: &mut HashSet<Dependency>,
+    ) {
         if let Some(dep) = self.latest_noop.as_ref() {
-            deps.insert(dep.clone());
+            deps.insert(dep.with_kind(AccessKind::new(read_only, false)));
         }
     }
 
@@ -76,18 +127,20 @@ impl SequentialKeyDeps {
         dot: Dot,
         cmd: &Command,
         mut deps: HashSet<Dependency>,
+        skip_deps: bool,
     ) -> HashSet<Dependency> {
         // create cmd dep
         let cmd_dep = Dependency::from_cmd(dot, cmd);
 
+        // snapshot reads are always ordered after the latest write on each of
+        // their keys (even if commands are executed at commit time), and thus
+        // their dependencies can't be skipped
+        let skip_deps = skip_deps && !cmd.is_snapshot_read();
+
         // flag indicating whether the command is read-only
         let read_only = cmd.read_only();
-        // we only support single-key read commands with NFR
-        assert!(if self.nfr && read_only {
-            cmd.total_key_count() == 1
-        } else {
-            true
-        });
+        // we only support single-key and snapshot read commands with NFR
+        assert!(super::nfr_supported(self.nfr, cmd));
 
         // iterate through all command keys, get their current latest and set
         // ourselves to be the new latest
@@ -98,7 +151,11 @@ impl SequentialKeyDeps {
                 None => self.latest.entry(key.clone()).or_default(),
             };
 
-            super::maybe_add_deps(read_only, self.nfr, latest_rw, &mut deps);
+            if !skip_deps {
+                super::maybe_add_deps(
+                    read_only, self.nfr, latest_rw, &mut deps,
+                );
+            }
 
             // finally, store the command
             if read_only {
@@ -110,8 +167,13 @@ impl SequentialKeyDeps {
             }
         });
 
+        if skip_deps {
+            // if skipping dependencies, return bottom
+            return HashSet::new();
+        }
+
         // always include latest noop, if any
-        self.maybe_add_noop_latest(&mut deps);
+        self.maybe_add_noop_latest(read_only, &mut deps);
 
         // and finally return the computed deps
         deps
@@ -126,7 +188,7 @@ impl SequentialKeyDeps {
         if let Some(dep) = self.latest_noop.replace(Dependency::from_noop(dot))
         {
             // if there was a previous latest, then it's a dependency
-            deps.insert(dep);
+            deps.insert(dep.with_kind(AccessKind::WriteWrite));
         }
 
         // compute deps for this noop
@@ -138,14 +200,9 @@ impl SequentialKeyDeps {
     fn do_noop_deps(&self, deps: &mut HashSet<Dependency>) {
         // iterate through all keys, grab a read lock, and include their latest
         // in the final `deps`
-        self.latest.values().for_each(|latest_rw| {
-            if let Some(rdep) = latest_rw.read.as_ref() {
-                deps.insert(rdep.clone());
-            }
-            if let Some(wdep) = latest_rw.write.as_ref() {
-                deps.insert(wdep.clone());
-            }
-        });
+        self.latest
+            .values()
+            .for_each(|latest_rw| super::add_noop_deps(latest_rw, deps));
     }
 
     #[cfg(test)]
@@ -161,3 +218,57 @@ impl SequentialKeyDeps {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::id::{DotGen, Rifl};
+    use fantoch::kvs::KVOp;
+    use std::iter::FromIterator;
+
+    fn put(rifl: Rifl, key: &str) -> Command {
+        Command::from(rifl, vec![(String::from(key), KVOp::Put(10))])
+    }
+
+    #[test]
+    fn gc() {
+        let shard_id = 0;
+        let nfr = false;
+        let mut key_deps = SequentialKeyDeps::new(shard_id, nfr);
+
+        let process_id = 1;
+        let mut dot_gen = DotGen::new(process_id);
+
+        // add A with {1,1}, B with {1,2} and a noop with {1,3}
+        let cmd_a = put(Rifl::new(100, 1), "A");
+        let cmd_b = put(Rifl::new(101, 1), "B");
+        key_deps.add_cmd(dot_gen.next_id(), &cmd_a, None);
+        key_deps.add_cmd(dot_gen.next_id(), &cmd_b, None);
+        key_deps.add_noop(dot_gen.next_id());
+        assert_eq!(key_deps.latest.len(), 2);
+        assert!(key_deps.latest_noop.is_some());
+
+        // gc with a stable clock that only covers {1,1}
+        let mut stable = VClock::with(vec![process_id]);
+        stable.add(&process_id, 1);
+        key_deps.gc(&stable);
+        assert_eq!(key_deps.latest.len(), 1);
+        assert!(key_deps.latest_noop.is_some());
+
+        // gc with a stable clock that covers all commands added
+        stable.add(&process_id, 3);
+        key_deps.gc(&stable);
+        assert!(key_deps.latest.is_empty());
+        assert!(key_deps.latest_noop.is_none());
+
+        // new commands no longer depend on stable commands
+        assert!(key_deps.cmd_deps(&cmd_a).is_empty());
+        assert!(key_deps.noop_deps().is_empty());
+
+        // but dependencies on new commands are still computed
+        let dot = dot_gen.next_id();
+        assert!(key_deps.add_cmd(dot, &cmd_a, None).is_empty());
+        assert_eq!(key_deps.cmd_deps(&cmd_a), HashSet::from_iter(vec![dot]));
+        assert!(key_deps.cmd_deps(&cmd_b).is_empty());
+    }
+}
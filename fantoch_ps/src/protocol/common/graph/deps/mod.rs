@@ -4,6 +4,13 @@ mod keys;
 // // This module contains the definition of `QuorumClocks`.
 mod quorum;
 
+// This module contains the definition of `CommittedDeps`.
+mod committed;
+
 // Re-exports.
-pub use keys::{Dependency, KeyDeps, LockedKeyDeps, SequentialKeyDeps, MultiRecordValues, Key_Deps_MRV};
+pub use committed::CommittedDeps;
+pub use keys::{
+    AccessKind, AutoKeyDeps, Dependency, KeyDeps, KeyDepsClone, Key_Deps_MRV,
+    LockedKeyDeps, MultiRecordValues, OracleKeyDeps, SequentialKeyDeps,
+};
 pub use quorum::QuorumDeps;
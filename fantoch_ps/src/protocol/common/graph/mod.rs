@@ -3,6 +3,7 @@ mod deps;
 
 // Re-exports.
 pub use deps::{
-    Dependency, KeyDeps, LockedKeyDeps, QuorumDeps, SequentialKeyDeps, MultiRecordValues,
-    Key_Deps_MRV,
+    AccessKind, AutoKeyDeps, CommittedDeps, Dependency, KeyDeps, KeyDepsClone,
+    Key_Deps_MRV, LockedKeyDeps, MultiRecordValues, OracleKeyDeps, QuorumDeps,
+    SequentialKeyDeps,
 };
@@ -75,6 +75,22 @@ impl Votes {
         self.votes.remove(key)
     }
 
+    /// Returns the votes on each key, with keys sorted and, within each key,
+    /// vote ranges sorted by voter and then by range start. Unlike
+    /// `into_iter`, this order doesn't depend on the order in which votes
+    /// were added.
+    pub fn sorted_iter(
+        &self,
+    ) -> impl Iterator<Item = (&Key, Vec<&VoteRange>)> + '_ {
+        let mut keys: Vec<_> = self.votes.keys().collect();
+        keys.sort();
+        keys.into_iter().map(move |key| {
+            let mut key_votes: Vec<_> = self.votes[key].iter().collect();
+            key_votes.sort_by_key(|vote| (vote.by, vote.start));
+            (key, key_votes)
+        })
+    }
+
     /// Get the number of votes.
     pub fn len(&self) -> usize {
         self.votes.len()
@@ -309,4 +325,44 @@ mod tests {
         assert_eq!(key_votes_by_p0.voter(), 0);
         assert_eq!(key_votes_by_p0.votes(), vec![1, 2]);
     }
+
+    #[test]
+    fn sorted_iter() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+
+        // votes from p2, then p0, then p1, with p0 voting twice on key a
+        let mut votes = Votes::new();
+        let mut votes_p2 = Votes::new();
+        votes_p2.add(&key_b, VoteRange::new(2, 1, 1));
+        votes_p2.add(&key_a, VoteRange::new(2, 1, 2));
+        votes.merge(votes_p2);
+        let mut votes_p0 = Votes::new();
+        votes_p0.set(
+            key_a.clone(),
+            vec![VoteRange::new(0, 5, 5), VoteRange::new(0, 1, 3)],
+        );
+        votes.merge(votes_p0);
+        let mut votes_p1 = Votes::new();
+        votes_p1.add(&key_a, VoteRange::new(1, 1, 1));
+        votes.merge(votes_p1);
+
+        // keys are sorted, and so are the ranges on each key
+        let sorted: Vec<_> = votes.sorted_iter().collect();
+        assert_eq!(
+            sorted,
+            vec![
+                (
+                    &key_a,
+                    vec![
+                        &VoteRange::new(0, 1, 3),
+                        &VoteRange::new(0, 5, 5),
+                        &VoteRange::new(1, 1, 1),
+                        &VoteRange::new(2, 1, 2),
+                    ]
+                ),
+                (&key_b, vec![&VoteRange::new(2, 1, 1)]),
+            ]
+        );
+    }
 }
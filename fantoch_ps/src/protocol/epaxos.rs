@@ -1,14 +1,15 @@
 use crate::executor::{GraphExecutionInfo, GraphExecutor};
 use crate::protocol::common::graph::{
-    Dependency, KeyDeps, LockedKeyDeps, QuorumDeps, SequentialKeyDeps, MultiRecordValues
+    CommittedDeps, Dependency, KeyDeps, LockedKeyDeps, QuorumDeps, SequentialKeyDeps, MultiRecordValues
 };
 use crate::protocol::common::synod::{Synod, SynodMessage};
 use fantoch::command::Command;
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    Action, BaseProcess, CommitCallback, Info, MessageIndex, Protocol,
+    ProtocolMetrics, SequentialCommandsInfo, StableCallback, VClockGCTrack,
 };
 use fantoch::time::SysTime;
 use fantoch::{singleton, trace};
@@ -21,7 +22,7 @@ pub type EPaxosSequential = EPaxos<SequentialKeyDeps>;
 pub type EPaxosLocked = EPaxos<LockedKeyDeps>;
 
 #[derive(Debug, Clone)]
-pub struct EPaxos<KD: KeyDeps> {
+pub struct EPaxos<KD: KeyDeps + Clone> {
     bp: BaseProcess,
     key_deps: KD,
     cmds: SequentialCommandsInfo<EPaxosInfo>,
@@ -31,9 +32,12 @@ pub struct EPaxos<KD: KeyDeps> {
     // commit notifications that arrived before the initial `MCollect` message
     // (this may be possible even without network failures due to multiplexing)
     buffered_commits: HashMap<Dot, (ProcessId, ConsensusValue)>,
+    // dependencies of committed commands (only tracked with transitive
+    // conflicts and gc)
+    committed_deps: CommittedDeps,
 }
 
-impl<KD: KeyDeps> Protocol for EPaxos<KD> {
+impl<KD: KeyDeps + Clone> Protocol for EPaxos<KD> {
     type Message = Message;
     type PeriodicEvent = PeriodicEvent;
     type Executor = GraphExecutor;
@@ -56,7 +60,7 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             fast_quorum_size,
             write_quorum_size,
         );
-        let key_deps = KD::new(shard_id, config.nfr());
+        let key_deps = KD::from_config(shard_id, &config);
         let f = Self::allowed_faults(config.n());
         let cmds = SequentialCommandsInfo::new(
             process_id,
@@ -70,6 +74,7 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
         let to_processes = Vec::new();
         let to_executors = Vec::new();
         let buffered_commits = HashMap::new();
+        let committed_deps = CommittedDeps::new(&config);
 
         // create `EPaxos`
         let protocol = Self {
@@ -80,6 +85,7 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             to_processes,
             to_executors,
             buffered_commits,
+            committed_deps,
         };
 
         // create periodic events
@@ -115,6 +121,7 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd);
     }
 
@@ -180,16 +187,36 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
         KD::parallel()
     }
 
+    fn is_parallel(&self) -> bool {
+        self.key_deps.is_parallel()
+    }
+
     fn leaderless() -> bool {
         true
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
 }
 
-impl<KD: KeyDeps> EPaxos<KD> {
+impl<KD: KeyDeps + Clone> EPaxos<KD> {
     /// EPaxos always tolerates a minority of faults.
     pub fn allowed_faults(n: usize) -> usize {
         n / 2
@@ -200,6 +227,10 @@ impl<KD: KeyDeps> EPaxos<KD> {
         // compute the command identifier
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
+        // track the command (and its quorum acks) until it's committed
+        self.bp.track_outstanding(dot);
+        self.bp.track_acks(dot);
+
         // compute its deps
         let deps = self.key_deps.add_cmd(dot, &cmd, None);
 
@@ -327,6 +358,9 @@ impl<KD: KeyDeps> EPaxos<KD> {
             return;
         }
 
+        // record the quorum ack
+        self.bp.ack(dot);
+
         // update quorum deps
         info.quorum_deps.add(from, deps);
 
@@ -403,12 +437,19 @@ impl<KD: KeyDeps> EPaxos<KD> {
             "handling noop's is not implemented yet"
         );
 
-        // create execution info
+        // notify the on-commit callback (if any) and create execution info;
+        // if enabled, transitively reduce the dependencies first
         let cmd = info.cmd.clone().expect("there should be a command payload");
-        let execution_info =
-            GraphExecutionInfo::add(dot, cmd, value.deps.clone());
+        command_span!("commit", cmd.rifl(), _time);
+        self.bp.committed(dot, &cmd);
+        let deps = self.committed_deps.commit(dot, &value.deps);
+        let execution_info = GraphExecutionInfo::add(dot, cmd, deps);
         self.to_executors.push(execution_info);
 
+        // the command is no longer outstanding
+        self.bp.untrack_outstanding(dot);
+        self.bp.untrack_acks(dot);
+
         // update command info:
         info.status = Status::COMMIT;
 
@@ -560,16 +601,22 @@ impl<KD: KeyDeps> EPaxos<KD> {
         &mut self,
         from: ProcessId,
         stable: Vec<(ProcessId, u64, u64)>,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
         trace!(
             "p{}: MStable({:?}) from {} | time={}",
             self.id(),
             stable,
             from,
-            _time.micros()
+            time.micros()
         );
         assert_eq!(from, self.bp.process_id);
+        if self.bp.config.transitive_conflicts() {
+            for dot in fantoch::util::dots(stable.clone()) {
+                self.committed_deps.gc(&dot);
+            }
+        }
+        self.bp.advance_stable_frontier(&stable, time);
         let stable_count = self.cmds.gc(stable);
         self.bp.stable(stable_count);
     }
@@ -581,6 +628,9 @@ impl<KD: KeyDeps> EPaxos<KD> {
             _time.micros()
         );
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         // retrieve the committed clock
         let committed = self.gc_track.clock().frontier();
 
@@ -765,7 +815,7 @@ mod tests {
         epaxos_flow::<LockedKeyDeps>();
     }
 
-    fn epaxos_flow<KD: KeyDeps>() {
+    fn epaxos_flow<KD: KeyDeps + Clone>() {
         // create simulation
         let mut simulation = Simulation::new();
 
@@ -5,12 +5,13 @@ use crate::protocol::common::graph::{
 };
 use crate::protocol::common::synod::{Synod, SynodMessage};
 use fantoch::command::Command;
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
-use fantoch::kvs::Key;
+use fantoch::kvs::{Key, RecordSelector, StorageMode};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    Action, BaseProcess, CommitCallback, Info, MessageIndex, Protocol,
+    ProtocolMetrics, SequentialCommandsInfo, StableCallback, VClockGCTrack,
 };
 use fantoch::time::SysTime;
 use fantoch::{singleton, trace};
@@ -58,7 +59,17 @@ impl Protocol for EPaxosMRV {
             fast_quorum_size,
             write_quorum_size,
         );
-        let key_deps = MultiRecordValues::new(shard_id, config.nfr());
+        let mut key_deps = MultiRecordValues::new(shard_id, config.nfr());
+        // records are picked as in the store, so that commands are ordered on
+        // the records they change; if keys have a single record, records are
+        // picked at random (given the seed, if any)
+        let selector = match config.storage_mode() {
+            StorageMode::MultiRecord(selector) => selector,
+            StorageMode::SingleRecord => RecordSelector::Random {
+                seed: config.seed().unwrap_or_default(),
+            },
+        };
+        key_deps.set_record_selector(selector);
         let f = Self::allowed_faults(config.n());
         let cmds = SequentialCommandsInfo::new(
             process_id,
@@ -117,6 +128,7 @@ impl Protocol for EPaxosMRV {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd);
     }
 
@@ -187,6 +199,22 @@ impl Protocol for EPaxosMRV {
         true
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
@@ -203,6 +231,10 @@ impl EPaxosMRV {
         // compute the command identifier
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
+        // track the command until it's committed
+        self.bp.track_outstanding(dot);
+        self.bp.track_acks(dot);
+
         // compute its deps
         let (deps, keys_n) = self.key_deps.add_cmd(dot, &cmd, None, None);
 
@@ -333,6 +365,9 @@ impl EPaxosMRV {
             return;
         }
 
+        // record the quorum ack
+        self.bp.ack(dot);
+
         // update quorum deps
         info.quorum_deps.add(from, deps);
 
@@ -409,12 +444,18 @@ impl EPaxosMRV {
             "handling noop's is not implemented yet"
         );
 
-        // create execution info
+        // notify the on-commit callback (if any) and create execution info
         let cmd = info.cmd.clone().expect("there should be a command payload");
+        command_span!("commit", cmd.rifl(), _time);
+        self.bp.committed(dot, &cmd);
         let execution_info =
             GraphExecutionInfo::add(dot, cmd, value.deps.clone());
         self.to_executors.push(execution_info);
 
+        // the command is no longer outstanding
+        self.bp.untrack_outstanding(dot);
+        self.bp.untrack_acks(dot);
+
         // update command info:
         info.status = Status::COMMIT;
 
@@ -566,16 +607,17 @@ impl EPaxosMRV {
         &mut self,
         from: ProcessId,
         stable: Vec<(ProcessId, u64, u64)>,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
         trace!(
             "p{}: MStable({:?}) from {} | time={}",
             self.id(),
             stable,
             from,
-            _time.micros()
+            time.micros()
         );
         assert_eq!(from, self.bp.process_id);
+        self.bp.advance_stable_frontier(&stable, time);
         let stable_count = self.cmds.gc(stable);
         self.bp.stable(stable_count);
     }
@@ -587,6 +629,9 @@ impl EPaxosMRV {
             _time.micros()
         );
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         // retrieve the committed clock
         let committed = self.gc_track.clock().frontier();
 
@@ -1,10 +1,12 @@
 use crate::executor::{SlotExecutionInfo, SlotExecutor};
 use crate::protocol::common::synod::{GCTrack, MultiSynod, MultiSynodMessage};
 use fantoch::command::Command;
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, MessageIndex, Protocol, ProtocolMetrics,
+    Action, BaseProcess, CommitCallback, MessageIndex, Protocol,
+    ProtocolMetrics, StableCallback,
 };
 use fantoch::time::SysTime;
 use fantoch::{singleton, trace};
@@ -47,7 +49,7 @@ impl Protocol for FPaxos {
         );
 
         // get leader from config
-        let initial_leader = config.leader().expect(
+        let initial_leader = config.leader_of(shard_id).expect(
             "in a leader-based protocol, the initial leader should be defined",
         );
         // create multi synod
@@ -99,6 +101,7 @@ impl Protocol for FPaxos {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd);
     }
 
@@ -157,6 +160,22 @@ impl Protocol for FPaxos {
         false
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
@@ -172,6 +191,11 @@ impl FPaxos {
                     fantoch::protocol::ProtocolMetricsKind::CommandKeyCount,
                     cmd.total_key_count() as u64,
                 );
+                // and track the command (identified by the leader and its
+                // slot), and its quorum acks, until it's chosen
+                let dot = Dot::new(self.bp.process_id, slot);
+                self.bp.track_outstanding(dot);
+                self.bp.track_acks(dot);
                 // and send a spawn commander to self (that can run in a
                 // different process for parallelism)
                 let mspawn = Message::MSpawnCommander { ballot, slot, cmd };
@@ -293,6 +317,10 @@ impl FPaxos {
             _time.micros()
         );
 
+        // record the quorum ack (if we're the leader that proposed the
+        // command in this slot)
+        self.bp.ack(Dot::new(self.bp.process_id, slot));
+
         if let Some(msg) = self
             .multi_synod
             .handle(from, MultiSynodMessage::MAccepted(ballot, slot))
@@ -323,10 +351,19 @@ impl FPaxos {
             _time.micros()
         );
 
+        // notify the on-commit callback (if any)
+        let dot = Dot::new(self.leader, slot);
+        command_span!("commit", cmd.rifl(), _time);
+        self.bp.committed(dot, &cmd);
+
         // create execution info
         let execution_info = SlotExecutionInfo::new(slot, cmd);
         self.to_executors.push(execution_info);
 
+        // the command is no longer outstanding
+        self.bp.untrack_outstanding(dot);
+        self.bp.untrack_acks(dot);
+
         if self.gc_running() {
             // register that it has been committed
             self.gc_track.commit(slot);
@@ -344,18 +381,21 @@ impl FPaxos {
         &mut self,
         from: ProcessId,
         committed: u64,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
         trace!(
             "p{}: MGarbageCollection({:?}) from {} | time={}",
             self.id(),
             committed,
             from,
-            _time.micros()
+            time.micros()
         );
         self.gc_track.committed_by(from, committed);
         // perform garbage collection of stable slots
         let stable = self.gc_track.stable();
+        let (start, end) = stable;
+        self.bp
+            .advance_stable_frontier(&[(self.leader, start, end)], time);
         let stable_count = self.multi_synod.gc(stable);
         self.bp.stable(stable_count);
     }
@@ -367,6 +407,9 @@ impl FPaxos {
             _time.micros()
         );
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         // retrieve the committed slot
         let committed = self.gc_track.committed();
 
@@ -691,4 +734,52 @@ mod tests {
         let check_msg = |msg: &Message| matches!(msg, Message::MSpawnCommander{slot, ..} if slot == &2);
         assert!(matches!(mcollect, Action::ToForward {msg} if check_msg(&msg)));
     }
+
+    #[test]
+    fn fpaxos_leaders_by_shard() {
+        use fantoch::id::Rifl;
+        use fantoch::kvs::KVOp;
+
+        // n and f
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_shard_count(2);
+
+        // process 1 leads shard 0 and process 5 leads shard 1
+        let leaders = HashMap::from_iter(vec![(0, 1), (1, 5)]);
+        config.set_leaders(leaders);
+
+        // given a process and its shard, returns the target of a submit
+        let submit = |process_id, shard_id| {
+            let (mut fpaxos, _) = FPaxos::new(process_id, shard_id, config);
+            let cmd = Command::from(
+                Rifl::new(1, 1),
+                vec![(String::from("A"), KVOp::Get)],
+            );
+            let time = SimTime::new();
+            fpaxos.submit(None, cmd, &time);
+            let mut actions: Vec<_> = fpaxos.to_processes_iter().collect();
+            assert_eq!(actions.len(), 1);
+            match actions.pop().unwrap() {
+                Action::ToSend {
+                    target,
+                    msg: Message::MForwardSubmit { .. },
+                } => target,
+                Action::ToForward {
+                    msg: Message::MSpawnCommander { .. },
+                } => singleton![process_id],
+                action => panic!("unexpected action {:?}", action),
+            }
+        };
+
+        // leaders handle their submits
+        assert_eq!(submit(1, 0), singleton![1]);
+        assert_eq!(submit(5, 1), singleton![5]);
+        // other processes forward to the leader of their shard
+        assert_eq!(submit(2, 0), singleton![1]);
+        assert_eq!(submit(3, 0), singleton![1]);
+        assert_eq!(submit(4, 1), singleton![5]);
+        assert_eq!(submit(6, 1), singleton![5]);
+    }
 }
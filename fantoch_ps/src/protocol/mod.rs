@@ -21,7 +21,7 @@ mod caesar;
 mod partial;
 
 // Re-exports.
-pub use atlas::{AtlasLocked, AtlasSequential};
+pub use atlas::{AtlasCommutative, AtlasLocked, AtlasSequential};
 pub use caesar::CaesarLocked;
 pub use epaxos::{EPaxosLocked, EPaxosSequential};
 pub use epaxos_mrv::EPaxosMRV;
@@ -31,15 +31,20 @@ pub use tempo::{TempoAtomic, TempoLocked, TempoSequential};
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::executor::GraphExecutor;
+    use crate::protocol::atlas::Atlas;
+    use crate::protocol::common::graph::AutoKeyDeps;
+    use crate::protocol::epaxos::EPaxos;
     use fantoch::client::{KeyGen, Workload};
     use fantoch::config::Config;
-    use fantoch::executor::ExecutionOrderMonitor;
+    use fantoch::executor::{ExecutionOrderMonitor, ExecutorMetricsKind};
     use fantoch::id::{ProcessId, Rifl};
     use fantoch::kvs::Key;
-    use fantoch::planet::Planet;
-    use fantoch::protocol::{Protocol, ProtocolMetrics};
+    use fantoch::metrics::Histogram;
+    use fantoch::planet::{Planet, Region};
+    use fantoch::protocol::{Protocol, ProtocolMetrics, ProtocolMetricsKind};
     use fantoch::run::tests::{run_test_with_inspect_fun, tokio_test_runtime};
-    use fantoch::sim::Runner;
+    use fantoch::sim::{MessageCounts, Runner};
     use fantoch::HashMap;
     use std::time::Duration;
 
@@ -383,6 +388,21 @@ mod tests {
         assert_eq!(metrics.slow_paths_reads(), 0);
     }
 
+    #[test]
+    fn sim_atlas_3_1_transitive_conflicts_test() {
+        // `sim_test` runs gc, so dependencies are transitively reduced
+        let mut config = config!(3, 1);
+        config.set_transitive_conflicts(true);
+        let metrics = sim_test::<AtlasSequential>(
+            config,
+            READ_ONLY_PERCENTAGE,
+            KEYS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(metrics.slow_paths(), 0);
+    }
+
     #[test]
     fn run_atlas_3_1_locked_test() {
         // atlas locked can handle as many workers as we want but only one
@@ -400,6 +420,24 @@ mod tests {
         assert_eq!(metrics.slow_paths(), 0);
     }
 
+    #[test]
+    fn run_atlas_3_1_auto_test() {
+        // with more than one worker, atlas picks the locked key deps at runtime
+        let workers = 4;
+        let executors = 1;
+        let mut config = config!(3, 1);
+        config.set_parallel_key_deps(workers > 1);
+        let metrics = run_test::<Atlas<AutoKeyDeps>>(
+            config,
+            SHARD_COUNT,
+            workers,
+            executors,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(metrics.slow_paths(), 0);
+    }
+
     // ---- atlas (partial replication) tests ---- //
     #[test]
     fn run_atlas_3_1_locked_partial_replication_two_shards_test() {
@@ -418,6 +456,122 @@ mod tests {
         assert_eq!(metrics.slow_paths(), 0);
     }
 
+    #[test]
+    fn sim_atlas_3_1_admission_control_test() {
+        // with a cap, the number of outstanding commands stays bounded and
+        // clients see rejections instead
+        let mut config = config!(3, 1);
+        config.set_max_outstanding(Some(2));
+        let (max_outstanding, rejected, max_pending) =
+            sim_admission_control::<AtlasSequential>(
+                config,
+                GraphExecutor::pending_count,
+            );
+        assert!(max_outstanding <= 2);
+        assert!(rejected > 0);
+
+        // the cap also bounds the number of commands waiting to be executed
+        // in the graph executors
+        let (_, _, uncapped_max_pending) =
+            sim_admission_control::<AtlasSequential>(
+                config!(3, 1),
+                GraphExecutor::pending_count,
+            );
+        assert!(max_pending <= uncapped_max_pending);
+    }
+
+    #[test]
+    fn sim_on_commit_test() {
+        // whatever the protocol, each command is committed once at each
+        // process
+        let leader = 1;
+        sim_on_commit::<AtlasSequential>(config!(3, 1));
+        sim_on_commit::<CaesarLocked>(caesar_config!(3, 1, false));
+        sim_on_commit::<EPaxosSequential>(config!(3, 1));
+        sim_on_commit::<FPaxos>(config!(3, 1, leader));
+        sim_on_commit::<TempoSequential>(tempo_config!(3, 1));
+    }
+
+    #[test]
+    fn sim_on_stable_test() {
+        // leaderless protocols end up with all commands stable at all
+        // processes; with FPaxos, at least the f+1 acceptors GC them
+        let leader = 1;
+        assert_eq!(sim_on_stable::<AtlasSequential>(config!(3, 1)), 3);
+        assert_eq!(
+            sim_on_stable::<CaesarLocked>(caesar_config!(3, 1, false)),
+            3
+        );
+        assert_eq!(sim_on_stable::<EPaxosSequential>(config!(3, 1)), 3);
+        assert!(sim_on_stable::<FPaxos>(config!(3, 1, leader)) >= 2);
+        assert_eq!(sim_on_stable::<TempoSequential>(tempo_config!(3, 1)), 3);
+    }
+
+    #[test]
+    fn sim_pending_acks_test() {
+        // whatever the protocol, the quorum acks of in-flight commands are
+        // collected, and in-flight commands never have acks from all
+        // processes
+        let leader = 1;
+        sim_pending_acks::<AtlasSequential>(config!(3, 1));
+        sim_pending_acks::<CaesarLocked>(caesar_config!(3, 1, false));
+        sim_pending_acks::<EPaxosSequential>(config!(3, 1));
+        sim_pending_acks::<EPaxosMRV>(config!(3, 1));
+        sim_pending_acks::<FPaxos>(config!(3, 1, leader));
+        sim_pending_acks::<TempoSequential>(tempo_config!(3, 1));
+    }
+
+    #[cfg(feature = "command_spans")]
+    #[test]
+    fn sim_execute_spans_test() {
+        // whatever the executor, each command is executed (inside an
+        // `execute` span) at every process
+        let leader = 1;
+        sim_execute_spans::<AtlasSequential>(config!(3, 1));
+        sim_execute_spans::<CaesarLocked>(caesar_config!(3, 1, false));
+        sim_execute_spans::<FPaxos>(config!(3, 1, leader));
+        sim_execute_spans::<TempoSequential>(tempo_config!(3, 1));
+    }
+
+    #[test]
+    fn sim_seed_test() {
+        // whatever the protocol, a seeded simulation is reproducible
+        let leader = 1;
+        sim_seed::<AtlasSequential>(config!(3, 1));
+        sim_seed::<CaesarLocked>(caesar_config!(3, 1, false));
+        sim_seed::<EPaxosSequential>(config!(3, 1));
+        sim_seed::<FPaxos>(config!(3, 1, leader));
+        sim_seed::<TempoSequential>(tempo_config!(3, 1));
+    }
+
+    #[test]
+    fn sim_phase_latencies_test() {
+        // with f = 1, Atlas always takes the fast path, and thus the time to
+        // commit doesn't depend on conflicts; the time to execute, instead,
+        // grows as commands wait for their concurrent dependencies
+        let (commit, execute) =
+            sim_phase_latencies::<AtlasSequential>(config!(3, 1), 0);
+        let (conflict_commit, conflict_execute) =
+            sim_phase_latencies::<AtlasSequential>(config!(3, 1), 100);
+        assert!(conflict_execute > execute);
+        assert!((conflict_commit - commit).abs() < 0.1 * commit);
+
+        // commands executed by the slot executor also have phase times
+        let leader = 1;
+        sim_phase_latencies::<FPaxos>(config!(3, 1, leader), 100);
+    }
+
+    #[test]
+    fn sim_atlas_3_1_cross_shard_wait_test() {
+        // with two shards, some commands wait on commands from the other shard
+        let cross_shard_wait = sim_cross_shard_wait::<AtlasLocked>(2);
+        assert!(cross_shard_wait > 0);
+
+        // with a single shard, commands never wait on other shards
+        let cross_shard_wait = sim_cross_shard_wait::<AtlasLocked>(1);
+        assert_eq!(cross_shard_wait, 0);
+    }
+
     #[test]
     fn run_atlas_3_1_locked_partial_replication_four_shards_test() {
         let shard_count = 4;
@@ -477,6 +631,21 @@ mod tests {
         assert!(metrics.slow_paths() > 0);
     }
 
+    #[test]
+    fn sim_epaxos_5_2_transitive_conflicts_test() {
+        // `sim_test` runs gc, so dependencies are transitively reduced
+        let mut config = config!(5, 2);
+        config.set_transitive_conflicts(true);
+        let metrics = sim_test::<EPaxosSequential>(
+            config,
+            READ_ONLY_PERCENTAGE,
+            KEYS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert!(metrics.slow_paths() > 0);
+    }
+
     #[test]
     fn sim_epaxos_7_3_nfr_test() {
         let mut config = config!(7, 3);
@@ -511,6 +680,25 @@ mod tests {
         assert_eq!(metrics.slow_paths(), 0);
     }
 
+    #[test]
+    fn run_epaxos_3_1_auto_test() {
+        // with more than one worker, epaxos picks the locked key deps at
+        // runtime
+        let workers = 4;
+        let executors = 1;
+        let mut config = config!(3, 1);
+        config.set_parallel_key_deps(workers > 1);
+        let metrics = run_test::<EPaxos<AutoKeyDeps>>(
+            config,
+            SHARD_COUNT,
+            workers,
+            executors,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(metrics.slow_paths(), 0);
+    }
+
     // ---- caesar tests ---- //
     #[test]
     fn sim_caesar_wait_3_1_test() {
@@ -643,6 +831,341 @@ mod tests {
         );
     }
 
+    // ---- message count tests ---- //
+    #[test]
+    fn sim_message_counts_fpaxos_vs_epaxos_test() {
+        let leader = 1;
+        let fpaxos_counts = sim_message_counts::<FPaxos>(config!(3, 1, leader));
+        let epaxos_counts =
+            sim_message_counts::<EPaxosSequential>(config!(3, 1));
+
+        // all processes exchange messages in both protocols
+        for counts in [&fpaxos_counts, &epaxos_counts].iter() {
+            assert_eq!(counts.len(), 3);
+            assert!(counts.values().all(|c| c.sent > 0 && c.received > 0));
+        }
+
+        // in fpaxos, the leader is the process sending the most messages (as
+        // it's the one sending both the `MAccept` and the `MChosen` messages)
+        let leader_sent = fpaxos_counts[&leader].sent;
+        assert!(fpaxos_counts
+            .iter()
+            .filter(|(process_id, _)| **process_id != leader)
+            .all(|(_, counts)| counts.sent < leader_sent));
+    }
+
+    fn sim_message_counts<P: Protocol>(
+        mut config: Config,
+    ) -> HashMap<ProcessId, MessageCounts> {
+        update_config(&mut config, SHARD_COUNT);
+
+        // create runner
+        let mut runner: Runner<P> = create_sim_runner(config, SHARD_COUNT);
+
+        // run simulation until the clients end + another 10 seconds (for GC)
+        let extra_sim_time = Some(Duration::from_secs(10));
+        runner.run(extra_sim_time);
+        runner.processes_message_counts()
+    }
+
+    // Returns the max number of outstanding commands at any process, the
+    // number of commands rejected, and the max number of commands pending (as
+    // reported by `pending_count`) at any executor.
+    fn sim_admission_control<P: Protocol>(
+        mut config: Config,
+        pending_count: fn(&P::Executor) -> usize,
+    ) -> (u64, usize, usize) {
+        update_config(&mut config, SHARD_COUNT);
+
+        // create runner
+        let mut runner: Runner<P> = create_sim_runner(config, SHARD_COUNT);
+
+        // step through the simulation (giving enough time for the commits to
+        // reach all processes), tracking the number of pending commands
+        runner.start(Some(Duration::from_secs(1)));
+        let mut max_pending = 0;
+        while !runner.is_done() {
+            runner.step();
+            let pending = runner.inspect_executors(pending_count);
+            max_pending = pending.into_values().fold(max_pending, usize::max);
+        }
+
+        // in the end, all commands have been executed
+        let pending = runner.inspect_executors(pending_count);
+        assert!(pending.values().all(|count| *count == 0));
+
+        // compute the max number of outstanding commands at any process, and
+        // the number of commands rejected
+        let max_outstanding = runner
+            .metrics()
+            .values()
+            .filter_map(|(process_metrics, _)| {
+                process_metrics.get_collected(ProtocolMetricsKind::Outstanding)
+            })
+            .map(|histogram| histogram.max().value() as u64)
+            .max()
+            .unwrap_or_default();
+        let rejected = runner.clients_rejected().values().sum();
+        (max_outstanding, rejected, max_pending)
+    }
+
+    fn sim_on_commit<P: Protocol>(mut config: Config) {
+        use fantoch::command::Command;
+        use fantoch::id::Dot;
+        use std::sync::{Arc, Mutex};
+
+        update_config(&mut config, SHARD_COUNT);
+        let n = config.n();
+
+        // create runner
+        let mut runner: Runner<P> = create_sim_runner(config, SHARD_COUNT);
+
+        // count how many times each command is committed
+        let commits = Arc::new(Mutex::new(HashMap::new()));
+        let commits_clone = commits.clone();
+        runner.set_on_commit(Arc::new(move |dot: Dot, _cmd: &Command| {
+            *commits_clone.lock().unwrap().entry(dot).or_insert(0) += 1;
+        }));
+        // give enough time for the commits to reach all processes
+        runner.run(Some(Duration::from_secs(1)));
+
+        let commits = commits.lock().unwrap();
+        let total_commands = n * CLIENTS_PER_PROCESS * COMMANDS_PER_CLIENT;
+        assert_eq!(commits.len(), total_commands);
+        assert!(commits.values().all(|count| *count == n));
+    }
+
+    fn sim_pending_acks<P: Protocol>(mut config: Config) {
+        update_config(&mut config, SHARD_COUNT);
+        let n = config.n();
+
+        // create runner
+        let mut runner: Runner<P> = create_sim_runner(config, SHARD_COUNT);
+        let (metrics, _, _, _) = runner.run(None);
+
+        // merge the pending acks collected at each process
+        let mut pending_acks = Histogram::new();
+        for (process_metrics, _) in metrics.values() {
+            if let Some(histogram) =
+                process_metrics.get_collected(ProtocolMetricsKind::PendingAcks)
+            {
+                pending_acks.merge(histogram);
+            }
+        }
+        assert!(pending_acks.count() > 0);
+        assert!((pending_acks.max().value() as usize) < n);
+    }
+
+    #[cfg(feature = "command_spans")]
+    fn sim_execute_spans<P: Protocol>(mut config: Config) {
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+        use tracing::span::{Attributes, Id};
+        use tracing::Subscriber;
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+        use tracing_subscriber::registry::LookupSpan;
+
+        // layer that counts the `execute` spans created and entered
+        #[derive(Clone, Default)]
+        struct ExecuteSpans(Arc<Mutex<(usize, usize)>>);
+
+        impl<S> Layer<S> for ExecuteSpans
+        where
+            S: Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(
+                &self,
+                attrs: &Attributes<'_>,
+                _id: &Id,
+                _ctx: Context<'_, S>,
+            ) {
+                if attrs.metadata().name() == "execute" {
+                    self.0.lock().0 += 1;
+                }
+            }
+
+            fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+                let span = ctx.span(id).expect("entered span should exist");
+                if span.name() == "execute" {
+                    self.0.lock().1 += 1;
+                }
+            }
+        }
+
+        update_config(&mut config, SHARD_COUNT);
+        let n = config.n();
+
+        let spans = ExecuteSpans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut runner: Runner<P> = create_sim_runner(config, SHARD_COUNT);
+            runner.run(None);
+        });
+
+        // every command is executed at each process, and each `execute` span
+        // is entered (while the command executes) exactly once
+        let (created, entered) = *spans.0.lock();
+        let commands = n * CLIENTS_PER_PROCESS * COMMANDS_PER_CLIENT;
+        assert!(created >= n * commands);
+        assert_eq!(entered, created);
+    }
+
+    // Returns the number of processes whose stable frontier ends up covering
+    // all commands.
+    fn sim_on_stable<P: Protocol>(mut config: Config) -> usize {
+        use fantoch::id::ProcessId;
+        use std::sync::{Arc, Mutex};
+
+        update_config(&mut config, SHARD_COUNT);
+        let n = config.n();
+
+        // create runner
+        let mut runner: Runner<P> = create_sim_runner(config, SHARD_COUNT);
+
+        // keep the latest stable frontier of each process
+        let frontiers = Arc::new(Mutex::new(HashMap::new()));
+        let frontiers_clone = frontiers.clone();
+        runner.set_on_stable(Arc::new(
+            move |process_id: ProcessId,
+                  _time: u64,
+                  frontier: &HashMap<ProcessId, u64>| {
+                let frontier: u64 = frontier.values().sum();
+                frontiers_clone.lock().unwrap().insert(process_id, frontier);
+            },
+        ));
+        // give enough time for all commands to become stable
+        runner.run(Some(Duration::from_secs(5)));
+
+        let frontiers = frontiers.lock().unwrap();
+        let total_commands = n * CLIENTS_PER_PROCESS * COMMANDS_PER_CLIENT;
+        frontiers
+            .values()
+            .filter(|frontier| **frontier as usize == total_commands)
+            .count()
+    }
+
+    // Returns the mean time to commit and mean time to execute of all
+    // commands, given the conflict rate of the workload.
+    fn sim_phase_latencies<P: Protocol>(
+        mut config: Config,
+        conflict_rate: usize,
+    ) -> (f64, f64) {
+        update_config(&mut config, SHARD_COUNT);
+        let n = config.n();
+
+        // clients workload
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate,
+            pool_size: 1,
+        };
+        let keys_per_command = 1;
+        let payload_size = 1;
+        let workload = Workload::new(
+            SHARD_COUNT,
+            key_gen,
+            keys_per_command,
+            COMMANDS_PER_CLIENT,
+            payload_size,
+        );
+
+        // process and client regions: regions are fixed so that the
+        // latencies obtained with different conflict rates are comparable
+        let planet = Planet::new();
+        let regions = fixed_regions(&planet, n);
+
+        // create runner
+        let mut runner: Runner<P> = Runner::new(
+            planet,
+            config,
+            workload,
+            CLIENTS_PER_PROCESS,
+            regions.clone(),
+            regions,
+        );
+        runner.run(None);
+
+        // merge the latencies of all regions
+        let mut commit = Histogram::new();
+        let mut execute = Histogram::new();
+        for (region_commit, region_execute) in
+            runner.clients_phase_latencies().values()
+        {
+            commit.merge(region_commit);
+            execute.merge(region_execute);
+        }
+        // all commands have phase times
+        let total_commands = n * CLIENTS_PER_PROCESS * COMMANDS_PER_CLIENT;
+        assert_eq!(commit.count(), total_commands);
+        (commit.mean().value(), execute.mean().value())
+    }
+
+    fn sim_seed<P: Protocol>(mut config: Config) {
+        update_config(&mut config, SHARD_COUNT);
+        config.set_seed(Some(42));
+        let n = config.n();
+
+        let run = || {
+            let payload_size = 1;
+            let workload = Workload::new(
+                SHARD_COUNT,
+                KEY_GEN,
+                KEYS_PER_COMMAND,
+                COMMANDS_PER_CLIENT,
+                payload_size,
+            );
+
+            // create runner (with the same regions in both runs)
+            let planet = Planet::new();
+            let regions = fixed_regions(&planet, n);
+            let mut runner: Runner<P> = Runner::new(
+                planet,
+                config,
+                workload,
+                CLIENTS_PER_PROCESS,
+                regions.clone(),
+                regions,
+            );
+            runner.reorder_messages();
+            let (metrics, _, clients_latencies, _) = runner.run(None);
+            (metrics, clients_latencies)
+        };
+
+        // the same seed produces the same simulation
+        let (metrics_a, latencies_a) = run();
+        let (metrics_b, latencies_b) = run();
+        assert_eq!(latencies_a, latencies_b);
+        assert_eq!(metrics_a, metrics_b);
+    }
+
+    fn sim_cross_shard_wait<P: Protocol>(shard_count: usize) -> u64 {
+        let mut config = config!(3, 1);
+        update_config(&mut config, shard_count);
+
+        // create runner
+        let mut runner: Runner<P> = create_sim_runner(config, shard_count);
+
+        // run simulation until the clients end + another 10 seconds (for GC)
+        let extra_sim_time = Some(Duration::from_secs(10));
+        let (metrics, _, clients_latencies, _) = runner.run(extra_sim_time);
+
+        // check that all commands completed
+        let issued: usize =
+            clients_latencies.values().map(|(issued, _)| issued).sum();
+        let client_count = CLIENTS_PER_PROCESS * config.n();
+        assert_eq!(issued, COMMANDS_PER_CLIENT * client_count);
+
+        // sum the cross-shard waits of all executors
+        metrics
+            .values()
+            .map(|(_, executor_metrics)| {
+                executor_metrics
+                    .get_aggregated(ExecutorMetricsKind::CrossShardWait)
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .sum()
+    }
+
     #[allow(dead_code)]
     fn metrics_inspect<P>(worker: &P) -> ProtocolMetrics
     where
@@ -711,9 +1234,6 @@ mod tests {
         let shard_count = 1;
         update_config(&mut config, shard_count);
 
-        // planet
-        let planet = Planet::new();
-
         // clients workload
         let payload_size = 1;
         let mut workload = Workload::new(
@@ -725,20 +1245,11 @@ mod tests {
         );
         workload.set_read_only_percentage(read_only_percentage);
 
-        // process and client regions
-        let mut regions = planet.regions();
-        regions.truncate(config.n());
-        let process_regions = regions.clone();
-        let client_regions = regions.clone();
-
         // create runner
-        let mut runner: Runner<P> = Runner::new(
-            planet,
+        let mut runner: Runner<P> = create_sim_runner_with_workload(
             config,
             workload,
             clients_per_process,
-            process_regions,
-            client_regions,
         );
 
         // reorder network messages
@@ -746,7 +1257,7 @@ mod tests {
 
         // run simulation until the clients end + another 10 seconds (for GC)
         let extra_sim_time = Some(Duration::from_secs(10));
-        let (metrics, executors_monitors, _) = runner.run(extra_sim_time);
+        let (metrics, executors_monitors, _, _) = runner.run(extra_sim_time);
 
         // fetch slow paths and stable count from metrics
         let metrics = metrics
@@ -769,6 +1280,57 @@ mod tests {
         check_metrics(config, commands_per_client, clients_per_process, metrics)
     }
 
+    // Creates a simulation runner with a process (and its clients) in each of
+    // the first `config.n()` regions, where clients issue
+    // `COMMANDS_PER_CLIENT` commands each.
+    fn create_sim_runner<P: Protocol>(
+        config: Config,
+        shard_count: usize,
+    ) -> Runner<P> {
+        let payload_size = 1;
+        let workload = Workload::new(
+            shard_count,
+            KEY_GEN,
+            KEYS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            payload_size,
+        );
+        create_sim_runner_with_workload(config, workload, CLIENTS_PER_PROCESS)
+    }
+
+    // Same as `create_sim_runner`, but with the workload given.
+    fn create_sim_runner_with_workload<P: Protocol>(
+        config: Config,
+        workload: Workload,
+        clients_per_process: usize,
+    ) -> Runner<P> {
+        // planet
+        let planet = Planet::new();
+
+        // process and client regions
+        let mut regions = planet.regions();
+        regions.truncate(config.n());
+        let process_regions = regions.clone();
+        let client_regions = regions;
+
+        Runner::new(
+            planet,
+            config,
+            workload,
+            clients_per_process,
+            process_regions,
+            client_regions,
+        )
+    }
+
+    // Returns the first `n` regions of `planet` (in sorted order).
+    fn fixed_regions(planet: &Planet, n: usize) -> Vec<Region> {
+        let mut regions = planet.regions();
+        regions.sort();
+        regions.truncate(n);
+        regions
+    }
+
     fn update_config(config: &mut Config, shard_count: usize) {
         // make sure execution order is monitored
         config.set_executor_monitor_execution_order(true);
@@ -6,11 +6,12 @@ use crate::protocol::common::table::{
 };
 use crate::protocol::partial::{self, ShardsCommits};
 use fantoch::command::Command;
+use fantoch::command_span;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    Action, BaseProcess, CommitCallback, Info, MessageIndex, Protocol,
+    ProtocolMetrics, SequentialCommandsInfo, StableCallback, VClockGCTrack,
 };
 use fantoch::time::SysTime;
 use fantoch::util;
@@ -155,6 +156,7 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        command_span!("submit", cmd.rifl(), _time);
         self.handle_submit(dot, cmd, true);
     }
 
@@ -260,6 +262,22 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
         true
     }
 
+    fn set_on_commit(&mut self, callback: CommitCallback) {
+        self.bp.set_on_commit(callback);
+    }
+
+    fn set_on_stable(&mut self, callback: StableCallback) {
+        self.bp.set_on_stable(callback);
+    }
+
+    fn overloaded(&self) -> bool {
+        self.bp.overloaded()
+    }
+
+    fn discovered(&self) -> bool {
+        self.bp.discovered()
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
@@ -276,6 +294,10 @@ impl<KC: KeyClocks> Tempo<KC> {
         // compute the command identifier
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
+        // track the command (and its quorum acks) until it's committed
+        self.bp.track_outstanding(dot);
+        self.bp.track_acks(dot);
+
         // record command size
         self.bp.collect_metric(
             fantoch::protocol::ProtocolMetricsKind::CommandKeyCount,
@@ -491,6 +513,9 @@ impl<KC: KeyClocks> Tempo<KC> {
             return;
         }
 
+        // record the quorum ack
+        self.bp.ack(dot);
+
         // update votes with remote votes
         info.votes.merge(remote_votes);
 
@@ -605,11 +630,13 @@ impl<KC: KeyClocks> Tempo<KC> {
             return;
         }
 
-        // create execution info
+        // notify the on-commit callback (if any) and create execution info
         let cmd = info
             .cmd
             .as_ref()
             .expect("there should be a command payload");
+        command_span!("commit", cmd.rifl(), _time);
+        self.bp.committed(dot, cmd);
         let rifl = cmd.rifl();
         let execution_info = cmd.iter(self.bp.shard_id).map(|(key, ops)| {
             // find votes on this key
@@ -635,6 +662,10 @@ impl<KC: KeyClocks> Tempo<KC> {
         });
         self.to_executors.extend(execution_info);
 
+        // the command is no longer outstanding
+        self.bp.untrack_outstanding(dot);
+        self.bp.untrack_acks(dot);
+
         // update command info:
         info.status = Status::COMMIT;
 
@@ -957,16 +988,17 @@ impl<KC: KeyClocks> Tempo<KC> {
         &mut self,
         from: ProcessId,
         stable: Vec<(ProcessId, u64, u64)>,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
         trace!(
             "p{}: MStable({:?}) from {} | time={}",
             self.id(),
             stable,
             from,
-            _time.micros()
+            time.micros()
         );
         assert_eq!(from, self.bp.process_id);
+        self.bp.advance_stable_frontier(&stable, time);
         let stable_count = self.cmds.gc(stable);
         self.bp.stable(stable_count);
     }
@@ -978,6 +1010,9 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
+        // record the quorum acks of in-flight commands
+        self.bp.collect_pending_acks();
+
         // retrieve the committed clock
         let committed = self.gc_track.clock().frontier();
 
@@ -1318,6 +1353,8 @@ mod tests {
     use super::*;
     use fantoch::client::{Client, KeyGen, Workload};
     use fantoch::executor::Executor;
+    use fantoch::id::Rifl;
+    use fantoch::kvs::KVOp;
     use fantoch::planet::{Planet, Region};
     use fantoch::sim::Simulation;
     use fantoch::time::SimTime;
@@ -1545,4 +1582,91 @@ mod tests {
             matches!(mcollect, Action::ToSend {msg, ..} if check_msg(&msg))
         );
     }
+
+    #[test]
+    fn sequential_tempo_clock_skew_test() {
+        tempo_clock_skew::<SequentialKeyClocks>();
+    }
+
+    #[test]
+    fn atomic_tempo_clock_skew_test() {
+        tempo_clock_skew::<AtomicKeyClocks>();
+    }
+
+    fn tempo_clock_skew<KC: KeyClocks>() {
+        // create simulation
+        let mut simulation = Simulation::new();
+
+        // there's a single shard
+        let shard_id = 0;
+
+        // processes (all in the same region)
+        let region = Region::new("europe-west2");
+        let processes: Vec<_> = (1..=3)
+            .map(|process_id| (process_id, shard_id, region.clone()))
+            .collect();
+        let planet = Planet::new();
+
+        // n and f
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        // register all tempos
+        for (process_id, _, _) in processes.clone() {
+            let executor = TableExecutor::new(process_id, shard_id, config);
+            let (mut tempo, _) = Tempo::<KC>::new(process_id, shard_id, config);
+            let sorted = util::sort_processes_by_distance(
+                &region,
+                &planet,
+                processes.clone(),
+            );
+            tempo.discover(sorted);
+            simulation.register_process(tempo, executor);
+        }
+
+        // process 1 runs 50ms ahead of everyone else
+        let skew = Duration::from_millis(50);
+        simulation.set_clock_skew(1, skew);
+        simulation.time().set_millis(100);
+
+        // returns the clock proposed in a new command on key `A`
+        let mut rifl_seq = 0;
+        let mut propose = |simulation: &mut Simulation<Tempo<KC>>,
+                           process_id: ProcessId| {
+            rifl_seq += 1;
+            let rifl = Rifl::new(process_id as u64, rifl_seq);
+            let cmd =
+                Command::from(rifl, vec![(String::from("A"), KVOp::Put(0))]);
+            let (process, _, _, time) = simulation.get_process(process_id);
+            process.submit(None, cmd, time);
+            process
+                .to_processes_iter()
+                .find_map(|action| match action {
+                    Action::ToSend {
+                        msg: Message::MCollect { clock, .. },
+                        ..
+                    } => Some(clock),
+                    _ => None,
+                })
+                .expect("there should be an mcollect")
+        };
+
+        let clocks: Vec<_> = (1..=3)
+            .map(|process_id| {
+                // make sure key `A` exists, then bump all clocks to the time
+                // perceived by each process, and propose again
+                propose(&mut simulation, process_id);
+                let (process, _, _, time) = simulation.get_process(process_id);
+                process.handle_event(PeriodicEvent::ClockBump, time);
+                propose(&mut simulation, process_id)
+            })
+            .collect();
+
+        // processes without skew propose the same clock
+        assert_eq!(clocks[1], 100_001);
+        assert_eq!(clocks[2], 100_001);
+        // process 1 is ahead
+        assert_eq!(clocks[0], clocks[1] + skew.as_micros() as u64);
+    }
 }